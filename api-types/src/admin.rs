@@ -0,0 +1,80 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct CreateUser {
+    pub first_name: String,
+    pub last_name: String,
+    pub username: String,
+    pub password: String,
+    pub admin: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateUser {
+    pub first_name: String,
+    pub last_name: String,
+    pub admin: bool,
+    pub publisher: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct User {
+    pub user_id: String,
+    pub first_name: String,
+    pub last_name: String,
+    pub username: String,
+    pub admin: bool,
+    pub publisher: bool,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+/// Blog post with the deletion information, used to list the posts
+/// that can be restored
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(
+    feature = "openapi",
+    derive(utoipa::ToSchema),
+    schema(as = DeletedBlogPost)
+)]
+#[serde(rename_all = "camelCase")]
+pub struct BlogPost {
+    pub id: String,
+    pub title: String,
+    pub creator_id: String,
+    pub creator_username: String,
+    pub creation_date: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+/// Audit event with the before and after states in relaxed extended JSON
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEvent {
+    pub id: String,
+    pub actor_id: String,
+    pub action: String,
+    pub target_collection: String,
+    pub target_id: String,
+    pub before: Option<serde_json::Value>,
+    pub after: Option<serde_json::Value>,
+    pub creation_date: DateTime<Utc>,
+}
+
+/// Page of audit events with the total number of events matching the query
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEventPage {
+    pub events: Vec<AuditEvent>,
+    pub page: u64,
+    pub page_size: u64,
+    pub total: u64,
+}
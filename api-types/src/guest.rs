@@ -0,0 +1,32 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct RegisterInfo {
+    pub first_name: String,
+    pub last_name: String,
+    pub username: String,
+    pub password: String,
+}
+
+/// Authorization payload for jwt token
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct JWTAuthPayload {
+    pub username: String,
+    pub password: String,
+}
+
+/// Authorization response for jwt token
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct LoggedUserInfoResponse {
+    // it is Some from the login and register requests but empty for the get_user_info
+    pub token: Option<String>,
+    pub user_id: String,
+    pub username: String,
+    pub admin: bool,
+}
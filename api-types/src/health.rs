@@ -0,0 +1,41 @@
+use serde::{Deserialize, Serialize};
+
+/// Status of the application or of one of its dependencies
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    Up,
+    Down,
+    /// The dependency is not checked, it does not affect the readiness
+    Skipped,
+}
+
+/// Result of the check of a dependency
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct DependencyHealth {
+    pub status: HealthStatus,
+    pub latency_ms: f64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Response of the liveness probe
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct LivenessReport {
+    pub status: HealthStatus,
+}
+
+/// Response of the readiness probe, the application is up
+/// only when none of the checked dependencies is down
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct ReadinessReport {
+    pub status: HealthStatus,
+    pub database: DependencyHealth,
+    pub object_storage: DependencyHealth,
+}
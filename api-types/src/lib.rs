@@ -0,0 +1,23 @@
+//! Types of the request and response bodies of the REST API, shared by
+//! the backend and the frontend so that a change of the contract breaks
+//! the build of both of them.
+//!
+//! The crate only depends on `serde`, `serde_json` and `chrono`, hence it
+//! compiles for the native and the `wasm32-unknown-unknown` targets. The
+//! `openapi` feature derives the schemas used by the backend documentation.
+//!
+//! Modules follow the routers of the backend:
+//!
+//! - `guest`: registration and login
+//! - `user`: blog posts of the logged users
+//! - `public`: published posts and authors, readable without an account
+//! - `admin`: users, deleted blog posts and audit log
+//! - `health`: liveness and readiness probes
+//! - `error`: body of the error responses
+
+pub mod admin;
+pub mod error;
+pub mod guest;
+pub mod health;
+pub mod public;
+pub mod user;
@@ -0,0 +1,13 @@
+use serde::{Deserialize, Serialize};
+
+use crate::user::BlogPost;
+
+/// Author with the public blog posts, readable without an account
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct AuthorPage {
+    pub author_id: String,
+    pub username: String,
+    pub posts: Vec<BlogPost>,
+}
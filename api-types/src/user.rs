@@ -0,0 +1,158 @@
+use std::{fmt, str::FromStr};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Who can read a blog post
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub enum PostVisibility {
+    /// Readable by everyone, also without an account
+    #[default]
+    Public,
+    /// Readable only by the logged users
+    MembersOnly,
+}
+
+impl PostVisibility {
+    /// Name of the visibility, the same used by the serialization
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PostVisibility::Public => "public",
+            PostVisibility::MembersOnly => "membersOnly",
+        }
+    }
+}
+
+impl fmt::Display for PostVisibility {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for PostVisibility {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "public" => Ok(PostVisibility::Public),
+            "membersOnly" => Ok(PostVisibility::MembersOnly),
+            _ => Err(format!("Invalid post visibility: {value}")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct PublishPost {
+    pub title: String,
+    pub content: String,
+    /// Public when missing
+    #[serde(default)]
+    pub visibility: PostVisibility,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct UpdatePost {
+    pub title: String,
+    pub content: String,
+    /// Public when missing
+    #[serde(default)]
+    pub visibility: PostVisibility,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct BlogPost {
+    pub id: String,
+    pub title: String,
+    pub content: String,
+    pub creator_id: String,
+    pub creator_username: String,
+    pub creation_date: DateTime<Utc>,
+    pub visibility: PostVisibility,
+}
+
+/// Change of a blog post pushed by the blog post stream
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum BlogPostChange {
+    Created { post: BlogPost },
+    Updated { post: BlogPost },
+    Deleted { id: String },
+}
+
+impl BlogPostChange {
+    /// Name of the server sent event
+    pub fn event_name(&self) -> &'static str {
+        match self {
+            BlogPostChange::Created { .. } => "created",
+            BlogPostChange::Updated { .. } => "updated",
+            BlogPostChange::Deleted { .. } => "deleted",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use serde_json::json;
+
+    use super::{BlogPost, BlogPostChange, PostVisibility, PublishPost};
+
+    #[test]
+    fn test_blog_post_change_wire_format() {
+        let change = BlogPostChange::Created {
+            post: BlogPost {
+                id: "65f1a3c2e4b0a1b2c3d4e5f6".into(),
+                title: "title".into(),
+                content: "content".into(),
+                creator_id: "65f1a3c2e4b0a1b2c3d4e5f7".into(),
+                creator_username: "marcel".into(),
+                creation_date: Utc.with_ymd_and_hms(2025, 11, 14, 10, 0, 0).unwrap(),
+                visibility: PostVisibility::MembersOnly,
+            },
+        };
+        let value = serde_json::to_value(&change).unwrap();
+        assert_eq!(value["type"], "created");
+        assert_eq!(value["post"]["creatorUsername"], "marcel");
+        assert_eq!(value["post"]["creationDate"], "2025-11-14T10:00:00Z");
+        assert_eq!(value["post"]["visibility"], "membersOnly");
+        assert_eq!(
+            serde_json::from_value::<BlogPostChange>(value).unwrap(),
+            change
+        );
+
+        let deleted = serde_json::from_value::<BlogPostChange>(json!({
+            "type": "deleted",
+            "id": "65f1a3c2e4b0a1b2c3d4e5f6"
+        }))
+        .unwrap();
+        assert_eq!(deleted.event_name(), "deleted");
+    }
+
+    #[test]
+    fn test_post_visibility() {
+        let publish = serde_json::from_value::<PublishPost>(json!({
+            "title": "title",
+            "content": "content"
+        }))
+        .unwrap();
+        assert_eq!(publish.visibility, PostVisibility::Public);
+
+        for visibility in [PostVisibility::Public, PostVisibility::MembersOnly] {
+            assert_eq!(
+                serde_json::to_value(visibility).unwrap(),
+                visibility.as_str()
+            );
+            assert_eq!(visibility.as_str().parse(), Ok(visibility));
+        }
+        assert!("private".parse::<PostVisibility>().is_err());
+    }
+}
@@ -12,14 +12,16 @@ use axum_extra::{
     TypedHeader,
     headers::{Authorization, authorization::Bearer},
 };
-use jsonwebtoken::{EncodingKey, Header, Validation, decode, encode};
+use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation, decode, encode};
 
 use mongodb::bson::doc;
 use tracing::error;
 
 use crate::{
-    AppState,
+    AppState, AuthResult, EnvironmentServiceTrait,
     error::{AppError, AuthError},
+    model::User,
+    service::database::document::DatabaseDocumentTrait,
 };
 
 /// Trait for auth info objects that need to return specific information
@@ -51,6 +53,10 @@ where
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct JWTAuthClaim {
     pub exp: u32,
+    /// Time of the login that started the session, the refreshed tokens keep it.
+    /// The tokens issued before it was added have none and cannot be refreshed
+    #[serde(default)]
+    pub auth_time: u32,
     #[serde(
         serialize_with = "serialize_object_id",
         deserialize_with = "deserialize_object_id"
@@ -60,9 +66,10 @@ pub struct JWTAuthClaim {
 }
 
 impl JWTAuthClaim {
-    pub fn new(exp: u32, user_id: ObjectId, username: String) -> Self {
+    pub fn new(exp: u32, auth_time: u32, user_id: ObjectId, username: String) -> Self {
         Self {
             exp,
+            auth_time,
             user_id,
             username,
         }
@@ -78,6 +85,66 @@ impl JWTAuthClaim {
         })?;
         Ok(token)
     }
+
+    /// Issue a new token of the user that expires after the configured
+    /// authentication duration, it starts a new session
+    pub fn issue_token(
+        environment_service: &dyn EnvironmentServiceTrait,
+        user: &User,
+    ) -> AuthResult<String> {
+        let now = chrono::offset::Local::now().timestamp() as u32;
+        Self::issue_session_token(environment_service, user, now, now)
+    }
+
+    /// Issue a new token of the user in the session of this token.
+    ///
+    /// It is refused once the session is older than the configured maximum age,
+    /// and the new token does not outlive the session.
+    pub fn refresh_token(
+        &self,
+        environment_service: &dyn EnvironmentServiceTrait,
+        user: &User,
+    ) -> AuthResult<String> {
+        let now = chrono::offset::Local::now().timestamp() as u32;
+        let max_session_age = environment_service.get_authentication_max_session_age();
+        if now.saturating_sub(self.auth_time) as usize >= max_session_age {
+            return Err(AuthError::SessionExpired);
+        }
+        Self::issue_session_token(environment_service, user, self.auth_time, now)
+    }
+
+    fn issue_session_token(
+        environment_service: &dyn EnvironmentServiceTrait,
+        user: &User,
+        auth_time: u32,
+        now: u32,
+    ) -> AuthResult<String> {
+        let exp = environment_service.get_authentication_jwt_expiration();
+        let max_session_age = environment_service.get_authentication_max_session_age();
+        JWTAuthClaim::new(
+            (now + exp as u32).min(auth_time + max_session_age as u32),
+            auth_time,
+            *user.get_id(),
+            user.username().clone(),
+        )
+        .build_token(
+            &Header::default(),
+            environment_service.get_authentication_jwt_encoding(),
+        )
+    }
+
+    /// Decode and validate the token returning its claims
+    ///
+    /// It is used directly when the token cannot be sent in the authorization
+    /// header, like in the event source requests done by browsers
+    pub fn decode_token(token: &str, decoding_key: &DecodingKey) -> AuthResult<Self> {
+        let token_data = decode::<JWTAuthClaim>(token, decoding_key, &Validation::default())
+            .map_err(|e| {
+                tracing::error!("Got error {}", e);
+                AuthError::InvalidToken
+            })?;
+        Ok(token_data.claims)
+    }
 }
 
 impl<S> FromRequestParts<S> for JWTAuthClaim
@@ -96,18 +163,10 @@ where
 
         let state = Arc::from_ref(state);
 
-        // Decode the user data
-        let token_data = decode::<JWTAuthClaim>(
+        Ok(JWTAuthClaim::decode_token(
             bearer.token(),
             state.environment_service.get_authentication_jwt_decoding(),
-            &Validation::default(),
-        )
-        .map_err(|e| {
-            tracing::error!("Got error {}", e);
-            AuthError::InvalidToken
-        })?;
-
-        Ok(token_data.claims)
+        )?)
     }
 }
 
@@ -2,5 +2,7 @@ pub mod admin_request;
 pub mod admin_response;
 pub mod guest_request;
 pub mod guest_response;
+pub mod health_response;
+pub mod public_response;
 pub mod user_request;
 pub mod user_response;
@@ -1,11 +1,37 @@
+use bson::oid::ObjectId;
+use chrono::{DateTime, Utc};
 use serde::Deserialize;
+use utoipa::IntoParams;
 
-#[derive(Deserialize)]
+use crate::enums::AuditAction;
+
+pub use api_types::admin::{CreateUser, UpdateUser};
+
+/// Filters and pagination of the audit log, pages start from 1
+#[derive(Deserialize, IntoParams)]
 #[serde(rename_all = "camelCase")]
-pub struct CreateUser {
-    pub first_name: String,
-    pub last_name: String,
-    pub username: String,
-    pub password: String,
-    pub admin: bool,
+#[into_params(parameter_in = Query)]
+pub struct AuditEventQuery {
+    #[param(value_type = Option<String>)]
+    pub actor_id: Option<ObjectId>,
+    pub action: Option<AuditAction>,
+    pub target_collection: Option<String>,
+    #[param(value_type = Option<String>)]
+    pub target_id: Option<ObjectId>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    #[serde(default = "default_page")]
+    #[param(default = 1, minimum = 1)]
+    pub page: u64,
+    #[serde(default = "default_page_size")]
+    #[param(default = 20, minimum = 1, maximum = 100)]
+    pub page_size: u64,
+}
+
+fn default_page() -> u64 {
+    1
+}
+
+fn default_page_size() -> u64 {
+    20
 }
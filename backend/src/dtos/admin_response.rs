@@ -1,16 +1,8 @@
-use serde::Serialize;
+use bson::{Bson, Document};
 
 use crate::{model, service::database::document::DatabaseDocumentTrait};
 
-#[derive(Serialize)]
-#[serde(rename_all = "camelCase")]
-pub struct User {
-    pub user_id: String,
-    pub first_name: String,
-    pub last_name: String,
-    pub username: String,
-    pub admin: bool,
-}
+pub use api_types::admin::{AuditEvent, AuditEventPage, BlogPost, User};
 
 impl From<model::User> for User {
     fn from(value: model::User) -> Self {
@@ -20,6 +12,42 @@ impl From<model::User> for User {
             last_name: value.last_name().clone(),
             username: value.username().clone(),
             admin: *value.admin(),
+            publisher: *value.publisher(),
+            deleted_at: value.deleted_at(),
+        }
+    }
+}
+
+impl From<model::BlogPost> for BlogPost {
+    fn from(value: model::BlogPost) -> Self {
+        Self {
+            id: value.get_id().to_hex(),
+            title: value.title().clone(),
+            creator_id: value.user_id().to_hex(),
+            creator_username: value.username().clone(),
+            creation_date: *value.creation_date(),
+            deleted_at: value.deleted_at(),
+        }
+    }
+}
+
+fn to_json(document: &Option<Document>) -> Option<serde_json::Value> {
+    document
+        .clone()
+        .map(|document| Bson::Document(document).into_relaxed_extjson())
+}
+
+impl From<model::AuditEvent> for AuditEvent {
+    fn from(value: model::AuditEvent) -> Self {
+        Self {
+            id: value.get_id().to_hex(),
+            actor_id: value.actor_id().to_hex(),
+            action: value.action().clone(),
+            target_collection: value.target_collection().clone(),
+            target_id: value.target_id().to_hex(),
+            before: to_json(value.before()),
+            after: to_json(value.after()),
+            creation_date: *value.creation_date(),
         }
     }
 }
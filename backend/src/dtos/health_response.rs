@@ -0,0 +1 @@
+pub use api_types::health::{DependencyHealth, HealthStatus, LivenessReport, ReadinessReport};
@@ -0,0 +1 @@
+pub use api_types::public::AuthorPage;
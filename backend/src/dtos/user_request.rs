@@ -1,8 +1,12 @@
 use serde::Deserialize;
+use utoipa::IntoParams;
 
-#[derive(Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct PublishPost {
-    pub title: String,
-    pub content: String,
+pub use api_types::user::{PublishPost, UpdatePost};
+
+/// Query parameters of the blog post stream, the token is sent as query
+/// parameter because browsers cannot set headers on event source requests
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct StreamQuery {
+    pub access_token: String,
 }
@@ -1,18 +1,9 @@
-use chrono::{DateTime, Utc};
-use serde::Serialize;
+use crate::{
+    model,
+    service::database::{ChangeEvent, document::DatabaseDocumentTrait},
+};
 
-use crate::{model, service::database::document::DatabaseDocumentTrait};
-
-#[derive(Serialize)]
-#[serde(rename_all = "camelCase")]
-pub struct BlogPost {
-    pub id: String,
-    pub title: String,
-    pub content: String,
-    pub creator_id: String,
-    pub creator_username: String,
-    pub creation_date: DateTime<Utc>,
-}
+pub use api_types::user::{BlogPost, BlogPostChange, PostVisibility};
 
 impl From<model::BlogPost> for BlogPost {
     fn from(value: model::BlogPost) -> Self {
@@ -23,6 +14,21 @@ impl From<model::BlogPost> for BlogPost {
             creator_id: value.user_id().to_string(),
             creator_username: value.username().to_string(),
             creation_date: *value.creation_date(),
+            // an unknown visibility is never exposed to the guests
+            visibility: value
+                .visibility()
+                .parse()
+                .unwrap_or(PostVisibility::MembersOnly),
+        }
+    }
+}
+
+impl From<ChangeEvent<model::BlogPost>> for BlogPostChange {
+    fn from(value: ChangeEvent<model::BlogPost>) -> Self {
+        match value {
+            ChangeEvent::Inserted(post) => BlogPostChange::Created { post: post.into() },
+            ChangeEvent::Updated(post) => BlogPostChange::Updated { post: post.into() },
+            ChangeEvent::Deleted(id) => BlogPostChange::Deleted { id: id.to_string() },
         }
     }
 }
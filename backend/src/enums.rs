@@ -3,7 +3,9 @@
 
 use std::fmt::Display;
 
+use axum::http::HeaderValue;
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
 use crate::error::ServiceAppError;
 
@@ -94,3 +96,236 @@ impl Display for FrontendMode {
         )
     }
 }
+
+/// Origins allowed to send cross origin requests to the application
+#[derive(Debug, PartialEq, Clone)]
+pub enum CorsOrigins {
+    /// Any origin is allowed, it cannot be used with credentials
+    Any,
+    /// Only the listed origins are allowed, when the list is empty
+    /// only same origin requests are allowed
+    List(Vec<HeaderValue>),
+}
+
+/// Enumeration of the database backends that can be used by the application
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum DatabaseBackend {
+    /// MongoDB cluster defined by the connection string
+    MongoDB,
+    /// In memory database, data is lost when the application stops
+    Memory,
+}
+
+impl TryFrom<&str> for DatabaseBackend {
+    type Error = ServiceAppError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value.to_lowercase().as_str() {
+            "mongodb" => Ok(Self::MongoDB),
+            "memory" => Ok(Self::Memory),
+            _ => Err(ServiceAppError::InvalidRequest(format!(
+                "Cannot create DatabaseBackend from {value}"
+            ))),
+        }
+    }
+}
+
+impl Display for DatabaseBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                DatabaseBackend::MongoDB => "mongodb",
+                DatabaseBackend::Memory => "memory",
+            }
+        )
+    }
+}
+
+/// Enumeration of the mutating operations recorded in the audit log
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Copy, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditAction {
+    Create,
+    Update,
+    Delete,
+    Restore,
+}
+
+impl TryFrom<&str> for AuditAction {
+    type Error = ServiceAppError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value.to_lowercase().as_str() {
+            "create" => Ok(Self::Create),
+            "update" => Ok(Self::Update),
+            "delete" => Ok(Self::Delete),
+            "restore" => Ok(Self::Restore),
+            _ => Err(ServiceAppError::InvalidRequest(format!(
+                "Cannot create AuditAction from {value}"
+            ))),
+        }
+    }
+}
+
+impl Display for AuditAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                AuditAction::Create => "create",
+                AuditAction::Update => "update",
+                AuditAction::Delete => "delete",
+                AuditAction::Restore => "restore",
+            }
+        )
+    }
+}
+
+/// Enumeration of the stores that keep the rate limit buckets
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum RateLimitStoreBackend {
+    /// Buckets are kept in the process memory, each instance has its own limits
+    Memory,
+    /// Buckets are kept in the database, the limits are shared by the instances
+    Database,
+}
+
+impl TryFrom<&str> for RateLimitStoreBackend {
+    type Error = ServiceAppError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value.to_lowercase().as_str() {
+            "memory" => Ok(Self::Memory),
+            "database" => Ok(Self::Database),
+            _ => Err(ServiceAppError::InvalidRequest(format!(
+                "Cannot create RateLimitStoreBackend from {value}"
+            ))),
+        }
+    }
+}
+
+impl Display for RateLimitStoreBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                RateLimitStoreBackend::Memory => "memory",
+                RateLimitStoreBackend::Database => "database",
+            }
+        )
+    }
+}
+
+/// Enumeration of the groups of routes with their own rate limit
+///
+/// Guest routes are limited by client IP, while the authenticated
+/// ones are limited by user.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum RateLimitRoute {
+    /// `POST /api/guest/login`
+    Login,
+    /// `POST /api/guest/register`
+    Register,
+    /// `POST /api/user/blog/post`
+    PublishPost,
+    /// Any other guest route, the public routes and the feeds
+    Guest,
+    /// Any other authenticated route
+    User,
+}
+
+impl Display for RateLimitRoute {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                RateLimitRoute::Login => "login",
+                RateLimitRoute::Register => "register",
+                RateLimitRoute::PublishPost => "publish_post",
+                RateLimitRoute::Guest => "guest",
+                RateLimitRoute::User => "user",
+            }
+        )
+    }
+}
+
+/// Enumeration of the formats of the application logs
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum LogFormat {
+    /// Human readable lines
+    Text,
+    /// One JSON object per line, including the fields of the spans
+    Json,
+}
+
+impl TryFrom<&str> for LogFormat {
+    type Error = ServiceAppError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value.to_lowercase().as_str() {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            _ => Err(ServiceAppError::InvalidRequest(format!(
+                "Cannot create LogFormat from {value}"
+            ))),
+        }
+    }
+}
+
+impl Display for LogFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                LogFormat::Text => "text",
+                LogFormat::Json => "json",
+            }
+        )
+    }
+}
+
+/// Enumeration of how often a new log file is created
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum LogRotation {
+    Minutely,
+    Hourly,
+    Daily,
+    Never,
+}
+
+impl TryFrom<&str> for LogRotation {
+    type Error = ServiceAppError;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        match value.to_lowercase().as_str() {
+            "minutely" => Ok(Self::Minutely),
+            "hourly" => Ok(Self::Hourly),
+            "daily" => Ok(Self::Daily),
+            "never" => Ok(Self::Never),
+            _ => Err(ServiceAppError::InvalidRequest(format!(
+                "Cannot create LogRotation from {value}"
+            ))),
+        }
+    }
+}
+
+impl Display for LogRotation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                LogRotation::Minutely => "minutely",
+                LogRotation::Hourly => "hourly",
+                LogRotation::Daily => "daily",
+                LogRotation::Never => "never",
+            }
+        )
+    }
+}
@@ -1,12 +1,29 @@
 //! Environment module provides a trait the defines the behavior of
 //! the EnvironemntService and defines the struct containing application
 //! environment variables.
+//!
+//! The raw configuration is loaded in layers by `AppConfig` and then
+//! validated by the `EnvironmentService`.
 
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use axum::http::{HeaderName, Method};
 use jsonwebtoken::{DecodingKey, EncodingKey};
 
-use crate::enums::{FrontendMode, ObjectSourceType};
+use crate::{
+    enums::{
+        CorsOrigins, DatabaseBackend, FrontendMode, LogFormat, LogRotation, ObjectSourceType,
+        RateLimitRoute, RateLimitStoreBackend,
+    },
+    types::RateLimit,
+};
 
+mod config;
 mod service;
+pub use config::AppConfig;
 pub use service::EnvironmentService;
 
 /// Trait that defines the behavior of an Environment Service
@@ -15,12 +32,32 @@ pub use service::EnvironmentService;
 /// and that needs to be defined in one place during the application
 /// initialization.
 pub trait EnvironmentServiceTrait: Send + Sync {
+    fn get_server_host(&self) -> &str;
+
+    fn get_server_port(&self) -> u16;
+
+    fn get_server_shutdown_timeout(&self) -> Duration;
+
+    /// Paths of the certificate chain and of the private key in PEM format,
+    /// when they are defined the server terminates TLS
+    fn get_tls_cert_and_key_paths(&self) -> Option<(&Path, &Path)>;
+
+    fn get_tls_redirect_http_port(&self) -> Option<u16>;
+
+    fn get_tls_reload_interval(&self) -> Duration;
+
+    fn get_database_backend(&self) -> &DatabaseBackend;
+
     fn get_database_connection_string(&self) -> &str;
 
     fn get_database_db_name(&self) -> &str;
 
+    fn get_database_soft_delete_retention_days(&self) -> u32;
+
     fn get_authentication_jwt_expiration(&self) -> usize;
 
+    fn get_authentication_max_session_age(&self) -> usize;
+
     fn get_authentication_jwt_encoding(&self) -> &EncodingKey;
 
     fn get_authentication_jwt_decoding(&self) -> &DecodingKey;
@@ -29,24 +66,111 @@ pub trait EnvironmentServiceTrait: Send + Sync {
 
     fn get_logging_level(&self) -> tracing::Level;
 
+    fn get_logging_format(&self) -> &LogFormat;
+
+    /// Directory of the log files, when it is not defined the logs
+    /// are written to the standard output
+    fn get_logging_directory(&self) -> Option<&Path>;
+
+    fn get_logging_rotation(&self) -> &LogRotation;
+
+    /// Number of rotated log files to keep, all of them when it is not defined
+    fn get_logging_max_files(&self) -> Option<usize>;
+
     fn get_object_storage_source_type(&self) -> &ObjectSourceType;
 
     fn get_object_storage_prefix_path(&self) -> &str;
 
     fn get_frontend_mode(&self) -> &FrontendMode;
+
+    /// URL where the readers open the frontend, without the trailing slash,
+    /// used to build the absolute links of the feeds
+    fn get_frontend_public_url(&self) -> &str;
+
+    fn get_cors_allowed_origins(&self) -> &CorsOrigins;
+
+    fn get_cors_allowed_methods(&self) -> &[Method];
+
+    fn get_cors_allowed_headers(&self) -> &[HeaderName];
+
+    fn get_cors_exposed_headers(&self) -> &[HeaderName];
+
+    fn get_cors_allow_credentials(&self) -> bool;
+
+    fn get_cors_max_age(&self) -> Duration;
+
+    fn get_rate_limit_enabled(&self) -> bool;
+
+    fn get_rate_limit_store(&self) -> &RateLimitStoreBackend;
+
+    /// Whether the client IP is read from the `X-Real-IP` header set by
+    /// the reverse proxy instead of the address of the connection
+    fn get_rate_limit_trust_proxy_headers(&self) -> bool;
+
+    fn get_rate_limit(&self, route: RateLimitRoute) -> &RateLimit;
+
+    fn get_metrics_enabled(&self) -> bool;
+
+    /// Port of the dedicated metrics listener, when it is not defined
+    /// the metrics are served by the application listener
+    fn get_metrics_port(&self) -> Option<u16>;
+
+    /// Bearer token required to read the metrics, when it is defined
+    fn get_metrics_token(&self) -> Option<&str>;
+
+    /// Base URL of the OTLP/HTTP collector, spans are exported
+    /// only when it is defined
+    fn get_telemetry_otlp_endpoint(&self) -> Option<&str>;
+
+    fn get_telemetry_service_name(&self) -> &str;
+
+    /// Ratio of the traces started by the backend that are sampled, the
+    /// traces started by the caller follow its sampling decision
+    fn get_telemetry_sample_ratio(&self) -> f64;
+}
+
+/// Server configuration variables
+///
+/// The shutdown timeout is the time given to in flight requests
+/// to complete once a shutdown signal is received
+#[derive(Debug, Clone)]
+struct ServerVariables {
+    host: String,
+    port: u16,
+    shutdown_timeout: Duration,
+}
+
+/// TLS configuration variables
+///
+/// When the certificate is defined the server listens for HTTPS connections
+/// and optionally redirects the HTTP ones from `redirect_http_port`.
+/// The certificate files are checked for changes every `reload_interval`.
+#[derive(Debug, Clone)]
+struct TlsVariables {
+    cert_and_key_paths: Option<(PathBuf, PathBuf)>,
+    redirect_http_port: Option<u16>,
+    reload_interval: Duration,
 }
 
 /// Logging configuration variables
+///
+/// When the directory is defined the logs are written to files
+/// rotated according to `rotation`, otherwise to the standard output.
 #[derive(Debug, Clone)]
 struct LoggingVariables {
     level: tracing::Level,
     include_headers: bool,
+    format: LogFormat,
+    directory: Option<PathBuf>,
+    rotation: LogRotation,
+    max_files: Option<usize>,
 }
 
 /// Authentication configuration variables used for JWT
 #[derive(Clone)]
 struct AuthenticationVariables {
     jwt_expiration: usize,
+    max_session_age: usize,
     jwt_encoding: EncodingKey,
     jwt_decoding: DecodingKey,
 }
@@ -69,11 +193,73 @@ struct ObjectStorageVariables {
 #[derive(Debug, Clone)]
 struct FrontendVariables {
     frontend_mode: FrontendMode,
+    public_url: String,
+}
+
+/// CORS configuration variables
+///
+/// By default only the frontend origin is allowed in external mode, while in
+/// integrated mode the frontend is served by the application itself hence
+/// only same origin requests are allowed.
+#[derive(Debug, Clone)]
+struct CorsVariables {
+    allowed_origins: CorsOrigins,
+    allowed_methods: Vec<Method>,
+    allowed_headers: Vec<HeaderName>,
+    exposed_headers: Vec<HeaderName>,
+    allow_credentials: bool,
+    max_age: Duration,
 }
 
-/// Database configuration with connection string and database name
+/// Rate limit configuration variables
+///
+/// Each group of routes has its own limit, guest routes are limited
+/// by client IP and authenticated routes by user.
+#[derive(Debug, Clone)]
+struct RateLimitVariables {
+    enabled: bool,
+    store: RateLimitStoreBackend,
+    trust_proxy_headers: bool,
+    login: RateLimit,
+    register: RateLimit,
+    publish_post: RateLimit,
+    guest: RateLimit,
+    user: RateLimit,
+}
+
+/// Metrics configuration variables
+///
+/// The `/metrics` endpoint is served on its own port when defined, so that
+/// it can be kept private, and it can be protected by a bearer token.
+#[derive(Debug, Clone)]
+struct MetricsVariables {
+    enabled: bool,
+    port: Option<u16>,
+    token: Option<String>,
+}
+
+/// OpenTelemetry configuration variables
+///
+/// The spans of the application are exported to the OTLP collector
+/// and they join the traces propagated with the W3C `traceparent` header.
+#[derive(Debug, Clone)]
+struct TelemetryVariables {
+    otlp_endpoint: Option<String>,
+    service_name: String,
+    sample_ratio: f64,
+}
+
+/// Database configuration with backend, connection string and database name
+///
+/// The connection string and the database name are used only by the
+/// MongoDB backend.
+///
+/// The retention is the number of days after which the soft deleted
+/// documents are permanently deleted
 #[derive(Debug, Clone)]
 struct DatabaseVariables {
+    backend: DatabaseBackend,
     connection_string: String,
     db_name: String,
+    soft_delete_retention_days: u32,
 }
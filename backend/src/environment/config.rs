@@ -0,0 +1,492 @@
+use std::path::Path;
+
+use serde::{Deserialize, Deserializer};
+
+use crate::error::{ConfigError, ConfigResult};
+
+/// Raw application configuration before validation.
+///
+/// It is loaded in layers, each one overriding the values of the previous:
+///
+/// - TOML configuration file
+/// - environment variables
+/// - command line flags
+///
+/// Every value is optional and kept as string, the `EnvironmentService`
+/// validates the merged configuration in a single pass.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AppConfig {
+    pub server: ServerConfig,
+    pub tls: TlsConfig,
+    pub logging: LoggingConfig,
+    pub authentication: AuthenticationConfig,
+    pub database: DatabaseConfig,
+    pub storage: ObjectStorageConfig,
+    pub frontend: FrontendConfig,
+    pub cors: CorsConfig,
+    pub rate_limit: RateLimitConfig,
+    pub metrics: MetricsConfig,
+    pub telemetry: TelemetryConfig,
+}
+
+/// `[server]` section of the configuration
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    #[serde(deserialize_with = "scalar")]
+    pub host: Option<String>,
+    #[serde(deserialize_with = "scalar")]
+    pub port: Option<String>,
+    #[serde(deserialize_with = "scalar")]
+    pub shutdown_timeout_seconds: Option<String>,
+}
+
+/// `[tls]` section of the configuration
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TlsConfig {
+    #[serde(deserialize_with = "scalar")]
+    pub cert_path: Option<String>,
+    #[serde(deserialize_with = "scalar")]
+    pub key_path: Option<String>,
+    #[serde(deserialize_with = "scalar")]
+    pub redirect_http_port: Option<String>,
+    #[serde(deserialize_with = "scalar")]
+    pub reload_interval_seconds: Option<String>,
+}
+
+/// `[logging]` section of the configuration
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoggingConfig {
+    #[serde(deserialize_with = "scalar")]
+    pub level: Option<String>,
+    #[serde(deserialize_with = "scalar")]
+    pub include_headers: Option<String>,
+    #[serde(deserialize_with = "scalar")]
+    pub format: Option<String>,
+    #[serde(deserialize_with = "scalar")]
+    pub directory: Option<String>,
+    #[serde(deserialize_with = "scalar")]
+    pub rotation: Option<String>,
+    #[serde(deserialize_with = "scalar")]
+    pub max_files: Option<String>,
+}
+
+/// `[authentication]` section of the configuration
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthenticationConfig {
+    #[serde(deserialize_with = "scalar")]
+    pub jwt_secret: Option<String>,
+    #[serde(deserialize_with = "scalar")]
+    pub jwt_expiration: Option<String>,
+    #[serde(deserialize_with = "scalar")]
+    pub max_session_age: Option<String>,
+}
+
+/// `[database]` section of the configuration
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseConfig {
+    #[serde(deserialize_with = "scalar")]
+    pub backend: Option<String>,
+    #[serde(deserialize_with = "scalar")]
+    pub connection_string: Option<String>,
+    #[serde(deserialize_with = "scalar")]
+    pub db_name: Option<String>,
+    #[serde(deserialize_with = "scalar")]
+    pub soft_delete_retention_days: Option<String>,
+}
+
+/// `[storage]` section of the configuration
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ObjectStorageConfig {
+    #[serde(deserialize_with = "scalar")]
+    pub backend: Option<String>,
+    #[serde(deserialize_with = "scalar")]
+    pub prefix_path: Option<String>,
+}
+
+/// `[frontend]` section of the configuration
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FrontendConfig {
+    #[serde(deserialize_with = "scalar")]
+    pub mode: Option<String>,
+    #[serde(deserialize_with = "scalar")]
+    pub origin: Option<String>,
+    #[serde(deserialize_with = "scalar")]
+    pub public_url: Option<String>,
+}
+
+/// `[cors]` section of the configuration, lists are comma separated
+/// strings or TOML arrays
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CorsConfig {
+    #[serde(deserialize_with = "scalar")]
+    pub allowed_origins: Option<String>,
+    #[serde(deserialize_with = "scalar")]
+    pub allowed_methods: Option<String>,
+    #[serde(deserialize_with = "scalar")]
+    pub allowed_headers: Option<String>,
+    #[serde(deserialize_with = "scalar")]
+    pub exposed_headers: Option<String>,
+    #[serde(deserialize_with = "scalar")]
+    pub allow_credentials: Option<String>,
+    #[serde(deserialize_with = "scalar")]
+    pub max_age_seconds: Option<String>,
+}
+
+/// `[rate_limit]` section of the configuration, limits are
+/// written as `<requests>/<seconds>`
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RateLimitConfig {
+    #[serde(deserialize_with = "scalar")]
+    pub enabled: Option<String>,
+    #[serde(deserialize_with = "scalar")]
+    pub store: Option<String>,
+    #[serde(deserialize_with = "scalar")]
+    pub trust_proxy_headers: Option<String>,
+    #[serde(deserialize_with = "scalar")]
+    pub login: Option<String>,
+    #[serde(deserialize_with = "scalar")]
+    pub register: Option<String>,
+    #[serde(deserialize_with = "scalar")]
+    pub publish_post: Option<String>,
+    #[serde(deserialize_with = "scalar")]
+    pub guest: Option<String>,
+    #[serde(deserialize_with = "scalar")]
+    pub user: Option<String>,
+}
+
+/// `[metrics]` section of the configuration
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MetricsConfig {
+    #[serde(deserialize_with = "scalar")]
+    pub enabled: Option<String>,
+    #[serde(deserialize_with = "scalar")]
+    pub port: Option<String>,
+    #[serde(deserialize_with = "scalar")]
+    pub token: Option<String>,
+}
+
+/// `[telemetry]` section of the configuration
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TelemetryConfig {
+    #[serde(deserialize_with = "scalar")]
+    pub otlp_endpoint: Option<String>,
+    #[serde(deserialize_with = "scalar")]
+    pub service_name: Option<String>,
+    #[serde(deserialize_with = "scalar")]
+    pub sample_ratio: Option<String>,
+}
+
+/// Accepts strings, integers, booleans and lists in the TOML file so that
+/// `jwt_expiration = 3600` and `jwt_expiration = "3600"` are equivalent,
+/// lists are joined with commas as in the environment variables
+fn scalar<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Scalar {
+        String(String),
+        Integer(i64),
+        Boolean(bool),
+        List(Vec<String>),
+    }
+
+    Ok(
+        Option::<Scalar>::deserialize(deserializer)?.map(|value| match value {
+            Scalar::String(value) => value,
+            Scalar::Integer(value) => value.to_string(),
+            Scalar::Boolean(value) => value.to_string(),
+            Scalar::List(values) => values.join(","),
+        }),
+    )
+}
+
+impl AppConfig {
+    /// Reads the configuration from a TOML file
+    pub fn from_toml_file(path: &Path) -> ConfigResult<Self> {
+        let content = std::fs::read_to_string(path).map_err(|err| ConfigError::FileError {
+            path: path.display().to_string(),
+            reason: err.to_string(),
+        })?;
+        Self::from_toml_str(&content).map_err(|err| match err {
+            ConfigError::ParseError { reason, .. } => ConfigError::ParseError {
+                path: path.display().to_string(),
+                reason,
+            },
+            err => err,
+        })
+    }
+
+    /// Parses the configuration from the content of a TOML file
+    pub fn from_toml_str(content: &str) -> ConfigResult<Self> {
+        toml::from_str(content).map_err(|err| ConfigError::ParseError {
+            path: "<string>".into(),
+            reason: err.to_string(),
+        })
+    }
+
+    /// Reads the configuration from the system environment variables
+    pub fn from_env() -> Self {
+        Self::from_lookup(|name| std::env::var(name).ok())
+    }
+
+    /// Reads the configuration from the environment variables returned by `lookup`
+    pub fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Self {
+        AppConfig {
+            server: ServerConfig {
+                host: lookup("SERVER_HOST"),
+                port: lookup("SERVER_PORT"),
+                shutdown_timeout_seconds: lookup("SHUTDOWN_TIMEOUT_SECONDS"),
+            },
+            tls: TlsConfig {
+                cert_path: lookup("TLS_CERT_PATH"),
+                key_path: lookup("TLS_KEY_PATH"),
+                redirect_http_port: lookup("TLS_REDIRECT_HTTP_PORT"),
+                reload_interval_seconds: lookup("TLS_RELOAD_INTERVAL_SECONDS"),
+            },
+            logging: LoggingConfig {
+                level: lookup("LOGGING_LEVEL"),
+                include_headers: lookup("LOGGING_INCLUDE_HEADERS"),
+                format: lookup("LOGGING_FORMAT"),
+                directory: lookup("LOGGING_DIRECTORY"),
+                rotation: lookup("LOGGING_ROTATION"),
+                max_files: lookup("LOGGING_MAX_FILES"),
+            },
+            authentication: AuthenticationConfig {
+                jwt_secret: lookup("JWT_SECRET"),
+                jwt_expiration: lookup("JWT_EXPIRATION"),
+                max_session_age: lookup("JWT_MAX_SESSION_AGE"),
+            },
+            database: DatabaseConfig {
+                backend: lookup("DATABASE_BACKEND"),
+                connection_string: lookup("MONGODB_CONNECTION_STRING"),
+                db_name: lookup("MONGODB_DB_NAME"),
+                soft_delete_retention_days: lookup("SOFT_DELETE_RETENTION_DAYS"),
+            },
+            storage: ObjectStorageConfig {
+                backend: lookup("OBJECT_STORAGE_BACKEND"),
+                prefix_path: lookup("OBJECT_STORAGE_PREFIX_PATH"),
+            },
+            frontend: FrontendConfig {
+                mode: lookup("FRONTEND_MODE"),
+                origin: lookup("FRONTEND_ORIGIN"),
+                public_url: lookup("FRONTEND_PUBLIC_URL"),
+            },
+            cors: CorsConfig {
+                allowed_origins: lookup("CORS_ALLOWED_ORIGINS"),
+                allowed_methods: lookup("CORS_ALLOWED_METHODS"),
+                allowed_headers: lookup("CORS_ALLOWED_HEADERS"),
+                exposed_headers: lookup("CORS_EXPOSED_HEADERS"),
+                allow_credentials: lookup("CORS_ALLOW_CREDENTIALS"),
+                max_age_seconds: lookup("CORS_MAX_AGE_SECONDS"),
+            },
+            rate_limit: RateLimitConfig {
+                enabled: lookup("RATE_LIMIT_ENABLED"),
+                store: lookup("RATE_LIMIT_STORE"),
+                trust_proxy_headers: lookup("RATE_LIMIT_TRUST_PROXY_HEADERS"),
+                login: lookup("RATE_LIMIT_LOGIN"),
+                register: lookup("RATE_LIMIT_REGISTER"),
+                publish_post: lookup("RATE_LIMIT_PUBLISH_POST"),
+                guest: lookup("RATE_LIMIT_GUEST"),
+                user: lookup("RATE_LIMIT_USER"),
+            },
+            metrics: MetricsConfig {
+                enabled: lookup("METRICS_ENABLED"),
+                port: lookup("METRICS_PORT"),
+                token: lookup("METRICS_TOKEN"),
+            },
+            telemetry: TelemetryConfig {
+                otlp_endpoint: lookup("OTEL_EXPORTER_OTLP_ENDPOINT"),
+                service_name: lookup("OTEL_SERVICE_NAME"),
+                sample_ratio: lookup("OTEL_TRACES_SAMPLER_ARG"),
+            },
+        }
+    }
+
+    /// Returns the configuration where the values defined in `overrides`
+    /// replace the ones of this configuration
+    pub fn merge(self, overrides: AppConfig) -> Self {
+        AppConfig {
+            server: ServerConfig {
+                host: overrides.server.host.or(self.server.host),
+                port: overrides.server.port.or(self.server.port),
+                shutdown_timeout_seconds: overrides
+                    .server
+                    .shutdown_timeout_seconds
+                    .or(self.server.shutdown_timeout_seconds),
+            },
+            tls: TlsConfig {
+                cert_path: overrides.tls.cert_path.or(self.tls.cert_path),
+                key_path: overrides.tls.key_path.or(self.tls.key_path),
+                redirect_http_port: overrides
+                    .tls
+                    .redirect_http_port
+                    .or(self.tls.redirect_http_port),
+                reload_interval_seconds: overrides
+                    .tls
+                    .reload_interval_seconds
+                    .or(self.tls.reload_interval_seconds),
+            },
+            logging: LoggingConfig {
+                level: overrides.logging.level.or(self.logging.level),
+                include_headers: overrides
+                    .logging
+                    .include_headers
+                    .or(self.logging.include_headers),
+                format: overrides.logging.format.or(self.logging.format),
+                directory: overrides.logging.directory.or(self.logging.directory),
+                rotation: overrides.logging.rotation.or(self.logging.rotation),
+                max_files: overrides.logging.max_files.or(self.logging.max_files),
+            },
+            authentication: AuthenticationConfig {
+                jwt_secret: overrides
+                    .authentication
+                    .jwt_secret
+                    .or(self.authentication.jwt_secret),
+                jwt_expiration: overrides
+                    .authentication
+                    .jwt_expiration
+                    .or(self.authentication.jwt_expiration),
+                max_session_age: overrides
+                    .authentication
+                    .max_session_age
+                    .or(self.authentication.max_session_age),
+            },
+            database: DatabaseConfig {
+                backend: overrides.database.backend.or(self.database.backend),
+                connection_string: overrides
+                    .database
+                    .connection_string
+                    .or(self.database.connection_string),
+                db_name: overrides.database.db_name.or(self.database.db_name),
+                soft_delete_retention_days: overrides
+                    .database
+                    .soft_delete_retention_days
+                    .or(self.database.soft_delete_retention_days),
+            },
+            storage: ObjectStorageConfig {
+                backend: overrides.storage.backend.or(self.storage.backend),
+                prefix_path: overrides.storage.prefix_path.or(self.storage.prefix_path),
+            },
+            frontend: FrontendConfig {
+                mode: overrides.frontend.mode.or(self.frontend.mode),
+                origin: overrides.frontend.origin.or(self.frontend.origin),
+                public_url: overrides.frontend.public_url.or(self.frontend.public_url),
+            },
+            cors: CorsConfig {
+                allowed_origins: overrides.cors.allowed_origins.or(self.cors.allowed_origins),
+                allowed_methods: overrides.cors.allowed_methods.or(self.cors.allowed_methods),
+                allowed_headers: overrides.cors.allowed_headers.or(self.cors.allowed_headers),
+                exposed_headers: overrides.cors.exposed_headers.or(self.cors.exposed_headers),
+                allow_credentials: overrides
+                    .cors
+                    .allow_credentials
+                    .or(self.cors.allow_credentials),
+                max_age_seconds: overrides.cors.max_age_seconds.or(self.cors.max_age_seconds),
+            },
+            rate_limit: RateLimitConfig {
+                enabled: overrides.rate_limit.enabled.or(self.rate_limit.enabled),
+                store: overrides.rate_limit.store.or(self.rate_limit.store),
+                trust_proxy_headers: overrides
+                    .rate_limit
+                    .trust_proxy_headers
+                    .or(self.rate_limit.trust_proxy_headers),
+                login: overrides.rate_limit.login.or(self.rate_limit.login),
+                register: overrides.rate_limit.register.or(self.rate_limit.register),
+                publish_post: overrides
+                    .rate_limit
+                    .publish_post
+                    .or(self.rate_limit.publish_post),
+                guest: overrides.rate_limit.guest.or(self.rate_limit.guest),
+                user: overrides.rate_limit.user.or(self.rate_limit.user),
+            },
+            metrics: MetricsConfig {
+                enabled: overrides.metrics.enabled.or(self.metrics.enabled),
+                port: overrides.metrics.port.or(self.metrics.port),
+                token: overrides.metrics.token.or(self.metrics.token),
+            },
+            telemetry: TelemetryConfig {
+                otlp_endpoint: overrides
+                    .telemetry
+                    .otlp_endpoint
+                    .or(self.telemetry.otlp_endpoint),
+                service_name: overrides
+                    .telemetry
+                    .service_name
+                    .or(self.telemetry.service_name),
+                sample_ratio: overrides
+                    .telemetry
+                    .sample_ratio
+                    .or(self.telemetry.sample_ratio),
+            },
+        }
+    }
+
+    /// Loads the layered configuration: the optional TOML file,
+    /// then the environment variables and finally the command line overrides
+    pub fn load(config_file: Option<&Path>, cli_overrides: AppConfig) -> ConfigResult<Self> {
+        let file_config = match config_file {
+            Some(path) => Self::from_toml_file(path)?,
+            None => Self::default(),
+        };
+        Ok(file_config.merge(Self::from_env()).merge(cli_overrides))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::AppConfig;
+
+    #[test]
+    fn test_layers_override_previous_values() {
+        let file_config = AppConfig::from_toml_str(
+            r#"
+            [logging]
+            level = "DEBUG"
+            include_headers = true
+
+            [authentication]
+            jwt_secret = "file-secret"
+            jwt_expiration = 3600
+            "#,
+        )
+        .unwrap();
+        let env_config = AppConfig::from_lookup(|name| match name {
+            "JWT_SECRET" => Some("env-secret".into()),
+            "LOGGING_LEVEL" => Some("WARN".into()),
+            _ => None,
+        });
+        let mut cli_config = AppConfig::default();
+        cli_config.logging.level = Some("ERROR".into());
+
+        let config = file_config.merge(env_config).merge(cli_config);
+        assert_eq!(config.logging.level.as_deref(), Some("ERROR"));
+        assert_eq!(config.logging.include_headers.as_deref(), Some("true"));
+        assert_eq!(
+            config.authentication.jwt_secret.as_deref(),
+            Some("env-secret")
+        );
+        assert_eq!(
+            config.authentication.jwt_expiration.as_deref(),
+            Some("3600")
+        );
+    }
+
+    #[test]
+    fn test_unknown_settings_are_rejected() {
+        assert!(AppConfig::from_toml_str("[logging]\nlevl = \"DEBUG\"").is_err());
+    }
+}
@@ -1,161 +1,606 @@
-use std::str::FromStr;
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
 
+use axum::http::{HeaderName, HeaderValue, Method, Uri};
 use jsonwebtoken::{DecodingKey, EncodingKey};
 
 use crate::{
-    enums::{FrontendMode, ObjectSourceType},
+    enums::{
+        CorsOrigins, DatabaseBackend, FrontendMode, LogFormat, LogRotation, ObjectSourceType,
+        RateLimitRoute, RateLimitStoreBackend,
+    },
+    environment::config::{CorsConfig, RateLimitConfig},
     environment::{
-        AuthenticationVariables, DatabaseVariables, EnvironmentServiceTrait, FrontendVariables,
-        LoggingVariables, ObjectStorageVariables,
+        AppConfig, AuthenticationVariables, CorsVariables, DatabaseVariables,
+        EnvironmentServiceTrait, FrontendVariables, LoggingVariables, MetricsVariables,
+        ObjectStorageVariables, RateLimitVariables, ServerVariables, TelemetryVariables,
+        TlsVariables,
     },
+    error::{ConfigError, ConfigResult},
+    types::RateLimit,
 };
 
-/// Basic environment service that initialize all the application
-/// environment variables from the system environment variables.
+/// Basic environment service that holds the validated application configuration.
 ///
-/// Environment variables can be organized in the following groups:
+/// The configuration is loaded in layers by `AppConfig::load` (TOML file, then
+/// environment variables, then command line flags) and validated by `from_config`,
+/// that reports all the missing or invalid settings at once.
 ///
+/// Settings can be organized in the following groups, the name of the
+/// environment variable is reported in brackets:
+///
+/// - SERVER: address the server listens on and shutdown behavior
+/// - TLS: certificate used to serve HTTPS
 /// - LOGGING: used to setup logging
 /// - AUTHENTICATION: used to build authentication tokens for the user
 /// - DATABASE: used to access the database
 /// - STORAGE: used to access object storage service
 /// - FRONTEND: mode of frontend if integrated or external to the application
+/// - CORS: cross origin requests allowed by the application
+/// - RATE LIMIT: requests allowed to each client in a period of time
+/// - METRICS: Prometheus metrics endpoint
+/// - TELEMETRY: export of the traces to an OpenTelemetry collector
+///
+/// Required settings are:
 ///
-/// Required variables are:
+/// - authentication.jwt_secret (JWT_SECRET): string used as secret to sign jwt
+/// - database.connection_string (MONGODB_CONNECTION_STRING): authenticated connection string to the mongodb cluster, required only with mongodb backend
+/// - database.db_name (MONGODB_DB_NAME): name of mongodb database, required only with mongodb backend
+/// - storage.backend (OBJECT_STORAGE_BACKEND): which type of backend to use as object storage
+/// - storage.prefix_path (OBJECT_STORAGE_PREFIX_PATH): prefix path to store objects. In case of remote object storage it contains also the bucket name
+/// - frontend.mode (FRONTEND_MODE): whether to work with integrated frontend or as external service
 ///
-/// - LOGGING_LEVEL: the logging level to use in the application
-/// - DEPLOY_ENVIRONMENT: in which context the application is running, it is used to specify some resources according to it
-/// - JWT_SECRET: string used as secret to sign jwt
-/// - JWT_EXPIRATION: time in seconds of the duration of a jwt
-/// - MONGODB_CONNECTION_STRING: authenticated connection string to the mongodb cluster
-/// - MONGODB_DB_NAME: name of mongodb database to use as prefix by adding deploy environment
-/// - OBJECT_STORAGE_BACKEND: which type of backend to use as object storage
-/// - OBJECT_STORAGE_PREFIX_PATH: prefix path to store objects. In case of remote object storage it contains also the bucket name
-/// - FRONTEND_MODE: whether to work with integrated frontend or as external service
+/// Optional settings are:
+///
+/// - server.host (SERVER_HOST): address the server binds to, default 0.0.0.0
+/// - server.port (SERVER_PORT): port the server listens on, default 3000
+/// - server.shutdown_timeout_seconds (SHUTDOWN_TIMEOUT_SECONDS): seconds given to in flight requests on shutdown, default 30
+/// - tls.cert_path (TLS_CERT_PATH) and tls.key_path (TLS_KEY_PATH): PEM certificate chain and private key, when both are set the server uses HTTPS
+/// - tls.redirect_http_port (TLS_REDIRECT_HTTP_PORT): port of a listener redirecting HTTP requests to HTTPS, it requires the certificate
+/// - tls.reload_interval_seconds (TLS_RELOAD_INTERVAL_SECONDS): seconds between the checks for certificate changes, default 60
+/// - logging.level (LOGGING_LEVEL): the logging level to use in the application, default INFO
+/// - logging.include_headers (LOGGING_INCLUDE_HEADERS): whether to log request headers, default false
+/// - logging.format (LOGGING_FORMAT): `text` (default) or `json` with one object per line
+/// - logging.directory (LOGGING_DIRECTORY): directory of the log files, by default the logs are written to the standard output
+/// - logging.rotation (LOGGING_ROTATION): how often a new log file is created, `minutely`, `hourly`, `daily` (default) or `never`
+/// - logging.max_files (LOGGING_MAX_FILES): number of log files to keep, by default all of them
+/// - authentication.jwt_expiration (JWT_EXPIRATION): time in seconds of the duration of a jwt, default one day
+/// - authentication.max_session_age (JWT_MAX_SESSION_AGE): seconds after the login beyond which the tokens are not refreshed, default 30 days
+/// - database.backend (DATABASE_BACKEND): database used by the application, `mongodb` (default) or `memory`
+/// - database.soft_delete_retention_days (SOFT_DELETE_RETENTION_DAYS): days after which soft deleted documents are purged, default 30
+/// - frontend.origin (FRONTEND_ORIGIN): origin of the external frontend, default http://127.0.0.1:8000
+/// - frontend.public_url (FRONTEND_PUBLIC_URL): URL where the readers open the blog, used by the links of the feeds, default the frontend origin
+/// - cors.allowed_origins (CORS_ALLOWED_ORIGINS): comma separated origins or `*`, default the frontend origin in external mode and none in integrated mode
+/// - cors.allowed_methods (CORS_ALLOWED_METHODS): comma separated methods, default GET, POST, PUT, DELETE
+/// - cors.allowed_headers (CORS_ALLOWED_HEADERS): comma separated request headers, default authorization, content-type, if-match
+/// - cors.exposed_headers (CORS_EXPOSED_HEADERS): comma separated response headers readable by the frontend, default etag, x-request-id
+/// - cors.allow_credentials (CORS_ALLOW_CREDENTIALS): whether cookies and authorization are allowed, default false
+/// - cors.max_age_seconds (CORS_MAX_AGE_SECONDS): seconds the preflight response can be cached, default 3600
+/// - rate_limit.enabled (RATE_LIMIT_ENABLED): whether the requests are rate limited, default true
+/// - rate_limit.store (RATE_LIMIT_STORE): where the buckets are kept, `memory` (default) or `database` to share them among instances
+/// - rate_limit.trust_proxy_headers (RATE_LIMIT_TRUST_PROXY_HEADERS): whether the client IP is read from `X-Real-IP`, default false
+/// - rate_limit.login (RATE_LIMIT_LOGIN): login requests per client IP, default 10/60
+/// - rate_limit.register (RATE_LIMIT_REGISTER): register requests per client IP, default 5/3600
+/// - rate_limit.publish_post (RATE_LIMIT_PUBLISH_POST): published posts per user, default 30/3600
+/// - rate_limit.guest (RATE_LIMIT_GUEST): requests to the other guest routes per client IP, default 60/60
+/// - rate_limit.user (RATE_LIMIT_USER): requests to the other authenticated routes per user, default 600/60
+/// - metrics.enabled (METRICS_ENABLED): whether the `/metrics` endpoint is served, default true
+/// - metrics.port (METRICS_PORT): port of a dedicated listener for `/metrics`, by default it is served with the application
+/// - metrics.token (METRICS_TOKEN): bearer token required to read the metrics, by default they are public
+/// - telemetry.otlp_endpoint (OTEL_EXPORTER_OTLP_ENDPOINT): base URL of the OTLP/HTTP collector like http://localhost:4318, by default traces are not exported
+/// - telemetry.service_name (OTEL_SERVICE_NAME): service name of the exported spans, default backend
+/// - telemetry.sample_ratio (OTEL_TRACES_SAMPLER_ARG): ratio between 0 and 1 of the sampled traces, default 1
 #[derive(Clone)]
 pub struct EnvironmentService {
+    server: ServerVariables,
+    tls: TlsVariables,
     logging: LoggingVariables,
     authentication: AuthenticationVariables,
     database: DatabaseVariables,
     storage: ObjectStorageVariables,
     frontend: FrontendVariables,
+    cors: CorsVariables,
+    rate_limit: RateLimitVariables,
+    metrics: MetricsVariables,
+    telemetry: TelemetryVariables,
 }
 
-impl Default for EnvironmentService {
-    fn default() -> Self {
-        Self::new()
-    }
+/// Collects the errors found while validating the configuration
+#[derive(Default)]
+struct Validator {
+    errors: Vec<String>,
 }
 
-impl EnvironmentService {
-    pub fn new() -> Self {
-        let deploy_environment = if cfg!(test) {
-            "test".into()
-        } else {
-            std::env::var("DEPLOY_ENVIRONMENT").expect("DEPLOY_ENVIRONMENT must be set")
-        };
-        EnvironmentService {
-            logging: Self::build_logging(),
-            authentication: Self::build_authentication(),
-            database: Self::build_database(&deploy_environment),
-            storage: Self::build_storage(),
-            frontend: Self::build_frontend(),
+impl Validator {
+    /// Returns the value of a required setting, recording an error when it is missing
+    fn required(&mut self, value: Option<String>, name: &str) -> Option<String> {
+        if value.is_none() {
+            self.errors.push(format!("{name} must be set"));
+        }
+        value
+    }
+
+    /// Parses the value of a setting, recording an error when it is not valid
+    fn parse<T>(
+        &mut self,
+        value: Option<String>,
+        name: &str,
+        parser: impl FnOnce(&str) -> Option<T>,
+    ) -> Option<T> {
+        let value = value?;
+        let parsed = parser(&value);
+        if parsed.is_none() {
+            self.errors
+                .push(format!("{name} is not valid. Got `{value}`"));
         }
+        parsed
     }
 
-    fn build_logging() -> LoggingVariables {
-        let level =
-            tracing::Level::from_str(&std::env::var("LOGGING_LEVEL").unwrap_or("INFO".into()))
-                .expect("Encountered an error while retrieving the logging level");
+    /// Parses each element of a comma separated setting
+    fn parse_list<T>(
+        &mut self,
+        value: Option<String>,
+        name: &str,
+        parser: impl Fn(&str) -> Option<T>,
+    ) -> Option<Vec<T>> {
+        self.parse(value, name, |s| {
+            s.split(',')
+                .map(str::trim)
+                .filter(|element| !element.is_empty())
+                .map(&parser)
+                .collect()
+        })
+    }
+}
+
+impl EnvironmentService {
+    /// Validates the configuration and builds the environment service.
+    ///
+    /// It is also used by tests to build the service from an in memory configuration.
+    pub fn from_config(config: AppConfig) -> ConfigResult<Self> {
+        let mut validator = Validator::default();
 
-        let include_headers = std::env::var("LOGGING_INCLUDE_HEADERS")
-            .unwrap_or("false".into())
-            .to_lowercase()
-            == "true";
+        let server = ServerVariables {
+            host: config.server.host.unwrap_or("0.0.0.0".into()),
+            port: validator
+                .parse(config.server.port, "server.port (SERVER_PORT)", |s| {
+                    u16::from_str(s).ok()
+                })
+                .unwrap_or(3000),
+            shutdown_timeout: validator
+                .parse(
+                    config.server.shutdown_timeout_seconds,
+                    "server.shutdown_timeout_seconds (SHUTDOWN_TIMEOUT_SECONDS)",
+                    |s| u64::from_str(s).ok().map(Duration::from_secs),
+                )
+                .unwrap_or(Duration::from_secs(30)),
+        };
 
-        LoggingVariables {
-            level,
-            include_headers,
+        let cert_and_key_paths = match (config.tls.cert_path, config.tls.key_path) {
+            (Some(cert_path), Some(key_path)) => {
+                Some((PathBuf::from(cert_path), PathBuf::from(key_path)))
+            }
+            (None, None) => None,
+            _ => {
+                validator.errors.push(
+                    "tls.cert_path (TLS_CERT_PATH) and tls.key_path (TLS_KEY_PATH) must be set together"
+                        .into(),
+                );
+                None
+            }
+        };
+        let redirect_http_port = validator.parse(
+            config.tls.redirect_http_port,
+            "tls.redirect_http_port (TLS_REDIRECT_HTTP_PORT)",
+            |s| u16::from_str(s).ok(),
+        );
+        if redirect_http_port.is_some() && cert_and_key_paths.is_none() {
+            validator.errors.push(
+                "tls.redirect_http_port (TLS_REDIRECT_HTTP_PORT) requires the TLS certificate"
+                    .into(),
+            );
         }
-    }
+        let tls = TlsVariables {
+            cert_and_key_paths,
+            redirect_http_port,
+            reload_interval: validator
+                .parse(
+                    config.tls.reload_interval_seconds,
+                    "tls.reload_interval_seconds (TLS_RELOAD_INTERVAL_SECONDS)",
+                    |s| {
+                        u64::from_str(s)
+                            .ok()
+                            .filter(|seconds| *seconds > 0)
+                            .map(Duration::from_secs)
+                    },
+                )
+                .unwrap_or(Duration::from_secs(60)),
+        };
 
-    fn build_authentication() -> AuthenticationVariables {
-        let secret = std::env::var("JWT_SECRET").expect("JWT_SECRET must be set");
-        let jwt_expiration = std::env::var("JWT_EXPIRATION").map_or(60 * 60 * 24, |s| {
-            usize::from_str(&s).expect("JWT_EXPIRATION is not valid")
-        });
+        let logging = LoggingVariables {
+            level: validator
+                .parse(config.logging.level, "logging.level (LOGGING_LEVEL)", |s| {
+                    tracing::Level::from_str(s).ok()
+                })
+                .unwrap_or(tracing::Level::INFO),
+            include_headers: validator
+                .parse(
+                    config.logging.include_headers,
+                    "logging.include_headers (LOGGING_INCLUDE_HEADERS)",
+                    |s| bool::from_str(&s.to_lowercase()).ok(),
+                )
+                .unwrap_or(false),
+            format: validator
+                .parse(
+                    config.logging.format,
+                    "logging.format (LOGGING_FORMAT)",
+                    |s| LogFormat::try_from(s).ok(),
+                )
+                .unwrap_or(LogFormat::Text),
+            directory: config.logging.directory.map(PathBuf::from),
+            rotation: validator
+                .parse(
+                    config.logging.rotation,
+                    "logging.rotation (LOGGING_ROTATION)",
+                    |s| LogRotation::try_from(s).ok(),
+                )
+                .unwrap_or(LogRotation::Daily),
+            max_files: validator.parse(
+                config.logging.max_files,
+                "logging.max_files (LOGGING_MAX_FILES)",
+                |s| usize::from_str(s).ok().filter(|max_files| *max_files > 0),
+            ),
+        };
 
-        AuthenticationVariables {
-            jwt_expiration,
+        let secret = validator
+            .required(
+                config.authentication.jwt_secret,
+                "authentication.jwt_secret (JWT_SECRET)",
+            )
+            .unwrap_or_default();
+        let authentication = AuthenticationVariables {
+            jwt_expiration: validator
+                .parse(
+                    config.authentication.jwt_expiration,
+                    "authentication.jwt_expiration (JWT_EXPIRATION)",
+                    |s| usize::from_str(s).ok(),
+                )
+                .unwrap_or(60 * 60 * 24),
+            max_session_age: validator
+                .parse(
+                    config.authentication.max_session_age,
+                    "authentication.max_session_age (JWT_MAX_SESSION_AGE)",
+                    |s| usize::from_str(s).ok(),
+                )
+                .unwrap_or(60 * 60 * 24 * 30),
             jwt_encoding: EncodingKey::from_secret(secret.as_bytes()),
             jwt_decoding: DecodingKey::from_secret(secret.as_bytes()),
-        }
-    }
+        };
 
-    fn build_database(deploy_environment: &str) -> DatabaseVariables {
-        let (connection_string, db_name) = if cfg!(test) {
-            let db_name = format!("application-database-{}", deploy_environment);
-            (
-                format!(
-                    "mongodb://localhost:27117/{}?replicaSet=rs0&directConnection=true",
-                    db_name
-                ),
-                db_name,
+        let backend = validator
+            .parse(
+                config.database.backend,
+                "database.backend (DATABASE_BACKEND)",
+                |s| DatabaseBackend::try_from(s).ok(),
             )
+            .unwrap_or(DatabaseBackend::MongoDB);
+        let (connection_string, db_name) = if backend == DatabaseBackend::Memory {
+            // the in memory database does not need any connection
+            (String::new(), String::new())
         } else {
             (
-                std::env::var("MONGODB_CONNECTION_STRING")
-                    .expect("MONGODB_CONNECTION_STRING must be set"),
-                std::env::var("MONGODB_DB_NAME").expect("MONGODB_DB_NAME must be set"),
+                validator
+                    .required(
+                        config.database.connection_string,
+                        "database.connection_string (MONGODB_CONNECTION_STRING)",
+                    )
+                    .unwrap_or_default(),
+                validator
+                    .required(
+                        config.database.db_name,
+                        "database.db_name (MONGODB_DB_NAME)",
+                    )
+                    .unwrap_or_default(),
             )
         };
-
-        DatabaseVariables {
+        let database = DatabaseVariables {
+            backend,
             connection_string,
             db_name,
+            soft_delete_retention_days: validator
+                .parse(
+                    config.database.soft_delete_retention_days,
+                    "database.soft_delete_retention_days (SOFT_DELETE_RETENTION_DAYS)",
+                    |s| u32::from_str(s).ok(),
+                )
+                .unwrap_or(30),
+        };
+
+        let storage_backend = validator.required(
+            config.storage.backend,
+            "storage.backend (OBJECT_STORAGE_BACKEND)",
+        );
+        let storage = ObjectStorageVariables {
+            storage_backend: validator
+                .parse(
+                    storage_backend,
+                    "storage.backend (OBJECT_STORAGE_BACKEND)",
+                    |s| ObjectSourceType::try_from(s).ok(),
+                )
+                .unwrap_or(ObjectSourceType::LocalFileSystem),
+            prefix_path: validator
+                .required(
+                    config.storage.prefix_path,
+                    "storage.prefix_path (OBJECT_STORAGE_PREFIX_PATH)",
+                )
+                .unwrap_or_default(),
+        };
+
+        let origin = config
+            .frontend
+            .origin
+            .unwrap_or("http://127.0.0.1:8000".into());
+        let frontend_origin = validator.parse(
+            Some(origin.clone()),
+            "frontend.origin (FRONTEND_ORIGIN)",
+            |s| HeaderValue::from_str(s.trim_end_matches('/')).ok(),
+        );
+        let frontend_mode =
+            validator.required(config.frontend.mode, "frontend.mode (FRONTEND_MODE)");
+        let frontend = FrontendVariables {
+            frontend_mode: validator
+                .parse(frontend_mode, "frontend.mode (FRONTEND_MODE)", |s| {
+                    FrontendMode::try_from(s).ok()
+                })
+                .unwrap_or(FrontendMode::External),
+            public_url: validator
+                .parse(
+                    Some(config.frontend.public_url.unwrap_or(origin)),
+                    "frontend.public_url (FRONTEND_PUBLIC_URL)",
+                    |s| {
+                        (s.starts_with("http://") || s.starts_with("https://"))
+                            .then(|| s.trim_end_matches('/').to_string())
+                    },
+                )
+                .unwrap_or_default(),
+        };
+
+        let cors = Self::build_cors(&mut validator, config.cors, frontend_origin, &frontend);
+        let rate_limit = Self::build_rate_limit(&mut validator, config.rate_limit);
+
+        let metrics = MetricsVariables {
+            enabled: validator
+                .parse(
+                    config.metrics.enabled,
+                    "metrics.enabled (METRICS_ENABLED)",
+                    |s| bool::from_str(&s.to_lowercase()).ok(),
+                )
+                .unwrap_or(true),
+            port: validator.parse(config.metrics.port, "metrics.port (METRICS_PORT)", |s| {
+                u16::from_str(s).ok()
+            }),
+            token: config.metrics.token.filter(|token| !token.is_empty()),
+        };
+        if metrics.port.is_some() && metrics.port == Some(server.port) {
+            validator.errors.push(
+                "metrics.port (METRICS_PORT) must be different from server.port (SERVER_PORT)"
+                    .into(),
+            );
+        }
+
+        let telemetry = TelemetryVariables {
+            otlp_endpoint: validator.parse(
+                config
+                    .telemetry
+                    .otlp_endpoint
+                    .filter(|endpoint| !endpoint.is_empty()),
+                "telemetry.otlp_endpoint (OTEL_EXPORTER_OTLP_ENDPOINT)",
+                |s| {
+                    let uri = Uri::from_str(s).ok()?;
+                    matches!(uri.scheme_str(), Some("http" | "https")).then(|| s.to_string())
+                },
+            ),
+            service_name: config
+                .telemetry
+                .service_name
+                .filter(|name| !name.is_empty())
+                .unwrap_or("backend".into()),
+            sample_ratio: validator
+                .parse(
+                    config.telemetry.sample_ratio,
+                    "telemetry.sample_ratio (OTEL_TRACES_SAMPLER_ARG)",
+                    |s| {
+                        f64::from_str(s)
+                            .ok()
+                            .filter(|ratio| (0.0..=1.0).contains(ratio))
+                    },
+                )
+                .unwrap_or(1.0),
+        };
+
+        if !validator.errors.is_empty() {
+            return Err(ConfigError::InvalidSettings(validator.errors));
         }
+        Ok(EnvironmentService {
+            server,
+            tls,
+            logging,
+            authentication,
+            database,
+            storage,
+            frontend,
+            cors,
+            rate_limit,
+            metrics,
+            telemetry,
+        })
     }
 
-    fn build_storage() -> ObjectStorageVariables {
-        if cfg!(test) {
-            ObjectStorageVariables {
-                storage_backend: ObjectSourceType::LocalFileSystem,
-                prefix_path: "../app-objects".into(),
-            }
-        } else {
-            let storage_backend_var = std::env::var("OBJECT_STORAGE_BACKEND")
-                .expect("OBJECT_STORAGE_BACKEND must be set.");
-            ObjectStorageVariables {
-                storage_backend: ObjectSourceType::try_from(storage_backend_var.as_str())
-                    .unwrap_or_else(|_| {
-                        panic!(
-                            "Invalid OBJECT_STORAGE_BACKEND value. Got {}",
-                            storage_backend_var
-                        )
-                    }),
-                prefix_path: std::env::var("OBJECT_STORAGE_PREFIX_PATH")
-                    .expect("OBJECT_STORAGE_PREFIX_PATH must be set"),
-            }
+    fn build_rate_limit(validator: &mut Validator, config: RateLimitConfig) -> RateLimitVariables {
+        let mut limit = |value: Option<String>, name: &str, default: &str| {
+            validator
+                .parse(Some(value.unwrap_or(default.into())), name, |s| {
+                    RateLimit::from_str(s).ok()
+                })
+                .unwrap_or(RateLimit::from_str(default).expect("default rate limit is valid"))
+        };
+        let login = limit(config.login, "rate_limit.login (RATE_LIMIT_LOGIN)", "10/60");
+        let register = limit(
+            config.register,
+            "rate_limit.register (RATE_LIMIT_REGISTER)",
+            "5/3600",
+        );
+        let publish_post = limit(
+            config.publish_post,
+            "rate_limit.publish_post (RATE_LIMIT_PUBLISH_POST)",
+            "30/3600",
+        );
+        let guest = limit(config.guest, "rate_limit.guest (RATE_LIMIT_GUEST)", "60/60");
+        let user = limit(config.user, "rate_limit.user (RATE_LIMIT_USER)", "600/60");
+
+        RateLimitVariables {
+            enabled: validator
+                .parse(
+                    config.enabled,
+                    "rate_limit.enabled (RATE_LIMIT_ENABLED)",
+                    |s| bool::from_str(&s.to_lowercase()).ok(),
+                )
+                .unwrap_or(true),
+            store: validator
+                .parse(config.store, "rate_limit.store (RATE_LIMIT_STORE)", |s| {
+                    RateLimitStoreBackend::try_from(s).ok()
+                })
+                .unwrap_or(RateLimitStoreBackend::Memory),
+            trust_proxy_headers: validator
+                .parse(
+                    config.trust_proxy_headers,
+                    "rate_limit.trust_proxy_headers (RATE_LIMIT_TRUST_PROXY_HEADERS)",
+                    |s| bool::from_str(&s.to_lowercase()).ok(),
+                )
+                .unwrap_or(false),
+            login,
+            register,
+            publish_post,
+            guest,
+            user,
         }
     }
 
-    fn build_frontend() -> FrontendVariables {
-        if cfg!(test) {
-            FrontendVariables {
-                frontend_mode: FrontendMode::External,
-            }
-        } else {
-            let frontend_mode = std::env::var("FRONTEND_MODE").expect("FRONTEND_MODE must be set.");
-            FrontendVariables {
-                frontend_mode: FrontendMode::try_from(frontend_mode.as_str()).unwrap_or_else(
-                    |_| panic!("Invalid FRONTEND_MODE value. Got `{frontend_mode}`"),
-                ),
-            }
+    fn build_cors(
+        validator: &mut Validator,
+        config: CorsConfig,
+        frontend_origin: Option<HeaderValue>,
+        frontend: &FrontendVariables,
+    ) -> CorsVariables {
+        let allowed_origins = match config.allowed_origins {
+            Some(origins) if origins.trim() == "*" => CorsOrigins::Any,
+            Some(origins) => CorsOrigins::List(
+                validator
+                    .parse_list(
+                        Some(origins),
+                        "cors.allowed_origins (CORS_ALLOWED_ORIGINS)",
+                        |s| HeaderValue::from_str(s).ok(),
+                    )
+                    .unwrap_or_default(),
+            ),
+            None => match frontend.frontend_mode {
+                FrontendMode::External => CorsOrigins::List(frontend_origin.into_iter().collect()),
+                FrontendMode::Integrated(_) => CorsOrigins::List(Vec::new()),
+            },
+        };
+        let allow_credentials = validator
+            .parse(
+                config.allow_credentials,
+                "cors.allow_credentials (CORS_ALLOW_CREDENTIALS)",
+                |s| bool::from_str(&s.to_lowercase()).ok(),
+            )
+            .unwrap_or(false);
+        // browsers reject credentials with wildcard origins
+        if allow_credentials && allowed_origins == CorsOrigins::Any {
+            validator.errors.push(
+                "cors.allow_credentials (CORS_ALLOW_CREDENTIALS) cannot be used with any origin"
+                    .into(),
+            );
+        }
+
+        CorsVariables {
+            allowed_origins,
+            allowed_methods: validator
+                .parse_list(
+                    Some(
+                        config
+                            .allowed_methods
+                            .unwrap_or("GET,POST,PUT,DELETE".into()),
+                    ),
+                    "cors.allowed_methods (CORS_ALLOWED_METHODS)",
+                    |s| Method::from_str(&s.to_uppercase()).ok(),
+                )
+                .unwrap_or_default(),
+            allowed_headers: validator
+                .parse_list(
+                    Some(
+                        config
+                            .allowed_headers
+                            .unwrap_or("authorization,content-type,if-match".into()),
+                    ),
+                    "cors.allowed_headers (CORS_ALLOWED_HEADERS)",
+                    |s| HeaderName::from_str(s).ok(),
+                )
+                .unwrap_or_default(),
+            exposed_headers: validator
+                .parse_list(
+                    Some(config.exposed_headers.unwrap_or("etag,x-request-id".into())),
+                    "cors.exposed_headers (CORS_EXPOSED_HEADERS)",
+                    |s| HeaderName::from_str(s).ok(),
+                )
+                .unwrap_or_default(),
+            allow_credentials,
+            max_age: validator
+                .parse(
+                    config.max_age_seconds,
+                    "cors.max_age_seconds (CORS_MAX_AGE_SECONDS)",
+                    |s| u64::from_str(s).ok().map(Duration::from_secs),
+                )
+                .unwrap_or(Duration::from_secs(60 * 60)),
         }
     }
 }
 
 impl EnvironmentServiceTrait for EnvironmentService {
+    fn get_server_host(&self) -> &str {
+        &self.server.host
+    }
+
+    fn get_server_port(&self) -> u16 {
+        self.server.port
+    }
+
+    fn get_server_shutdown_timeout(&self) -> Duration {
+        self.server.shutdown_timeout
+    }
+
+    fn get_tls_cert_and_key_paths(&self) -> Option<(&Path, &Path)> {
+        self.tls
+            .cert_and_key_paths
+            .as_ref()
+            .map(|(cert_path, key_path)| (cert_path.as_path(), key_path.as_path()))
+    }
+
+    fn get_tls_redirect_http_port(&self) -> Option<u16> {
+        self.tls.redirect_http_port
+    }
+
+    fn get_tls_reload_interval(&self) -> Duration {
+        self.tls.reload_interval
+    }
+
+    fn get_database_backend(&self) -> &DatabaseBackend {
+        &self.database.backend
+    }
+
     fn get_database_connection_string(&self) -> &str {
         &self.database.connection_string
     }
@@ -164,10 +609,18 @@ impl EnvironmentServiceTrait for EnvironmentService {
         &self.database.db_name
     }
 
+    fn get_database_soft_delete_retention_days(&self) -> u32 {
+        self.database.soft_delete_retention_days
+    }
+
     fn get_authentication_jwt_expiration(&self) -> usize {
         self.authentication.jwt_expiration
     }
 
+    fn get_authentication_max_session_age(&self) -> usize {
+        self.authentication.max_session_age
+    }
+
     fn get_authentication_jwt_encoding(&self) -> &EncodingKey {
         &self.authentication.jwt_encoding
     }
@@ -184,6 +637,22 @@ impl EnvironmentServiceTrait for EnvironmentService {
         self.logging.level
     }
 
+    fn get_logging_format(&self) -> &LogFormat {
+        &self.logging.format
+    }
+
+    fn get_logging_directory(&self) -> Option<&Path> {
+        self.logging.directory.as_deref()
+    }
+
+    fn get_logging_rotation(&self) -> &LogRotation {
+        &self.logging.rotation
+    }
+
+    fn get_logging_max_files(&self) -> Option<usize> {
+        self.logging.max_files
+    }
+
     fn get_object_storage_source_type(&self) -> &ObjectSourceType {
         &self.storage.storage_backend
     }
@@ -195,4 +664,142 @@ impl EnvironmentServiceTrait for EnvironmentService {
     fn get_frontend_mode(&self) -> &FrontendMode {
         &self.frontend.frontend_mode
     }
+
+    fn get_frontend_public_url(&self) -> &str {
+        &self.frontend.public_url
+    }
+
+    fn get_cors_allowed_origins(&self) -> &CorsOrigins {
+        &self.cors.allowed_origins
+    }
+
+    fn get_cors_allowed_methods(&self) -> &[Method] {
+        &self.cors.allowed_methods
+    }
+
+    fn get_cors_allowed_headers(&self) -> &[HeaderName] {
+        &self.cors.allowed_headers
+    }
+
+    fn get_cors_exposed_headers(&self) -> &[HeaderName] {
+        &self.cors.exposed_headers
+    }
+
+    fn get_cors_allow_credentials(&self) -> bool {
+        self.cors.allow_credentials
+    }
+
+    fn get_cors_max_age(&self) -> Duration {
+        self.cors.max_age
+    }
+
+    fn get_rate_limit_enabled(&self) -> bool {
+        self.rate_limit.enabled
+    }
+
+    fn get_rate_limit_store(&self) -> &RateLimitStoreBackend {
+        &self.rate_limit.store
+    }
+
+    fn get_rate_limit_trust_proxy_headers(&self) -> bool {
+        self.rate_limit.trust_proxy_headers
+    }
+
+    fn get_metrics_enabled(&self) -> bool {
+        self.metrics.enabled
+    }
+
+    fn get_metrics_port(&self) -> Option<u16> {
+        self.metrics.port
+    }
+
+    fn get_metrics_token(&self) -> Option<&str> {
+        self.metrics.token.as_deref()
+    }
+
+    fn get_telemetry_otlp_endpoint(&self) -> Option<&str> {
+        self.telemetry.otlp_endpoint.as_deref()
+    }
+
+    fn get_telemetry_service_name(&self) -> &str {
+        &self.telemetry.service_name
+    }
+
+    fn get_telemetry_sample_ratio(&self) -> f64 {
+        self.telemetry.sample_ratio
+    }
+
+    fn get_rate_limit(&self, route: RateLimitRoute) -> &RateLimit {
+        match route {
+            RateLimitRoute::Login => &self.rate_limit.login,
+            RateLimitRoute::Register => &self.rate_limit.register,
+            RateLimitRoute::PublishPost => &self.rate_limit.publish_post,
+            RateLimitRoute::Guest => &self.rate_limit.guest,
+            RateLimitRoute::User => &self.rate_limit.user,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ConfigError, EnvironmentServiceTrait,
+        enums::DatabaseBackend,
+        environment::{AppConfig, EnvironmentService},
+    };
+
+    #[test]
+    fn test_memory_backend_does_not_require_mongodb() {
+        let config = AppConfig::from_toml_str(
+            r#"
+            [authentication]
+            jwt_secret = "secret"
+
+            [database]
+            backend = "memory"
+
+            [storage]
+            backend = "LocalFileSystem"
+            prefix_path = "./data"
+
+            [frontend]
+            mode = "external"
+            "#,
+        )
+        .unwrap();
+        let environment_service = EnvironmentService::from_config(config).unwrap();
+        assert_eq!(
+            environment_service.get_database_backend(),
+            &DatabaseBackend::Memory
+        );
+        assert_eq!(
+            environment_service.get_database_soft_delete_retention_days(),
+            30
+        );
+        assert_eq!(
+            environment_service.get_frontend_public_url(),
+            "http://127.0.0.1:8000"
+        );
+    }
+
+    #[test]
+    fn test_all_invalid_settings_are_reported() {
+        let mut config = AppConfig::default();
+        config.logging.level = Some("LOUD".into());
+        config.authentication.jwt_expiration = Some("tomorrow".into());
+        config.server.port = Some("70000".into());
+        config.tls.cert_path = Some("cert.pem".into());
+
+        let Err(ConfigError::InvalidSettings(errors)) = EnvironmentService::from_config(config)
+        else {
+            panic!("configuration should not be valid");
+        };
+        // invalid port, tls key, level and expiration, missing secret, connection
+        // string, db name, storage backend, storage prefix path and frontend mode
+        assert_eq!(errors.len(), 10, "{errors:?}");
+        assert!(errors.iter().any(|error| error.contains("TLS_KEY_PATH")));
+        assert!(errors.iter().any(|error| error.contains("SERVER_PORT")));
+        assert!(errors.iter().any(|error| error.contains("LOGGING_LEVEL")));
+        assert!(errors.iter().any(|error| error.contains("JWT_SECRET")));
+    }
 }
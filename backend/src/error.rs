@@ -10,24 +10,34 @@
 //!
 //! `AuthError` is a variant of `ServiceAppError` specific for the authorization.
 //!
+//! `ConfigError` is returned when the application configuration cannot be loaded.
+//!
 //! Facades are responsible to translate `ServiceAppError` objects to `AppError` ones
 //! according to the specific situation.
 //!
 //! Each enum variant has an associated Result type for syntactic sugar.  
 
-use axum::{extract::rejection::JsonRejection, http::StatusCode, response::IntoResponse};
+use axum::{
+    extract::rejection::JsonRejection,
+    http::{HeaderValue, StatusCode, header},
+    response::IntoResponse,
+};
 use bson::oid::ObjectId;
-use serde::Serialize;
 use thiserror::Error;
 use tracing::error;
 
-use crate::types::AppJson;
+use crate::{
+    middleware::current_request_id, service::database::document::ExpectedVersion, types::AppJson,
+};
+
+pub use api_types::error::ErrorResponse;
 
 pub type AppResult<T> = Result<AppJson<T>, AppError>;
 pub type FacadeResult<T> = Result<T, AppError>;
 pub type ServiceResult<T> = Result<T, ServiceAppError>;
 pub type AuthResult<T> = Result<T, AuthError>;
 pub type DatabaseResult<T> = Result<T, DatabaseError>;
+pub type ConfigResult<T> = Result<T, ConfigError>;
 
 /// Enumeration of different error typologies that the application
 /// can return to the client.
@@ -56,15 +66,30 @@ pub enum AppError {
     /// Equivalent to 400
     #[error("Invalid request: {0}")]
     InvalidRequest(String),
+    /// Equivalent to 409
+    #[error("Version conflict: {0}")]
+    VersionConflict(String),
+    /// Equivalent to 409, the resource conflicts with an existing one
+    #[error("The resource already exists: {0}")]
+    AlreadyExists(String),
+    /// Equivalent to 428, the request must be conditional
+    #[error("Precondition required: {0}")]
+    PreconditionRequired(String),
+    /// Equivalent to 429, the response contains the `Retry-After` header
+    /// with the seconds to wait before the next request
+    #[error("Too many requests, retry after {retry_after} seconds")]
+    TooManyRequests { retry_after: u64 },
 }
 
 impl IntoResponse for AppError {
     fn into_response(self) -> axum::response::Response {
-        // Create a temporary support struct to be passed to AppJson
-        #[derive(Serialize)]
-        struct ErrorResponse {
-            message: String,
-        }
+        let request_id = current_request_id();
+
+        // The rate limited responses tell the client when to retry
+        let retry_after = match self {
+            AppError::TooManyRequests { retry_after } => Some(retry_after),
+            _ => None,
+        };
 
         // For each enum variant we return the message and associated status code
         // Note that for some variants we ignore the message because we don't want
@@ -81,7 +106,8 @@ impl IntoResponse for AppError {
                 error!(
                     msg,
                     source_error = source_error.to_string(),
-                    error_type = "InternalServerError"
+                    error_type = "InternalServerError",
+                    request_id = request_id.as_deref().unwrap_or_default(),
                 );
                 (
                     StatusCode::INTERNAL_SERVER_ERROR,
@@ -91,9 +117,29 @@ impl IntoResponse for AppError {
             AppError::AccessControlError(message) => (StatusCode::FORBIDDEN, message),
             AppError::DoesNotExist(message) => (StatusCode::NOT_FOUND, message),
             AppError::InvalidRequest(message) => (StatusCode::BAD_REQUEST, message),
+            AppError::VersionConflict(message) => (StatusCode::CONFLICT, message),
+            AppError::AlreadyExists(message) => (StatusCode::CONFLICT, message),
+            AppError::PreconditionRequired(message) => (StatusCode::PRECONDITION_REQUIRED, message),
             AppError::AuthorizationError(auth_error) => auth_error.to_status_message(),
+            AppError::TooManyRequests { retry_after } => (
+                StatusCode::TOO_MANY_REQUESTS,
+                format!("Too many requests, retry after {retry_after} seconds"),
+            ),
         };
-        (status, AppJson(ErrorResponse { message })).into_response()
+        let mut response = (
+            status,
+            AppJson(ErrorResponse {
+                message,
+                request_id,
+            }),
+        )
+            .into_response();
+        if let Some(retry_after) = retry_after {
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(retry_after));
+        }
+        response
     }
 }
 
@@ -148,9 +194,13 @@ pub enum AuthError {
     /// Error in jwt creation, so it is internal
     #[error("Error in JWT creation")]
     TokenCreation,
-    /// Equivalent to 400
+    /// Equivalent to 401, the client has to authenticate again
     #[error("Invalid JWT")]
     InvalidToken,
+    /// Equivalent to 401, the session is older than its maximum age
+    /// and the user has to log in again
+    #[error("Session expired")]
+    SessionExpired,
     /// Equivalent to 400
     #[error("Invalid API Key")]
     InvalidApiKey,
@@ -173,7 +223,8 @@ impl AuthError {
                 StatusCode::INTERNAL_SERVER_ERROR,
                 "Token creation error".into(),
             ),
-            AuthError::InvalidToken => (StatusCode::BAD_REQUEST, "Invalid token".into()),
+            AuthError::InvalidToken => (StatusCode::UNAUTHORIZED, "Invalid token".into()),
+            AuthError::SessionExpired => (StatusCode::UNAUTHORIZED, "Session expired".into()),
         };
         (status, message)
     }
@@ -188,6 +239,9 @@ pub enum DatabaseError {
     TransactionError(String),
     #[error("Document has already an associated id")]
     DocumentHasAlreadyAnId,
+    /// When an inserted document has the same value of another one on a unique index
+    #[error("Document violates a unique index: {0}")]
+    DuplicateKey(String),
     #[error("Object id is invalid")]
     InvalidObjectId,
     #[error("Client is not connected to the cluster")]
@@ -200,4 +254,39 @@ pub enum DatabaseError {
     DatabaseServiceError(String),
     #[error("Error encountered during a database operation: {0}")]
     DatabaseOperationError(String),
+    /// When the document has been modified since the expected version was read
+    #[error("Document with id {id} has been modified, expected version {expected_version}")]
+    VersionConflict {
+        id: ObjectId,
+        expected_version: ExpectedVersion,
+    },
+    /// When a schema migration cannot be applied
+    #[error("Migration error: {0}")]
+    MigrationError(String),
+}
+
+/// Errors encountered while loading the application configuration
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    /// The configuration file cannot be read
+    #[error("Cannot read configuration file {path}: {reason}")]
+    FileError { path: String, reason: String },
+    /// The configuration file is not valid TOML or contains unknown settings
+    #[error("Cannot parse configuration file {path}: {reason}")]
+    ParseError { path: String, reason: String },
+    /// The merged configuration has missing or invalid settings,
+    /// all of them are reported at once
+    #[error("Invalid configuration:\n- {}", .0.join("\n- "))]
+    InvalidSettings(Vec<String>),
+}
+
+/// Errors encountered while initializing the logging
+#[derive(Error, Debug)]
+pub enum LoggingError {
+    /// The log files cannot be created in the logging directory
+    #[error("Cannot create log files: {0}")]
+    FileAppenderError(#[from] tracing_appender::rolling::InitError),
+    /// The OTLP exporter of the spans cannot be built
+    #[error("Cannot build OTLP exporter: {0}")]
+    ExporterError(#[from] opentelemetry_otlp::ExporterBuildError),
 }
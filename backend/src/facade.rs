@@ -1,3 +1,4 @@
 pub mod admin;
 pub mod guest;
+pub mod public;
 pub mod user;
@@ -1,19 +1,25 @@
 use std::sync::Arc;
 
+use tracing::instrument;
+
 use crate::{
     auth::AuthInfo,
-    error::{AppError, FacadeResult, ServiceAppError},
-    model::{User, UserBuilder},
+    enums::AuditAction,
+    error::{AppError, DatabaseError, FacadeResult, ServiceAppError},
+    model::{AuditEvent, BlogPost, User, UserBuilder},
     service::{
         access_control::AccessControl,
+        audit::{AuditEventFilter, AuditService},
+        blog::BlogService,
         database::{
-            DatabaseServiceTrait, document::DatabaseDocumentTrait,
+            DatabaseServiceTrait,
+            document::{DELETED_AT_FIELD, DatabaseDocumentTrait, ExpectedVersion},
             smart_document::SmartDocumentReference,
         },
     },
     utils::hash_password,
 };
-use bson::{doc, oid::ObjectId};
+use bson::{Document, doc, oid::ObjectId};
 use tokio::sync::RwLock;
 
 pub struct AdminFacade<D>
@@ -21,6 +27,7 @@ where
     D: DatabaseServiceTrait,
 {
     database_service: Arc<D>,
+    actor_id: ObjectId,
 }
 
 impl<D> AdminFacade<D>
@@ -52,10 +59,54 @@ where
                     source_error: other,
                 },
             })?;
-        Ok(Self { database_service })
+        Ok(Self {
+            database_service,
+            actor_id: *auth_info.user_id(),
+        })
+    }
+
+    /// Record in the audit log a change done by the admin
+    async fn audit<T: DatabaseDocumentTrait>(
+        &self,
+        transaction: Arc<RwLock<D::Transaction>>,
+        action: AuditAction,
+        target_id: ObjectId,
+        before: Option<Document>,
+        after: Option<Document>,
+    ) -> FacadeResult<()> {
+        AuditService::new(self.database_service.clone(), Some(transaction))
+            .record::<T>(self.actor_id, action, target_id, before, after)
+            .await
+            .map(|_| ())
+            .map_err(|err| AppError::InternalServerError {
+                msg: "Error in recording audit event".into(),
+                source_error: err,
+            })
+    }
+
+    /// Return the page of audit events that match the filter
+    /// together with the total number of matching events
+    #[instrument(name = "AdminFacade::get_audit_events", skip_all)]
+    pub async fn get_audit_events(
+        &self,
+        filter: AuditEventFilter,
+        page: u64,
+        page_size: u64,
+    ) -> FacadeResult<(Vec<AuditEvent>, u64)> {
+        AuditService::new(self.database_service.clone(), None)
+            .get_events(&filter, page, page_size)
+            .await
+            .map_err(|err| match err {
+                ServiceAppError::InvalidRequest(msg) => AppError::InvalidRequest(msg),
+                other => AppError::InternalServerError {
+                    msg: "Error in retrieving audit events".into(),
+                    source_error: other,
+                },
+            })
     }
 
     /// Return the list of users in the application
+    #[instrument(name = "AdminFacade::get_users", skip_all)]
     pub async fn get_users(&self) -> FacadeResult<Vec<User>> {
         self.database_service
             .find_many::<User>(doc! {})
@@ -66,7 +117,242 @@ where
             })
     }
 
+    /// Return the user with the given id
+    #[instrument(name = "AdminFacade::get_user", skip_all)]
+    pub async fn get_user(&self, user_id: ObjectId) -> FacadeResult<User> {
+        self.database_service
+            .find_one::<User>(doc! { "_id": user_id })
+            .await
+            .map_err(|err| AppError::InternalServerError {
+                msg: "Error in retrieving user from database".into(),
+                source_error: ServiceAppError::from(err),
+            })?
+            .ok_or(AppError::DoesNotExist(format!(
+                "User {user_id} does not exist"
+            )))
+    }
+
+    /// Update the user information if its current version is the expected
+    /// one and return the new version
+    #[allow(clippy::too_many_arguments)]
+    #[instrument(name = "AdminFacade::update_user", skip_all)]
+    pub async fn update_user(
+        &self,
+        transaction: Arc<RwLock<D::Transaction>>,
+        user_id: ObjectId,
+        expected_version: ExpectedVersion,
+        first_name: String,
+        last_name: String,
+        admin: bool,
+        publisher: bool,
+    ) -> FacadeResult<i64> {
+        let user = self.get_user(user_id).await?;
+        let changes = doc! {
+            "first_name": first_name,
+            "last_name": last_name,
+            "admin": admin,
+            "publisher": publisher,
+        };
+        let version = self
+            .database_service
+            .update_one_versioned::<User>(
+                user_id,
+                expected_version,
+                doc! { "$set": changes.clone() },
+                Some(transaction.clone()),
+            )
+            .await
+            .map_err(|err| match err {
+                DatabaseError::DocumentDoesNotExist(id) => {
+                    AppError::DoesNotExist(format!("User {id} does not exist"))
+                }
+                conflict @ DatabaseError::VersionConflict { .. } => {
+                    AppError::VersionConflict(conflict.to_string())
+                }
+                other => AppError::InternalServerError {
+                    msg: "Error in updating user".into(),
+                    source_error: ServiceAppError::from(other),
+                },
+            })?;
+
+        self.audit::<User>(
+            transaction,
+            AuditAction::Update,
+            user_id,
+            Some(doc! {
+                "first_name": user.first_name(),
+                "last_name": user.last_name(),
+                "admin": user.admin(),
+                "publisher": user.publisher(),
+            }),
+            Some(changes),
+        )
+        .await?;
+        Ok(version)
+    }
+
+    /// Soft delete the user with the given id
+    #[instrument(name = "AdminFacade::delete_user", skip_all)]
+    pub async fn delete_user(
+        &self,
+        transaction: Arc<RwLock<D::Transaction>>,
+        user_id: ObjectId,
+    ) -> FacadeResult<()> {
+        // verify that the user exists and it is not already deleted
+        self.get_user(user_id).await?;
+        let deleted_at = bson::DateTime::now();
+        self.database_service
+            .update_one::<User>(
+                doc! { "_id": user_id },
+                doc! { "$set": { DELETED_AT_FIELD: deleted_at } },
+                Some(transaction.clone()),
+            )
+            .await
+            .map_err(|err| AppError::InternalServerError {
+                msg: "Error in deleting user".into(),
+                source_error: ServiceAppError::from(err),
+            })?;
+        // the public posts of the user leave the feeds with it
+        BlogService::new(self.database_service.clone(), Some(transaction.clone()))
+            .touch_user_posts(user_id)
+            .await
+            .map_err(|err| AppError::InternalServerError {
+                msg: "Error in updating the posts of the user".into(),
+                source_error: err,
+            })?;
+
+        self.audit::<User>(
+            transaction,
+            AuditAction::Delete,
+            user_id,
+            Some(doc! { DELETED_AT_FIELD: null }),
+            Some(doc! { DELETED_AT_FIELD: deleted_at }),
+        )
+        .await
+    }
+
+    /// Return the list of soft deleted users
+    #[instrument(name = "AdminFacade::get_deleted_users", skip_all)]
+    pub async fn get_deleted_users(&self) -> FacadeResult<Vec<User>> {
+        self.database_service
+            .find_many::<User>(doc! { DELETED_AT_FIELD: { "$ne": null } })
+            .await
+            .map_err(|err| AppError::InternalServerError {
+                msg: "Error in retrieving deleted users from database".into(),
+                source_error: ServiceAppError::from(err),
+            })
+    }
+
+    /// Restore the soft deleted user with the given id, unless its username
+    /// has been taken by another user in the meantime
+    #[instrument(name = "AdminFacade::restore_user", skip_all)]
+    pub async fn restore_user(
+        &self,
+        transaction: Arc<RwLock<D::Transaction>>,
+        user_id: ObjectId,
+    ) -> FacadeResult<()> {
+        let deleted_user = self
+            .database_service
+            .find_one::<User>(doc! { "_id": user_id, DELETED_AT_FIELD: { "$ne": null } })
+            .await
+            .map_err(|err| AppError::InternalServerError {
+                msg: "Error in retrieving deleted user from database".into(),
+                source_error: ServiceAppError::from(err),
+            })?
+            .ok_or(AppError::DoesNotExist(format!(
+                "Deleted user {user_id} does not exist"
+            )))?;
+
+        // the username may have been taken after the deletion
+        let username_taken = self
+            .database_service
+            .count_documents::<User>(doc! { "username": deleted_user.username() })
+            .await
+            .map_err(|err| AppError::InternalServerError {
+                msg: "Error in retrieving users from database".into(),
+                source_error: ServiceAppError::from(err),
+            })?
+            > 0;
+        if username_taken {
+            return Err(AppError::AlreadyExists(format!(
+                "Username {} is taken by another user",
+                deleted_user.username()
+            )));
+        }
+
+        self.database_service
+            .update_one::<User>(
+                doc! { "_id": user_id },
+                doc! { "$set": { DELETED_AT_FIELD: null } },
+                Some(transaction.clone()),
+            )
+            .await
+            .map_err(|err| AppError::InternalServerError {
+                msg: "Error in restoring user".into(),
+                source_error: ServiceAppError::from(err),
+            })?;
+        // the public posts of the user return to the feeds with it
+        BlogService::new(self.database_service.clone(), Some(transaction.clone()))
+            .touch_user_posts(user_id)
+            .await
+            .map_err(|err| AppError::InternalServerError {
+                msg: "Error in updating the posts of the user".into(),
+                source_error: err,
+            })?;
+
+        self.audit::<User>(
+            transaction,
+            AuditAction::Restore,
+            user_id,
+            Some(doc! { DELETED_AT_FIELD: deleted_user.deleted_at() }),
+            Some(doc! { DELETED_AT_FIELD: null }),
+        )
+        .await
+    }
+
+    /// Return the list of soft deleted blog posts
+    #[instrument(name = "AdminFacade::get_deleted_posts", skip_all)]
+    pub async fn get_deleted_posts(&self) -> FacadeResult<Vec<BlogPost>> {
+        BlogService::new(self.database_service.clone(), None)
+            .get_deleted_posts()
+            .await
+            .map_err(|err| AppError::InternalServerError {
+                msg: "Error in retrieving deleted posts".into(),
+                source_error: err,
+            })
+    }
+
+    /// Restore the soft deleted blog post with the given id
+    #[instrument(name = "AdminFacade::restore_post", skip_all)]
+    pub async fn restore_post(
+        &self,
+        transaction: Arc<RwLock<D::Transaction>>,
+        post_id: ObjectId,
+    ) -> FacadeResult<()> {
+        let deleted_post =
+            BlogService::new(self.database_service.clone(), Some(transaction.clone()))
+                .restore_post(post_id)
+                .await
+                .map_err(|err| match err {
+                    ServiceAppError::DoesNotExist(msg) => AppError::DoesNotExist(msg),
+                    other => AppError::InternalServerError {
+                        msg: "Error in restoring post".into(),
+                        source_error: other,
+                    },
+                })?;
+
+        self.audit::<BlogPost>(
+            transaction,
+            AuditAction::Restore,
+            post_id,
+            Some(doc! { DELETED_AT_FIELD: deleted_post.deleted_at() }),
+            Some(doc! { DELETED_AT_FIELD: null }),
+        )
+        .await
+    }
+
     /// Create a new user in the application
+    #[instrument(name = "AdminFacade::create_user", skip_all)]
     pub async fn create_user(
         &self,
         transaction: Arc<RwLock<D::Transaction>>,
@@ -88,12 +374,20 @@ where
             .username(username)
             .password_hash(password_hash)
             .admin(admin)
-            .build(Some(transaction))
+            .build(Some(transaction.clone()))
             .await
             .map_err(|err| AppError::InternalServerError {
                 msg: err.to_string(),
                 source_error: ServiceAppError::from(err),
             })?;
+
+        AuditService::new(self.database_service.clone(), Some(transaction))
+            .record_creation(self.actor_id, &result)
+            .await
+            .map_err(|err| AppError::InternalServerError {
+                msg: "Error in recording audit event".into(),
+                source_error: err,
+            })?;
         Ok(*result.get_id())
     }
 }
@@ -1,15 +1,16 @@
 use std::sync::Arc;
 
-use jsonwebtoken::Header;
 use tokio::sync::RwLock;
+use tracing::instrument;
 
 use crate::{
     AppResult, AppState,
     auth::JWTAuthClaim,
     dtos::guest_response::{self, LoggedUserInfoResponse},
     error::{AppError, ServiceAppError},
-    model::{User, UserBuilder},
+    model::UserBuilder,
     service::{
+        audit::AuditService,
         database::{DatabaseServiceTrait, document::DatabaseDocumentTrait},
         user::UserService,
     },
@@ -26,27 +27,7 @@ impl GuestFacade {
         Self { state }
     }
 
-    fn create_jwt(&self, user: &User) -> Result<String, AppError> {
-        let exp = self
-            .state
-            .environment_service
-            .get_authentication_jwt_expiration();
-        let now = chrono::offset::Local::now().timestamp();
-        let claims: JWTAuthClaim = JWTAuthClaim::new(
-            now as u32 + exp as u32,
-            *user.get_id(),
-            user.username().clone(),
-        );
-        let token = claims.build_token(
-            &Header::default(),
-            self.state
-                .environment_service
-                .get_authentication_jwt_encoding(),
-        )?;
-
-        Ok(token)
-    }
-
+    #[instrument(name = "GuestFacade::register_user", skip_all)]
     pub async fn register_user<D: DatabaseServiceTrait>(
         &self,
         database_service: Arc<D>,
@@ -69,14 +50,23 @@ impl GuestFacade {
             .password_hash(password_hash)
             .admin(false)
             .publisher(true)
-            .build(Some(transaction))
+            .build(Some(transaction.clone()))
             .await
             .map_err(|err| AppError::InternalServerError {
                 msg: err.to_string(),
                 source_error: ServiceAppError::from(err),
             })?;
 
-        let token = self.create_jwt(&user)?;
+        // the registered user is the actor of its own creation
+        AuditService::new(database_service, Some(transaction))
+            .record_creation(*user.get_id(), &user)
+            .await
+            .map_err(|err| AppError::InternalServerError {
+                msg: "Error in recording audit event".into(),
+                source_error: err,
+            })?;
+
+        let token = JWTAuthClaim::issue_token(self.state.environment_service.as_ref(), &user)?;
 
         Ok(AppJson(LoggedUserInfoResponse {
             token: Some(token),
@@ -86,6 +76,7 @@ impl GuestFacade {
         }))
     }
 
+    #[instrument(name = "GuestFacade::authenticate_user", skip_all)]
     pub async fn authenticate_user(
         &self,
         username: &str,
@@ -102,7 +93,7 @@ impl GuestFacade {
                     source_error: any_other,
                 },
             })?;
-        let token = self.create_jwt(&user)?;
+        let token = JWTAuthClaim::issue_token(self.state.environment_service.as_ref(), &user)?;
         Ok(AppJson(LoggedUserInfoResponse {
             token: Some(token),
             user_id: user.get_id().to_string(),
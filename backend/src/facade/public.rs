@@ -0,0 +1,134 @@
+use std::sync::Arc;
+
+use api_types::user::PostVisibility;
+use bson::oid::ObjectId;
+use tokio::sync::RwLock;
+use tracing::instrument;
+
+use crate::{
+    dtos::public_response::AuthorPage,
+    error::{AppError, DatabaseError, FacadeResult, ServiceAppError},
+    model::{BlogPost, User},
+    service::{
+        blog::BlogService,
+        database::{
+            DatabaseServiceTrait, document::DatabaseDocumentTrait,
+            smart_document::SmartDocumentReference,
+        },
+        feed::{Feed, FeedService},
+        user::UserService,
+    },
+};
+
+/// Read only access to the public blog posts and to their authors,
+/// used by the routes that do not require an account
+pub struct PublicFacade<D>
+where
+    D: DatabaseServiceTrait,
+{
+    database_service: Arc<D>,
+}
+
+impl<D> PublicFacade<D>
+where
+    D: DatabaseServiceTrait,
+{
+    pub fn new(database_service: Arc<D>) -> Self {
+        Self { database_service }
+    }
+
+    #[instrument(name = "PublicFacade::get_posts", skip_all)]
+    pub async fn get_posts(&self) -> FacadeResult<Vec<BlogPost>> {
+        self.get_public_posts(None).await
+    }
+
+    /// Returns the blog post with the given id, members only posts and
+    /// the posts of the soft deleted authors do not exist for the guests
+    #[instrument(name = "PublicFacade::get_post", skip_all)]
+    pub async fn get_post(&self, post_id: ObjectId) -> FacadeResult<BlogPost> {
+        let post = BlogService::new(self.database_service.clone(), None)
+            .get_post(post_id)
+            .await
+            .map_err(|err| match err {
+                ServiceAppError::DoesNotExist(msg) => AppError::DoesNotExist(msg),
+                other => AppError::InternalServerError {
+                    msg: "Error in retrieving post".into(),
+                    source_error: other,
+                },
+            })?;
+        if post.visibility() != PostVisibility::Public.as_str() {
+            return Err(AppError::DoesNotExist(format!(
+                "Blog post {post_id} does not exist"
+            )));
+        }
+        self.get_user(*post.user_id())
+            .await
+            .map_err(|err| match err {
+                AppError::DoesNotExist(_) => {
+                    AppError::DoesNotExist(format!("Blog post {post_id} does not exist"))
+                }
+                other => other,
+            })?;
+        Ok(post)
+    }
+
+    /// Returns the author with the given id and its public blog posts
+    #[instrument(name = "PublicFacade::get_author", skip_all)]
+    pub async fn get_author(&self, author_id: ObjectId) -> FacadeResult<AuthorPage> {
+        let author = self.get_user(author_id).await?;
+        let posts = self.get_public_posts(Some(author_id)).await?;
+
+        Ok(AuthorPage {
+            author_id: author.get_id().to_string(),
+            username: author.username().clone(),
+            posts: posts.into_iter().map(|post| post.into()).collect(),
+        })
+    }
+
+    /// Returns the feed of the public blog posts, only the ones written
+    /// by the author when it is given
+    #[instrument(name = "PublicFacade::get_feed", skip_all)]
+    pub async fn get_feed(&self, author_id: Option<ObjectId>) -> FacadeResult<Feed> {
+        let author = match author_id {
+            Some(author_id) => Some(self.get_user(author_id).await?),
+            None => None,
+        };
+        FeedService::new(self.database_service.clone())
+            .get_feed(author.as_ref())
+            .await
+            .map_err(|err| AppError::InternalServerError {
+                msg: "Error in building the feed".into(),
+                source_error: err,
+            })
+    }
+
+    async fn get_user(&self, author_id: ObjectId) -> FacadeResult<User> {
+        UserService::new(
+            Arc::new(RwLock::new(SmartDocumentReference::<User>::from(
+                &author_id,
+            ))),
+            self.database_service.clone(),
+        )
+        .get()
+        .await
+        .map_err(|err| match err {
+            ServiceAppError::DatabaseError(DatabaseError::DocumentDoesNotExist(_)) => {
+                AppError::DoesNotExist(format!("Author {author_id} does not exist"))
+            }
+            other => AppError::InternalServerError {
+                msg: "Error in retrieving author".into(),
+                source_error: other,
+            },
+        })
+    }
+
+    async fn get_public_posts(&self, user_id: Option<ObjectId>) -> FacadeResult<Vec<BlogPost>> {
+        BlogService::new(self.database_service.clone(), None)
+            .get_public_posts(user_id)
+            .await
+            .map_err(|err| AppError::InternalServerError {
+                msg: "Error in retrieving posts".into(),
+                source_error: err,
+            })
+    }
+}
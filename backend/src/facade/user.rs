@@ -1,18 +1,25 @@
 use std::sync::Arc;
 
-use bson::oid::ObjectId;
+use api_types::user::PostVisibility;
+use bson::{Document, doc, oid::ObjectId};
+use futures::{StreamExt, stream::BoxStream};
 use tokio::sync::RwLock;
+use tracing::instrument;
 
 use crate::{
-    auth::AuthInfo,
+    EnvironmentServiceTrait, ServiceResult,
+    auth::{AuthInfo, JWTAuthClaim},
     dtos::guest_response::LoggedUserInfoResponse,
+    enums::AuditAction,
     error::{AppError, DatabaseError, FacadeResult, ServiceAppError},
     model::{BlogPost, User},
     service::{
         access_control::AccessControl,
+        audit::AuditService,
         blog::BlogService,
         database::{
-            DatabaseServiceTrait, document::DatabaseDocumentTrait,
+            ChangeEvent, DatabaseServiceTrait,
+            document::{DELETED_AT_FIELD, DatabaseDocumentTrait, ExpectedVersion},
             smart_document::SmartDocumentReference,
         },
         user::UserService,
@@ -73,6 +80,26 @@ where
         })
     }
 
+    /// Record in the audit log a change done by the user
+    async fn audit(
+        &self,
+        transaction: Arc<RwLock<D::Transaction>>,
+        action: AuditAction,
+        target_id: ObjectId,
+        before: Option<Document>,
+        after: Option<Document>,
+    ) -> FacadeResult<()> {
+        AuditService::new(self.database_service.clone(), Some(transaction))
+            .record::<BlogPost>(*self.user.get_id(), action, target_id, before, after)
+            .await
+            .map(|_| ())
+            .map_err(|err| AppError::InternalServerError {
+                msg: "Error in recording audit event".into(),
+                source_error: err,
+            })
+    }
+
+    #[instrument(name = "UserFacade::get_info", skip_all)]
     pub async fn get_info(&self) -> FacadeResult<LoggedUserInfoResponse> {
         Ok(LoggedUserInfoResponse {
             token: None,
@@ -82,11 +109,30 @@ where
         })
     }
 
+    /// Issue a new token of the user in the session of the given token,
+    /// so that the client can replace the current one before it expires
+    #[instrument(name = "UserFacade::refresh_token", skip_all)]
+    pub async fn refresh_token(
+        &self,
+        jwt_claim: &JWTAuthClaim,
+        environment_service: &dyn EnvironmentServiceTrait,
+    ) -> FacadeResult<LoggedUserInfoResponse> {
+        let token = jwt_claim.refresh_token(environment_service, &self.user)?;
+        Ok(LoggedUserInfoResponse {
+            token: Some(token),
+            user_id: self.user.get_id().to_string(),
+            username: self.user.username().clone(),
+            admin: *self.user.admin(),
+        })
+    }
+
+    #[instrument(name = "UserFacade::publish_post", skip_all)]
     pub async fn publish_post(
         &self,
         transaction: Arc<RwLock<D::Transaction>>,
         title: String,
         content: String,
+        visibility: PostVisibility,
     ) -> FacadeResult<String> {
         self.access_control
             .is_publisher_ref()
@@ -99,11 +145,13 @@ where
                 },
             })?;
 
-        let blog_service = BlogService::new(self.database_service.clone(), Some(transaction));
+        let blog_service =
+            BlogService::new(self.database_service.clone(), Some(transaction.clone()));
         let blog_post = blog_service
             .publish_post(
                 title,
                 content,
+                visibility,
                 *self.user.get_id(),
                 self.user.username().to_string(),
                 chrono::offset::Utc::now(),
@@ -117,9 +165,166 @@ where
                 },
             })?;
 
+        AuditService::new(self.database_service.clone(), Some(transaction))
+            .record_creation(*self.user.get_id(), &blog_post)
+            .await
+            .map_err(|err| AppError::InternalServerError {
+                msg: "Error in recording audit event".into(),
+                source_error: err,
+            })?;
+
         Ok(blog_post.get_id().to_hex())
     }
 
+    #[instrument(name = "UserFacade::get_post", skip_all)]
+    pub async fn get_post(&self, post_id: ObjectId) -> FacadeResult<BlogPost> {
+        let blog_service = BlogService::new(self.database_service.clone(), None);
+        blog_service
+            .get_post(post_id)
+            .await
+            .map_err(|err| match err {
+                ServiceAppError::DoesNotExist(msg) => AppError::DoesNotExist(msg),
+                other => AppError::InternalServerError {
+                    msg: "Error in retrieving post".into(),
+                    source_error: other,
+                },
+            })
+    }
+
+    /// Updates the post if the user is its author and the current version
+    /// is the expected one. Returns the new version of the post.
+    #[instrument(name = "UserFacade::update_post", skip_all)]
+    pub async fn update_post(
+        &self,
+        transaction: Arc<RwLock<D::Transaction>>,
+        post_id: ObjectId,
+        expected_version: ExpectedVersion,
+        title: String,
+        content: String,
+        visibility: PostVisibility,
+    ) -> FacadeResult<i64> {
+        self.access_control
+            .is_publisher_ref()
+            .await
+            .map_err(|err| match err {
+                ServiceAppError::AccessControlError(msg) => AppError::AccessControlError(msg),
+                other => AppError::InternalServerError {
+                    msg: other.to_string(),
+                    source_error: other,
+                },
+            })?;
+
+        let post = self.get_post(post_id).await?;
+        if post.user_id() != self.user.get_id() {
+            return Err(AppError::AccessControlError(
+                "User is not the author of the post".into(),
+            ));
+        }
+
+        let changes = doc! {
+            "title": &title,
+            "content": &content,
+            "visibility": visibility.as_str(),
+        };
+        let blog_service =
+            BlogService::new(self.database_service.clone(), Some(transaction.clone()));
+        let version = blog_service
+            .update_post(post_id, expected_version, title, content, visibility)
+            .await
+            .map_err(|err| match err {
+                ServiceAppError::DoesNotExist(msg) => AppError::DoesNotExist(msg),
+                ServiceAppError::DatabaseError(
+                    conflict @ DatabaseError::VersionConflict { .. },
+                ) => AppError::VersionConflict(conflict.to_string()),
+                other => AppError::InternalServerError {
+                    msg: "Error in updating BlogPost document".into(),
+                    source_error: other,
+                },
+            })?;
+
+        self.audit(
+            transaction,
+            AuditAction::Update,
+            post_id,
+            Some(doc! {
+                "title": post.title(),
+                "content": post.content(),
+                "visibility": post.visibility(),
+            }),
+            Some(changes),
+        )
+        .await?;
+        Ok(version)
+    }
+
+    /// Soft deletes the post if the user is its author
+    #[instrument(name = "UserFacade::delete_post", skip_all)]
+    pub async fn delete_post(
+        &self,
+        transaction: Arc<RwLock<D::Transaction>>,
+        post_id: ObjectId,
+    ) -> FacadeResult<()> {
+        let post = self.get_post(post_id).await?;
+        if post.user_id() != self.user.get_id() {
+            return Err(AppError::AccessControlError(
+                "User is not the author of the post".into(),
+            ));
+        }
+
+        let deleted_at = BlogService::new(self.database_service.clone(), Some(transaction.clone()))
+            .delete_post(post_id)
+            .await
+            .map_err(|err| match err {
+                ServiceAppError::DoesNotExist(msg) => AppError::DoesNotExist(msg),
+                other => AppError::InternalServerError {
+                    msg: "Error in deleting BlogPost document".into(),
+                    source_error: other,
+                },
+            })?;
+
+        self.audit(
+            transaction,
+            AuditAction::Delete,
+            post_id,
+            Some(doc! { DELETED_AT_FIELD: null }),
+            Some(doc! { DELETED_AT_FIELD: deleted_at }),
+        )
+        .await
+    }
+
+    /// Returns the stream of the changes of the blog posts
+    #[instrument(name = "UserFacade::watch_posts", skip_all)]
+    pub async fn watch_posts(
+        &self,
+    ) -> FacadeResult<BoxStream<'static, ServiceResult<ChangeEvent<BlogPost>>>>
+    where
+        D: Send + Sync + 'static,
+    {
+        let changes = BlogService::new(self.database_service.clone(), None)
+            .watch_posts()
+            .await
+            .map_err(|err| AppError::InternalServerError {
+                msg: "Error in watching posts".into(),
+                source_error: err,
+            })?;
+
+        // the stream ends at the first change after the user is deleted
+        let database_service = self.database_service.clone();
+        let user_id = *self.user.get_id();
+        Ok(changes
+            .take_while(move |_| {
+                let database_service = database_service.clone();
+                async move {
+                    database_service
+                        .count_documents::<User>(doc! { "_id": user_id })
+                        .await
+                        .is_ok_and(|count| count > 0)
+                }
+            })
+            .boxed())
+    }
+
+    #[instrument(name = "UserFacade::get_posts", skip_all)]
     pub async fn get_posts(&self, user_id: Option<ObjectId>) -> FacadeResult<Vec<BlogPost>> {
         let blog_service = BlogService::new(self.database_service.clone(), None);
         blog_service
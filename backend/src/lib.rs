@@ -4,14 +4,21 @@ mod enums;
 mod environment;
 mod error;
 mod facade;
+pub mod logging;
+pub mod metrics;
 pub mod middleware;
+pub mod migration;
 mod model;
 pub mod router;
+pub mod server;
 pub mod service;
+pub mod telemetry;
+#[cfg(test)]
+mod test_utils;
 mod types;
 pub mod utils;
 
-pub use enums::FrontendMode;
-pub use environment::{EnvironmentService, EnvironmentServiceTrait};
-pub use error::{AppResult, AuthResult, DatabaseResult, ServiceResult};
+pub use enums::{DatabaseBackend, FrontendMode};
+pub use environment::{AppConfig, EnvironmentService, EnvironmentServiceTrait};
+pub use error::{AppResult, AuthResult, ConfigError, ConfigResult, DatabaseResult, ServiceResult};
 pub use types::AppState;
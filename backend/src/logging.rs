@@ -0,0 +1,169 @@
+//! Logging module initializes the tracing subscriber of the application.
+//!
+//! Logs are written as text or JSON lines, to the standard output or to
+//! files in the logging directory rotated by `tracing-appender`.
+//! The request spans carry the request id, hence every log written while
+//! serving a request can be correlated with it.
+//!
+//! When the OTLP endpoint is configured the spans are exported to the
+//! collector as well, see the `telemetry` module.
+
+use opentelemetry_sdk::trace::SdkTracerProvider;
+use tracing::Subscriber;
+use tracing_appender::{
+    non_blocking::WorkerGuard,
+    rolling::{RollingFileAppender, Rotation},
+};
+use tracing_subscriber::{
+    Layer, filter::LevelFilter, fmt::writer::BoxMakeWriter, layer::SubscriberExt,
+    registry::LookupSpan, util::SubscriberInitExt,
+};
+
+use crate::{
+    EnvironmentServiceTrait,
+    enums::{LogFormat, LogRotation},
+    error::LoggingError,
+    telemetry::{init_tracer_provider, telemetry_layer},
+};
+
+/// Prefix of the log files, followed by the date and the `.log` extension
+const LOG_FILE_PREFIX: &str = "backend";
+
+impl From<LogRotation> for Rotation {
+    fn from(rotation: LogRotation) -> Self {
+        match rotation {
+            LogRotation::Minutely => Rotation::MINUTELY,
+            LogRotation::Hourly => Rotation::HOURLY,
+            LogRotation::Daily => Rotation::DAILY,
+            LogRotation::Never => Rotation::NEVER,
+        }
+    }
+}
+
+/// Keeps the background writers of the logs and of the spans alive, when
+/// dropped the pending log lines are flushed and the pending spans exported
+pub struct LoggingGuard {
+    _worker_guard: Option<WorkerGuard>,
+    tracer_provider: Option<SdkTracerProvider>,
+}
+
+impl Drop for LoggingGuard {
+    fn drop(&mut self) {
+        if let Some(tracer_provider) = self.tracer_provider.take()
+            && let Err(shutdown_error) = tracer_provider.shutdown()
+        {
+            eprintln!("Error in exporting the pending spans: {shutdown_error}");
+        }
+    }
+}
+
+/// Builds the layer that formats the events with the given format and writer
+fn format_layer<S>(
+    format: LogFormat,
+    writer: BoxMakeWriter,
+    ansi: bool,
+) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    let layer = tracing_subscriber::fmt::layer()
+        .with_writer(writer)
+        .with_ansi(ansi);
+    match format {
+        LogFormat::Text => layer.boxed(),
+        LogFormat::Json => layer.json().boxed(),
+    }
+}
+
+/// Initializes the global tracing subscriber defined by the logging settings.
+///
+/// When the logs are written to files they are written by a background thread,
+/// as the spans exported to the OTLP collector, the returned guard flushes them
+/// when dropped hence it must be kept alive until the application exits.
+pub fn init_logging(
+    environment_service: &dyn EnvironmentServiceTrait,
+) -> Result<LoggingGuard, LoggingError> {
+    let format = *environment_service.get_logging_format();
+    let (writer, guard, ansi) = match environment_service.get_logging_directory() {
+        Some(directory) => {
+            let mut builder = RollingFileAppender::builder()
+                .rotation((*environment_service.get_logging_rotation()).into())
+                .filename_prefix(LOG_FILE_PREFIX)
+                .filename_suffix("log");
+            if let Some(max_files) = environment_service.get_logging_max_files() {
+                builder = builder.max_log_files(max_files);
+            }
+            let (writer, guard) = tracing_appender::non_blocking(builder.build(directory)?);
+            (BoxMakeWriter::new(writer), Some(guard), false)
+        }
+        None => (
+            BoxMakeWriter::new(std::io::stdout),
+            None,
+            format == LogFormat::Text,
+        ),
+    };
+
+    let tracer_provider = init_tracer_provider(environment_service)?;
+
+    tracing_subscriber::registry()
+        .with(LevelFilter::from_level(
+            environment_service.get_logging_level(),
+        ))
+        .with(format_layer(format, writer, ansi))
+        .with(tracer_provider.as_ref().map(telemetry_layer))
+        .init();
+    Ok(LoggingGuard {
+        _worker_guard: guard,
+        tracer_provider,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::Write,
+        sync::{Arc, Mutex},
+    };
+
+    use tracing_subscriber::{fmt::writer::BoxMakeWriter, layer::SubscriberExt};
+
+    use crate::enums::LogFormat;
+
+    use super::format_layer;
+
+    /// Writer that keeps the logs in memory to be inspected by the test
+    #[derive(Clone, Default)]
+    struct MemoryWriter(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for MemoryWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_json_logs_contain_request_id() {
+        let writer = MemoryWriter::default();
+        let make_writer = {
+            let writer = writer.clone();
+            BoxMakeWriter::new(move || writer.clone())
+        };
+        let subscriber =
+            tracing_subscriber::registry().with(format_layer(LogFormat::Json, make_writer, false));
+
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("request", request_id = "8a9e1c2f");
+            let _entered = span.enter();
+            tracing::error!("something failed");
+        });
+
+        let logs = String::from_utf8(writer.0.lock().unwrap().clone()).unwrap();
+        let line: serde_json::Value = serde_json::from_str(logs.trim()).unwrap();
+        assert_eq!(line["fields"]["message"], "something failed");
+        assert_eq!(line["span"]["request_id"], "8a9e1c2f");
+    }
+}
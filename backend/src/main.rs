@@ -1,98 +1,298 @@
-use std::{path::Path, sync::Arc};
+use std::{path::PathBuf, process::ExitCode, sync::Arc, time::Duration};
 
-use axum::{
-    Router,
-    routing::{get, get_service},
-};
 use backend::{
-    AppState, EnvironmentService, EnvironmentServiceTrait, FrontendMode, middleware, router,
-    service::database::{DatabaseServiceTrait, MongoDBDatabaseService},
+    AppConfig, AppState, EnvironmentService, EnvironmentServiceTrait, logging,
+    migration::MigrationRunner,
+    router,
+    server::{self, ReloadableCertificate, TlsListener, redirect_router, spawn_certificate_reload},
+    service::{
+        database::{AppDatabaseService, DatabaseServiceTrait},
+        purge::spawn_purge_job,
+    },
 };
-use tower_http::services::{ServeDir, ServeFile};
+use clap::{Parser, Subcommand};
 use tracing::{error, info};
 
+/// Command line interface of the backend
+///
+/// The flags override the values of the configuration file and of the
+/// environment variables.
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// TOML configuration file, its values are overridden by environment variables
+    #[arg(long, env = "CONFIG_FILE")]
+    config: Option<PathBuf>,
+    #[arg(long)]
+    host: Option<String>,
+    #[arg(long)]
+    port: Option<String>,
+    #[arg(long)]
+    tls_cert_path: Option<String>,
+    #[arg(long)]
+    tls_key_path: Option<String>,
+    #[arg(long)]
+    tls_redirect_http_port: Option<String>,
+    #[arg(long)]
+    logging_level: Option<String>,
+    #[arg(long)]
+    logging_include_headers: Option<String>,
+    #[arg(long)]
+    logging_format: Option<String>,
+    #[arg(long)]
+    logging_directory: Option<String>,
+    #[arg(long)]
+    metrics_port: Option<String>,
+    #[arg(long)]
+    jwt_expiration: Option<String>,
+    #[arg(long)]
+    jwt_max_session_age: Option<String>,
+    #[arg(long)]
+    database_backend: Option<String>,
+    #[arg(long)]
+    database_db_name: Option<String>,
+    #[arg(long)]
+    soft_delete_retention_days: Option<String>,
+    #[arg(long)]
+    object_storage_backend: Option<String>,
+    #[arg(long)]
+    object_storage_prefix_path: Option<String>,
+    #[arg(long)]
+    frontend_mode: Option<String>,
+}
+
+#[derive(Subcommand, Clone, Copy, PartialEq)]
+enum Command {
+    /// Apply pending migrations and start the server, it is the default command
+    Serve,
+    /// Apply pending migrations and exit
+    Migrate,
+}
+
+impl Cli {
+    /// Configuration layer defined by the command line flags
+    fn config_overrides(&self) -> AppConfig {
+        let mut config = AppConfig::default();
+        config.server.host = self.host.clone();
+        config.server.port = self.port.clone();
+        config.tls.cert_path = self.tls_cert_path.clone();
+        config.tls.key_path = self.tls_key_path.clone();
+        config.tls.redirect_http_port = self.tls_redirect_http_port.clone();
+        config.logging.level = self.logging_level.clone();
+        config.logging.include_headers = self.logging_include_headers.clone();
+        config.logging.format = self.logging_format.clone();
+        config.logging.directory = self.logging_directory.clone();
+        config.metrics.port = self.metrics_port.clone();
+        config.authentication.jwt_expiration = self.jwt_expiration.clone();
+        config.authentication.max_session_age = self.jwt_max_session_age.clone();
+        config.database.backend = self.database_backend.clone();
+        config.database.db_name = self.database_db_name.clone();
+        config.database.soft_delete_retention_days = self.soft_delete_retention_days.clone();
+        config.storage.backend = self.object_storage_backend.clone();
+        config.storage.prefix_path = self.object_storage_prefix_path.clone();
+        config.frontend.mode = self.frontend_mode.clone();
+        config
+    }
+}
+
 /// Start the application server listening for requests
 ///
 /// The initialization steps are:
 ///
-/// - build environment service
+/// - load and validate the configuration
+/// - setup logging, to the standard output or to rotated files, and the
+///   export of the spans to the OpenTelemetry collector
+/// - connect to the database and apply pending migrations
 /// - build app state
-/// - setup logging
 /// - build app
-/// - serve app
+/// - serve app, over HTTPS when the certificate is configured, until
+///   SIGINT or SIGTERM, then drain in flight requests
+/// - shut down the database connection
+///
+/// When the binary is invoked with the `migrate` subcommand then only
+/// the pending migrations are applied and the process exits.
+///
+/// The exit code is a failure when the startup, the migrations or the
+/// server fail, so that the deploy steps running `migrate` stop. Returning
+/// it instead of calling `std::process::exit` flushes the log files.
 #[tokio::main]
-async fn main() {
-    let environment_service = EnvironmentService::default();
-    let mut database_service = MongoDBDatabaseService::new(
-        environment_service.get_database_db_name().into(),
-        environment_service.get_database_connection_string().into(),
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    let migrate_only = cli.command == Some(Command::Migrate);
+
+    // all the configuration errors are reported before exiting
+    let environment_service = match AppConfig::load(cli.config.as_deref(), cli.config_overrides())
+        .and_then(EnvironmentService::from_config)
+    {
+        Ok(environment_service) => environment_service,
+        Err(config_error) => {
+            eprintln!("{config_error}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    // initialize tracing logging with level, format and destination defined by the
+    // environment service and the export of the spans to the OTLP collector, the
+    // guard flushes the log files and the pending spans when the process exits
+    let _logging_guard = match logging::init_logging(&environment_service) {
+        Ok(guard) => guard,
+        Err(logging_error) => {
+            eprintln!("Error in initializing logging: {logging_error}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    // the database backend is selected with the `database.backend` setting
+    info!(
+        "Using database backend {backend}",
+        backend = environment_service.get_database_backend()
     );
+    let mut database_service = AppDatabaseService::new(&environment_service);
     let connection_result = database_service.connect().await;
     if let Err(connection_error) = connection_result {
         error!(
             "Error in connecting to database: {err}",
             err = connection_error
         );
-        return;
+        return ExitCode::FAILURE;
     }
+    let database_service = Arc::new(database_service);
+
+    match MigrationRunner::new(database_service.clone()).run().await {
+        Ok(applied) => info!("Applied {count} migrations", count = applied.len()),
+        Err(migration_error) => {
+            error!(
+                "Error in applying database migrations: {err}",
+                err = migration_error
+            );
+            return ExitCode::FAILURE;
+        }
+    }
+    if migrate_only {
+        return ExitCode::SUCCESS;
+    }
+
+    // soft deleted documents are permanently deleted once the retention period is expired
+    let purge_job = spawn_purge_job(
+        database_service.clone(),
+        chrono::Duration::days(
+            environment_service
+                .get_database_soft_delete_retention_days()
+                .into(),
+        ),
+        Duration::from_secs(60 * 60),
+    );
 
     let app_state = Arc::new(AppState::new(
         Box::new(environment_service),
-        Arc::new(database_service),
+        database_service.clone(),
     ));
 
-    // initialize tracing logging with level defined by the environment service
-    tracing_subscriber::fmt()
-        .with_max_level(app_state.environment_service.get_logging_level())
-        .with_ansi(true)
-        .init();
+    let environment_service = &app_state.environment_service;
+    let host = environment_service.get_server_host().to_string();
+    let address = format!(
+        "{host}:{port}",
+        port = environment_service.get_server_port()
+    );
+    let drain_timeout = environment_service.get_server_shutdown_timeout();
+
+    // the certificate is loaded before binding so that an invalid one stops the startup
+    let certificate = match environment_service.get_tls_cert_and_key_paths() {
+        Some((cert_path, key_path)) => match ReloadableCertificate::load(cert_path, key_path) {
+            Ok(certificate) => Some(Arc::new(certificate)),
+            Err(tls_error) => {
+                error!("Error in loading TLS certificate: {err}", err = tls_error);
+                return ExitCode::FAILURE;
+            }
+        },
+        None => None,
+    };
+    let reload_interval = environment_service.get_tls_reload_interval();
+    let redirect_http_port = environment_service.get_tls_redirect_http_port();
+    let metrics_port = environment_service
+        .get_metrics_enabled()
+        .then(|| environment_service.get_metrics_port())
+        .flatten();
 
-    let listener = tokio::net::TcpListener::bind("0.0.0.0:3000")
+    let listener = tokio::net::TcpListener::bind(&address)
         .await
         .expect("Error in binding TcpListener");
-    info!("Starting listener on port 3000");
-    axum::serve(listener, build_app(app_state)).await.unwrap();
-}
-
-/// Build our application routes. According to frontend mode we change the root behavior.
-/// When frontend mode is integrated, the root returns index.html and the other static content
-/// via fallback service.
-///
-/// When frontend mode is external then the root returns standard 200 OK
-fn build_app(state: Arc<AppState>) -> Router {
-    let mut app =
-        if let FrontendMode::Integrated(path) = state.environment_service.get_frontend_mode() {
-            tracing::info!("working with frontend mode `integrated` with path {path}");
-            Router::new()
-                .route(
-                    "/",
-                    get_service(ServeFile::new(Path::new(path).join("index.html"))),
-                )
-                .fallback_service(get_service(
-                    ServeDir::new(path)
-                        .not_found_service(ServeFile::new(Path::new(path).join("index.html"))),
-                ))
-                .route("/api/health", get(router::health_handler))
-        } else {
-            Router::new()
-                // `GET /` goes to `root`
-                .route("/", get(router::health_handler))
+    // the metrics listener holds the application state, hence it is awaited
+    // before shutting down the database service
+    let metrics_server = match metrics_port {
+        Some(metrics_port) => {
+            let metrics_address = format!("{host}:{metrics_port}");
+            let metrics_listener = tokio::net::TcpListener::bind(&metrics_address)
+                .await
+                .expect("Error in binding metrics TcpListener");
+            info!("Starting metrics listener on {metrics_address}");
+            Some(tokio::spawn(server::serve(
+                metrics_listener,
+                router::build_metrics_app(Arc::clone(&app_state)),
+                server::shutdown_signal(),
+                drain_timeout,
+            )))
+        }
+        None => None,
+    };
+    let app = router::build_app(app_state);
+    let serve_result = if let Some(certificate) = certificate {
+        let server_config = match certificate.server_config() {
+            Ok(server_config) => server_config,
+            Err(tls_error) => {
+                error!("Error in configuring TLS: {err}", err = tls_error);
+                return ExitCode::FAILURE;
+            }
         };
+        spawn_certificate_reload(certificate, reload_interval);
 
-    app = router::add_guest_router("/api/guest", app);
-    app = router::add_admin_router("/api/admin", app);
-    app = router::add_user_router("/api/user", app);
-
-    // Add middlewares to our application.
-    // Layers are accessed from bottom to up, hence the order is very important
-    app = middleware::add_mongodb_transaction_middleware(Arc::clone(&state), app);
-    app = middleware::add_logging_middleware(
-        app,
-        state.environment_service.get_logging_include_headers(),
-        state.environment_service.get_logging_level(),
-    );
-    app = middleware::add_cors_middleware(app);
+        if let Some(redirect_port) = redirect_http_port {
+            let redirect_address = format!("{host}:{redirect_port}");
+            let redirect_listener = tokio::net::TcpListener::bind(&redirect_address)
+                .await
+                .expect("Error in binding redirect TcpListener");
+            info!("Redirecting HTTP requests from {redirect_address} to HTTPS");
+            let https_port = listener.local_addr().map_or(443, |address| address.port());
+            tokio::spawn(server::serve(
+                redirect_listener,
+                redirect_router(https_port),
+                server::shutdown_signal(),
+                drain_timeout,
+            ));
+        }
 
-    // Attach the state of the application that will be used by its services
-    app.with_state(state)
+        info!("Starting HTTPS listener on {address}");
+        let listener =
+            TlsListener::new(listener, server_config).expect("Error in creating TLS listener");
+        server::serve(listener, app, server::shutdown_signal(), drain_timeout).await
+    } else {
+        info!("Starting listener on {address}");
+        server::serve(listener, app, server::shutdown_signal(), drain_timeout).await
+    };
+    let mut exit_code = ExitCode::SUCCESS;
+    if let Err(serve_error) = serve_result {
+        error!("Error in serving the application: {err}", err = serve_error);
+        exit_code = ExitCode::FAILURE;
+    }
+    if let Some(metrics_server) = metrics_server
+        && let Ok(Err(serve_error)) = metrics_server.await
+    {
+        error!("Error in serving the metrics: {err}", err = serve_error);
+        exit_code = ExitCode::FAILURE;
+    }
+
+    // the streams of the server sent events end when the shutdown starts, hence
+    // the connections are closed and no database operation is pending
+    purge_job.abort();
+    let _ = purge_job.await;
+    match database_service.shutdown().await {
+        Ok(()) => info!("Database connection closed"),
+        Err(shutdown_error) => error!(
+            "Error in closing database connection: {err}",
+            err = shutdown_error
+        ),
+    }
+    exit_code
 }
@@ -0,0 +1,242 @@
+//! Metrics module defines the Prometheus metrics of the application
+//! and their encoding in the text exposition format.
+//!
+//! Metrics are kept in a process wide `AppMetrics` instance, returned by
+//! `metrics()`, because they are recorded by components that do not have
+//! access to the application state, like the database service.
+//!
+//! Recorded metrics are:
+//!
+//! - `http_requests_total`: requests by method, route and status
+//! - `http_request_duration_seconds`: request latency by method and route
+//! - `database_transactions_total`: transactions of the requests by outcome
+//! - `database_operation_duration_seconds`: latency of the database operations
+//!   by operation, collection and result
+
+use std::{
+    sync::LazyLock,
+    time::{Duration, Instant},
+};
+
+use axum::http::{Method, StatusCode};
+use prometheus_client::{
+    encoding::{EncodeLabelSet, text::encode},
+    metrics::{
+        counter::Counter,
+        family::Family,
+        histogram::{Histogram, exponential_buckets},
+    },
+    registry::{Registry, Unit},
+};
+
+use crate::DatabaseResult;
+
+static METRICS: LazyLock<AppMetrics> = LazyLock::new(AppMetrics::new);
+
+/// Returns the metrics of the application
+pub fn metrics() -> &'static AppMetrics {
+    &METRICS
+}
+
+/// Outcome of the transaction created for a request
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq)]
+pub enum TransactionOutcome {
+    /// The request succeeded and the transaction has been committed
+    Commit,
+    /// The request failed and the transaction has been aborted
+    Abort,
+    /// The request has been cancelled during the shutdown
+    Cancel,
+}
+
+impl TransactionOutcome {
+    fn label(&self) -> &'static str {
+        match self {
+            TransactionOutcome::Commit => "commit",
+            TransactionOutcome::Abort => "abort",
+            TransactionOutcome::Cancel => "cancel",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct HttpRequestLabels {
+    method: String,
+    route: String,
+    status: u16,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct HttpRouteLabels {
+    method: String,
+    route: String,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct TransactionLabels {
+    outcome: &'static str,
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq, EncodeLabelSet)]
+struct DatabaseOperationLabels {
+    operation: &'static str,
+    collection: &'static str,
+    result: &'static str,
+}
+
+type HistogramFamily<L> = Family<L, Histogram, fn() -> Histogram>;
+
+/// Buckets from 1 millisecond to about 16 seconds
+fn latency_histogram() -> Histogram {
+    Histogram::new(exponential_buckets(0.001, 2.0, 15))
+}
+
+/// Registry and metric families of the application
+pub struct AppMetrics {
+    registry: Registry,
+    http_requests: Family<HttpRequestLabels, Counter>,
+    http_request_duration: HistogramFamily<HttpRouteLabels>,
+    database_transactions: Family<TransactionLabels, Counter>,
+    database_operation_duration: HistogramFamily<DatabaseOperationLabels>,
+}
+
+impl AppMetrics {
+    fn new() -> Self {
+        let mut registry = Registry::default();
+
+        let http_requests = Family::<HttpRequestLabels, Counter>::default();
+        registry.register(
+            "http_requests",
+            "Number of HTTP requests by method, route and status",
+            http_requests.clone(),
+        );
+        let http_request_duration: HistogramFamily<HttpRouteLabels> =
+            Family::new_with_constructor(latency_histogram);
+        registry.register_with_unit(
+            "http_request_duration",
+            "Latency of the HTTP requests by method and route",
+            Unit::Seconds,
+            http_request_duration.clone(),
+        );
+        let database_transactions = Family::<TransactionLabels, Counter>::default();
+        registry.register(
+            "database_transactions",
+            "Number of database transactions of the requests by outcome",
+            database_transactions.clone(),
+        );
+        let database_operation_duration: HistogramFamily<DatabaseOperationLabels> =
+            Family::new_with_constructor(latency_histogram);
+        registry.register_with_unit(
+            "database_operation_duration",
+            "Latency of the database operations by operation, collection and result",
+            Unit::Seconds,
+            database_operation_duration.clone(),
+        );
+
+        Self {
+            registry,
+            http_requests,
+            http_request_duration,
+            database_transactions,
+            database_operation_duration,
+        }
+    }
+
+    /// Records a served request, `route` is the matched path
+    /// like `/api/user/blog/post/{id}` to limit the number of series
+    pub fn observe_http_request(
+        &self,
+        method: &Method,
+        route: &str,
+        status: StatusCode,
+        duration: Duration,
+    ) {
+        self.http_requests
+            .get_or_create(&HttpRequestLabels {
+                method: method.to_string(),
+                route: route.to_string(),
+                status: status.as_u16(),
+            })
+            .inc();
+        self.http_request_duration
+            .get_or_create(&HttpRouteLabels {
+                method: method.to_string(),
+                route: route.to_string(),
+            })
+            .observe(duration.as_secs_f64());
+    }
+
+    pub fn record_transaction(&self, outcome: TransactionOutcome) {
+        self.database_transactions
+            .get_or_create(&TransactionLabels {
+                outcome: outcome.label(),
+            })
+            .inc();
+    }
+
+    /// Runs the database operation recording its latency
+    pub async fn observe_database_operation<R>(
+        &self,
+        operation: &'static str,
+        collection: &'static str,
+        future: impl Future<Output = DatabaseResult<R>>,
+    ) -> DatabaseResult<R> {
+        let start = Instant::now();
+        let result = future.await;
+        self.database_operation_duration
+            .get_or_create(&DatabaseOperationLabels {
+                operation,
+                collection,
+                result: if result.is_ok() { "ok" } else { "error" },
+            })
+            .observe(start.elapsed().as_secs_f64());
+        result
+    }
+
+    /// Encodes the metrics in the Prometheus text format
+    pub fn encode(&self) -> String {
+        let mut buffer = String::new();
+        encode(&mut buffer, &self.registry).expect("writing to a string does not fail");
+        buffer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use axum::http::{Method, StatusCode};
+
+    use super::{AppMetrics, TransactionOutcome};
+
+    #[tokio::test]
+    async fn test_encode_metrics() {
+        let metrics = AppMetrics::new();
+        metrics.observe_http_request(
+            &Method::GET,
+            "/api/user/blog/post/{id}",
+            StatusCode::OK,
+            Duration::from_millis(3),
+        );
+        metrics.record_transaction(TransactionOutcome::Commit);
+        metrics
+            .observe_database_operation("find_one", "user", async { Ok(()) })
+            .await
+            .unwrap();
+
+        let encoded = metrics.encode();
+        assert!(
+            encoded.contains(
+                r#"http_requests_total{method="GET",route="/api/user/blog/post/{id}",status="200"} 1"#
+            ),
+            "{encoded}"
+        );
+        assert!(encoded.contains(
+            r#"http_request_duration_seconds_count{method="GET",route="/api/user/blog/post/{id}"} 1"#
+        ));
+        assert!(encoded.contains(r#"database_transactions_total{outcome="commit"} 1"#));
+        assert!(encoded.contains(
+            r#"database_operation_duration_seconds_count{operation="find_one",collection="user",result="ok"} 1"#
+        ));
+    }
+}
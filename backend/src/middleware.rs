@@ -5,36 +5,138 @@
 //! functions that takes a router, add the middleware
 //! as new layer and then returns it
 
+mod metrics;
+mod rate_limit;
+mod request_id;
 mod transaction;
 
 use std::sync::Arc;
 
-use axum::Router;
+use axum::{
+    Router,
+    extract::MatchedPath,
+    http::{Request, Uri},
+};
+use tower::ServiceBuilder;
 use tower_http::{
     LatencyUnit,
-    cors::{Any, CorsLayer},
-    trace::{DefaultMakeSpan, DefaultOnRequest, DefaultOnResponse, TraceLayer},
+    cors::{AllowOrigin, CorsLayer},
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
+    trace::{DefaultOnRequest, DefaultOnResponse, MakeSpan, TraceLayer},
 };
+use tracing::Span;
+
+pub use request_id::{REQUEST_ID_HEADER, current_request_id};
 
-use crate::{middleware::transaction::mongodb_transaction_middleware, types::AppState};
+use crate::{
+    EnvironmentServiceTrait,
+    enums::{CorsOrigins, RateLimitRoute},
+    middleware::{
+        metrics::metrics_middleware, rate_limit::rate_limit_middleware,
+        request_id::request_id_middleware, transaction::transaction_middleware,
+    },
+    telemetry::set_remote_parent,
+    types::AppState,
+};
 
 /// CORS Layer for the application
 ///
-/// In this simple version, we allow everything,
-/// for production code the scope can be narrowed
-pub fn add_cors_middleware(router: Router<Arc<AppState>>) -> Router<Arc<AppState>> {
+/// The allowed origins, methods and headers are defined by the environment,
+/// by default only the frontend origin is allowed in external mode and only
+/// same origin requests in integrated mode
+pub fn add_cors_middleware(
+    router: Router<Arc<AppState>>,
+    environment_service: &dyn EnvironmentServiceTrait,
+) -> Router<Arc<AppState>> {
+    let allow_origin = match environment_service.get_cors_allowed_origins() {
+        CorsOrigins::Any => AllowOrigin::any(),
+        CorsOrigins::List(origins) => AllowOrigin::list(origins.iter().cloned()),
+    };
     router.layer(
         CorsLayer::new()
-            .allow_origin(Any)
-            .allow_methods(Any)
-            .allow_headers(Any),
+            .allow_origin(allow_origin)
+            .allow_methods(environment_service.get_cors_allowed_methods().to_vec())
+            .allow_headers(environment_service.get_cors_allowed_headers().to_vec())
+            .expose_headers(environment_service.get_cors_exposed_headers().to_vec())
+            .allow_credentials(environment_service.get_cors_allow_credentials())
+            .max_age(environment_service.get_cors_max_age()),
     )
 }
 
+/// Query parameters whose value is not recorded in the request span,
+/// like the token of the event stream
+const REDACTED_QUERY_PARAMETERS: [&str; 1] = ["access_token"];
+
+/// Returns the uri with the values of the sensitive query parameters redacted
+fn redacted_uri(uri: &Uri) -> String {
+    let Some(query) = uri.query() else {
+        return uri.to_string();
+    };
+    let query = query
+        .split('&')
+        .map(|parameter| match parameter.split_once('=') {
+            Some((name, _)) if REDACTED_QUERY_PARAMETERS.contains(&name) => {
+                format!("{name}=[redacted]")
+            }
+            _ => parameter.to_string(),
+        })
+        .collect::<Vec<String>>()
+        .join("&");
+    format!("{}?{query}", uri.path())
+}
+
+/// Span of a request with method, uri and request id, the
+/// headers are included only when `include_headers` is `true`.
+/// Sensitive query parameters of the uri are redacted.
+/// It continues the trace propagated with the `traceparent` header.
+#[derive(Clone)]
+struct RequestSpan {
+    include_headers: bool,
+}
+
+impl<B> MakeSpan<B> for RequestSpan {
+    fn make_span(&mut self, request: &Request<B>) -> Span {
+        let request_id = request
+            .headers()
+            .get(&REQUEST_ID_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default();
+        // the matched route keeps the number of distinct span names bounded
+        let name = request.extensions().get::<MatchedPath>().map_or_else(
+            || request.method().to_string(),
+            |path| format!("{} {}", request.method(), path.as_str()),
+        );
+        let span = if self.include_headers {
+            tracing::info_span!(
+                "request",
+                otel.name = name,
+                otel.kind = "server",
+                method = %request.method(),
+                uri = redacted_uri(request.uri()),
+                version = ?request.version(),
+                request_id,
+                headers = ?request.headers(),
+            )
+        } else {
+            tracing::info_span!(
+                "request",
+                otel.name = name,
+                otel.kind = "server",
+                method = %request.method(),
+                uri = redacted_uri(request.uri()),
+                version = ?request.version(),
+                request_id,
+            )
+        };
+        set_remote_parent(&span, request.headers());
+        span
+    }
+}
+
 /// Add logging layer to record the requests log
 ///
-/// When the argument `include_headers` is `true` then
-/// each span is instrumented with headers as parameters.
+/// Each span contains the request id, when the argument `include_headers`
+/// is `true` then it is instrumented with headers as parameters too.
 pub fn add_logging_middleware(
     router: Router<Arc<AppState>>,
     include_headers: bool,
@@ -42,7 +144,7 @@ pub fn add_logging_middleware(
 ) -> Router<Arc<AppState>> {
     router.layer(
         TraceLayer::new_for_http()
-            .make_span_with(DefaultMakeSpan::new().include_headers(include_headers))
+            .make_span_with(RequestSpan { include_headers })
             .on_request(DefaultOnRequest::new().level(logging_level))
             .on_response(
                 DefaultOnResponse::new()
@@ -52,13 +154,173 @@ pub fn add_logging_middleware(
     )
 }
 
+/// Add the request id layers, they must wrap the logging layer
+///
+/// The `X-Request-Id` header sent by the client is kept, otherwise a new
+/// UUID is generated. The id is returned in the response header and it is
+/// available to the error responses through `current_request_id`.
+pub fn add_request_id_middleware(router: Router<Arc<AppState>>) -> Router<Arc<AppState>> {
+    router.layer(
+        ServiceBuilder::new()
+            .layer(SetRequestIdLayer::new(
+                REQUEST_ID_HEADER.clone(),
+                MakeRequestUuid,
+            ))
+            .layer(PropagateRequestIdLayer::new(REQUEST_ID_HEADER.clone()))
+            .layer(axum::middleware::from_fn(request_id_middleware)),
+    )
+}
+
 /// Add a middleware that creates a database transaction for the request
-pub fn add_mongodb_transaction_middleware(
+pub fn add_transaction_middleware(
     state: Arc<AppState>,
     router: Router<Arc<AppState>>,
 ) -> Router<Arc<AppState>> {
     router.layer(axum::middleware::from_fn_with_state(
         state,
-        mongodb_transaction_middleware,
+        transaction_middleware,
+    ))
+}
+
+/// Add a middleware that rate limits the routes of the router by client IP or user
+/// with the buckets of the given group of routes. It is added with `route_layer`,
+/// hence the routes added to the router afterwards are not limited, and it is not
+/// added when the rate limit is disabled
+pub fn add_rate_limit_middleware(
+    state: Arc<AppState>,
+    route: RateLimitRoute,
+    router: Router<Arc<AppState>>,
+) -> Router<Arc<AppState>> {
+    if !state.environment_service.get_rate_limit_enabled() {
+        return router;
+    }
+    router.route_layer(axum::middleware::from_fn_with_state(
+        (state, route),
+        rate_limit_middleware,
     ))
 }
+
+/// Add a middleware that records the requests metrics,
+/// it is not added when the metrics are disabled
+pub fn add_metrics_middleware(
+    state: Arc<AppState>,
+    router: Router<Arc<AppState>>,
+) -> Router<Arc<AppState>> {
+    if !state.environment_service.get_metrics_enabled() {
+        return router;
+    }
+    router.layer(axum::middleware::from_fn(metrics_middleware))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use axum::{
+        Router,
+        body::Body,
+        http::{HeaderMap, Method, Request, StatusCode, header},
+        routing::get,
+    };
+    use serde_json::json;
+    use tower::ServiceExt;
+
+    use crate::{
+        AppState, EnvironmentService,
+        service::database::AppDatabaseService,
+        test_utils::{TestApp, test_config},
+    };
+
+    use super::{REQUEST_ID_HEADER, add_cors_middleware, redacted_uri};
+
+    /// Sends a preflight request from `origin` to a router with the CORS
+    /// middleware configured with `frontend_mode`
+    async fn preflight(frontend_mode: &str, origin: &str) -> HeaderMap {
+        let mut config = test_config();
+        config.frontend.mode = Some(frontend_mode.into());
+        config.frontend.origin = Some("https://blog.example.com".into());
+        let environment_service = EnvironmentService::from_config(config).unwrap();
+        let state = Arc::new(AppState::new(
+            Box::new(environment_service.clone()),
+            Arc::new(AppDatabaseService::default()),
+        ));
+        let router = add_cors_middleware(
+            Router::new().route("/", get(|| async { "Ok!" })),
+            &environment_service,
+        )
+        .with_state(state);
+
+        let response = router
+            .oneshot(
+                Request::builder()
+                    .method(Method::OPTIONS)
+                    .uri("/")
+                    .header(header::ORIGIN, origin)
+                    .header(header::ACCESS_CONTROL_REQUEST_METHOD, "PUT")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        response.headers().clone()
+    }
+
+    #[tokio::test]
+    async fn test_external_mode_allows_frontend_origin() {
+        let headers = preflight("external", "https://blog.example.com").await;
+        assert_eq!(
+            headers[header::ACCESS_CONTROL_ALLOW_ORIGIN],
+            "https://blog.example.com"
+        );
+        assert_eq!(headers[header::ACCESS_CONTROL_MAX_AGE], "3600");
+
+        let headers = preflight("external", "https://evil.example.com").await;
+        assert!(!headers.contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+    }
+
+    #[tokio::test]
+    async fn test_integrated_mode_allows_same_origin_only() {
+        let headers = preflight("integrated:./dist", "https://blog.example.com").await;
+        assert!(!headers.contains_key(header::ACCESS_CONTROL_ALLOW_ORIGIN));
+    }
+
+    #[tokio::test]
+    async fn test_request_id_is_generated() {
+        let app = TestApp::new();
+        let response = app.call(Method::GET, "/", None, None).await;
+        let request_id = response.headers[&REQUEST_ID_HEADER].to_str().unwrap();
+        assert_eq!(request_id.len(), 36, "{request_id} is not a UUID");
+    }
+
+    #[tokio::test]
+    async fn test_request_id_is_propagated_to_error_response() {
+        let app = TestApp::new();
+        let request = TestApp::request(Method::POST, "/api/guest/login", None, Some(json!({})))
+            .header(&REQUEST_ID_HEADER, "client-request-1")
+            .body(Body::from(
+                json!({ "username": "nobody", "password": "password" }).to_string(),
+            ))
+            .unwrap();
+
+        let response = app.send(request).await;
+        assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+        assert_eq!(response.headers[&REQUEST_ID_HEADER], "client-request-1");
+        assert_eq!(response.body["requestId"], "client-request-1");
+    }
+
+    #[test]
+    fn test_redacted_uri() {
+        assert_eq!(
+            redacted_uri(
+                &"/api/user/blog/stream?access_token=secret&x=1"
+                    .parse()
+                    .unwrap()
+            ),
+            "/api/user/blog/stream?access_token=[redacted]&x=1"
+        );
+        assert_eq!(
+            redacted_uri(&"/api/public/blog/post?page=2".parse().unwrap()),
+            "/api/public/blog/post?page=2"
+        );
+    }
+}
@@ -0,0 +1,27 @@
+use std::time::Instant;
+
+use axum::{body::Body, extract::MatchedPath, http::Request, middleware::Next, response::Response};
+
+use crate::metrics::metrics;
+
+/// Route label of the requests that do not match any route, like the
+/// static content of the integrated frontend
+const UNMATCHED_ROUTE: &str = "unmatched";
+
+/// Records the number and the latency of the requests by method, route
+/// and status. The route is the matched path, hence the requests to
+/// `/api/user/blog/post/{id}` share the same series whatever the id.
+pub async fn metrics_middleware(request: Request<Body>, next: Next) -> Response {
+    let method = request.method().clone();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or(UNMATCHED_ROUTE.to_string(), |path| {
+            path.as_str().to_string()
+        });
+
+    let start = Instant::now();
+    let response = next.run(request).await;
+    metrics().observe_http_request(&method, &route, response.status(), start.elapsed());
+    response
+}
@@ -0,0 +1,232 @@
+use std::sync::Arc;
+
+use axum::{
+    body::Body,
+    extract::{ConnectInfo, State},
+    http::{HeaderMap, Request, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use chrono::Utc;
+use tracing::debug;
+
+use crate::{
+    AppState, auth::JWTAuthClaim, enums::RateLimitRoute, error::AppError, server::ClientAddr,
+    service::rate_limit::RateLimitDecision,
+};
+
+/// Header set by the reverse proxy with the address of the client
+const REAL_IP_HEADER: &str = "x-real-ip";
+
+/// Address of the client, read from the `X-Real-IP` header only when the
+/// application is behind a trusted reverse proxy
+fn client_ip(request: &Request<Body>, trust_proxy_headers: bool) -> String {
+    let proxy_ip = trust_proxy_headers
+        .then(|| request.headers().get(REAL_IP_HEADER))
+        .flatten()
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<std::net::IpAddr>().ok());
+    match proxy_ip {
+        Some(ip) => ip.to_string(),
+        None => request
+            .extensions()
+            .get::<ConnectInfo<ClientAddr>>()
+            .map_or("unknown".into(), |ConnectInfo(ClientAddr(address))| {
+                address.ip().to_string()
+            }),
+    }
+}
+
+/// User id of the bearer token, the token is validated again by the route
+fn user_id(headers: &HeaderMap, state: &AppState) -> Option<String> {
+    let token = headers
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")?;
+    JWTAuthClaim::decode_token(
+        token,
+        state.environment_service.get_authentication_jwt_decoding(),
+    )
+    .ok()
+    .map(|claim| claim.user_id.to_hex())
+}
+
+/// Limits the requests with a token bucket for each client and group of routes,
+/// the group is given by the router that attached the middleware to its routes.
+///
+/// Guest routes are limited by client IP, while authenticated routes are
+/// limited by the user of the token. Requests with a missing or invalid token
+/// are limited by client IP, they are rejected by the route anyway.
+///
+/// When the bucket is empty the response is `429 Too Many Requests`
+/// with the `Retry-After` header.
+pub async fn rate_limit_middleware(
+    State((app_state, route)): State<(Arc<AppState>, RateLimitRoute)>,
+    request: Request<Body>,
+    next: Next,
+) -> Response {
+    let environment_service = &app_state.environment_service;
+
+    let identity = match route {
+        RateLimitRoute::PublishPost | RateLimitRoute::User => {
+            user_id(request.headers(), &app_state).map(|user_id| format!("user:{user_id}"))
+        }
+        RateLimitRoute::Login | RateLimitRoute::Register | RateLimitRoute::Guest => None,
+    }
+    .unwrap_or_else(|| {
+        format!(
+            "ip:{ip}",
+            ip = client_ip(
+                &request,
+                environment_service.get_rate_limit_trust_proxy_headers()
+            )
+        )
+    });
+    let key = format!("{route}:{identity}");
+
+    match app_state
+        .rate_limit_service
+        .acquire(&key, environment_service.get_rate_limit(route), Utc::now())
+        .await
+    {
+        RateLimitDecision::Allowed => next.run(request).await,
+        RateLimitDecision::Limited { retry_after } => {
+            debug!("Request of {key} is rate limited for {retry_after:?}");
+            AppError::TooManyRequests {
+                retry_after: retry_after.as_secs_f64().ceil() as u64,
+            }
+            .into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{Method, StatusCode, header};
+    use serde_json::json;
+
+    use crate::test_utils::{TestApp, test_config};
+
+    /// Builds the test app with small limits, the requests sent with `oneshot`
+    /// have no client address hence all the guests share the same bucket
+    fn rate_limited_app() -> TestApp {
+        let mut config = test_config();
+        config.rate_limit.login = Some("2/60".into());
+        config.rate_limit.user = Some("3/60".into());
+        TestApp::with_config(config)
+    }
+
+    #[tokio::test]
+    async fn test_login_is_limited_by_client() {
+        let app = rate_limited_app();
+        app.register_user("marcel", "password").await;
+        app.login("marcel", "password").await;
+        app.login("marcel", "password").await;
+
+        let response = app
+            .call(
+                Method::POST,
+                "/api/guest/login",
+                None,
+                Some(json!({ "username": "marcel", "password": "password" })),
+            )
+            .await;
+        assert_eq!(response.status, StatusCode::TOO_MANY_REQUESTS);
+        // a token is refilled every 30 seconds, minus the time spent by the logins
+        let retry_after: u64 = response.headers[header::RETRY_AFTER]
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!((1..=30).contains(&retry_after), "{retry_after}");
+    }
+
+    #[tokio::test]
+    async fn test_authenticated_routes_are_limited_by_user() {
+        let app = rate_limited_app();
+        let marcel = app.register_user("marcel", "password").await;
+        let sophie = app.register_user("sophie", "password").await;
+
+        for _ in 0..3 {
+            let response = app
+                .call(Method::GET, "/api/user/info", Some(&marcel.token), None)
+                .await;
+            assert_eq!(response.status, StatusCode::OK);
+        }
+        let response = app
+            .call(Method::GET, "/api/user/info", Some(&marcel.token), None)
+            .await;
+        assert_eq!(response.status, StatusCode::TOO_MANY_REQUESTS);
+        assert!(response.headers.contains_key(header::RETRY_AFTER));
+
+        // the other users have their own bucket
+        let response = app
+            .call(Method::GET, "/api/user/info", Some(&sophie.token), None)
+            .await;
+        assert_eq!(response.status, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_published_posts_have_their_own_limit() {
+        let mut config = test_config();
+        config.rate_limit.publish_post = Some("1/60".into());
+        config.rate_limit.user = Some("2/60".into());
+        let app = TestApp::with_config(config);
+        let marcel = app.register_user("marcel", "password").await;
+        let post = json!({ "title": "Title", "content": "Content" });
+
+        let response = app
+            .call(
+                Method::POST,
+                "/api/user/blog/post",
+                Some(&marcel.token),
+                Some(post.clone()),
+            )
+            .await;
+        assert_eq!(response.status, StatusCode::OK);
+        let response = app
+            .call(
+                Method::POST,
+                "/api/user/blog/post",
+                Some(&marcel.token),
+                Some(post),
+            )
+            .await;
+        assert_eq!(response.status, StatusCode::TOO_MANY_REQUESTS);
+
+        // the other methods of the path use the bucket of the authenticated routes
+        for _ in 0..2 {
+            let response = app
+                .call(
+                    Method::GET,
+                    "/api/user/blog/post",
+                    Some(&marcel.token),
+                    None,
+                )
+                .await;
+            assert_eq!(response.status, StatusCode::OK);
+        }
+        let response = app
+            .call(
+                Method::GET,
+                "/api/user/blog/post",
+                Some(&marcel.token),
+                None,
+            )
+            .await;
+        assert_eq!(response.status, StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    #[tokio::test]
+    async fn test_disabled_rate_limit() {
+        let mut config = test_config();
+        config.rate_limit.enabled = Some("false".into());
+        config.rate_limit.login = Some("1/60".into());
+        let app = TestApp::with_config(config);
+        app.register_user("marcel", "password").await;
+        for _ in 0..3 {
+            app.login("marcel", "password").await;
+        }
+    }
+}
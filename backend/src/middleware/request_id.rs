@@ -0,0 +1,35 @@
+use axum::{
+    body::Body,
+    http::{HeaderName, Request},
+    middleware::Next,
+    response::Response,
+};
+use tower_http::request_id::RequestId;
+
+/// Header containing the id of the request, it is accepted from the
+/// client or generated, and returned in the response
+pub static REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+tokio::task_local! {
+    /// Id of the request served by the current task
+    static REQUEST_ID: String;
+}
+
+/// Returns the id of the request served by the current task, it is `None`
+/// outside of the requests, like in background jobs
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(Clone::clone).ok()
+}
+
+/// Makes the request id set by `SetRequestIdLayer` available to
+/// `current_request_id` while the request is served, so that it can be
+/// added to the error responses and logs without passing it around
+pub async fn request_id_middleware(request: Request<Body>, next: Next) -> Response {
+    let request_id = request
+        .extensions()
+        .get::<RequestId>()
+        .and_then(|request_id| request_id.header_value().to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    REQUEST_ID.scope(request_id, next.run(request)).await
+}
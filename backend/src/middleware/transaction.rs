@@ -10,15 +10,49 @@ use axum::{
 use tokio::sync::RwLock;
 use tracing::{debug, error};
 
-use crate::service::database::transaction::DatabaseTransactionTrait;
-use crate::{AppState, service::database::DatabaseServiceTrait};
+use crate::service::database::transaction::{AppDatabaseTransaction, DatabaseTransactionTrait};
+use crate::{
+    AppState,
+    metrics::{TransactionOutcome, metrics},
+    service::database::DatabaseServiceTrait,
+};
+
+/// Aborts the transaction when the request is dropped before completing,
+/// for example when the in flight requests are cancelled at the end of
+/// the graceful shutdown period.
+///
+/// The abort is spawned because it is asynchronous and cannot run in `drop`.
+struct TransactionGuard {
+    transaction: Option<Arc<RwLock<AppDatabaseTransaction>>>,
+}
+
+impl TransactionGuard {
+    /// Disarms the guard because the transaction is committed or aborted by the middleware
+    fn complete(mut self) {
+        self.transaction.take();
+    }
+}
+
+impl Drop for TransactionGuard {
+    fn drop(&mut self) {
+        if let Some(transaction) = self.transaction.take() {
+            debug!("Request has been cancelled, aborting transaction");
+            metrics().record_transaction(TransactionOutcome::Cancel);
+            tokio::spawn(async move {
+                if let Err(err) = transaction.write().await.abort_transaction().await {
+                    error!("Error in aborting transaction of cancelled request: {err}");
+                }
+            });
+        }
+    }
+}
 
-/// Creates a mongodb transaction if the request is not a GET
+/// Creates a database transaction if the request is not a GET
 /// and put it in the request extensions to be used by routes.
 ///
 /// If the request is success then the transaction is committed
-/// otherwise it is aborted
-pub async fn mongodb_transaction_middleware(
+/// otherwise it is aborted, also when the request is cancelled
+pub async fn transaction_middleware(
     State(app_state): State<Arc<AppState>>,
     mut request: Request<Body>,
     next: Next,
@@ -42,8 +76,12 @@ where
         )?));
 
         request.extensions_mut().insert(transaction.clone());
+        let transaction_guard = TransactionGuard {
+            transaction: Some(transaction.clone()),
+        };
 
         let response = next.run(request).await;
+        transaction_guard.complete();
         let mut guard = transaction.write().await;
 
         if response.status().is_success() {
@@ -58,6 +96,7 @@ where
                 );
                 axum::http::StatusCode::INTERNAL_SERVER_ERROR
             })?;
+            metrics().record_transaction(TransactionOutcome::Commit);
         } else {
             debug!(
                 "Response status {status}, aborting transaction",
@@ -71,6 +110,7 @@ where
                 );
                 axum::http::StatusCode::INTERNAL_SERVER_ERROR
             })?;
+            metrics().record_transaction(TransactionOutcome::Abort);
         }
         Ok(response)
     } else {
@@ -0,0 +1,392 @@
+//! Migration module defines the versioned schema migrations used to
+//! evolve the documents stored in the database.
+//!
+//! Each migration implements the `Migration` trait and it is identified by
+//! a unique and increasing version number. The `MigrationRunner` applies the
+//! pending migrations in order, each one inside its own database transaction,
+//! and records them in the `_migrations` collection so that they are applied
+//! only once.
+//!
+//! Migrations must be idempotent: if the process stops between the execution
+//! of a migration and its commit, running it again must produce the same result.
+//!
+//! Modules:
+//!
+//! - `user_publisher`: adds the `publisher` field to users created before its introduction
+//! - `document_version`: adds the `version` field to users and blog posts
+//! - `post_visibility`: makes public the blog posts created before the `visibility` field
+//! - `post_updated_at`: adds the `updated_at` field to blog posts
+//! - `rate_limit_bucket_key`: adds the unique index on the key of the rate limit buckets
+
+use std::{collections::HashSet, sync::Arc};
+
+use async_trait::async_trait;
+use bson::doc;
+use chrono::{DateTime, Utc};
+use tokio::sync::RwLock;
+use tracing::{debug, error, info};
+
+use crate::{
+    DatabaseResult, database_document,
+    error::DatabaseError,
+    service::database::{DatabaseServiceTrait, transaction::DatabaseTransactionTrait},
+};
+
+mod document_version;
+mod post_updated_at;
+mod post_visibility;
+mod rate_limit_bucket_key;
+mod user_publisher;
+
+database_document!(
+    #[doc = "Record of an applied migration"]
+    MigrationRecord,
+    "_migrations",
+    version: i64,
+    name: String,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    applied_at: DateTime<Utc>,
+);
+
+/// Trait that defines a single versioned migration
+///
+/// The `up` method receives the database service and the transaction in which
+/// every operation must be done, so that the migration and its record are
+/// committed or aborted together.
+#[async_trait]
+pub trait Migration<D>: Send + Sync
+where
+    D: DatabaseServiceTrait + Send + Sync,
+{
+    /// Unique version of the migration, migrations are applied in ascending order
+    fn version(&self) -> i64;
+
+    /// Short human readable name of the migration
+    fn name(&self) -> &'static str;
+
+    /// Apply the migration inside the given transaction
+    async fn up(
+        &self,
+        database_service: Arc<D>,
+        transaction: Arc<RwLock<D::Transaction>>,
+    ) -> DatabaseResult<()>;
+}
+
+/// Returns all the migrations of the application
+///
+/// New migrations must be added at the end of the list with a greater version
+pub fn registered_migrations<D>() -> Vec<Box<dyn Migration<D>>>
+where
+    D: DatabaseServiceTrait + Send + Sync + 'static,
+{
+    vec![
+        Box::new(user_publisher::UserPublisherMigration),
+        Box::new(document_version::DocumentVersionMigration),
+        Box::new(post_visibility::PostVisibilityMigration),
+        Box::new(post_updated_at::PostUpdatedAtMigration),
+        Box::new(rate_limit_bucket_key::RateLimitBucketKeyMigration),
+    ]
+}
+
+/// Applies the pending migrations to the database
+pub struct MigrationRunner<D>
+where
+    D: DatabaseServiceTrait + Send + Sync,
+{
+    database_service: Arc<D>,
+    migrations: Vec<Box<dyn Migration<D>>>,
+}
+
+impl<D> MigrationRunner<D>
+where
+    D: DatabaseServiceTrait + Send + Sync + 'static,
+{
+    /// Creates a runner with the registered migrations of the application
+    pub fn new(database_service: Arc<D>) -> Self {
+        Self::with_migrations(database_service, registered_migrations())
+    }
+
+    /// Creates a runner with a custom list of migrations, they are sorted by version
+    pub fn with_migrations(
+        database_service: Arc<D>,
+        mut migrations: Vec<Box<dyn Migration<D>>>,
+    ) -> Self {
+        migrations.sort_by_key(|migration| migration.version());
+        Self {
+            database_service,
+            migrations,
+        }
+    }
+
+    /// Applies the migrations that are not yet recorded in the database
+    /// and returns the versions that have been applied.
+    ///
+    /// Each migration is executed in its own transaction together with
+    /// the insertion of its record. If a migration fails then the transaction
+    /// is aborted and the following migrations are not applied.
+    pub async fn run(&self) -> DatabaseResult<Vec<i64>> {
+        self.validate()?;
+
+        let applied_versions = self
+            .database_service
+            .find_many::<MigrationRecord>(doc! {})
+            .await?
+            .into_iter()
+            .map(|record| *record.version())
+            .collect::<HashSet<i64>>();
+
+        let mut newly_applied = vec![];
+        for migration in self.migrations.iter() {
+            if applied_versions.contains(&migration.version()) {
+                debug!(
+                    "Migration {version} `{name}` already applied",
+                    version = migration.version(),
+                    name = migration.name()
+                );
+                continue;
+            }
+
+            info!(
+                "Applying migration {version} `{name}`",
+                version = migration.version(),
+                name = migration.name()
+            );
+            let transaction = Arc::new(RwLock::new(self.database_service.new_transaction().await?));
+
+            let result = self.apply(migration.as_ref(), transaction.clone()).await;
+
+            let mut guard = transaction.write().await;
+            if let Err(err) = result {
+                error!(
+                    "Migration {version} `{name}` failed, aborting transaction",
+                    version = migration.version(),
+                    name = migration.name()
+                );
+                guard.abort_transaction().await?;
+                return Err(DatabaseError::MigrationError(format!(
+                    "migration {} `{}` failed: {err}",
+                    migration.version(),
+                    migration.name()
+                )));
+            }
+            guard.commit_transaction().await?;
+            newly_applied.push(migration.version());
+        }
+
+        Ok(newly_applied)
+    }
+
+    /// Runs the migration and records it in the same transaction
+    async fn apply(
+        &self,
+        migration: &dyn Migration<D>,
+        transaction: Arc<RwLock<D::Transaction>>,
+    ) -> DatabaseResult<()> {
+        migration
+            .up(self.database_service.clone(), transaction.clone())
+            .await?;
+
+        MigrationRecordBuilder::new(self.database_service.clone())
+            .version(migration.version())
+            .name(migration.name().to_string())
+            .applied_at(Utc::now())
+            .build(Some(transaction))
+            .await?;
+        Ok(())
+    }
+
+    /// Verify that there are no migrations with the same version
+    fn validate(&self) -> DatabaseResult<()> {
+        let mut versions = HashSet::new();
+        for migration in self.migrations.iter() {
+            if !versions.insert(migration.version()) {
+                return Err(DatabaseError::MigrationError(format!(
+                    "duplicated migration version {}",
+                    migration.version()
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use async_trait::async_trait;
+    use bson::doc;
+    use tokio::sync::RwLock;
+
+    use crate::{
+        DatabaseResult,
+        error::DatabaseError,
+        migration::{Migration, MigrationRecord, MigrationRunner},
+        model::{BlogPost, RateLimitBucket, User},
+        service::database::{DatabaseServiceTrait, memory_service::MemoryDatabaseService},
+    };
+
+    /// Migration that changes the users and then fails
+    struct FailingMigration;
+
+    #[async_trait]
+    impl Migration<MemoryDatabaseService> for FailingMigration {
+        fn version(&self) -> i64 {
+            1000
+        }
+
+        fn name(&self) -> &'static str {
+            "failing"
+        }
+
+        async fn up(
+            &self,
+            database_service: Arc<MemoryDatabaseService>,
+            transaction: Arc<RwLock<<MemoryDatabaseService as DatabaseServiceTrait>::Transaction>>,
+        ) -> DatabaseResult<()> {
+            database_service
+                .insert_one::<User>(
+                    doc! {
+                        "first_name": "Albert",
+                        "last_name": "Camus",
+                        "username": "albert",
+                        "password_hash": "hash",
+                        "admin": false,
+                        "publisher": false,
+                        "version": 0_i64
+                    },
+                    Some(transaction.clone()),
+                )
+                .await?;
+            database_service
+                .update_many::<User>(
+                    doc! {},
+                    doc! { "$set": { "admin": true } },
+                    Some(transaction),
+                )
+                .await?;
+            Err(DatabaseError::DatabaseOperationError("failure".into()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run_is_idempotent() {
+        let database_service = Arc::new(MemoryDatabaseService::default());
+        database_service
+            .insert_one::<User>(
+                doc! {
+                    "first_name": "Marcel",
+                    "last_name": "Proust",
+                    "username": "marcel",
+                    "password_hash": "hash",
+                    "admin": false
+                },
+                None,
+            )
+            .await
+            .unwrap();
+        database_service
+            .insert_one::<BlogPost>(
+                doc! {
+                    "title": "title",
+                    "content": "content",
+                    "creation_date": bson::DateTime::now(),
+                    "user_id": bson::oid::ObjectId::new(),
+                    "username": "marcel"
+                },
+                None,
+            )
+            .await
+            .unwrap();
+
+        let runner = MigrationRunner::new(database_service.clone());
+        let applied = runner.run().await.unwrap();
+        assert_eq!(applied, vec![1, 2, 3, 4, 5]);
+
+        let users = database_service.find_many::<User>(doc! {}).await.unwrap();
+        assert_eq!(users.len(), 1);
+        assert!(*users[0].publisher());
+        assert_eq!(
+            database_service
+                .count_documents::<User>(doc! { "version": 0_i64 })
+                .await
+                .unwrap(),
+            1
+        );
+        assert_eq!(
+            database_service
+                .count_documents::<BlogPost>(doc! { "visibility": "public", "version": 0_i64 })
+                .await
+                .unwrap(),
+            1
+        );
+        let posts = database_service
+            .find_many::<BlogPost>(doc! {})
+            .await
+            .unwrap();
+        assert_eq!(posts[0].updated_at(), posts[0].creation_date());
+        let bucket = doc! { "key": "login:127.0.0.1", "tokens": 1.0, "version": 0_i64 };
+        database_service
+            .insert_one::<RateLimitBucket>(bucket.clone(), None)
+            .await
+            .unwrap();
+        assert!(matches!(
+            database_service
+                .insert_one::<RateLimitBucket>(bucket, None)
+                .await,
+            Err(DatabaseError::DuplicateKey(_))
+        ));
+
+        let applied = runner.run().await.unwrap();
+        assert!(applied.is_empty());
+        assert_eq!(
+            database_service
+                .count_documents::<MigrationRecord>(doc! {})
+                .await
+                .unwrap(),
+            5
+        );
+    }
+
+    #[tokio::test]
+    async fn test_failed_migration_is_not_recorded() {
+        let database_service = Arc::new(MemoryDatabaseService::default());
+        database_service
+            .insert_one::<User>(
+                doc! {
+                    "first_name": "Marcel",
+                    "last_name": "Proust",
+                    "username": "marcel",
+                    "password_hash": "hash",
+                    "admin": false,
+                    "publisher": false,
+                    "version": 0_i64
+                },
+                None,
+            )
+            .await
+            .unwrap();
+
+        let runner = MigrationRunner::with_migrations(
+            database_service.clone(),
+            vec![Box::new(FailingMigration)],
+        );
+        assert!(matches!(
+            runner.run().await,
+            Err(DatabaseError::MigrationError(_))
+        ));
+        assert_eq!(
+            database_service
+                .count_documents::<MigrationRecord>(doc! {})
+                .await
+                .unwrap(),
+            0
+        );
+
+        // the partial writes of the migration are rolled back
+        let users = database_service.find_many::<User>(doc! {}).await.unwrap();
+        assert_eq!(users.len(), 1);
+        assert_eq!(users[0].username(), "marcel");
+        assert!(!*users[0].admin());
+    }
+}
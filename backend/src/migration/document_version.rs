@@ -0,0 +1,52 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use bson::doc;
+use tokio::sync::RwLock;
+
+use crate::{
+    DatabaseResult,
+    migration::Migration,
+    model::{BlogPost, User},
+    service::database::DatabaseServiceTrait,
+};
+
+/// Adds the `version` field to users and blog posts created before
+/// they were declared as versioned documents, so that they can be
+/// updated with `update_one_versioned`.
+pub struct DocumentVersionMigration;
+
+#[async_trait]
+impl<D> Migration<D> for DocumentVersionMigration
+where
+    D: DatabaseServiceTrait + Send + Sync + 'static,
+{
+    fn version(&self) -> i64 {
+        2
+    }
+
+    fn name(&self) -> &'static str {
+        "document_version"
+    }
+
+    async fn up(
+        &self,
+        database_service: Arc<D>,
+        transaction: Arc<RwLock<D::Transaction>>,
+    ) -> DatabaseResult<()> {
+        database_service
+            .update_many::<User>(
+                doc! { "version": { "$exists": false } },
+                doc! { "$set": { "version": 0_i64 } },
+                Some(transaction.clone()),
+            )
+            .await?;
+        database_service
+            .update_many::<BlogPost>(
+                doc! { "version": { "$exists": false } },
+                doc! { "$set": { "version": 0_i64 } },
+                Some(transaction),
+            )
+            .await
+    }
+}
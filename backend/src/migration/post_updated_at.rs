@@ -0,0 +1,64 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use bson::{Bson, Document, doc};
+use tokio::sync::RwLock;
+
+use crate::{
+    DatabaseResult,
+    migration::Migration,
+    model::BlogPost,
+    service::database::{DatabaseServiceTrait, document::DELETED_AT_FIELD},
+};
+
+/// Adds the `updated_at` field to blog posts created before its
+/// introduction, it is the deletion date of the soft deleted posts
+/// and the creation date of the other ones.
+pub struct PostUpdatedAtMigration;
+
+#[async_trait]
+impl<D> Migration<D> for PostUpdatedAtMigration
+where
+    D: DatabaseServiceTrait + Send + Sync + 'static,
+{
+    fn version(&self) -> i64 {
+        4
+    }
+
+    fn name(&self) -> &'static str {
+        "post_updated_at"
+    }
+
+    async fn up(
+        &self,
+        database_service: Arc<D>,
+        transaction: Arc<RwLock<D::Transaction>>,
+    ) -> DatabaseResult<()> {
+        // the soft deleted posts are returned only by a query on `deleted_at`
+        for query in [
+            doc! { "updated_at": { "$exists": false } },
+            doc! { "updated_at": { "$exists": false }, DELETED_AT_FIELD: { "$ne": null } },
+        ] {
+            let posts = database_service
+                .find_many_projection::<BlogPost, Document>(
+                    query,
+                    doc! { "_id": 1, "creation_date": 1, DELETED_AT_FIELD: 1 },
+                )
+                .await?;
+            for post in posts {
+                let updated_at = match post.get(DELETED_AT_FIELD) {
+                    Some(Bson::DateTime(deleted_at)) => Bson::DateTime(*deleted_at),
+                    _ => post.get("creation_date").cloned().unwrap_or(Bson::Null),
+                };
+                database_service
+                    .update_one::<BlogPost>(
+                        doc! { "_id": post.get("_id").cloned().unwrap_or(Bson::Null) },
+                        doc! { "$set": { "updated_at": updated_at } },
+                        Some(transaction.clone()),
+                    )
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,43 @@
+use std::sync::Arc;
+
+use api_types::user::PostVisibility;
+use async_trait::async_trait;
+use bson::doc;
+use tokio::sync::RwLock;
+
+use crate::{
+    DatabaseResult, migration::Migration, model::BlogPost, service::database::DatabaseServiceTrait,
+};
+
+/// Adds the `visibility` field to blog posts created before its
+/// introduction, they were readable only by logged users but the
+/// previous behaviour did not distinguish them, so they become public.
+pub struct PostVisibilityMigration;
+
+#[async_trait]
+impl<D> Migration<D> for PostVisibilityMigration
+where
+    D: DatabaseServiceTrait + Send + Sync + 'static,
+{
+    fn version(&self) -> i64 {
+        3
+    }
+
+    fn name(&self) -> &'static str {
+        "post_visibility"
+    }
+
+    async fn up(
+        &self,
+        database_service: Arc<D>,
+        transaction: Arc<RwLock<D::Transaction>>,
+    ) -> DatabaseResult<()> {
+        database_service
+            .update_many::<BlogPost>(
+                doc! { "visibility": { "$exists": false } },
+                doc! { "$set": { "visibility": PostVisibility::Public.as_str() } },
+                Some(transaction),
+            )
+            .await
+    }
+}
@@ -0,0 +1,45 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use bson::doc;
+use tokio::sync::RwLock;
+
+use crate::{
+    DatabaseResult, migration::Migration, model::RateLimitBucket,
+    service::database::DatabaseServiceTrait,
+};
+
+/// Adds the unique index on the key of the rate limit buckets, so that
+/// concurrent requests of a new client cannot create two buckets.
+///
+/// The existing buckets are removed first because they may already contain
+/// duplicates, it only resets the rate limits once. Both operations are
+/// done outside the transaction, which cannot create the index.
+pub struct RateLimitBucketKeyMigration;
+
+#[async_trait]
+impl<D> Migration<D> for RateLimitBucketKeyMigration
+where
+    D: DatabaseServiceTrait + Send + Sync + 'static,
+{
+    fn version(&self) -> i64 {
+        5
+    }
+
+    fn name(&self) -> &'static str {
+        "rate_limit_bucket_key"
+    }
+
+    async fn up(
+        &self,
+        database_service: Arc<D>,
+        _transaction: Arc<RwLock<D::Transaction>>,
+    ) -> DatabaseResult<()> {
+        database_service
+            .delete_many::<RateLimitBucket>(doc! {}, None)
+            .await?;
+        database_service
+            .create_unique_index::<RateLimitBucket>("key")
+            .await
+    }
+}
@@ -0,0 +1,43 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use bson::doc;
+use tokio::sync::RwLock;
+
+use crate::{
+    DatabaseResult, migration::Migration, model::User, service::database::DatabaseServiceTrait,
+};
+
+/// Adds the `publisher` field to the users created before its introduction.
+///
+/// Registered users are publishers by default, hence the existing ones
+/// keep the ability to publish posts.
+pub struct UserPublisherMigration;
+
+#[async_trait]
+impl<D> Migration<D> for UserPublisherMigration
+where
+    D: DatabaseServiceTrait + Send + Sync + 'static,
+{
+    fn version(&self) -> i64 {
+        1
+    }
+
+    fn name(&self) -> &'static str {
+        "user_publisher"
+    }
+
+    async fn up(
+        &self,
+        database_service: Arc<D>,
+        transaction: Arc<RwLock<D::Transaction>>,
+    ) -> DatabaseResult<()> {
+        database_service
+            .update_many::<User>(
+                doc! { "publisher": { "$exists": false } },
+                doc! { "$set": { "publisher": true } },
+                Some(transaction),
+            )
+            .await
+    }
+}
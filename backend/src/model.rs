@@ -1,11 +1,13 @@
 use crate::database_document;
-use bson::oid::ObjectId;
+use bson::{Document, oid::ObjectId};
 use chrono::{DateTime, Utc};
 
 database_document!(
     #[doc = "User document"]
     User,
     "user",
+    versioned,
+    soft_delete,
     first_name: String,
     last_name: String,
     username: String,
@@ -18,10 +20,40 @@ database_document!(
     #[doc = "Blog post document"]
     BlogPost,
     "blog_post",
+    versioned,
+    soft_delete,
     title: String,
     content: String,
     #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
     creation_date: DateTime<Utc>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    updated_at: DateTime<Utc>,
     user_id: ObjectId,
     username: String,
+    visibility: String,
+);
+
+database_document!(
+    #[doc = "Audit event recording a mutating operation done by a user"]
+    AuditEvent,
+    "audit_event",
+    actor_id: ObjectId,
+    action: String,
+    target_collection: String,
+    target_id: ObjectId,
+    before: Option<Document>,
+    after: Option<Document>,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    creation_date: DateTime<Utc>,
+);
+
+database_document!(
+    #[doc = "Token bucket of a rate limited client, used by the database rate limit store"]
+    RateLimitBucket,
+    "rate_limit_bucket",
+    versioned,
+    key: String,
+    tokens: f64,
+    #[serde(with = "bson::serde_helpers::chrono_datetime_as_bson_datetime")]
+    updated_at: DateTime<Utc>,
 );
@@ -1,12 +1,115 @@
-use axum::response::Html;
+#[cfg(not(feature = "ssr"))]
+use std::path::Path;
+use std::sync::Arc;
+
+#[cfg(not(feature = "ssr"))]
+use axum::routing::get_service;
+use axum::{Router, response::Html, routing::get};
+#[cfg(not(feature = "ssr"))]
+use tower_http::services::{ServeDir, ServeFile};
+
+use crate::{AppState, FrontendMode, middleware};
+
 mod admin;
+mod feed;
 mod guest;
+mod health;
+mod metrics;
+mod openapi;
+mod public;
+#[cfg(feature = "ssr")]
+mod ssr;
 mod user;
 
 pub use admin::add_admin_router;
+pub use feed::add_feed_router;
 pub use guest::add_guest_router;
+pub use health::add_health_router;
+pub use metrics::add_metrics_router;
+pub use openapi::{ApiDoc, add_openapi_router};
+pub use public::add_public_router;
 pub use user::add_user_router;
 
+#[cfg(feature = "ssr")]
+use ssr::add_frontend_router;
+
+/// Path of the Prometheus metrics endpoint
+pub const METRICS_PATH: &str = "/metrics";
+
 pub async fn health_handler() -> Html<&'static str> {
     Html("Ok!")
 }
+
+/// Serves the static content of the frontend, the root and the paths that do not
+/// match a file return index.html so that the frontend handles its routes
+#[cfg(not(feature = "ssr"))]
+fn add_frontend_router(
+    path: &str,
+    _state: Arc<AppState>,
+    base_router: Router<Arc<AppState>>,
+) -> Router<Arc<AppState>> {
+    base_router
+        .route(
+            "/",
+            get_service(ServeFile::new(Path::new(path).join("index.html"))),
+        )
+        .fallback_service(get_service(
+            ServeDir::new(path)
+                .not_found_service(ServeFile::new(Path::new(path).join("index.html"))),
+        ))
+}
+
+/// Build our application routes. According to frontend mode we change the root behavior.
+/// When frontend mode is integrated, the root returns index.html and the other static content
+/// via fallback service. With the `ssr` feature the pages are rendered by the server instead.
+///
+/// When frontend mode is external then the root returns standard 200 OK
+pub fn build_app(state: Arc<AppState>) -> Router {
+    let mut app =
+        if let FrontendMode::Integrated(path) = state.environment_service.get_frontend_mode() {
+            tracing::info!("working with frontend mode `integrated` with path {path}");
+            add_frontend_router(path, Arc::clone(&state), Router::new())
+                .route("/api/health", get(health_handler))
+        } else {
+            Router::new()
+                // `GET /` goes to `root`
+                .route("/", get(health_handler))
+        };
+
+    app = add_health_router("/api/health", app);
+    // the routers attach the rate limit of their group of routes
+    app = add_guest_router("/api/guest", Arc::clone(&state), app);
+    app = add_public_router("/api/public", Arc::clone(&state), app);
+    app = add_feed_router(Arc::clone(&state), app);
+    app = add_admin_router("/api/admin", Arc::clone(&state), app);
+    app = add_user_router("/api/user", Arc::clone(&state), app);
+    app = add_openapi_router(app);
+    // metrics are served by the application unless they have a dedicated port
+    if state.environment_service.get_metrics_enabled()
+        && state.environment_service.get_metrics_port().is_none()
+    {
+        app = add_metrics_router(METRICS_PATH, app);
+    }
+
+    // Add middlewares to our application.
+    // Layers are accessed from bottom to up, hence the order is very important
+    app = middleware::add_transaction_middleware(Arc::clone(&state), app);
+    // the requests rejected by the rate limit of the routers are recorded too
+    app = middleware::add_metrics_middleware(Arc::clone(&state), app);
+    app = middleware::add_logging_middleware(
+        app,
+        state.environment_service.get_logging_include_headers(),
+        state.environment_service.get_logging_level(),
+    );
+    app = middleware::add_request_id_middleware(app);
+    app = middleware::add_cors_middleware(app, state.environment_service.as_ref());
+
+    // Attach the state of the application that will be used by its services
+    app.with_state(state)
+}
+
+/// Build the router of the dedicated metrics listener, used when
+/// the metrics port is configured to keep them off the public port
+pub fn build_metrics_app(state: Arc<AppState>) -> Router {
+    add_metrics_router(METRICS_PATH, Router::new()).with_state(state)
+}
@@ -2,30 +2,72 @@ use std::sync::Arc;
 
 use axum::{
     Extension, Json, Router,
-    extract::State,
-    routing::{get, post},
+    extract::{Path, Query, State},
+    routing::{delete, get, post, put},
 };
+use bson::oid::ObjectId;
 use tokio::sync::RwLock;
+use utoipa::OpenApi;
 
 use crate::{
     AppResult, AppState,
     auth::JWTAuthClaim,
     dtos::{admin_request, admin_response},
+    enums::RateLimitRoute,
+    error::{ErrorResponse, FacadeResult},
     facade::admin::AdminFacade,
-    service::database::transaction::MongoDBDatabaseTransaction,
-    types::AppJson,
+    middleware,
+    service::{audit::AuditEventFilter, database::transaction::AppDatabaseTransaction},
+    types::{AppJson, IfMatchVersion, VersionedJson},
 };
 
+/// OpenAPI description of the admin routes
+#[derive(OpenApi)]
+#[openapi(paths(
+    get_users,
+    create_user,
+    get_deleted_users,
+    get_user,
+    update_user,
+    delete_user,
+    restore_user,
+    get_deleted_posts,
+    restore_post,
+    get_audit_events
+))]
+pub struct AdminApi;
+
 pub fn add_admin_router(
     base_path: &str,
+    state: Arc<AppState>,
     base_router: Router<Arc<AppState>>,
 ) -> Router<Arc<AppState>> {
     let router = Router::new()
         .route("/user", get(get_users))
-        .route("/user", post(create_user));
+        .route("/user", post(create_user))
+        .route("/user/deleted", get(get_deleted_users))
+        .route("/user/{id}", get(get_user))
+        .route("/user/{id}", put(update_user))
+        .route("/user/{id}", delete(delete_user))
+        .route("/user/{id}/restore", post(restore_user))
+        .route("/blog/post/deleted", get(get_deleted_posts))
+        .route("/blog/post/{id}/restore", post(restore_post))
+        .route("/audit", get(get_audit_events));
+    let router = middleware::add_rate_limit_middleware(state, RateLimitRoute::User, router);
     base_router.nest(base_path, router)
 }
 
+/// Returns the users that are not deleted
+#[utoipa::path(
+    get,
+    path = "/user",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Users", body = Vec<admin_response::User>),
+        (status = 401, description = "Missing or invalid token", body = ErrorResponse),
+        (status = 403, description = "The user is not an admin", body = ErrorResponse),
+    ),
+)]
 async fn get_users(
     State(state): State<Arc<AppState>>,
     jwt_claim: JWTAuthClaim,
@@ -42,9 +84,21 @@ async fn get_users(
     })
 }
 
+/// Creates a new user and returns its id
+#[utoipa::path(
+    post,
+    path = "/user",
+    request_body = admin_request::CreateUser,
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Id of the created user", body = String),
+        (status = 401, description = "Missing or invalid token", body = ErrorResponse),
+        (status = 403, description = "The user is not an admin", body = ErrorResponse),
+    ),
+)]
 async fn create_user(
     State(state): State<Arc<AppState>>,
-    Extension(transaction): Extension<Arc<RwLock<MongoDBDatabaseTransaction>>>,
+    Extension(transaction): Extension<Arc<RwLock<AppDatabaseTransaction>>>,
     jwt_claim: JWTAuthClaim,
     Json(payload): Json<admin_request::CreateUser>,
 ) -> AppResult<String> {
@@ -62,3 +116,317 @@ async fn create_user(
         .await
         .map(|value| AppJson(value.to_hex()))
 }
+
+/// Returns the user with its version in the `ETag` header
+#[utoipa::path(
+    get,
+    path = "/user/{id}",
+    params(("id" = String, Path, description = "Id of the user")),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "User", body = admin_response::User,
+            headers(("ETag" = String, description = "Version of the user"))),
+        (status = 401, description = "Missing or invalid token", body = ErrorResponse),
+        (status = 403, description = "The user is not an admin", body = ErrorResponse),
+        (status = 404, description = "The user does not exist", body = ErrorResponse),
+    ),
+)]
+async fn get_user(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<ObjectId>,
+    jwt_claim: JWTAuthClaim,
+) -> FacadeResult<VersionedJson<admin_response::User>> {
+    let facade = AdminFacade::new(jwt_claim, state.database_service.clone()).await?;
+
+    facade
+        .get_user(id)
+        .await
+        .map(|user| VersionedJson::new(user.version(), user.into()))
+}
+
+/// Updates the user only if the `If-Match` header contains its current version
+#[utoipa::path(
+    put,
+    path = "/user/{id}",
+    params(
+        ("id" = String, Path, description = "Id of the user"),
+        ("If-Match" = String, Header, description = "Expected versions of the user, or `*` for any version"),
+    ),
+    request_body = admin_request::UpdateUser,
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Id of the updated user", body = String,
+            headers(("ETag" = String, description = "New version of the user"))),
+        (status = 400, description = "Invalid If-Match header", body = ErrorResponse),
+        (status = 401, description = "Missing or invalid token", body = ErrorResponse),
+        (status = 403, description = "The user is not an admin", body = ErrorResponse),
+        (status = 404, description = "The user does not exist", body = ErrorResponse),
+        (status = 409, description = "The user has been modified meanwhile", body = ErrorResponse),
+        (status = 428, description = "Missing If-Match header", body = ErrorResponse),
+    ),
+)]
+async fn update_user(
+    State(state): State<Arc<AppState>>,
+    Extension(transaction): Extension<Arc<RwLock<AppDatabaseTransaction>>>,
+    Path(id): Path<ObjectId>,
+    IfMatchVersion(expected_version): IfMatchVersion,
+    jwt_claim: JWTAuthClaim,
+    Json(payload): Json<admin_request::UpdateUser>,
+) -> FacadeResult<VersionedJson<String>> {
+    let facade = AdminFacade::new(jwt_claim, state.database_service.clone()).await?;
+
+    facade
+        .update_user(
+            transaction,
+            id,
+            expected_version,
+            payload.first_name,
+            payload.last_name,
+            payload.admin,
+            payload.publisher,
+        )
+        .await
+        .map(|version| VersionedJson::new(version, id.to_hex()))
+}
+
+/// Deletes the user, it can be restored later
+#[utoipa::path(
+    delete,
+    path = "/user/{id}",
+    params(("id" = String, Path, description = "Id of the user")),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Id of the deleted user", body = String),
+        (status = 401, description = "Missing or invalid token", body = ErrorResponse),
+        (status = 403, description = "The user is not an admin", body = ErrorResponse),
+        (status = 404, description = "The user does not exist", body = ErrorResponse),
+    ),
+)]
+async fn delete_user(
+    State(state): State<Arc<AppState>>,
+    Extension(transaction): Extension<Arc<RwLock<AppDatabaseTransaction>>>,
+    Path(id): Path<ObjectId>,
+    jwt_claim: JWTAuthClaim,
+) -> AppResult<String> {
+    let facade = AdminFacade::new(jwt_claim, state.database_service.clone()).await?;
+
+    facade
+        .delete_user(transaction, id)
+        .await
+        .map(|_| AppJson(id.to_hex()))
+}
+
+/// Returns the deleted users that can be restored
+#[utoipa::path(
+    get,
+    path = "/user/deleted",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Deleted users", body = Vec<admin_response::User>),
+        (status = 401, description = "Missing or invalid token", body = ErrorResponse),
+        (status = 403, description = "The user is not an admin", body = ErrorResponse),
+    ),
+)]
+async fn get_deleted_users(
+    State(state): State<Arc<AppState>>,
+    jwt_claim: JWTAuthClaim,
+) -> AppResult<Vec<admin_response::User>> {
+    let facade = AdminFacade::new(jwt_claim, state.database_service.clone()).await?;
+
+    facade.get_deleted_users().await.map(|values| {
+        AppJson(
+            values
+                .into_iter()
+                .map(|elem| elem.into())
+                .collect::<Vec<admin_response::User>>(),
+        )
+    })
+}
+
+/// Restores the deleted user if its username is still available
+#[utoipa::path(
+    post,
+    path = "/user/{id}/restore",
+    params(("id" = String, Path, description = "Id of the user")),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Id of the restored user", body = String),
+        (status = 401, description = "Missing or invalid token", body = ErrorResponse),
+        (status = 403, description = "The user is not an admin", body = ErrorResponse),
+        (status = 404, description = "The user does not exist", body = ErrorResponse),
+        (status = 409, description = "The username is taken by another user", body = ErrorResponse),
+    ),
+)]
+async fn restore_user(
+    State(state): State<Arc<AppState>>,
+    Extension(transaction): Extension<Arc<RwLock<AppDatabaseTransaction>>>,
+    Path(id): Path<ObjectId>,
+    jwt_claim: JWTAuthClaim,
+) -> AppResult<String> {
+    let facade = AdminFacade::new(jwt_claim, state.database_service.clone()).await?;
+
+    facade
+        .restore_user(transaction, id)
+        .await
+        .map(|_| AppJson(id.to_hex()))
+}
+
+/// Returns the deleted posts that can be restored
+#[utoipa::path(
+    get,
+    path = "/blog/post/deleted",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Deleted posts", body = Vec<admin_response::BlogPost>),
+        (status = 401, description = "Missing or invalid token", body = ErrorResponse),
+        (status = 403, description = "The user is not an admin", body = ErrorResponse),
+    ),
+)]
+async fn get_deleted_posts(
+    State(state): State<Arc<AppState>>,
+    jwt_claim: JWTAuthClaim,
+) -> AppResult<Vec<admin_response::BlogPost>> {
+    let facade = AdminFacade::new(jwt_claim, state.database_service.clone()).await?;
+
+    facade.get_deleted_posts().await.map(|values| {
+        AppJson(
+            values
+                .into_iter()
+                .map(|elem| elem.into())
+                .collect::<Vec<admin_response::BlogPost>>(),
+        )
+    })
+}
+
+/// Restores the deleted post
+#[utoipa::path(
+    post,
+    path = "/blog/post/{id}/restore",
+    params(("id" = String, Path, description = "Id of the post")),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Id of the restored post", body = String),
+        (status = 401, description = "Missing or invalid token", body = ErrorResponse),
+        (status = 403, description = "The user is not an admin", body = ErrorResponse),
+        (status = 404, description = "The post does not exist", body = ErrorResponse),
+    ),
+)]
+async fn restore_post(
+    State(state): State<Arc<AppState>>,
+    Extension(transaction): Extension<Arc<RwLock<AppDatabaseTransaction>>>,
+    Path(id): Path<ObjectId>,
+    jwt_claim: JWTAuthClaim,
+) -> AppResult<String> {
+    let facade = AdminFacade::new(jwt_claim, state.database_service.clone()).await?;
+
+    facade
+        .restore_post(transaction, id)
+        .await
+        .map(|_| AppJson(id.to_hex()))
+}
+
+/// Returns the page of audit events matching the filters
+#[utoipa::path(
+    get,
+    path = "/audit",
+    params(admin_request::AuditEventQuery),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Page of audit events", body = admin_response::AuditEventPage),
+        (status = 400, description = "Invalid page or page size", body = ErrorResponse),
+        (status = 401, description = "Missing or invalid token", body = ErrorResponse),
+        (status = 403, description = "The user is not an admin", body = ErrorResponse),
+    ),
+)]
+async fn get_audit_events(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<admin_request::AuditEventQuery>,
+    jwt_claim: JWTAuthClaim,
+) -> AppResult<admin_response::AuditEventPage> {
+    let facade = AdminFacade::new(jwt_claim, state.database_service.clone()).await?;
+
+    let filter = AuditEventFilter {
+        actor_id: query.actor_id,
+        action: query.action,
+        target_collection: query.target_collection,
+        target_id: query.target_id,
+        from: query.from,
+        to: query.to,
+    };
+    facade
+        .get_audit_events(filter, query.page, query.page_size)
+        .await
+        .map(|(events, total)| {
+            AppJson(admin_response::AuditEventPage {
+                events: events.into_iter().map(|elem| elem.into()).collect(),
+                page: query.page,
+                page_size: query.page_size,
+                total,
+            })
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{Method, StatusCode};
+
+    use crate::test_utils::TestApp;
+
+    #[tokio::test]
+    async fn test_admin_routes_forbidden_to_users() {
+        let app = TestApp::new();
+        let user = app.register_user("marcel", "password").await;
+
+        let response = app
+            .call(Method::GET, "/api/admin/user", Some(&user.token), None)
+            .await;
+        assert_eq!(response.status, StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_admin_lists_users_and_audit_events() {
+        let app = TestApp::new();
+        app.register_user("marcel", "password").await;
+        let admin = app.create_admin("admin", "password").await;
+        let token = Some(admin.token.as_str());
+
+        let response = app.call(Method::GET, "/api/admin/user", token, None).await;
+        assert_eq!(response.status, StatusCode::OK);
+        assert_eq!(response.body.as_array().unwrap().len(), 2);
+
+        let response = app
+            .call(Method::GET, "/api/admin/audit?action=create", token, None)
+            .await;
+        assert_eq!(response.status, StatusCode::OK);
+        assert_eq!(response.body["total"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_audit_events_with_huge_page() {
+        let app = TestApp::new();
+        let admin = app.create_admin("admin", "password").await;
+
+        let uri = format!("/api/admin/audit?page={}&pageSize=100", u64::MAX);
+        let response = app.call(Method::GET, &uri, Some(&admin.token), None).await;
+        assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_restore_user_with_taken_username() {
+        let app = TestApp::new();
+        let user = app.register_user("marcel", "password").await;
+        let admin = app.create_admin("admin", "password").await;
+        let token = Some(admin.token.as_str());
+
+        let user_uri = format!("/api/admin/user/{}", user.user_id);
+        let response = app.call(Method::DELETE, &user_uri, token, None).await;
+        assert_eq!(response.status, StatusCode::OK);
+
+        // another user registers with the same username
+        app.register_user("marcel", "password").await;
+
+        let restore_uri = format!("{user_uri}/restore");
+        let response = app.call(Method::POST, &restore_uri, token, None).await;
+        assert_eq!(response.status, StatusCode::CONFLICT);
+    }
+}
@@ -0,0 +1,253 @@
+use std::{sync::Arc, time::SystemTime};
+
+use axum::{
+    Router,
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode, Uri, header},
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use bson::oid::ObjectId;
+use headers::{ETag, HeaderMapExt, IfModifiedSince, IfNoneMatch, LastModified};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    AppState, enums::RateLimitRoute, error::FacadeResult, facade::public::PublicFacade, middleware,
+};
+
+/// Format of the feed documents
+#[derive(Clone, Copy)]
+enum FeedFormat {
+    Rss,
+    Atom,
+}
+
+impl FeedFormat {
+    fn content_type(&self) -> &'static str {
+        match self {
+            FeedFormat::Rss => "application/rss+xml; charset=utf-8",
+            FeedFormat::Atom => "application/atom+xml; charset=utf-8",
+        }
+    }
+}
+
+/// Serves the RSS and Atom feeds of the public posts, they are not under
+/// `/api` because the feed readers discover them at the root of the site
+pub fn add_feed_router(
+    state: Arc<AppState>,
+    base_router: Router<Arc<AppState>>,
+) -> Router<Arc<AppState>> {
+    let router = Router::new()
+        .route("/feed.xml", get(get_rss_feed))
+        .route("/atom.xml", get(get_atom_feed))
+        .route("/author/{id}/feed.xml", get(get_author_rss_feed));
+    let router = middleware::add_rate_limit_middleware(state, RateLimitRoute::Guest, router);
+    base_router.merge(router)
+}
+
+async fn get_rss_feed(
+    State(state): State<Arc<AppState>>,
+    uri: Uri,
+    headers: HeaderMap,
+) -> FacadeResult<Response> {
+    feed_response(state, None, FeedFormat::Rss, &uri, &headers).await
+}
+
+async fn get_atom_feed(
+    State(state): State<Arc<AppState>>,
+    uri: Uri,
+    headers: HeaderMap,
+) -> FacadeResult<Response> {
+    feed_response(state, None, FeedFormat::Atom, &uri, &headers).await
+}
+
+async fn get_author_rss_feed(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<ObjectId>,
+    uri: Uri,
+    headers: HeaderMap,
+) -> FacadeResult<Response> {
+    feed_response(state, Some(id), FeedFormat::Rss, &uri, &headers).await
+}
+
+/// Renders the feed with its `ETag`, the SHA-256 of the document, and its
+/// `Last-Modified`, the most recent change of the posts.
+///
+/// The hash does not depend on the process, so the instances of the
+/// backend give the same `ETag` to the same document.
+///
+/// The response is `304 Not Modified` when the client already has the feed,
+/// `If-Modified-Since` is ignored when `If-None-Match` is sent
+async fn feed_response(
+    state: Arc<AppState>,
+    author_id: Option<ObjectId>,
+    format: FeedFormat,
+    uri: &Uri,
+    headers: &HeaderMap,
+) -> FacadeResult<Response> {
+    let feed = PublicFacade::new(state.database_service.clone())
+        .get_feed(author_id)
+        .await?;
+    let public_url = state.environment_service.get_frontend_public_url();
+    let feed_url = format!("{public_url}{}", uri.path());
+    let body = match format {
+        FeedFormat::Rss => feed.to_rss(public_url, &feed_url),
+        FeedFormat::Atom => feed.to_atom(public_url, &feed_url),
+    };
+
+    let etag = format!("\"{:x}\"", Sha256::digest(body.as_bytes()))
+        .parse::<ETag>()
+        .expect("the hash is a valid entity tag");
+    let last_modified = feed.last_modified().map(SystemTime::from);
+
+    let not_modified = match headers.typed_get::<IfNoneMatch>() {
+        Some(if_none_match) => !if_none_match.precondition_passes(&etag),
+        None => headers
+            .typed_get::<IfModifiedSince>()
+            .zip(last_modified)
+            .is_some_and(|(since, last_modified)| !since.is_modified(last_modified)),
+    };
+    let mut response = if not_modified {
+        StatusCode::NOT_MODIFIED.into_response()
+    } else {
+        ([(header::CONTENT_TYPE, format.content_type())], body).into_response()
+    };
+    response.headers_mut().typed_insert(etag);
+    if let Some(last_modified) = last_modified {
+        response
+            .headers_mut()
+            .typed_insert(LastModified::from(last_modified));
+    }
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::Body,
+        http::{Method, StatusCode, header},
+    };
+    use serde_json::json;
+
+    use crate::test_utils::TestApp;
+
+    #[tokio::test]
+    async fn test_feeds() {
+        let app = TestApp::new();
+        let user = app.register_user("marcel", "password").await;
+        for (title, visibility) in [("Fish & chips", "public"), ("Secret", "membersOnly")] {
+            app.call(
+                Method::POST,
+                "/api/user/blog/post",
+                Some(&user.token),
+                Some(json!({ "title": title, "content": "Content", "visibility": visibility })),
+            )
+            .await;
+        }
+
+        let response = app.call(Method::GET, "/feed.xml", None, None).await;
+        assert_eq!(response.status, StatusCode::OK);
+        assert_eq!(
+            response.headers[header::CONTENT_TYPE],
+            "application/rss+xml; charset=utf-8"
+        );
+        assert!(response.text.contains("<title>Fish &amp; chips</title>"));
+        assert!(!response.text.contains("Secret"));
+        assert!(response.headers.contains_key(header::LAST_MODIFIED));
+        let etag = response.headers[header::ETAG].clone();
+
+        let request = TestApp::request(Method::GET, "/feed.xml", None, None)
+            .header(header::IF_NONE_MATCH, etag)
+            .body(Body::empty())
+            .unwrap();
+        let response = app.send(request).await;
+        assert_eq!(response.status, StatusCode::NOT_MODIFIED);
+        assert!(response.text.is_empty());
+
+        let response = app.call(Method::GET, "/atom.xml", None, None).await;
+        assert_eq!(response.status, StatusCode::OK);
+        assert!(
+            response
+                .text
+                .contains("<feed xmlns=\"http://www.w3.org/2005/Atom\">")
+        );
+
+        let response = app
+            .call(
+                Method::GET,
+                &format!("/author/{}/feed.xml", user.user_id),
+                None,
+                None,
+            )
+            .await;
+        assert_eq!(response.status, StatusCode::OK);
+        assert!(response.text.contains("Hello Blog - marcel"));
+        let response = app
+            .call(
+                Method::GET,
+                &format!("/author/{}/feed.xml", bson::oid::ObjectId::new()),
+                None,
+                None,
+            )
+            .await;
+        assert_eq!(response.status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_feed_changes_with_the_posts() {
+        let app = TestApp::new();
+        let user = app.register_user("marcel", "password").await;
+        let token = Some(user.token.as_str());
+        let response = app
+            .call(
+                Method::POST,
+                "/api/user/blog/post",
+                token,
+                Some(json!({ "title": "Title", "content": "Content" })),
+            )
+            .await;
+        let post_uri = format!("/api/user/blog/post/{}", response.body.as_str().unwrap());
+
+        let response = app.call(Method::GET, "/atom.xml", None, None).await;
+        let etag = response.headers[header::ETAG].clone();
+        // the hex encoded SHA-256 of the document between quotes
+        assert_eq!(etag.len(), 66);
+        let last_modified = response.headers[header::LAST_MODIFIED].clone();
+
+        // the dates of the validators have a resolution of one second
+        tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+        let update = json!({ "title": "New title", "content": "Content" });
+        let request = TestApp::request(Method::PUT, &post_uri, token, Some(update.clone()))
+            .header(header::IF_MATCH, "*")
+            .body(Body::from(update.to_string()))
+            .unwrap();
+        assert_eq!(app.send(request).await.status, StatusCode::OK);
+
+        let request = TestApp::request(Method::GET, "/atom.xml", None, None)
+            .header(header::IF_MODIFIED_SINCE, last_modified.clone())
+            .body(Body::empty())
+            .unwrap();
+        let response = app.send(request).await;
+        assert_eq!(response.status, StatusCode::OK);
+        assert!(response.text.contains("<title>New title</title>"));
+        assert_ne!(response.headers[header::ETAG], etag);
+        let updated_last_modified = response.headers[header::LAST_MODIFIED].clone();
+        assert_ne!(updated_last_modified, last_modified);
+
+        // the deleted post leaves the feed and moves its date forward
+        tokio::time::sleep(std::time::Duration::from_millis(1100)).await;
+        let response = app.call(Method::DELETE, &post_uri, token, None).await;
+        assert_eq!(response.status, StatusCode::OK);
+        let request = TestApp::request(Method::GET, "/atom.xml", None, None)
+            .header(header::IF_MODIFIED_SINCE, updated_last_modified.clone())
+            .body(Body::empty())
+            .unwrap();
+        let response = app.send(request).await;
+        assert_eq!(response.status, StatusCode::OK);
+        assert!(!response.text.contains("<entry>"));
+        assert_ne!(
+            response.headers[header::LAST_MODIFIED],
+            updated_last_modified
+        );
+    }
+}
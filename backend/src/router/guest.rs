@@ -3,27 +3,54 @@ use std::sync::Arc;
 use crate::{
     AppResult, AppState,
     dtos::{guest_request, guest_response},
+    enums::RateLimitRoute,
+    error::ErrorResponse,
     facade::guest::GuestFacade,
-    service::database::transaction::MongoDBDatabaseTransaction,
+    middleware,
+    service::database::transaction::AppDatabaseTransaction,
 };
 use axum::{Extension, Json, Router, extract::State, routing::post};
 use tokio::sync::RwLock;
+use utoipa::OpenApi;
+
+/// OpenAPI description of the guest routes
+#[derive(OpenApi)]
+#[openapi(paths(register, login))]
+pub struct GuestApi;
 
 pub fn add_guest_router(
     base_path: &str,
+    state: Arc<AppState>,
     base_router: Router<Arc<AppState>>,
 ) -> Router<Arc<AppState>> {
-    let router = Router::new()
-        .route("/login", post(login))
-        .route("/register", post(register));
-    base_router.nest(base_path, router)
+    let login_router = middleware::add_rate_limit_middleware(
+        Arc::clone(&state),
+        RateLimitRoute::Login,
+        Router::new().route("/login", post(login)),
+    );
+    let register_router = middleware::add_rate_limit_middleware(
+        state,
+        RateLimitRoute::Register,
+        Router::new().route("/register", post(register)),
+    );
+    base_router.nest(base_path, login_router.merge(register_router))
 }
 
 /// Receives first_name, last_name, username and password, creates the user and then
 /// generate JWT for the session
+#[utoipa::path(
+    post,
+    path = "/register",
+    request_body = guest_request::RegisterInfo,
+    responses(
+        (status = 200, description = "User created and logged in", body = guest_response::LoggedUserInfoResponse),
+        (status = 422, description = "Missing or invalid fields", body = ErrorResponse),
+        (status = 429, description = "Too many registrations from the client", body = ErrorResponse),
+    ),
+)]
 async fn register(
     State(state): State<Arc<AppState>>,
-    Extension(transaction): Extension<Arc<RwLock<MongoDBDatabaseTransaction>>>,
+    Extension(transaction): Extension<Arc<RwLock<AppDatabaseTransaction>>>,
     Json(payload): Json<guest_request::RegisterInfo>,
 ) -> AppResult<guest_response::LoggedUserInfoResponse> {
     let database_service = state.database_service.clone();
@@ -41,6 +68,16 @@ async fn register(
 
 /// Receives username and password, validates the user and
 /// generate JWT for the session
+#[utoipa::path(
+    post,
+    path = "/login",
+    request_body = guest_request::JWTAuthPayload,
+    responses(
+        (status = 200, description = "User logged in", body = guest_response::LoggedUserInfoResponse),
+        (status = 401, description = "Wrong credentials", body = ErrorResponse),
+        (status = 429, description = "Too many login attempts from the client", body = ErrorResponse),
+    ),
+)]
 async fn login(
     State(state): State<Arc<AppState>>,
     Json(payload): Json<guest_request::JWTAuthPayload>,
@@ -49,3 +86,41 @@ async fn login(
         .authenticate_user(&payload.username, &payload.password)
         .await
 }
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{Method, StatusCode};
+    use serde_json::json;
+
+    use crate::test_utils::TestApp;
+
+    #[tokio::test]
+    async fn test_register_and_login() {
+        let app = TestApp::new();
+        let registered = app.register_user("marcel", "password").await;
+        let logged = app.login("marcel", "password").await;
+        assert_eq!(registered.user_id, logged.user_id);
+
+        let response = app
+            .call(Method::GET, "/api/user/info", Some(&logged.token), None)
+            .await;
+        assert_eq!(response.status, StatusCode::OK);
+        assert_eq!(response.body["username"], "marcel");
+    }
+
+    #[tokio::test]
+    async fn test_login_with_wrong_password() {
+        let app = TestApp::new();
+        app.register_user("marcel", "password").await;
+
+        let response = app
+            .call(
+                Method::POST,
+                "/api/guest/login",
+                None,
+                Some(json!({ "username": "marcel", "password": "wrong" })),
+            )
+            .await;
+        assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+    }
+}
@@ -0,0 +1,104 @@
+use std::{path::PathBuf, sync::Arc};
+
+use axum::{Router, extract::State, http::StatusCode, routing::get};
+use utoipa::OpenApi;
+
+use crate::{
+    AppState,
+    dtos::health_response::{HealthStatus, LivenessReport, ReadinessReport},
+    service::health::HealthService,
+    types::AppJson,
+};
+
+/// OpenAPI description of the health routes
+#[derive(OpenApi)]
+#[openapi(paths(live, ready))]
+pub struct HealthApi;
+
+pub fn add_health_router(
+    base_path: &str,
+    base_router: Router<Arc<AppState>>,
+) -> Router<Arc<AppState>> {
+    let router = Router::new()
+        .route("/live", get(live))
+        .route("/ready", get(ready));
+    base_router.nest(base_path, router)
+}
+
+/// Liveness probe, it answers as long as the server is serving
+/// requests and it does not check any dependency
+#[utoipa::path(
+    get,
+    path = "/live",
+    responses((status = 200, description = "Server is running", body = LivenessReport)),
+)]
+async fn live() -> AppJson<LivenessReport> {
+    AppJson(LivenessReport {
+        status: HealthStatus::Up,
+    })
+}
+
+/// Readiness probe, it checks the database and the object storage and
+/// returns the report of each dependency with `503 Service Unavailable`
+/// when any of them is down
+#[utoipa::path(
+    get,
+    path = "/ready",
+    responses(
+        (status = 200, description = "All the dependencies are up", body = ReadinessReport),
+        (status = 503, description = "At least one dependency is down", body = ReadinessReport),
+    ),
+)]
+async fn ready(State(state): State<Arc<AppState>>) -> (StatusCode, AppJson<ReadinessReport>) {
+    let environment_service = &state.environment_service;
+    let report = HealthService::new(
+        state.database_service.clone(),
+        *environment_service.get_object_storage_source_type(),
+        PathBuf::from(environment_service.get_object_storage_prefix_path()),
+    )
+    .check()
+    .await;
+    let status = match report.status {
+        HealthStatus::Down => StatusCode::SERVICE_UNAVAILABLE,
+        _ => StatusCode::OK,
+    };
+    (status, AppJson(report))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{Method, StatusCode};
+
+    use crate::test_utils::{TestApp, test_config};
+
+    #[tokio::test]
+    async fn test_live() {
+        let app = TestApp::new();
+        let response = app.call(Method::GET, "/api/health/live", None, None).await;
+        assert_eq!(response.status, StatusCode::OK);
+        assert_eq!(response.body["status"], "up");
+    }
+
+    #[tokio::test]
+    async fn test_ready() {
+        let mut config = test_config();
+        config.storage.prefix_path = Some(std::env::temp_dir().display().to_string());
+        let app = TestApp::with_config(config);
+        let response = app.call(Method::GET, "/api/health/ready", None, None).await;
+        assert_eq!(response.status, StatusCode::OK);
+        assert_eq!(response.body["status"], "up");
+        assert_eq!(response.body["database"]["status"], "up");
+        assert_eq!(response.body["objectStorage"]["status"], "up");
+        assert!(response.body["database"]["latencyMs"].is_f64());
+
+        let mut config = test_config();
+        config.storage.prefix_path = Some("./missing-object-storage".into());
+        let app = TestApp::with_config(config);
+        let response = app.call(Method::GET, "/api/health/ready", None, None).await;
+        assert_eq!(response.status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.body["status"], "down");
+        assert_eq!(response.body["database"]["status"], "up");
+        assert_eq!(response.body["objectStorage"]["status"], "down");
+        assert!(response.body["objectStorage"]["error"].is_string());
+    }
+}
@@ -0,0 +1,86 @@
+use std::sync::Arc;
+
+use axum::{
+    Router,
+    extract::State,
+    http::{HeaderMap, header},
+    response::{IntoResponse, Response},
+    routing::get,
+};
+
+use crate::{
+    AppState,
+    error::{AppError, AuthError},
+    metrics::metrics,
+};
+
+/// Content type of the OpenMetrics text format used by the encoder
+const OPENMETRICS_CONTENT_TYPE: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+
+pub fn add_metrics_router(path: &str, base_router: Router<Arc<AppState>>) -> Router<Arc<AppState>> {
+    base_router.route(path, get(get_metrics))
+}
+
+/// Returns the application metrics to be scraped by Prometheus
+///
+/// When the metrics token is configured the request must contain
+/// it as bearer token, otherwise the metrics are public.
+async fn get_metrics(State(state): State<Arc<AppState>>, headers: HeaderMap) -> Response {
+    if let Some(token) = state.environment_service.get_metrics_token() {
+        let bearer = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+        if bearer != Some(token) {
+            return AppError::AuthorizationError(AuthError::WrongCredentials).into_response();
+        }
+    }
+    (
+        [(header::CONTENT_TYPE, OPENMETRICS_CONTENT_TYPE)],
+        metrics().encode(),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{Method, StatusCode};
+
+    use crate::test_utils::{TestApp, test_config};
+
+    #[tokio::test]
+    async fn test_metrics_record_requests() {
+        let app = TestApp::new();
+        let user = app.register_user("marcel", "password").await;
+        app.call(Method::GET, "/api/user/info", Some(&user.token), None)
+            .await;
+
+        let response = app.call(Method::GET, "/metrics", None, None).await;
+        assert_eq!(response.status, StatusCode::OK);
+        let metrics = response.text;
+        assert!(
+            metrics.contains(
+                r#"http_requests_total{method="GET",route="/api/user/info",status="200"}"#
+            ),
+            "{metrics}"
+        );
+        assert!(metrics.contains(r#"database_transactions_total{outcome="commit"}"#));
+        assert!(metrics.contains(
+            r#"database_operation_duration_seconds_count{operation="insert_one",collection="user",result="ok"}"#
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_metrics_token() {
+        let mut config = test_config();
+        config.metrics.token = Some("scraper-token".into());
+        let app = TestApp::with_config(config);
+
+        let response = app.call(Method::GET, "/metrics", None, None).await;
+        assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+        let response = app
+            .call(Method::GET, "/metrics", Some("scraper-token"), None)
+            .await;
+        assert_eq!(response.status, StatusCode::OK);
+    }
+}
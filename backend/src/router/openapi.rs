@@ -0,0 +1,135 @@
+use std::sync::Arc;
+
+use axum::Router;
+use utoipa::{
+    Modify, OpenApi,
+    openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme},
+};
+use utoipa_swagger_ui::SwaggerUi;
+
+use crate::{
+    AppState,
+    router::{
+        admin::AdminApi, guest::GuestApi, health::HealthApi, public::PublicApi, user::UserApi,
+    },
+};
+
+/// Path of the OpenAPI document
+pub const OPENAPI_PATH: &str = "/api/openapi.json";
+
+/// Path of the Swagger UI that renders the OpenAPI document
+pub const SWAGGER_UI_PATH: &str = "/api/docs";
+
+/// OpenAPI document of the application, generated from the `utoipa::path`
+/// annotations of the handlers and the DTOs used by them.
+///
+/// Each router module describes its routes with relative paths,
+/// they are nested here with the same base path used in `build_app`.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Hello Blog API", description = "REST API of the Hello Blog backend"),
+    nest(
+        (path = "/api/health", api = HealthApi, tags = ["health"]),
+        (path = "/api/guest", api = GuestApi, tags = ["guest"]),
+        (path = "/api/public", api = PublicApi, tags = ["public"]),
+        (path = "/api/user", api = UserApi, tags = ["user"]),
+        (path = "/api/admin", api = AdminApi, tags = ["admin"]),
+    ),
+    modifiers(&BearerSecurity),
+)]
+pub struct ApiDoc;
+
+/// Adds the JWT bearer authentication used by the user and admin routes
+struct BearerSecurity;
+
+impl Modify for BearerSecurity {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .bearer_format("JWT")
+                    .build(),
+            ),
+        );
+    }
+}
+
+/// Serves the OpenAPI document and the Swagger UI
+pub fn add_openapi_router(base_router: Router<Arc<AppState>>) -> Router<Arc<AppState>> {
+    base_router.merge(SwaggerUi::new(SWAGGER_UI_PATH).url(OPENAPI_PATH, ApiDoc::openapi()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use axum::http::{Method, StatusCode};
+    use utoipa::OpenApi;
+
+    use crate::test_utils::TestApp;
+
+    use super::{ApiDoc, OPENAPI_PATH};
+
+    /// Committed OpenAPI document, it is read by the API consumers
+    /// without running the backend
+    const OPENAPI_FILE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/openapi.json");
+
+    /// Fails when the routes or the DTOs change without updating the committed
+    /// document, run the test with `UPDATE_OPENAPI=1` to regenerate it
+    #[test]
+    fn test_openapi_document_is_up_to_date() {
+        let generated = ApiDoc::openapi().to_pretty_json().unwrap() + "\n";
+        if std::env::var_os("UPDATE_OPENAPI").is_some() {
+            std::fs::write(OPENAPI_FILE, &generated).unwrap();
+        }
+        let committed = std::fs::read_to_string(Path::new(OPENAPI_FILE)).unwrap_or_default();
+        assert!(
+            committed == generated,
+            "{OPENAPI_FILE} is out of date, run `UPDATE_OPENAPI=1 cargo test openapi` to regenerate it"
+        );
+    }
+
+    /// Fails when a documented operation is not served by the router, the
+    /// router answers `404` with an empty body or `405` to unknown routes
+    #[tokio::test]
+    async fn test_documented_operations_are_routed() {
+        let app = TestApp::new();
+        let admin = app.create_admin("admin", "password").await;
+        let openapi = ApiDoc::openapi();
+        assert!(!openapi.paths.paths.is_empty());
+
+        for (path, item) in &openapi.paths.paths {
+            let uri = path.replace("{id}", "65f1a3c2e4b0a1b2c3d4e5f6");
+            let operations = [
+                (Method::GET, &item.get),
+                (Method::POST, &item.post),
+                (Method::PUT, &item.put),
+                (Method::DELETE, &item.delete),
+            ];
+            for (method, _) in operations.iter().filter(|(_, op)| op.is_some()) {
+                let response = app
+                    .call(method.clone(), &uri, Some(&admin.token), None)
+                    .await;
+                let unrouted = response.status == StatusCode::METHOD_NOT_ALLOWED
+                    || (response.status == StatusCode::NOT_FOUND && response.body.is_null());
+                assert!(!unrouted, "{method} {path} is documented but not routed");
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_serve_openapi_document() {
+        let app = TestApp::new();
+        let response = app.call(Method::GET, OPENAPI_PATH, None, None).await;
+        assert_eq!(response.status, StatusCode::OK);
+        assert_eq!(response.body["info"]["title"], "Hello Blog API");
+        assert!(response.body["paths"]["/api/user/blog/post/{id}"]["put"].is_object());
+        assert!(response.body["components"]["schemas"]["ErrorResponse"].is_object());
+
+        let response = app.call(Method::GET, "/api/docs/", None, None).await;
+        assert_eq!(response.status, StatusCode::OK);
+    }
+}
@@ -0,0 +1,262 @@
+use std::sync::Arc;
+
+use axum::{
+    Router,
+    extract::{Path, State},
+    routing::get,
+};
+use bson::oid::ObjectId;
+use utoipa::OpenApi;
+
+use crate::{
+    AppState,
+    dtos::{public_response::AuthorPage, user_response::BlogPost},
+    enums::RateLimitRoute,
+    error::{ErrorResponse, FacadeResult},
+    facade::public::PublicFacade,
+    middleware,
+    types::CachedJson,
+};
+
+/// Seconds for which the responses of the public routes can be cached
+const PUBLIC_CACHE_MAX_AGE: u64 = 60;
+
+/// OpenAPI description of the public routes
+#[derive(OpenApi)]
+#[openapi(paths(get_posts, get_post, get_author))]
+pub struct PublicApi;
+
+/// Routes readable without an account, they expose only the public posts
+pub fn add_public_router(
+    base_path: &str,
+    state: Arc<AppState>,
+    base_router: Router<Arc<AppState>>,
+) -> Router<Arc<AppState>> {
+    let router = Router::new()
+        .route("/blog/post", get(get_posts))
+        .route("/blog/post/{id}", get(get_post))
+        .route("/author/{id}", get(get_author));
+    let router = middleware::add_rate_limit_middleware(state, RateLimitRoute::Guest, router);
+    base_router.nest(base_path, router)
+}
+
+/// Returns the public posts
+#[utoipa::path(
+    get,
+    path = "/blog/post",
+    responses(
+        (status = 200, description = "Public posts", body = Vec<BlogPost>,
+            headers(("Cache-Control" = String, description = "Caching policy of the posts"))),
+        (status = 429, description = "Too many requests from the client", body = ErrorResponse),
+    ),
+)]
+async fn get_posts(State(state): State<Arc<AppState>>) -> FacadeResult<CachedJson<Vec<BlogPost>>> {
+    PublicFacade::new(state.database_service.clone())
+        .get_posts()
+        .await
+        .map(|posts| {
+            CachedJson::new(
+                PUBLIC_CACHE_MAX_AGE,
+                posts.into_iter().map(|post| post.into()).collect(),
+            )
+        })
+}
+
+/// Returns the public post, members only posts are reported as missing
+#[utoipa::path(
+    get,
+    path = "/blog/post/{id}",
+    params(("id" = String, Path, description = "Id of the post")),
+    responses(
+        (status = 200, description = "Post", body = BlogPost,
+            headers(("Cache-Control" = String, description = "Caching policy of the post"))),
+        (status = 404, description = "The post does not exist or it is not public", body = ErrorResponse),
+        (status = 429, description = "Too many requests from the client", body = ErrorResponse),
+    ),
+)]
+async fn get_post(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<ObjectId>,
+) -> FacadeResult<CachedJson<BlogPost>> {
+    PublicFacade::new(state.database_service.clone())
+        .get_post(id)
+        .await
+        .map(|post| CachedJson::new(PUBLIC_CACHE_MAX_AGE, post.into()))
+}
+
+/// Returns the author with its public posts
+#[utoipa::path(
+    get,
+    path = "/author/{id}",
+    params(("id" = String, Path, description = "Id of the author")),
+    responses(
+        (status = 200, description = "Author and public posts", body = AuthorPage,
+            headers(("Cache-Control" = String, description = "Caching policy of the page"))),
+        (status = 404, description = "The author does not exist", body = ErrorResponse),
+        (status = 429, description = "Too many requests from the client", body = ErrorResponse),
+    ),
+)]
+async fn get_author(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<ObjectId>,
+) -> FacadeResult<CachedJson<AuthorPage>> {
+    PublicFacade::new(state.database_service.clone())
+        .get_author(id)
+        .await
+        .map(|author| CachedJson::new(PUBLIC_CACHE_MAX_AGE, author))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{Method, StatusCode, header};
+    use serde_json::json;
+
+    use crate::test_utils::TestApp;
+
+    #[tokio::test]
+    async fn test_public_posts() {
+        let app = TestApp::new();
+        let user = app.register_user("marcel", "password").await;
+        let token = Some(user.token.as_str());
+
+        let mut post_ids = vec![];
+        for visibility in ["public", "membersOnly"] {
+            let response = app
+                .call(
+                    Method::POST,
+                    "/api/user/blog/post",
+                    token,
+                    Some(
+                        json!({ "title": "Title", "content": "Content", "visibility": visibility }),
+                    ),
+                )
+                .await;
+            assert_eq!(response.status, StatusCode::OK);
+            post_ids.push(response.body.as_str().unwrap().to_string());
+        }
+
+        let response = app
+            .call(Method::GET, "/api/public/blog/post", None, None)
+            .await;
+        assert_eq!(response.status, StatusCode::OK);
+        assert_eq!(
+            response.headers[header::CACHE_CONTROL],
+            "public, max-age=60"
+        );
+        let posts = response.body.as_array().unwrap();
+        assert_eq!(posts.len(), 1);
+        assert_eq!(posts[0]["id"], post_ids[0].as_str());
+
+        let response = app
+            .call(
+                Method::GET,
+                &format!("/api/public/blog/post/{}", post_ids[0]),
+                None,
+                None,
+            )
+            .await;
+        assert_eq!(response.status, StatusCode::OK);
+        assert_eq!(response.body["visibility"], "public");
+
+        // members only posts are hidden to the guests but not to the users
+        let response = app
+            .call(
+                Method::GET,
+                &format!("/api/public/blog/post/{}", post_ids[1]),
+                None,
+                None,
+            )
+            .await;
+        assert_eq!(response.status, StatusCode::NOT_FOUND);
+        let response = app
+            .call(Method::GET, "/api/user/blog/post", token, None)
+            .await;
+        assert_eq!(response.body.as_array().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_author_page() {
+        let app = TestApp::new();
+        let user = app.register_user("marcel", "password").await;
+        app.call(
+            Method::POST,
+            "/api/user/blog/post",
+            Some(&user.token),
+            Some(json!({ "title": "Title", "content": "Content" })),
+        )
+        .await;
+
+        let response = app
+            .call(
+                Method::GET,
+                &format!("/api/public/author/{}", user.user_id),
+                None,
+                None,
+            )
+            .await;
+        assert_eq!(response.status, StatusCode::OK);
+        assert_eq!(response.body["username"], "marcel");
+        assert_eq!(response.body["posts"].as_array().unwrap().len(), 1);
+
+        let response = app
+            .call(
+                Method::GET,
+                &format!("/api/public/author/{}", bson::oid::ObjectId::new()),
+                None,
+                None,
+            )
+            .await;
+        assert_eq!(response.status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_posts_of_deleted_authors_are_hidden() {
+        let app = TestApp::new();
+        let admin = app.create_admin("admin", "password").await;
+        let mut albert_post_id = String::new();
+        for username in ["marcel", "albert"] {
+            let user = app.register_user(username, "password").await;
+            let response = app
+                .call(
+                    Method::POST,
+                    "/api/user/blog/post",
+                    Some(&user.token),
+                    Some(json!({ "title": username, "content": "Content" })),
+                )
+                .await;
+            if username == "albert" {
+                albert_post_id = response.body.as_str().unwrap().to_string();
+                let response = app
+                    .call(
+                        Method::DELETE,
+                        &format!("/api/admin/user/{}", user.user_id),
+                        Some(&admin.token),
+                        None,
+                    )
+                    .await;
+                assert_eq!(response.status, StatusCode::OK);
+            }
+        }
+
+        let response = app
+            .call(Method::GET, "/api/public/blog/post", None, None)
+            .await;
+        let posts = response.body.as_array().unwrap();
+        assert_eq!(posts.len(), 1);
+        assert_eq!(posts[0]["title"], "marcel");
+        let response = app
+            .call(
+                Method::GET,
+                &format!("/api/public/blog/post/{}", albert_post_id),
+                None,
+                None,
+            )
+            .await;
+        assert_eq!(response.status, StatusCode::NOT_FOUND);
+
+        let response = app.call(Method::GET, "/feed.xml", None, None).await;
+        assert_eq!(response.status, StatusCode::OK);
+        assert!(response.text.contains("<title>marcel</title>"));
+        assert!(!response.text.contains("<title>albert</title>"));
+    }
+}
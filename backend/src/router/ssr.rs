@@ -0,0 +1,95 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use axum::{
+    Extension, Router,
+    extract::State,
+    http::{StatusCode, Uri},
+    response::Html,
+    routing::get,
+};
+use bson::oid::ObjectId;
+use frontend::{
+    app::AppRoute,
+    ssr::{recognize_route, render_page},
+    types::PreloadedData,
+};
+use tower_http::services::ServeDir;
+
+use crate::{
+    AppState,
+    error::{AppError, FacadeResult, ServiceAppError},
+    facade::public::PublicFacade,
+};
+
+/// Serves the frontend rendering its pages on the server, the requests that
+/// do not match a static file of `path` are rendered from its `index.html`
+pub fn add_frontend_router(
+    path: &str,
+    state: Arc<AppState>,
+    base_router: Router<Arc<AppState>>,
+) -> Router<Arc<AppState>> {
+    let render = get(render_frontend_page)
+        .layer(Extension(Arc::new(Path::new(path).join("index.html"))))
+        .with_state(state);
+    base_router
+        .route_service("/", render.clone())
+        .fallback_service(ServeDir::new(path).fallback(render))
+}
+
+/// Renders the page of the requested path with the data it shows.
+///
+/// The pages are rendered for a guest because the token is kept by the browser,
+/// the pages requiring the login are rendered by the client after the hydration
+async fn render_frontend_page(
+    State(state): State<Arc<AppState>>,
+    Extension(template_path): Extension<Arc<PathBuf>>,
+    uri: Uri,
+) -> FacadeResult<(StatusCode, Html<String>)> {
+    let template = tokio::fs::read_to_string(template_path.as_path())
+        .await
+        .map_err(|err| AppError::InternalServerError {
+            msg: "Error in reading the frontend template".into(),
+            source_error: ServiceAppError::InternalServerError(err.to_string()),
+        })?;
+
+    let mut preloaded = PreloadedData::default();
+    let status = match recognize_route(uri.path()) {
+        Some(AppRoute::Home) => {
+            let posts = PublicFacade::new(state.database_service.clone())
+                .get_posts()
+                .await?;
+            preloaded.posts = Some(posts.into_iter().map(|post| post.into()).collect());
+            StatusCode::OK
+        }
+        Some(AppRoute::Post { id }) => {
+            // an invalid id and a members only post are reported as a missing
+            // post, the logged users load the latter after the hydration
+            if let Ok(post_id) = ObjectId::parse_str(&id) {
+                preloaded.post = match PublicFacade::new(state.database_service.clone())
+                    .get_post(post_id)
+                    .await
+                {
+                    Ok(post) => Some(post.into()),
+                    Err(AppError::DoesNotExist(_)) => None,
+                    Err(err) => return Err(err),
+                };
+            }
+            if preloaded.post.is_some() {
+                StatusCode::OK
+            } else {
+                StatusCode::NOT_FOUND
+            }
+        }
+        Some(AppRoute::NotFound) | None => StatusCode::NOT_FOUND,
+        Some(_) => StatusCode::OK,
+    };
+
+    let url = uri
+        .path_and_query()
+        .map_or(uri.path(), |path_and_query| path_and_query.as_str())
+        .to_string();
+    Ok((status, Html(render_page(&template, url, preloaded).await)))
+}
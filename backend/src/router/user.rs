@@ -1,35 +1,85 @@
-use std::sync::Arc;
+use std::{convert::Infallible, sync::Arc, time::Duration};
 
 use axum::{
     Extension, Json, Router,
-    extract::{Path, State},
-    routing::{get, post},
+    extract::{Path, Query, State},
+    response::sse::{Event, KeepAlive, Sse},
+    routing::{delete, get, post, put},
 };
 
 use bson::oid::ObjectId;
+use chrono::Utc;
+use futures::{Stream, StreamExt};
 use tokio::sync::RwLock;
+use tracing::error;
+use utoipa::OpenApi;
 
 use crate::{
     AppResult, AppState,
     auth::JWTAuthClaim,
-    dtos::{guest_response::LoggedUserInfoResponse, user_request, user_response::BlogPost},
+    dtos::{
+        guest_response::LoggedUserInfoResponse,
+        user_request,
+        user_response::{BlogPost, BlogPostChange},
+    },
+    enums::RateLimitRoute,
+    error::{ErrorResponse, FacadeResult},
     facade::user::UserFacade,
-    service::database::transaction::MongoDBDatabaseTransaction,
-    types::AppJson,
+    middleware,
+    server::ShutdownReceiver,
+    service::database::transaction::AppDatabaseTransaction,
+    types::{AppJson, IfMatchVersion, VersionedJson},
 };
 
+/// OpenAPI description of the user routes
+#[derive(OpenApi)]
+#[openapi(paths(
+    get_user_info,
+    refresh_token,
+    publish_post,
+    get_posts,
+    get_post,
+    update_post,
+    delete_post,
+    get_user_posts,
+    stream_posts
+))]
+pub struct UserApi;
+
 pub fn add_user_router(
     base_path: &str,
+    state: Arc<AppState>,
     base_router: Router<Arc<AppState>>,
 ) -> Router<Arc<AppState>> {
+    // publishing has its own limit, the other methods of the path share the user one
+    let publish_router = middleware::add_rate_limit_middleware(
+        Arc::clone(&state),
+        RateLimitRoute::PublishPost,
+        Router::new().route("/blog/post", post(publish_post)),
+    );
     let router = Router::new()
         .route("/info", get(get_user_info))
-        .route("/blog/post", post(publish_post))
+        .route("/refresh", post(refresh_token))
         .route("/blog/post", get(get_posts))
-        .route("/blog/post/user/{id}", get(get_user_posts));
-    base_router.nest(base_path, router)
+        .route("/blog/post/{id}", get(get_post))
+        .route("/blog/post/{id}", put(update_post))
+        .route("/blog/post/{id}", delete(delete_post))
+        .route("/blog/post/user/{id}", get(get_user_posts))
+        .route("/blog/stream", get(stream_posts));
+    let router = middleware::add_rate_limit_middleware(state, RateLimitRoute::User, router);
+    base_router.nest(base_path, router.merge(publish_router))
 }
 
+/// Returns the information of the logged user
+#[utoipa::path(
+    get,
+    path = "/info",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Logged user", body = LoggedUserInfoResponse),
+        (status = 401, description = "Missing or invalid token", body = ErrorResponse),
+    ),
+)]
 async fn get_user_info(
     State(state): State<Arc<AppState>>,
     jwt_claim: JWTAuthClaim,
@@ -41,19 +91,70 @@ async fn get_user_info(
         .map(AppJson)
 }
 
+/// Issues a new token of the logged user, the client calls it before
+/// the current token expires. The session started by the login cannot be
+/// extended beyond the configured maximum age
+#[utoipa::path(
+    post,
+    path = "/refresh",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Logged user with the new token", body = LoggedUserInfoResponse),
+        (status = 401, description = "Missing or invalid token, or expired session", body = ErrorResponse),
+    ),
+)]
+async fn refresh_token(
+    State(state): State<Arc<AppState>>,
+    jwt_claim: JWTAuthClaim,
+) -> AppResult<LoggedUserInfoResponse> {
+    UserFacade::new(jwt_claim.clone(), state.database_service.clone())
+        .await?
+        .refresh_token(&jwt_claim, state.environment_service.as_ref())
+        .await
+        .map(AppJson)
+}
+
+/// Publishes a new post of the logged user and returns its id
+#[utoipa::path(
+    post,
+    path = "/blog/post",
+    request_body = user_request::PublishPost,
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Id of the published post", body = String),
+        (status = 401, description = "Missing or invalid token", body = ErrorResponse),
+        (status = 403, description = "The user cannot publish posts", body = ErrorResponse),
+        (status = 429, description = "Too many posts published by the user", body = ErrorResponse),
+    ),
+)]
 async fn publish_post(
     State(state): State<Arc<AppState>>,
-    Extension(transaction): Extension<Arc<RwLock<MongoDBDatabaseTransaction>>>,
+    Extension(transaction): Extension<Arc<RwLock<AppDatabaseTransaction>>>,
     jwt_claim: JWTAuthClaim,
     Json(payload): Json<user_request::PublishPost>,
 ) -> AppResult<String> {
     UserFacade::new(jwt_claim, state.database_service.clone())
         .await?
-        .publish_post(transaction, payload.title, payload.content)
+        .publish_post(
+            transaction,
+            payload.title,
+            payload.content,
+            payload.visibility,
+        )
         .await
         .map(AppJson)
 }
 
+/// Returns all the posts
+#[utoipa::path(
+    get,
+    path = "/blog/post",
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Posts", body = Vec<BlogPost>),
+        (status = 401, description = "Missing or invalid token", body = ErrorResponse),
+    ),
+)]
 async fn get_posts(
     State(state): State<Arc<AppState>>,
     jwt_claim: JWTAuthClaim,
@@ -72,6 +173,111 @@ async fn get_posts(
         })
 }
 
+/// Returns the post with its version in the `ETag` header
+#[utoipa::path(
+    get,
+    path = "/blog/post/{id}",
+    params(("id" = String, Path, description = "Id of the post")),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Post", body = BlogPost,
+            headers(("ETag" = String, description = "Version of the post"))),
+        (status = 401, description = "Missing or invalid token", body = ErrorResponse),
+        (status = 404, description = "The post does not exist", body = ErrorResponse),
+    ),
+)]
+async fn get_post(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<ObjectId>,
+    jwt_claim: JWTAuthClaim,
+) -> FacadeResult<VersionedJson<BlogPost>> {
+    UserFacade::new(jwt_claim, state.database_service.clone())
+        .await?
+        .get_post(id)
+        .await
+        .map(|post| VersionedJson::new(post.version(), post.into()))
+}
+
+/// Updates the post only if the `If-Match` header contains its current version
+#[utoipa::path(
+    put,
+    path = "/blog/post/{id}",
+    params(
+        ("id" = String, Path, description = "Id of the post"),
+        ("If-Match" = String, Header, description = "Expected versions of the post, or `*` for any version"),
+    ),
+    request_body = user_request::UpdatePost,
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Id of the updated post", body = String,
+            headers(("ETag" = String, description = "New version of the post"))),
+        (status = 400, description = "Invalid If-Match header", body = ErrorResponse),
+        (status = 401, description = "Missing or invalid token", body = ErrorResponse),
+        (status = 403, description = "The user cannot update the post", body = ErrorResponse),
+        (status = 404, description = "The post does not exist", body = ErrorResponse),
+        (status = 409, description = "The post has been modified meanwhile", body = ErrorResponse),
+        (status = 428, description = "Missing If-Match header", body = ErrorResponse),
+    ),
+)]
+async fn update_post(
+    State(state): State<Arc<AppState>>,
+    Extension(transaction): Extension<Arc<RwLock<AppDatabaseTransaction>>>,
+    Path(id): Path<ObjectId>,
+    IfMatchVersion(expected_version): IfMatchVersion,
+    jwt_claim: JWTAuthClaim,
+    Json(payload): Json<user_request::UpdatePost>,
+) -> FacadeResult<VersionedJson<String>> {
+    UserFacade::new(jwt_claim, state.database_service.clone())
+        .await?
+        .update_post(
+            transaction,
+            id,
+            expected_version,
+            payload.title,
+            payload.content,
+            payload.visibility,
+        )
+        .await
+        .map(|version| VersionedJson::new(version, id.to_hex()))
+}
+
+/// Deletes the post, it can be restored by an admin
+#[utoipa::path(
+    delete,
+    path = "/blog/post/{id}",
+    params(("id" = String, Path, description = "Id of the post")),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Id of the deleted post", body = String),
+        (status = 401, description = "Missing or invalid token", body = ErrorResponse),
+        (status = 403, description = "The user cannot delete the post", body = ErrorResponse),
+        (status = 404, description = "The post does not exist", body = ErrorResponse),
+    ),
+)]
+async fn delete_post(
+    State(state): State<Arc<AppState>>,
+    Extension(transaction): Extension<Arc<RwLock<AppDatabaseTransaction>>>,
+    Path(id): Path<ObjectId>,
+    jwt_claim: JWTAuthClaim,
+) -> AppResult<String> {
+    UserFacade::new(jwt_claim, state.database_service.clone())
+        .await?
+        .delete_post(transaction, id)
+        .await
+        .map(|_| AppJson(id.to_hex()))
+}
+
+/// Returns the posts published by the user
+#[utoipa::path(
+    get,
+    path = "/blog/post/user/{id}",
+    params(("id" = String, Path, description = "Id of the creator of the posts")),
+    security(("bearer" = [])),
+    responses(
+        (status = 200, description = "Posts of the user", body = Vec<BlogPost>),
+        (status = 401, description = "Missing or invalid token", body = ErrorResponse),
+    ),
+)]
 async fn get_user_posts(
     State(state): State<Arc<AppState>>,
     Path(id): Path<ObjectId>,
@@ -90,3 +296,311 @@ async fn get_user_posts(
             )
         })
 }
+
+/// Pushes the created, updated and deleted posts as server sent events
+///
+/// The token is read from the `access_token` query parameter because
+/// browsers cannot set the authorization header on event source requests.
+/// The stream ends when the token expires, the user is deleted or the server shuts down.
+#[utoipa::path(
+    get,
+    path = "/blog/stream",
+    params(user_request::StreamQuery),
+    responses(
+        (status = 200, description = "Stream of the changes of the posts",
+            content_type = "text/event-stream", body = BlogPostChange),
+        (status = 401, description = "Missing or invalid token", body = ErrorResponse),
+    ),
+)]
+async fn stream_posts(
+    State(state): State<Arc<AppState>>,
+    shutdown: Option<Extension<ShutdownReceiver>>,
+    Query(query): Query<user_request::StreamQuery>,
+) -> FacadeResult<Sse<impl Stream<Item = Result<Event, Infallible>>>> {
+    let jwt_claim = JWTAuthClaim::decode_token(
+        &query.access_token,
+        state.environment_service.get_authentication_jwt_decoding(),
+    )?;
+    // the token is verified only when the stream is opened, hence the stream
+    // ends when it expires and the client reconnects with a new token
+    let expires_in = Duration::from_secs(
+        u64::from(jwt_claim.exp).saturating_sub(Utc::now().timestamp().unsigned_abs()),
+    );
+
+    let changes = UserFacade::new(jwt_claim, state.database_service.clone())
+        .await?
+        .watch_posts()
+        .await?;
+
+    let events = changes.filter_map(|change| async move {
+        match change {
+            Ok(change) => {
+                let change = BlogPostChange::from(change);
+                Event::default()
+                    .event(change.event_name())
+                    .json_data(&change)
+                    .inspect_err(|err| error!("Error in serializing post change: {err}"))
+                    .ok()
+                    .map(Ok)
+            }
+            Err(err) => {
+                error!("Error in watching posts: {err}");
+                None
+            }
+        }
+    });
+
+    // the stream ends when the server shuts down as well, so that the shutdown
+    // does not wait for the drain timeout, the extension is missing in the tests
+    let shutdown = async move {
+        match shutdown {
+            Some(Extension(shutdown)) => shutdown.wait().await,
+            None => std::future::pending().await,
+        }
+    };
+    let events = events
+        .take_until(tokio::time::sleep(expires_in))
+        .take_until(shutdown);
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{
+        body::Body,
+        http::{Method, StatusCode, header},
+    };
+    use std::time::Duration;
+
+    use bson::oid::ObjectId;
+    use chrono::Utc;
+    use jsonwebtoken::Header;
+    use serde_json::json;
+
+    use crate::{
+        auth::JWTAuthClaim,
+        test_utils::{TestApp, test_config},
+    };
+
+    #[tokio::test]
+    async fn test_post_lifecycle() {
+        let app = TestApp::new();
+        let user = app.register_user("marcel", "password").await;
+        let token = Some(user.token.as_str());
+
+        let response = app
+            .call(
+                Method::POST,
+                "/api/user/blog/post",
+                token,
+                Some(json!({ "title": "Title", "content": "Content" })),
+            )
+            .await;
+        assert_eq!(response.status, StatusCode::OK);
+        let post_id = response.body.as_str().unwrap().to_string();
+        let post_uri = format!("/api/user/blog/post/{post_id}");
+
+        let response = app
+            .call(Method::GET, "/api/user/blog/post", token, None)
+            .await;
+        assert_eq!(response.status, StatusCode::OK);
+        assert_eq!(response.body.as_array().unwrap().len(), 1);
+
+        let response = app.call(Method::GET, &post_uri, token, None).await;
+        assert_eq!(response.status, StatusCode::OK);
+        let etag = response.headers[header::ETAG].to_str().unwrap().to_string();
+
+        let update = json!({ "title": "New title", "content": "New content" });
+        let request = TestApp::request(Method::PUT, &post_uri, token, Some(update.clone()))
+            .header(header::IF_MATCH, &etag)
+            .body(Body::from(update.to_string()))
+            .unwrap();
+        assert_eq!(app.send(request).await.status, StatusCode::OK);
+
+        // the version in the etag is stale after the update
+        let request = TestApp::request(Method::PUT, &post_uri, token, Some(update.clone()))
+            .header(header::IF_MATCH, &etag)
+            .body(Body::from(update.to_string()))
+            .unwrap();
+        assert_eq!(app.send(request).await.status, StatusCode::CONFLICT);
+
+        // the update must be conditional
+        let response = app
+            .call(Method::PUT, &post_uri, token, Some(update.clone()))
+            .await;
+        assert_eq!(response.status, StatusCode::PRECONDITION_REQUIRED);
+
+        // weak tags never match with the strong comparison
+        let request = TestApp::request(Method::PUT, &post_uri, token, Some(update.clone()))
+            .header(header::IF_MATCH, "W/\"1\"")
+            .body(Body::from(update.to_string()))
+            .unwrap();
+        assert_eq!(app.send(request).await.status, StatusCode::BAD_REQUEST);
+
+        // any of the listed versions is accepted
+        let request = TestApp::request(Method::PUT, &post_uri, token, Some(update.clone()))
+            .header(header::IF_MATCH, format!("{etag}, W/\"1\", \"1\""))
+            .body(Body::from(update.to_string()))
+            .unwrap();
+        let response = app.send(request).await;
+        assert_eq!(response.status, StatusCode::OK);
+        assert_eq!(response.headers[header::ETAG], "\"2\"");
+
+        let request = TestApp::request(Method::PUT, &post_uri, token, Some(update.clone()))
+            .header(header::IF_MATCH, "*")
+            .body(Body::from(update.to_string()))
+            .unwrap();
+        let response = app.send(request).await;
+        assert_eq!(response.status, StatusCode::OK);
+        assert_eq!(response.headers[header::ETAG], "\"3\"");
+
+        let response = app.call(Method::DELETE, &post_uri, token, None).await;
+        assert_eq!(response.status, StatusCode::OK);
+        let response = app.call(Method::GET, &post_uri, token, None).await;
+        assert_eq!(response.status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_posts_require_token() {
+        let app = TestApp::new();
+        let response = app
+            .call(Method::GET, "/api/user/blog/post", None, None)
+            .await;
+        // a missing bearer token is reported as an invalid token
+        assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_refresh_token() {
+        let app = TestApp::new();
+        let user = app.register_user("marcel", "password").await;
+
+        let response = app
+            .call(Method::POST, "/api/user/refresh", Some(&user.token), None)
+            .await;
+        assert_eq!(response.status, StatusCode::OK);
+        assert_eq!(response.body["username"], "marcel");
+        let token = response.body["token"].as_str().unwrap();
+        let response = app
+            .call(Method::GET, "/api/user/info", Some(token), None)
+            .await;
+        assert_eq!(response.status, StatusCode::OK);
+
+        let response = app
+            .call(Method::POST, "/api/user/refresh", Some("corrupted"), None)
+            .await;
+        assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn test_refresh_is_limited_by_the_session_age() {
+        let mut config = test_config();
+        config.authentication.max_session_age = Some("7200".into());
+        let app = TestApp::with_config(config);
+        let user = app.register_user("marcel", "password").await;
+        let environment_service = app.state.environment_service.as_ref();
+        let session_token = |auth_time: u32| {
+            JWTAuthClaim::new(
+                Utc::now().timestamp() as u32 + 60,
+                auth_time,
+                ObjectId::parse_str(&user.user_id).unwrap(),
+                "marcel".into(),
+            )
+            .build_token(
+                &Header::default(),
+                environment_service.get_authentication_jwt_encoding(),
+            )
+            .unwrap()
+        };
+
+        // the refreshed token keeps the session and does not outlive it
+        let auth_time = Utc::now().timestamp() as u32 - 7000;
+        let response = app
+            .call(
+                Method::POST,
+                "/api/user/refresh",
+                Some(&session_token(auth_time)),
+                None,
+            )
+            .await;
+        assert_eq!(response.status, StatusCode::OK);
+        let claim = JWTAuthClaim::decode_token(
+            response.body["token"].as_str().unwrap(),
+            environment_service.get_authentication_jwt_decoding(),
+        )
+        .unwrap();
+        assert_eq!(claim.auth_time, auth_time);
+        assert_eq!(claim.exp, auth_time + 7200);
+
+        let auth_time = Utc::now().timestamp() as u32 - 7200;
+        let response = app
+            .call(
+                Method::POST,
+                "/api/user/refresh",
+                Some(&session_token(auth_time)),
+                None,
+            )
+            .await;
+        assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+        assert_eq!(response.body["message"], "Session expired");
+    }
+
+    #[tokio::test]
+    async fn test_stream_ends_with_token_or_user() {
+        let app = TestApp::new();
+        let user = app.register_user("marcel", "password").await;
+
+        // the expired token is still accepted within the leeway of the validation
+        let expired_token = JWTAuthClaim::new(
+            Utc::now().timestamp() as u32 - 1,
+            Utc::now().timestamp() as u32 - 60,
+            ObjectId::parse_str(&user.user_id).unwrap(),
+            "marcel".into(),
+        )
+        .build_token(
+            &Header::default(),
+            app.state
+                .environment_service
+                .get_authentication_jwt_encoding(),
+        )
+        .unwrap();
+        let stream_uri = format!("/api/user/blog/stream?access_token={expired_token}");
+        let response = tokio::time::timeout(
+            Duration::from_secs(5),
+            app.call(Method::GET, &stream_uri, None, None),
+        )
+        .await
+        .expect("the stream did not end with the token");
+        assert_eq!(response.status, StatusCode::OK);
+
+        // the stream ends at the first change after the user is deleted
+        let admin = app.create_admin("admin", "password").await;
+        let author = app.register_user("albert", "password").await;
+        let stream_uri = format!("/api/user/blog/stream?access_token={}", user.token);
+        let (response, _) = tokio::time::timeout(
+            Duration::from_secs(5),
+            futures::future::join(app.call(Method::GET, &stream_uri, None, None), async {
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                let user_uri = format!("/api/admin/user/{}", user.user_id);
+                let response = app
+                    .call(Method::DELETE, &user_uri, Some(&admin.token), None)
+                    .await;
+                assert_eq!(response.status, StatusCode::OK);
+                let post = json!({ "title": "Title", "content": "Content" });
+                let response = app
+                    .call(
+                        Method::POST,
+                        "/api/user/blog/post",
+                        Some(&author.token),
+                        Some(post),
+                    )
+                    .await;
+                assert_eq!(response.status, StatusCode::OK);
+            }),
+        )
+        .await
+        .expect("the stream did not end with the user");
+        assert_eq!(response.status, StatusCode::OK);
+    }
+}
@@ -0,0 +1,262 @@
+//! Server module serves the application router and handles
+//! its graceful shutdown, optionally terminating TLS.
+
+mod tls;
+
+use std::{future::Future, net::SocketAddr, time::Duration};
+
+use axum::{
+    Extension, Router,
+    extract::{Request, connect_info::Connected},
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
+    serve::{IncomingStream, Listener},
+};
+use tokio::{net::TcpListener, sync::watch};
+use tracing::{info, warn};
+
+pub use tls::{ReloadableCertificate, TlsListener, redirect_router, spawn_certificate_reload};
+
+/// Time given to the cancelled requests to send their response
+const CANCEL_GRACE_PERIOD: Duration = Duration::from_secs(1);
+
+/// Address of the client connected to the server, both over plain TCP
+/// and TLS, available to the requests as `ConnectInfo<ClientAddr>`
+#[derive(Debug, Clone, Copy)]
+pub struct ClientAddr(pub SocketAddr);
+
+impl Connected<IncomingStream<'_, TcpListener>> for ClientAddr {
+    fn connect_info(stream: IncomingStream<'_, TcpListener>) -> Self {
+        Self(*stream.remote_addr())
+    }
+}
+
+impl Connected<IncomingStream<'_, TlsListener>> for ClientAddr {
+    fn connect_info(stream: IncomingStream<'_, TlsListener>) -> Self {
+        Self(*stream.remote_addr())
+    }
+}
+
+/// Notification of the server shutdown available to the requests as extension.
+///
+/// Long lived responses, like the streams of server sent events, end when
+/// the shutdown starts so that the in flight requests can be drained.
+#[derive(Clone)]
+pub struct ShutdownReceiver(watch::Receiver<bool>);
+
+impl ShutdownReceiver {
+    /// Completes when the server starts the shutdown
+    pub async fn wait(mut self) {
+        // the sender is dropped only after the server has stopped
+        let _ = self.0.wait_for(|shutdown| *shutdown).await;
+    }
+}
+
+/// Completes when the process receives SIGINT (Ctrl+C) or SIGTERM
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("Error in installing Ctrl+C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Error in installing SIGTERM handler")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => info!("Received SIGINT, shutting down"),
+        _ = terminate => info!("Received SIGTERM, shutting down"),
+    }
+}
+
+/// Serves the application until the `signal` completes, then stops accepting
+/// new connections and waits for the in flight requests to complete.
+///
+/// Requests still running after `drain_timeout` are cancelled with
+/// `503 Service Unavailable` and their transactions aborted.
+/// It returns whether all the requests completed in time.
+pub async fn serve<L>(
+    listener: L,
+    app: Router,
+    signal: impl Future<Output = ()>,
+    drain_timeout: Duration,
+) -> std::io::Result<bool>
+where
+    L: Listener<Addr = SocketAddr>,
+    ClientAddr: for<'a> Connected<IncomingStream<'a, L>>,
+{
+    // connections are served by their own tasks, hence the requests are cancelled
+    // by racing them against this channel rather than dropping the server
+    let (cancel_sender, cancel_receiver) = watch::channel(false);
+    let app = app.layer(axum::middleware::from_fn(move |request, next| {
+        cancellable(cancel_receiver.clone(), request, next)
+    }));
+
+    let (shutdown_sender, shutdown_receiver) = watch::channel(false);
+    let app = app.layer(Extension(ShutdownReceiver(shutdown_receiver.clone())));
+
+    // the address of the client is used by the rate limit middleware
+    let app = app.into_make_service_with_connect_info::<ClientAddr>();
+    let server = axum::serve(listener, app)
+        .with_graceful_shutdown(ShutdownReceiver(shutdown_receiver).wait());
+    let mut server = std::pin::pin!(server.into_future());
+
+    tokio::select! {
+        result = &mut server => return result.map(|_| true),
+        _ = signal => shutdown_sender.send_replace(true),
+    };
+
+    info!("Waiting up to {drain_timeout:?} for in flight requests");
+    match tokio::time::timeout(drain_timeout, &mut server).await {
+        Ok(result) => result.map(|_| true),
+        Err(_) => {
+            warn!("In flight requests did not complete in {drain_timeout:?}, cancelling them");
+            cancel_sender.send_replace(true);
+            // streaming bodies that do not end with the `ShutdownReceiver` are not
+            // cancelled by the channel so the wait is bounded and their tasks end
+            // with the runtime
+            if tokio::time::timeout(CANCEL_GRACE_PERIOD, server)
+                .await
+                .is_err()
+            {
+                warn!("Some connections are still open, closing them");
+            }
+            Ok(false)
+        }
+    }
+}
+
+/// Runs the request unless it is cancelled, in that case the request future
+/// is dropped, with the transaction middleware aborting its transaction
+async fn cancellable(
+    mut cancel_receiver: watch::Receiver<bool>,
+    request: Request,
+    next: Next,
+) -> Response {
+    tokio::select! {
+        response = next.run(request) => response,
+        _ = cancel_receiver.wait_for(|cancelled| *cancelled) => {
+            StatusCode::SERVICE_UNAVAILABLE.into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        convert::Infallible,
+        time::{Duration, Instant},
+    };
+
+    use axum::{Extension, Router, body::Body, routing::get};
+    use futures::StreamExt;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+        sync::oneshot,
+    };
+
+    use super::{ShutdownReceiver, serve};
+
+    /// Router with a route that takes `handler_duration` to respond
+    fn slow_app(handler_duration: Duration) -> Router {
+        Router::new().route(
+            "/slow",
+            get(move || async move {
+                tokio::time::sleep(handler_duration).await;
+                "done"
+            }),
+        )
+    }
+
+    /// Starts the server with the router, sends a request to `path` and
+    /// triggers the shutdown while it is in flight
+    async fn shutdown_during_request(
+        app: Router,
+        path: &str,
+        drain_timeout: Duration,
+    ) -> (bool, String) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let (signal_sender, signal_receiver) = oneshot::channel::<()>();
+        let server = tokio::spawn(serve(
+            listener,
+            app,
+            async move {
+                signal_receiver.await.ok();
+            },
+            drain_timeout,
+        ));
+
+        let mut stream = TcpStream::connect(address).await.unwrap();
+        stream
+            .write_all(
+                format!("GET {path} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+                    .as_bytes(),
+            )
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        signal_sender.send(()).unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.ok();
+        (server.await.unwrap().unwrap(), response)
+    }
+
+    #[tokio::test]
+    async fn test_in_flight_requests_are_drained() {
+        let (drained, response) = shutdown_during_request(
+            slow_app(Duration::from_millis(200)),
+            "/slow",
+            Duration::from_secs(5),
+        )
+        .await;
+        assert!(drained);
+        assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+        assert!(response.ends_with("done"));
+    }
+
+    #[tokio::test]
+    async fn test_slow_requests_are_cancelled_after_timeout() {
+        let (drained, response) = shutdown_during_request(
+            slow_app(Duration::from_secs(5)),
+            "/slow",
+            Duration::from_millis(100),
+        )
+        .await;
+        assert!(!drained);
+        assert!(response.starts_with("HTTP/1.1 503"), "{response}");
+    }
+
+    #[tokio::test]
+    async fn test_streams_end_when_shutdown_starts() {
+        let app = Router::new().route(
+            "/stream",
+            get(
+                |Extension(shutdown): Extension<ShutdownReceiver>| async move {
+                    let chunks = futures::stream::once(async { Ok::<_, Infallible>("chunk") })
+                        .chain(futures::stream::pending())
+                        .take_until(shutdown.wait());
+                    Body::from_stream(chunks)
+                },
+            ),
+        );
+
+        let start = Instant::now();
+        let (drained, response) =
+            shutdown_during_request(app, "/stream", Duration::from_secs(5)).await;
+        assert!(drained);
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+        assert!(response.contains("chunk"));
+    }
+}
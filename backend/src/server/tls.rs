@@ -0,0 +1,385 @@
+//! TLS termination for the application server
+//!
+//! The certificate is served by `ReloadableCertificate`, that can be reloaded
+//! while the server is running, and the connections are accepted by
+//! `TlsListener` that plugs into `axum::serve` as any other listener.
+
+use std::{
+    io,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+    time::{Duration, SystemTime},
+};
+
+use axum::{
+    Router,
+    extract::State,
+    http::{HeaderMap, StatusCode, Uri, header},
+    response::{IntoResponse, Redirect, Response},
+};
+use tokio::{
+    net::{TcpListener, TcpStream},
+    sync::mpsc,
+    task::JoinHandle,
+};
+use tokio_rustls::{
+    TlsAcceptor,
+    rustls::{
+        ServerConfig,
+        crypto::{CryptoProvider, ring},
+        pki_types::{CertificateDer, PrivateKeyDer, pem::PemObject},
+        server::{ClientHello, ResolvesServerCert},
+        sign::CertifiedKey,
+    },
+    server::TlsStream,
+};
+use tracing::{debug, error, info};
+
+/// Time given to the client to complete the TLS handshake
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Certificate and private key read from PEM files that can be reloaded
+/// without restarting the server, new connections use the last loaded certificate
+#[derive(Debug)]
+pub struct ReloadableCertificate {
+    cert_path: PathBuf,
+    key_path: PathBuf,
+    provider: Arc<CryptoProvider>,
+    certified_key: RwLock<Arc<CertifiedKey>>,
+}
+
+impl ReloadableCertificate {
+    /// Loads the certificate chain and the private key from the PEM files
+    pub fn load(cert_path: &Path, key_path: &Path) -> io::Result<Self> {
+        let provider = Arc::new(ring::default_provider());
+        let certified_key = Self::read(cert_path, key_path, &provider)?;
+        Ok(Self {
+            cert_path: cert_path.to_path_buf(),
+            key_path: key_path.to_path_buf(),
+            provider,
+            certified_key: RwLock::new(Arc::new(certified_key)),
+        })
+    }
+
+    /// Reads again the PEM files, the current certificate is kept when they are not valid
+    pub fn reload(&self) -> io::Result<()> {
+        let certified_key = Self::read(&self.cert_path, &self.key_path, &self.provider)?;
+        *self
+            .certified_key
+            .write()
+            .expect("certificate lock is poisoned") = Arc::new(certified_key);
+        Ok(())
+    }
+
+    /// Builds the rustls configuration that resolves the certificate with this instance
+    pub fn server_config(self: &Arc<Self>) -> io::Result<Arc<ServerConfig>> {
+        let mut config = ServerConfig::builder_with_provider(self.provider.clone())
+            .with_safe_default_protocol_versions()
+            .map_err(io::Error::other)?
+            .with_no_client_auth()
+            .with_cert_resolver(self.clone());
+        // axum is built without the http2 feature
+        config.alpn_protocols = vec![b"http/1.1".to_vec()];
+        Ok(Arc::new(config))
+    }
+
+    /// Last modification time of the certificate and key files
+    fn modified(&self) -> io::Result<(SystemTime, SystemTime)> {
+        Ok((
+            std::fs::metadata(&self.cert_path)?.modified()?,
+            std::fs::metadata(&self.key_path)?.modified()?,
+        ))
+    }
+
+    fn read(
+        cert_path: &Path,
+        key_path: &Path,
+        provider: &CryptoProvider,
+    ) -> io::Result<CertifiedKey> {
+        let invalid = |path: &Path, err: &dyn std::fmt::Display| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{path}: {err}", path = path.display()),
+            )
+        };
+        let cert_chain = CertificateDer::pem_file_iter(cert_path)
+            .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+            .map_err(|err| invalid(cert_path, &err))?;
+        if cert_chain.is_empty() {
+            return Err(invalid(cert_path, &"no certificate found"));
+        }
+        let key = PrivateKeyDer::from_pem_file(key_path).map_err(|err| invalid(key_path, &err))?;
+        CertifiedKey::from_der(cert_chain, key, provider).map_err(|err| invalid(key_path, &err))
+    }
+}
+
+impl ResolvesServerCert for ReloadableCertificate {
+    fn resolve(&self, _client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        Some(
+            self.certified_key
+                .read()
+                .expect("certificate lock is poisoned")
+                .clone(),
+        )
+    }
+}
+
+/// Spawns a job that reloads the certificate when its files change
+///
+/// Modification times are polled instead of using file system events because
+/// certificates are often replaced by swapping symbolic links, for example
+/// in Kubernetes secrets.
+pub fn spawn_certificate_reload(
+    certificate: Arc<ReloadableCertificate>,
+    interval: Duration,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut last_modified = certificate.modified().ok();
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            let modified = certificate.modified().ok();
+            if modified == last_modified {
+                continue;
+            }
+            match certificate.reload() {
+                Ok(()) => {
+                    info!("Reloaded TLS certificate");
+                    last_modified = modified;
+                }
+                // files may be partially written, the reload is retried at the next tick
+                Err(err) => error!("Error in reloading TLS certificate: {err}"),
+            }
+        }
+    })
+}
+
+/// Listener that accepts TCP connections and completes their TLS handshake
+///
+/// Handshakes run in their own tasks so that a slow client does not block
+/// the other connections.
+pub struct TlsListener {
+    connections: mpsc::Receiver<(TlsStream<TcpStream>, SocketAddr)>,
+    local_addr: SocketAddr,
+    accept_task: JoinHandle<()>,
+}
+
+impl TlsListener {
+    pub fn new(listener: TcpListener, config: Arc<ServerConfig>) -> io::Result<Self> {
+        let local_addr = listener.local_addr()?;
+        let acceptor = TlsAcceptor::from(config);
+        let (sender, connections) = mpsc::channel(64);
+        let accept_task = tokio::spawn(async move {
+            loop {
+                let (stream, address) = match listener.accept().await {
+                    Ok(connection) => connection,
+                    Err(err) => {
+                        error!("Error in accepting connection: {err}");
+                        tokio::time::sleep(Duration::from_secs(1)).await;
+                        continue;
+                    }
+                };
+                let acceptor = acceptor.clone();
+                let sender = sender.clone();
+                tokio::spawn(async move {
+                    match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                        Ok(Ok(stream)) => {
+                            let _ = sender.send((stream, address)).await;
+                        }
+                        Ok(Err(err)) => debug!("TLS handshake with {address} failed: {err}"),
+                        Err(_) => debug!("TLS handshake with {address} timed out"),
+                    }
+                });
+            }
+        });
+        Ok(Self {
+            connections,
+            local_addr,
+            accept_task,
+        })
+    }
+}
+
+impl Drop for TlsListener {
+    fn drop(&mut self) {
+        self.accept_task.abort();
+    }
+}
+
+impl axum::serve::Listener for TlsListener {
+    type Io = TlsStream<TcpStream>;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        match self.connections.recv().await {
+            Some(connection) => connection,
+            // the accept task never stops while the listener exists
+            None => std::future::pending().await,
+        }
+    }
+
+    fn local_addr(&self) -> io::Result<Self::Addr> {
+        Ok(self.local_addr)
+    }
+}
+
+/// Router that redirects every HTTP request to the HTTPS server listening on `https_port`
+pub fn redirect_router(https_port: u16) -> Router {
+    Router::new()
+        .fallback(redirect_to_https)
+        .with_state(https_port)
+}
+
+async fn redirect_to_https(
+    State(https_port): State<u16>,
+    headers: HeaderMap,
+    uri: Uri,
+) -> Response {
+    let Some(host) = headers
+        .get(header::HOST)
+        .and_then(|host| host.to_str().ok())
+    else {
+        return (StatusCode::BAD_REQUEST, "Missing host header").into_response();
+    };
+    // the port of the host header is the one of the HTTP listener
+    let host = match host.rsplit_once(':') {
+        Some((name, port)) if port.chars().all(|c| c.is_ascii_digit()) => name,
+        _ => host,
+    };
+    let authority = if https_port == 443 {
+        host.to_string()
+    } else {
+        format!("{host}:{https_port}")
+    };
+    let path = uri.path_and_query().map_or("/", |path| path.as_str());
+    Redirect::permanent(&format!("https://{authority}{path}")).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::Path, sync::Arc, time::Duration};
+
+    use axum::{
+        Router,
+        body::Body,
+        http::{Request, StatusCode, header},
+        routing::get,
+    };
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+    };
+    use tokio_rustls::{
+        TlsConnector,
+        rustls::{
+            ClientConfig, RootCertStore,
+            pki_types::{CertificateDer, ServerName, pem::PemObject},
+        },
+    };
+    use tower::ServiceExt;
+
+    use super::{ReloadableCertificate, TlsListener, redirect_router};
+
+    /// Writes a self signed certificate for `name` and returns it in PEM format
+    fn write_certificate(directory: &Path, name: &str) -> String {
+        let certificate = rcgen::generate_simple_self_signed(vec![name.to_string()]).unwrap();
+        std::fs::write(directory.join("cert.pem"), certificate.cert.pem()).unwrap();
+        std::fs::write(
+            directory.join("key.pem"),
+            certificate.key_pair.serialize_pem(),
+        )
+        .unwrap();
+        certificate.cert.pem()
+    }
+
+    /// Sends a GET request over TLS trusting only `trusted_pem` for `name`
+    async fn https_get(port: u16, name: &str, trusted_pem: &str) -> std::io::Result<String> {
+        let mut roots = RootCertStore::empty();
+        roots.add_parsable_certificates(
+            CertificateDer::pem_slice_iter(trusted_pem.as_bytes()).map(Result::unwrap),
+        );
+        let config = ClientConfig::builder_with_provider(Arc::new(
+            tokio_rustls::rustls::crypto::ring::default_provider(),
+        ))
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+        let stream = TcpStream::connect(("127.0.0.1", port)).await?;
+        let mut stream = TlsConnector::from(Arc::new(config))
+            .connect(ServerName::try_from(name.to_string()).unwrap(), stream)
+            .await?;
+        stream
+            .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+            .await?;
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.ok();
+        Ok(response)
+    }
+
+    #[tokio::test]
+    async fn test_serves_https_and_reloads_certificate() {
+        let directory = std::env::temp_dir().join(format!("tls-test-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let first_pem = write_certificate(&directory, "first.localhost");
+        let certificate = Arc::new(
+            ReloadableCertificate::load(&directory.join("cert.pem"), &directory.join("key.pem"))
+                .unwrap(),
+        );
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let listener = TlsListener::new(listener, certificate.server_config().unwrap()).unwrap();
+        let app = Router::new().route("/", get(|| async { "secure" }));
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        let response = https_get(port, "first.localhost", &first_pem)
+            .await
+            .unwrap();
+        assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+        assert!(response.ends_with("secure"));
+
+        let second_pem = write_certificate(&directory, "second.localhost");
+        certificate.reload().unwrap();
+        assert!(
+            https_get(port, "first.localhost", &first_pem)
+                .await
+                .is_err()
+        );
+        let response = https_get(port, "second.localhost", &second_pem)
+            .await
+            .unwrap();
+        assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+
+        // invalid files do not replace the current certificate
+        std::fs::write(directory.join("cert.pem"), "not a certificate").unwrap();
+        assert!(certificate.reload().is_err());
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        assert!(
+            https_get(port, "second.localhost", &second_pem)
+                .await
+                .is_ok()
+        );
+        std::fs::remove_dir_all(&directory).ok();
+    }
+
+    #[tokio::test]
+    async fn test_redirect_to_https() {
+        let response = redirect_router(8443)
+            .oneshot(
+                Request::builder()
+                    .uri("/api/health?check=true")
+                    .header(header::HOST, "example.com:8080")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::PERMANENT_REDIRECT);
+        assert_eq!(
+            response.headers()[header::LOCATION],
+            "https://example.com:8443/api/health?check=true"
+        );
+    }
+}
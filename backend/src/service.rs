@@ -9,6 +9,11 @@
 //!   like access control and database.
 
 pub mod access_control;
+pub mod audit;
 pub mod blog;
 pub mod database;
+pub mod feed;
+pub mod health;
+pub mod purge;
+pub mod rate_limit;
 pub mod user;
@@ -0,0 +1,295 @@
+use std::sync::Arc;
+
+use bson::{Bson, Document, doc, oid::ObjectId};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::sync::RwLock;
+
+use crate::{
+    ServiceResult,
+    enums::AuditAction,
+    error::ServiceAppError,
+    model::{AuditEvent, AuditEventBuilder},
+    service::database::{DatabaseServiceTrait, document::DatabaseDocumentTrait},
+};
+
+/// Fields whose value is never written in the audit log
+const REDACTED_FIELDS: [&str; 1] = ["password_hash"];
+
+/// Value stored in place of the redacted fields
+const REDACTED_VALUE: &str = "[redacted]";
+
+/// Maximum number of events returned in a single page
+pub const MAX_PAGE_SIZE: u64 = 100;
+
+/// Filters used to query the audit events, all of them are optional
+#[derive(Debug, Default, Clone)]
+pub struct AuditEventFilter {
+    pub actor_id: Option<ObjectId>,
+    pub action: Option<AuditAction>,
+    pub target_collection: Option<String>,
+    pub target_id: Option<ObjectId>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+impl AuditEventFilter {
+    fn to_query(&self) -> Document {
+        let mut query = doc! {};
+        if let Some(actor_id) = self.actor_id {
+            query.insert("actor_id", actor_id);
+        }
+        if let Some(action) = self.action {
+            query.insert("action", action.to_string());
+        }
+        if let Some(target_collection) = &self.target_collection {
+            query.insert("target_collection", target_collection);
+        }
+        if let Some(target_id) = self.target_id {
+            query.insert("target_id", target_id);
+        }
+        let mut creation_date = doc! {};
+        if let Some(from) = self.from {
+            creation_date.insert("$gte", bson::DateTime::from_chrono(from));
+        }
+        if let Some(to) = self.to {
+            creation_date.insert("$lt", bson::DateTime::from_chrono(to));
+        }
+        if !creation_date.is_empty() {
+            query.insert("creation_date", creation_date);
+        }
+        query
+    }
+}
+
+/// AuditService is a secondary service that records who changed
+/// what in the application.
+///
+/// The events must be recorded in the same transaction of the change
+/// so that they are committed or aborted together with it.
+pub struct AuditService<D: DatabaseServiceTrait> {
+    database_service: Arc<D>,
+    transaction: Option<Arc<RwLock<D::Transaction>>>,
+}
+
+impl<D: DatabaseServiceTrait> AuditService<D> {
+    pub fn new(database_service: Arc<D>, transaction: Option<Arc<RwLock<D::Transaction>>>) -> Self {
+        Self {
+            database_service,
+            transaction,
+        }
+    }
+
+    /// Records the creation of the document by `actor_id`, the whole document
+    /// is stored as the `after` state of the event
+    pub async fn record_creation<T: DatabaseDocumentTrait + Serialize>(
+        &self,
+        actor_id: ObjectId,
+        document: &T,
+    ) -> ServiceResult<AuditEvent> {
+        let after = bson::to_document(document).map_err(|err| {
+            ServiceAppError::InternalServerError(format!(
+                "Error in serializing audited document: {err}"
+            ))
+        })?;
+        self.record::<T>(
+            actor_id,
+            AuditAction::Create,
+            *document.get_id(),
+            None,
+            Some(after),
+        )
+        .await
+    }
+
+    /// Records a change done by `actor_id` on the document `target_id` of the collection of T.
+    ///
+    /// `after` contains the fields written by the operation and `before` the state of
+    /// the document before it, only the fields of `after` whose value has changed are stored.
+    /// Sensitive fields like the password hash are redacted.
+    pub async fn record<T: DatabaseDocumentTrait>(
+        &self,
+        actor_id: ObjectId,
+        action: AuditAction,
+        target_id: ObjectId,
+        before: Option<Document>,
+        after: Option<Document>,
+    ) -> ServiceResult<AuditEvent> {
+        let (before, after) = diff(before, after);
+
+        Ok(AuditEventBuilder::new(self.database_service.clone())
+            .actor_id(actor_id)
+            .action(action.to_string())
+            .target_collection(T::collection_name().to_string())
+            .target_id(target_id)
+            .before(before.map(sanitize))
+            .after(after.map(sanitize))
+            .creation_date(Utc::now())
+            .build(self.transaction.clone())
+            .await?)
+    }
+
+    /// Returns the page of events that match the filter, from the most recent,
+    /// together with the total number of matching events
+    pub async fn get_events(
+        &self,
+        filter: &AuditEventFilter,
+        page: u64,
+        page_size: u64,
+    ) -> ServiceResult<(Vec<AuditEvent>, u64)> {
+        if page == 0 || page_size == 0 || page_size > MAX_PAGE_SIZE {
+            return Err(ServiceAppError::InvalidRequest(format!(
+                "page must be greater than zero and page size between 1 and {MAX_PAGE_SIZE}"
+            )));
+        }
+        // a huge page would overflow the number of skipped events, MongoDB
+        // stores it as a signed integer
+        let skip = (page - 1)
+            .checked_mul(page_size)
+            .filter(|skip| i64::try_from(*skip).is_ok())
+            .ok_or_else(|| ServiceAppError::InvalidRequest(format!("page {page} is too large")))?;
+        let query = filter.to_query();
+        let total = self
+            .database_service
+            .count_documents::<AuditEvent>(query.clone())
+            .await?;
+        let events = self
+            .database_service
+            .find_page::<AuditEvent>(
+                query,
+                doc! { "creation_date": -1, "_id": -1 },
+                skip,
+                page_size,
+            )
+            .await?;
+        Ok((events, total))
+    }
+}
+
+/// Keeps only the fields of `after` that differ from `before`
+fn diff(before: Option<Document>, after: Option<Document>) -> (Option<Document>, Option<Document>) {
+    let (Some(before), Some(after)) = (&before, &after) else {
+        return (before, after);
+    };
+
+    let mut before_diff = Document::new();
+    let mut after_diff = Document::new();
+    for (key, value) in after.iter() {
+        let previous = before.get(key).cloned().unwrap_or(Bson::Null);
+        if &previous != value {
+            before_diff.insert(key, previous);
+            after_diff.insert(key, value.clone());
+        }
+    }
+    (Some(before_diff), Some(after_diff))
+}
+
+/// Removes the id, already stored as target id, and redacts the sensitive fields
+fn sanitize(mut document: Document) -> Document {
+    document.remove("_id");
+    for field in REDACTED_FIELDS {
+        if document.contains_key(field) {
+            document.insert(field, REDACTED_VALUE);
+        }
+    }
+    document
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use bson::{doc, oid::ObjectId};
+
+    use crate::{
+        enums::AuditAction,
+        model::User,
+        service::{
+            audit::{AuditEventFilter, AuditService},
+            database::memory_service::MemoryDatabaseService,
+        },
+    };
+
+    #[tokio::test]
+    async fn test_record_stores_redacted_diff() {
+        let database_service = Arc::new(MemoryDatabaseService::default());
+        let audit_service = AuditService::new(database_service.clone(), None);
+        let actor_id = ObjectId::new();
+        let target_id = ObjectId::new();
+
+        let event = audit_service
+            .record::<User>(
+                actor_id,
+                AuditAction::Update,
+                target_id,
+                Some(doc! { "first_name": "Marcel", "last_name": "Proust", "password_hash": "a" }),
+                Some(
+                    doc! { "first_name": "Gilberte", "last_name": "Proust", "password_hash": "b" },
+                ),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(event.action(), "update");
+        assert_eq!(event.target_collection(), "user");
+        assert_eq!(
+            event.before(),
+            &Some(doc! { "first_name": "Marcel", "password_hash": "[redacted]" })
+        );
+        assert_eq!(
+            event.after(),
+            &Some(doc! { "first_name": "Gilberte", "password_hash": "[redacted]" })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_events_filters_and_paginates() {
+        let database_service = Arc::new(MemoryDatabaseService::default());
+        let audit_service = AuditService::new(database_service.clone(), None);
+        let actor_id = ObjectId::new();
+
+        let mut target_ids = vec![];
+        for _ in 0..5 {
+            let target_id = ObjectId::new();
+            audit_service
+                .record::<User>(
+                    actor_id,
+                    AuditAction::Create,
+                    target_id,
+                    None,
+                    Some(doc! { "username": "user" }),
+                )
+                .await
+                .unwrap();
+            target_ids.push(target_id);
+        }
+        audit_service
+            .record::<User>(
+                ObjectId::new(),
+                AuditAction::Delete,
+                target_ids[0],
+                Some(doc! { "deleted_at": null }),
+                Some(doc! { "deleted_at": bson::DateTime::now() }),
+            )
+            .await
+            .unwrap();
+
+        let filter = AuditEventFilter {
+            actor_id: Some(actor_id),
+            ..Default::default()
+        };
+        let (first_page, total) = audit_service.get_events(&filter, 1, 2).await.unwrap();
+        assert_eq!(total, 5);
+        assert_eq!(first_page.len(), 2);
+        let (last_page, _) = audit_service.get_events(&filter, 3, 2).await.unwrap();
+        assert_eq!(last_page.len(), 1);
+
+        let filter = AuditEventFilter {
+            action: Some(AuditAction::Delete),
+            ..Default::default()
+        };
+        let (events, total) = audit_service.get_events(&filter, 1, 10).await.unwrap();
+        assert_eq!(total, 1);
+        assert_eq!(events[0].target_id(), &target_ids[0]);
+    }
+}
@@ -1,15 +1,20 @@
 use std::sync::Arc;
 
-use bson::doc;
+use api_types::user::PostVisibility;
 use bson::oid::ObjectId;
+use bson::{Document, doc};
 use chrono::{DateTime, Utc};
+use futures::{StreamExt, TryStreamExt, stream::BoxStream};
 use tokio::sync::RwLock;
 
 use crate::{
     ServiceResult,
     error::{DatabaseError, ServiceAppError},
-    model::{BlogPost, BlogPostBuilder},
-    service::database::DatabaseServiceTrait,
+    model::{BlogPost, BlogPostBuilder, User},
+    service::database::{
+        ChangeEvent, DatabaseServiceTrait,
+        document::{DELETED_AT_FIELD, DatabaseDocumentTrait, ExpectedVersion},
+    },
 };
 
 /// BlogService manages the BlogPost resources providing
@@ -32,6 +37,7 @@ impl<D: DatabaseServiceTrait> BlogService<D> {
         &self,
         title: String,
         content: String,
+        visibility: PostVisibility,
         user_id: ObjectId,
         username: String,
         creation_date: DateTime<Utc>,
@@ -42,6 +48,8 @@ impl<D: DatabaseServiceTrait> BlogService<D> {
             .user_id(user_id)
             .username(username)
             .creation_date(creation_date)
+            .updated_at(creation_date)
+            .visibility(visibility.to_string())
             .build(self.transaction.clone())
             .await
             .map_err(|err| match err {
@@ -63,20 +71,215 @@ impl<D: DatabaseServiceTrait> BlogService<D> {
 
         Ok(blog_posts)
     }
+
+    /// Returns the public blog posts, readable without an account, if the
+    /// user_id is specified then only the posts created by him are returned.
+    ///
+    /// The posts of the soft deleted users are hidden together with their author
+    pub async fn get_public_posts(
+        &self,
+        user_id: Option<ObjectId>,
+    ) -> ServiceResult<Vec<BlogPost>> {
+        let query = self.public_posts_query(user_id).await?;
+        Ok(self.database_service.find_many::<BlogPost>(query).await?)
+    }
+
+    /// Returns the `limit` most recent public blog posts, of the given user
+    /// when it is specified, sorted by descending creation date
+    pub async fn get_recent_public_posts(
+        &self,
+        user_id: Option<ObjectId>,
+        limit: u64,
+    ) -> ServiceResult<Vec<BlogPost>> {
+        let query = self.public_posts_query(user_id).await?;
+        Ok(self
+            .database_service
+            .find_page::<BlogPost>(query, doc! { "creation_date": -1 }, 0, limit)
+            .await?)
+    }
+
+    /// Query of the public posts, of the given user when it is specified,
+    /// excluding the posts of the soft deleted users
+    async fn public_posts_query(&self, user_id: Option<ObjectId>) -> ServiceResult<Document> {
+        let deleted_user_ids = self
+            .database_service
+            .find_many::<User>(doc! { DELETED_AT_FIELD: { "$ne": null } })
+            .await?
+            .iter()
+            .map(|user| *user.get_id())
+            .collect::<Vec<ObjectId>>();
+        // a single condition on the user id, so that the author filter
+        // does not replace the exclusion of the deleted users
+        let mut user_condition = doc! { "$nin": deleted_user_ids };
+        if let Some(user_id) = user_id {
+            user_condition.insert("$in", vec![user_id]);
+        }
+
+        Ok(doc! {
+            "visibility": PostVisibility::Public.as_str(),
+            "user_id": user_condition,
+        })
+    }
+
+    /// Returns the most recent `updated_at` of the blog posts, of the given
+    /// user when it is specified, or None when there are no posts.
+    ///
+    /// Every post is considered, also the soft deleted and the members only
+    /// ones, since a post that leaves the public posts changes them too
+    pub async fn get_last_modified(
+        &self,
+        user_id: Option<ObjectId>,
+    ) -> ServiceResult<Option<DateTime<Utc>>> {
+        let mut last_modified = None;
+        for mut query in [doc! {}, doc! { DELETED_AT_FIELD: { "$ne": null } }] {
+            if let Some(user_id) = user_id {
+                query.insert("user_id", user_id);
+            }
+            let posts = self
+                .database_service
+                .find_page::<BlogPost>(query, doc! { "updated_at": -1 }, 0, 1)
+                .await?;
+            last_modified = last_modified.max(posts.first().map(|post| *post.updated_at()));
+        }
+        Ok(last_modified)
+    }
+
+    /// Moves forward the `updated_at` of the posts of the user, it is called
+    /// when the user is deleted or restored since its posts are hidden with it
+    pub async fn touch_user_posts(&self, user_id: ObjectId) -> ServiceResult<()> {
+        Ok(self
+            .database_service
+            .update_many::<BlogPost>(
+                doc! { "user_id": user_id },
+                doc! { "$set": { "updated_at": bson::DateTime::now() } },
+                self.transaction.clone(),
+            )
+            .await?)
+    }
+
+    /// Returns the stream of the changes of the blog posts done after the call,
+    /// soft deleted posts are notified as deleted
+    pub async fn watch_posts(
+        &self,
+    ) -> ServiceResult<BoxStream<'static, ServiceResult<ChangeEvent<BlogPost>>>> {
+        let changes = self.database_service.watch::<BlogPost>().await?;
+        Ok(changes
+            .map_ok(|change| match change {
+                ChangeEvent::Updated(post) if post.deleted_at().is_some() => {
+                    ChangeEvent::Deleted(*post.get_id())
+                }
+                other => other,
+            })
+            .map_err(ServiceAppError::from)
+            .boxed())
+    }
+
+    /// Returns the blog post with the given id
+    pub async fn get_post(&self, post_id: ObjectId) -> ServiceResult<BlogPost> {
+        self.database_service
+            .find_one::<BlogPost>(doc! { "_id": post_id })
+            .await?
+            .ok_or(ServiceAppError::DoesNotExist(format!(
+                "Blog post {post_id} does not exist"
+            )))
+    }
+
+    /// Soft deletes the blog post with the given id and returns the deletion time
+    pub async fn delete_post(&self, post_id: ObjectId) -> ServiceResult<DateTime<Utc>> {
+        // the post is retrieved to verify that it exists and it is not already deleted
+        self.get_post(post_id).await?;
+        let deleted_at = bson::DateTime::now();
+        self.database_service
+            .update_one::<BlogPost>(
+                doc! { "_id": post_id },
+                doc! { "$set": { DELETED_AT_FIELD: deleted_at, "updated_at": deleted_at } },
+                self.transaction.clone(),
+            )
+            .await?;
+        Ok(deleted_at.to_chrono())
+    }
+
+    /// Returns the soft deleted blog posts that can be restored
+    pub async fn get_deleted_posts(&self) -> ServiceResult<Vec<BlogPost>> {
+        Ok(self
+            .database_service
+            .find_many::<BlogPost>(doc! { DELETED_AT_FIELD: { "$ne": null } })
+            .await?)
+    }
+
+    /// Restores the soft deleted blog post with the given id and
+    /// returns the post as it was before the restore
+    pub async fn restore_post(&self, post_id: ObjectId) -> ServiceResult<BlogPost> {
+        let deleted_post = self
+            .database_service
+            .find_one::<BlogPost>(doc! { "_id": post_id, DELETED_AT_FIELD: { "$ne": null } })
+            .await?
+            .ok_or(ServiceAppError::DoesNotExist(format!(
+                "Deleted blog post {post_id} does not exist"
+            )))?;
+        self.database_service
+            .update_one::<BlogPost>(
+                doc! { "_id": post_id },
+                doc! { "$set": { DELETED_AT_FIELD: null, "updated_at": bson::DateTime::now() } },
+                self.transaction.clone(),
+            )
+            .await?;
+        Ok(deleted_post)
+    }
+
+    /// Updates title, content and visibility of the blog post if its current
+    /// version is the expected one and returns the new version
+    pub async fn update_post(
+        &self,
+        post_id: ObjectId,
+        expected_version: ExpectedVersion,
+        title: String,
+        content: String,
+        visibility: PostVisibility,
+    ) -> ServiceResult<i64> {
+        self.database_service
+            .update_one_versioned::<BlogPost>(
+                post_id,
+                expected_version,
+                doc! {
+                    "$set": {
+                        "title": title,
+                        "content": content,
+                        "visibility": visibility.as_str(),
+                        "updated_at": bson::DateTime::now(),
+                    }
+                },
+                self.transaction.clone(),
+            )
+            .await
+            .map_err(|err| match err {
+                DatabaseError::DocumentDoesNotExist(id) => {
+                    ServiceAppError::DoesNotExist(format!("Blog post {id} does not exist"))
+                }
+                other => ServiceAppError::DatabaseError(other),
+            })
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use std::sync::Arc;
 
+    use api_types::user::PostVisibility;
     use bson::{doc, oid::ObjectId};
     use chrono::{DateTime, Utc};
+    use futures::StreamExt;
 
     use crate::{
-        model::{BlogPost, BlogPostBuilder},
+        error::{DatabaseError, ServiceAppError},
+        model::{BlogPost, BlogPostBuilder, User},
         service::{
             blog::BlogService,
-            database::{DatabaseServiceTrait, memory_service::MemoryDatabaseService},
+            database::{
+                ChangeEvent, DatabaseServiceTrait,
+                document::{DELETED_AT_FIELD, DatabaseDocumentTrait},
+                memory_service::MemoryDatabaseService,
+            },
         },
     };
 
@@ -93,6 +296,8 @@ mod tests {
             .user_id(*user_id)
             .username(username)
             .creation_date(DateTime::<Utc>::default())
+            .updated_at(DateTime::<Utc>::default())
+            .visibility(PostVisibility::Public.to_string())
             .build(None)
             .await
             .unwrap()
@@ -122,22 +327,139 @@ mod tests {
             );
         }
 
-        let first_user_posts = blog_service
-            .get_posts(Some(first_user.clone()))
-            .await
-            .unwrap();
+        let first_user_posts = blog_service.get_posts(Some(first_user)).await.unwrap();
         assert_eq!(first_user_posts.len(), 3);
 
-        let second_user_posts = blog_service
-            .get_posts(Some(second_user.clone()))
-            .await
-            .unwrap();
+        let second_user_posts = blog_service.get_posts(Some(second_user)).await.unwrap();
         assert_eq!(second_user_posts.len(), 2);
 
         let all_user_posts = blog_service.get_posts(None).await.unwrap();
         assert_eq!(all_user_posts.len(), 5);
     }
 
+    #[tokio::test]
+    async fn test_update_post_version_conflict() {
+        let database_service = Arc::new(MemoryDatabaseService::default());
+        let blog_service = BlogService::new(database_service.clone(), None);
+
+        let post = create_blog(
+            database_service.clone(),
+            "title".into(),
+            "content".into(),
+            &ObjectId::new(),
+            "username".into(),
+        )
+        .await;
+        assert_eq!(post.version(), 0);
+
+        let new_version = blog_service
+            .update_post(
+                *post.get_id(),
+                0.into(),
+                "new title".into(),
+                "new content".into(),
+                PostVisibility::Public,
+            )
+            .await
+            .unwrap();
+        assert_eq!(new_version, 1);
+
+        let result = blog_service
+            .update_post(
+                *post.get_id(),
+                0.into(),
+                "stale title".into(),
+                "stale content".into(),
+                PostVisibility::Public,
+            )
+            .await;
+        assert!(matches!(
+            result,
+            Err(ServiceAppError::DatabaseError(
+                DatabaseError::VersionConflict { .. }
+            ))
+        ));
+
+        let updated_post = blog_service.get_post(*post.get_id()).await.unwrap();
+        assert_eq!(updated_post.title(), "new title");
+        assert_eq!(updated_post.version(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_delete_and_restore_post() {
+        let database_service = Arc::new(MemoryDatabaseService::default());
+        let blog_service = BlogService::new(database_service.clone(), None);
+
+        let user_id = ObjectId::new();
+        let mut posts = vec![];
+        for i in 0..2 {
+            posts.push(
+                create_blog(
+                    database_service.clone(),
+                    format!("title {i}"),
+                    format!("content {i}"),
+                    &user_id,
+                    "username".into(),
+                )
+                .await,
+            );
+        }
+
+        blog_service.delete_post(*posts[0].get_id()).await.unwrap();
+        assert_eq!(blog_service.get_posts(None).await.unwrap().len(), 1);
+        assert!(blog_service.get_post(*posts[0].get_id()).await.is_err());
+
+        let deleted_posts = blog_service.get_deleted_posts().await.unwrap();
+        assert_eq!(deleted_posts.len(), 1);
+        assert!(deleted_posts[0].deleted_at().is_some());
+
+        blog_service.restore_post(*posts[0].get_id()).await.unwrap();
+        assert_eq!(blog_service.get_posts(None).await.unwrap().len(), 2);
+        assert!(blog_service.get_deleted_posts().await.unwrap().is_empty());
+        assert!(blog_service.restore_post(*posts[1].get_id()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_watch_posts() {
+        let database_service = Arc::new(MemoryDatabaseService::default());
+        let blog_service = BlogService::new(database_service.clone(), None);
+        let mut changes = blog_service.watch_posts().await.unwrap();
+
+        let user_id = ObjectId::new();
+        let post = create_blog(
+            database_service.clone(),
+            "title".into(),
+            "content".into(),
+            &user_id,
+            "username".into(),
+        )
+        .await;
+        blog_service
+            .update_post(
+                *post.get_id(),
+                0.into(),
+                "new title".into(),
+                "content".into(),
+                PostVisibility::Public,
+            )
+            .await
+            .unwrap();
+        blog_service.delete_post(*post.get_id()).await.unwrap();
+
+        assert!(matches!(
+            changes.next().await,
+            Some(Ok(ChangeEvent::Inserted(inserted))) if inserted.title() == "title"
+        ));
+        assert!(matches!(
+            changes.next().await,
+            Some(Ok(ChangeEvent::Updated(updated))) if updated.title() == "new title"
+        ));
+        assert!(matches!(
+            changes.next().await,
+            Some(Ok(ChangeEvent::Deleted(id))) if &id == post.get_id()
+        ));
+    }
+
     #[tokio::test]
     async fn test_publish_post() {
         let database_service = Arc::new(MemoryDatabaseService::default());
@@ -150,10 +472,16 @@ mod tests {
         for i in 0..5 {
             let user_id = if i < 3 { first_user } else { second_user };
             let title = format!("title {i}");
+            let visibility = if i == 4 {
+                PostVisibility::MembersOnly
+            } else {
+                PostVisibility::Public
+            };
             blog_service
                 .publish_post(
                     title.clone(),
                     title.clone(),
+                    visibility,
                     user_id,
                     "username".to_string(),
                     DateTime::<Utc>::default(),
@@ -171,5 +499,52 @@ mod tests {
                 .len(),
             5
         );
+        assert_eq!(blog_service.get_public_posts(None).await.unwrap().len(), 4);
+        assert_eq!(
+            blog_service
+                .get_public_posts(Some(second_user))
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+
+        // the public posts of a soft deleted author are hidden, also when
+        // they are requested by author
+        let deleted_user = database_service
+            .insert_one::<User>(
+                doc! {
+                    "first_name": "first",
+                    "last_name": "last",
+                    "username": "deleted",
+                    "password_hash": "hash",
+                    "admin": false,
+                    "publisher": true,
+                    "version": 0_i64,
+                    DELETED_AT_FIELD: bson::DateTime::now(),
+                },
+                None,
+            )
+            .await
+            .unwrap();
+        blog_service
+            .publish_post(
+                "deleted author".to_string(),
+                "deleted author".to_string(),
+                PostVisibility::Public,
+                deleted_user,
+                "deleted".to_string(),
+                DateTime::<Utc>::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(blog_service.get_public_posts(None).await.unwrap().len(), 4);
+        assert!(
+            blog_service
+                .get_public_posts(Some(deleted_user))
+                .await
+                .unwrap()
+                .is_empty()
+        );
     }
 }
@@ -8,27 +8,46 @@
 //! - `transaction`: defines DatabaseTransactionTrait and implementations
 //! - `mongodb_service`: implementation of DatabaseServiceTrait that interacts with MongoDB cluster
 //! - `memory_service`: implementation of DatabaseServiceTrait for in memory database, used for testing
+//! - `app_service`: implementation of DatabaseServiceTrait that dispatches to the service selected at runtime
 
 use std::sync::Arc;
 
 use crate::{
     DatabaseResult,
     service::database::{
-        document::DecoratedDatabaseDocumentTrait, transaction::DatabaseTransactionTrait,
+        document::{DecoratedDatabaseDocumentTrait, ExpectedVersion, VersionedDocumentTrait},
+        transaction::DatabaseTransactionTrait,
     },
 };
 
+mod app_service;
 pub mod document;
 pub mod memory_service;
 mod mongodb_service;
 pub mod smart_document;
 pub mod transaction;
 
+pub use app_service::AppDatabaseService;
 use bson::{Document, oid::ObjectId};
+use futures::stream::BoxStream;
 pub use mongodb_service::MongoDBDatabaseService;
 use serde::{Serialize, de::DeserializeOwned};
 use tokio::sync::RwLock;
 
+/// Change of a document notified by `DatabaseServiceTrait::watch`
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChangeEvent<T> {
+    /// The document has been inserted
+    Inserted(T),
+    /// The document has been updated, it contains the document after the update
+    Updated(T),
+    /// The document with the given id has been removed from the collection
+    Deleted(ObjectId),
+}
+
+/// Stream of changes returned by `DatabaseServiceTrait::watch`
+pub type ChangeStream<T> = BoxStream<'static, DatabaseResult<ChangeEvent<T>>>;
+
 /// Trait to define the database service behavior
 ///
 /// The first two methods allows to open and close the connection with the database,
@@ -39,6 +58,9 @@ use tokio::sync::RwLock;
 /// DecoratedDatabaseDocumentTrait that is used to get collection name and return
 /// the specific document struct.
 ///
+/// When T supports soft deletion, the find and count operations exclude the
+/// soft deleted documents unless the query contains a condition on `deleted_at`.
+///
 /// If transaction is provided then the operations will be done inside its context.
 /// Transaction is provided as Arc<RwLock<>> so that it can be shared among threads
 /// safely.
@@ -51,17 +73,41 @@ pub trait DatabaseServiceTrait: Default {
 
     fn connect(&mut self) -> impl std::future::Future<Output = DatabaseResult<()>>;
 
-    fn shutdown(&mut self) -> impl std::future::Future<Output = DatabaseResult<()>>;
+    /// Closes the connections to the database, it does not need exclusive
+    /// access so that it can be called while other references to the service
+    /// still exist, their following operations fail
+    fn shutdown(&self) -> impl std::future::Future<Output = DatabaseResult<()>>;
 
     fn get_db_name(&self) -> &str;
 
+    /// Checks that the database is reachable with a round trip to the server,
+    /// it is used by the readiness probe
+    fn ping(&self) -> impl std::future::Future<Output = DatabaseResult<()>> + Send;
+
     fn new_transaction(
         &self,
     ) -> impl std::future::Future<Output = DatabaseResult<Self::Transaction>> + Send;
 
+    /// Creates a unique index on the field of the collection specified by T,
+    /// nothing is done if the index already exists.
+    ///
+    /// It fails with `DatabaseError::DuplicateKey` when the collection already
+    /// contains documents with the same value of the field. It is not done inside
+    /// a transaction because MongoDB does not create indexes of existing collections
+    /// in transactions.
+    fn create_unique_index<T>(
+        &self,
+        field: &str,
+    ) -> impl std::future::Future<Output = DatabaseResult<()>> + Send
+    where
+        T: DecoratedDatabaseDocumentTrait;
+
     /// Inserts the mongodb document in the collection specified by T
     /// and returns the id of the inserted document.
     ///
+    /// If the document has the same value of another one on a unique index
+    /// then `DatabaseError::DuplicateKey` is returned.
+    ///
     /// If transaction is provided then the operation will be done inside its context
     fn insert_one<T>(
         &self,
@@ -99,6 +145,19 @@ pub trait DatabaseServiceTrait: Default {
     where
         T: DecoratedDatabaseDocumentTrait;
 
+    /// Finds the documents that match the query sorted by the given sort document,
+    /// like `{"creation_date": -1}`, skipping the first `skip` documents and
+    /// returning at most `limit` documents
+    fn find_page<T>(
+        &self,
+        query: Document,
+        sort: Document,
+        skip: u64,
+        limit: u64,
+    ) -> impl std::future::Future<Output = DatabaseResult<Vec<T>>> + Send
+    where
+        T: DecoratedDatabaseDocumentTrait;
+
     /// Finds the first document that matches the query or None if it does not exist
     ///
     /// The returned type is P that is a document with a subset of fields of original
@@ -146,6 +205,27 @@ pub trait DatabaseServiceTrait: Default {
     where
         T: DecoratedDatabaseDocumentTrait;
 
+    /// Updates the document with the given id only if its version is one
+    /// of the expected ones, incrementing it. Returns the new version.
+    ///
+    /// The update document must not contain the `$inc` operator because it is
+    /// used to increment the version.
+    ///
+    /// If the document exists with a version that is not expected then
+    /// `DatabaseError::VersionConflict` is returned, if it does not exist
+    /// `DatabaseError::DocumentDoesNotExist` is returned.
+    ///
+    /// If transaction is provided then the operation will be done inside its context
+    fn update_one_versioned<T>(
+        &self,
+        id: ObjectId,
+        expected_version: ExpectedVersion,
+        update: Document,
+        transaction: Option<Arc<RwLock<Self::Transaction>>>,
+    ) -> impl std::future::Future<Output = DatabaseResult<i64>> + Send
+    where
+        T: DecoratedDatabaseDocumentTrait + VersionedDocumentTrait;
+
     /// Updates the documents that matches the query with the given
     /// document containing attributes to set.
     ///
@@ -181,6 +261,14 @@ pub trait DatabaseServiceTrait: Default {
     where
         T: DecoratedDatabaseDocumentTrait;
 
+    /// Returns the stream of the changes done on the collection specified by T
+    /// after the call.
+    ///
+    /// Changes done inside a transaction are notified once it is committed.
+    fn watch<T>(&self) -> impl std::future::Future<Output = DatabaseResult<ChangeStream<T>>> + Send
+    where
+        T: DecoratedDatabaseDocumentTrait + 'static;
+
     /// Apply the operations in the pipeline and returns the specified document.
     ///
     /// If transaction is provided then the operation will be done inside its context
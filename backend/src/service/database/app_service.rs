@@ -0,0 +1,529 @@
+use std::sync::Arc;
+
+use bson::{Document, oid::ObjectId};
+use serde::{Serialize, de::DeserializeOwned};
+use tokio::sync::RwLock;
+use tracing::Instrument;
+
+use crate::{
+    DatabaseResult, EnvironmentServiceTrait,
+    enums::DatabaseBackend,
+    metrics::metrics,
+    service::database::{
+        ChangeStream, DatabaseServiceTrait, MongoDBDatabaseService,
+        document::{DecoratedDatabaseDocumentTrait, ExpectedVersion, VersionedDocumentTrait},
+        memory_service::MemoryDatabaseService,
+        transaction::{
+            AppDatabaseTransaction, MemoryDatabaseTransaction, MongoDBDatabaseTransaction,
+        },
+    },
+};
+
+/// Database service used by the application, the actual implementation
+/// is selected at runtime with the `DATABASE_BACKEND` environment variable.
+///
+/// `DatabaseServiceTrait` cannot be used as trait object because its methods
+/// are generic, hence this enum dispatches each operation to the wrapped service.
+/// It allows to run the whole application, routers included, with the in memory
+/// database for demos and end to end tests.
+///
+/// Each document operation runs in its own span, exported to the tracing
+/// collector when configured, and its latency is recorded in the application metrics.
+#[derive(Debug)]
+pub enum AppDatabaseService {
+    MongoDB(MongoDBDatabaseService),
+    Memory(MemoryDatabaseService),
+}
+
+impl AppDatabaseService {
+    /// Creates the database service defined by the environment, it must be
+    /// connected before being used
+    pub fn new(environment_service: &dyn EnvironmentServiceTrait) -> Self {
+        match environment_service.get_database_backend() {
+            DatabaseBackend::MongoDB => Self::MongoDB(MongoDBDatabaseService::new(
+                environment_service.get_database_db_name().into(),
+                environment_service.get_database_connection_string().into(),
+            )),
+            DatabaseBackend::Memory => Self::Memory(MemoryDatabaseService::default()),
+        }
+    }
+
+    /// Runs the document operation in a span named after the operation
+    /// and the collection, recording its latency in the metrics
+    fn observe<R>(
+        &self,
+        operation: &'static str,
+        collection: &'static str,
+        future: impl Future<Output = DatabaseResult<R>>,
+    ) -> impl Future<Output = DatabaseResult<R>> {
+        let system = match self {
+            Self::MongoDB(_) => "mongodb",
+            Self::Memory(_) => "memory",
+        };
+        let span = tracing::info_span!(
+            "database",
+            otel.name = format!("{operation} {collection}"),
+            otel.kind = "client",
+            db.system.name = system,
+            db.operation.name = operation,
+            db.collection.name = collection,
+        );
+        metrics()
+            .observe_database_operation(operation, collection, future)
+            .instrument(span)
+    }
+}
+
+impl Default for AppDatabaseService {
+    fn default() -> Self {
+        Self::Memory(MemoryDatabaseService::default())
+    }
+}
+
+/// Unwraps the MongoDB transaction from the application one
+async fn mongodb_transaction(
+    transaction: Option<Arc<RwLock<AppDatabaseTransaction>>>,
+) -> DatabaseResult<Option<Arc<RwLock<MongoDBDatabaseTransaction>>>> {
+    match transaction {
+        Some(transaction) => Ok(Some(transaction.read().await.mongodb()?)),
+        None => Ok(None),
+    }
+}
+
+/// Unwraps the memory transaction from the application one
+async fn memory_transaction(
+    transaction: Option<Arc<RwLock<AppDatabaseTransaction>>>,
+) -> DatabaseResult<Option<Arc<RwLock<MemoryDatabaseTransaction>>>> {
+    match transaction {
+        Some(transaction) => Ok(Some(transaction.read().await.memory()?)),
+        None => Ok(None),
+    }
+}
+
+impl DatabaseServiceTrait for AppDatabaseService {
+    type Transaction = AppDatabaseTransaction;
+
+    async fn connect(&mut self) -> DatabaseResult<()> {
+        match self {
+            Self::MongoDB(service) => service.connect().await,
+            Self::Memory(service) => service.connect().await,
+        }
+    }
+
+    async fn shutdown(&self) -> DatabaseResult<()> {
+        match self {
+            Self::MongoDB(service) => service.shutdown().await,
+            Self::Memory(service) => service.shutdown().await,
+        }
+    }
+
+    async fn ping(&self) -> DatabaseResult<()> {
+        match self {
+            Self::MongoDB(service) => service.ping().await,
+            Self::Memory(service) => service.ping().await,
+        }
+    }
+
+    fn get_db_name(&self) -> &str {
+        match self {
+            Self::MongoDB(service) => service.get_db_name(),
+            Self::Memory(service) => service.get_db_name(),
+        }
+    }
+
+    async fn new_transaction(&self) -> DatabaseResult<Self::Transaction> {
+        Ok(match self {
+            Self::MongoDB(service) => AppDatabaseTransaction::MongoDB(Arc::new(RwLock::new(
+                service.new_transaction().await?,
+            ))),
+            Self::Memory(service) => AppDatabaseTransaction::Memory(Arc::new(RwLock::new(
+                service.new_transaction().await?,
+            ))),
+        })
+    }
+
+    async fn create_unique_index<T>(&self, field: &str) -> DatabaseResult<()>
+    where
+        T: DecoratedDatabaseDocumentTrait,
+    {
+        self.observe("create_unique_index", T::collection_name(), async move {
+            match self {
+                Self::MongoDB(service) => service.create_unique_index::<T>(field).await,
+                Self::Memory(service) => service.create_unique_index::<T>(field).await,
+            }
+        })
+        .await
+    }
+
+    async fn insert_one<T>(
+        &self,
+        document: Document,
+        transaction: Option<Arc<RwLock<Self::Transaction>>>,
+    ) -> DatabaseResult<ObjectId>
+    where
+        T: DecoratedDatabaseDocumentTrait,
+    {
+        self.observe("insert_one", T::collection_name(), async move {
+            match self {
+                Self::MongoDB(service) => {
+                    service
+                        .insert_one::<T>(document, mongodb_transaction(transaction).await?)
+                        .await
+                }
+                Self::Memory(service) => {
+                    service
+                        .insert_one::<T>(document, memory_transaction(transaction).await?)
+                        .await
+                }
+            }
+        })
+        .await
+    }
+
+    async fn insert_many<T>(
+        &self,
+        documents: Vec<Document>,
+        transaction: Option<Arc<RwLock<Self::Transaction>>>,
+    ) -> DatabaseResult<Vec<ObjectId>>
+    where
+        T: DecoratedDatabaseDocumentTrait,
+    {
+        self.observe("insert_many", T::collection_name(), async move {
+            match self {
+                Self::MongoDB(service) => {
+                    service
+                        .insert_many::<T>(documents, mongodb_transaction(transaction).await?)
+                        .await
+                }
+                Self::Memory(service) => {
+                    service
+                        .insert_many::<T>(documents, memory_transaction(transaction).await?)
+                        .await
+                }
+            }
+        })
+        .await
+    }
+
+    async fn find_one<T>(&self, query: Document) -> DatabaseResult<Option<T>>
+    where
+        T: DecoratedDatabaseDocumentTrait,
+    {
+        self.observe("find_one", T::collection_name(), async move {
+            match self {
+                Self::MongoDB(service) => service.find_one::<T>(query).await,
+                Self::Memory(service) => service.find_one::<T>(query).await,
+            }
+        })
+        .await
+    }
+
+    async fn find_many<T>(&self, query: Document) -> DatabaseResult<Vec<T>>
+    where
+        T: DecoratedDatabaseDocumentTrait,
+    {
+        self.observe("find_many", T::collection_name(), async move {
+            match self {
+                Self::MongoDB(service) => service.find_many::<T>(query).await,
+                Self::Memory(service) => service.find_many::<T>(query).await,
+            }
+        })
+        .await
+    }
+
+    async fn find_page<T>(
+        &self,
+        query: Document,
+        sort: Document,
+        skip: u64,
+        limit: u64,
+    ) -> DatabaseResult<Vec<T>>
+    where
+        T: DecoratedDatabaseDocumentTrait,
+    {
+        self.observe("find_page", T::collection_name(), async move {
+            match self {
+                Self::MongoDB(service) => service.find_page::<T>(query, sort, skip, limit).await,
+                Self::Memory(service) => service.find_page::<T>(query, sort, skip, limit).await,
+            }
+        })
+        .await
+    }
+
+    async fn find_one_projection<T, P>(
+        &self,
+        query: Document,
+        projection: Document,
+    ) -> DatabaseResult<Option<P>>
+    where
+        T: DecoratedDatabaseDocumentTrait,
+        P: Send + Sync + Serialize + DeserializeOwned,
+    {
+        self.observe("find_one_projection", T::collection_name(), async move {
+            match self {
+                Self::MongoDB(service) => {
+                    service.find_one_projection::<T, P>(query, projection).await
+                }
+                Self::Memory(service) => {
+                    service.find_one_projection::<T, P>(query, projection).await
+                }
+            }
+        })
+        .await
+    }
+
+    async fn find_many_projection<T, P>(
+        &self,
+        query: Document,
+        projection: Document,
+    ) -> DatabaseResult<Vec<P>>
+    where
+        T: DecoratedDatabaseDocumentTrait,
+        P: Send + Sync + Serialize + DeserializeOwned,
+    {
+        self.observe("find_many_projection", T::collection_name(), async move {
+            match self {
+                Self::MongoDB(service) => {
+                    service
+                        .find_many_projection::<T, P>(query, projection)
+                        .await
+                }
+                Self::Memory(service) => {
+                    service
+                        .find_many_projection::<T, P>(query, projection)
+                        .await
+                }
+            }
+        })
+        .await
+    }
+
+    async fn count_documents<T>(&self, query: Document) -> DatabaseResult<u64>
+    where
+        T: DecoratedDatabaseDocumentTrait,
+    {
+        self.observe("count_documents", T::collection_name(), async move {
+            match self {
+                Self::MongoDB(service) => service.count_documents::<T>(query).await,
+                Self::Memory(service) => service.count_documents::<T>(query).await,
+            }
+        })
+        .await
+    }
+
+    async fn update_one<T>(
+        &self,
+        query: Document,
+        update: Document,
+        transaction: Option<Arc<RwLock<Self::Transaction>>>,
+    ) -> DatabaseResult<()>
+    where
+        T: DecoratedDatabaseDocumentTrait,
+    {
+        self.observe("update_one", T::collection_name(), async move {
+            match self {
+                Self::MongoDB(service) => {
+                    service
+                        .update_one::<T>(query, update, mongodb_transaction(transaction).await?)
+                        .await
+                }
+                Self::Memory(service) => {
+                    service
+                        .update_one::<T>(query, update, memory_transaction(transaction).await?)
+                        .await
+                }
+            }
+        })
+        .await
+    }
+
+    async fn update_one_versioned<T>(
+        &self,
+        id: ObjectId,
+        expected_version: ExpectedVersion,
+        update: Document,
+        transaction: Option<Arc<RwLock<Self::Transaction>>>,
+    ) -> DatabaseResult<i64>
+    where
+        T: DecoratedDatabaseDocumentTrait + VersionedDocumentTrait,
+    {
+        self.observe("update_one_versioned", T::collection_name(), async move {
+            match self {
+                Self::MongoDB(service) => {
+                    service
+                        .update_one_versioned::<T>(
+                            id,
+                            expected_version,
+                            update,
+                            mongodb_transaction(transaction).await?,
+                        )
+                        .await
+                }
+                Self::Memory(service) => {
+                    service
+                        .update_one_versioned::<T>(
+                            id,
+                            expected_version,
+                            update,
+                            memory_transaction(transaction).await?,
+                        )
+                        .await
+                }
+            }
+        })
+        .await
+    }
+
+    async fn update_many<T>(
+        &self,
+        query: Document,
+        update: Document,
+        transaction: Option<Arc<RwLock<Self::Transaction>>>,
+    ) -> DatabaseResult<()>
+    where
+        T: DecoratedDatabaseDocumentTrait,
+    {
+        self.observe("update_many", T::collection_name(), async move {
+            match self {
+                Self::MongoDB(service) => {
+                    service
+                        .update_many::<T>(query, update, mongodb_transaction(transaction).await?)
+                        .await
+                }
+                Self::Memory(service) => {
+                    service
+                        .update_many::<T>(query, update, memory_transaction(transaction).await?)
+                        .await
+                }
+            }
+        })
+        .await
+    }
+
+    async fn delete_one<T>(
+        &self,
+        query: Document,
+        transaction: Option<Arc<RwLock<Self::Transaction>>>,
+    ) -> DatabaseResult<()>
+    where
+        T: DecoratedDatabaseDocumentTrait,
+    {
+        self.observe("delete_one", T::collection_name(), async move {
+            match self {
+                Self::MongoDB(service) => {
+                    service
+                        .delete_one::<T>(query, mongodb_transaction(transaction).await?)
+                        .await
+                }
+                Self::Memory(service) => {
+                    service
+                        .delete_one::<T>(query, memory_transaction(transaction).await?)
+                        .await
+                }
+            }
+        })
+        .await
+    }
+
+    async fn delete_many<T>(
+        &self,
+        query: Document,
+        transaction: Option<Arc<RwLock<Self::Transaction>>>,
+    ) -> DatabaseResult<()>
+    where
+        T: DecoratedDatabaseDocumentTrait,
+    {
+        self.observe("delete_many", T::collection_name(), async move {
+            match self {
+                Self::MongoDB(service) => {
+                    service
+                        .delete_many::<T>(query, mongodb_transaction(transaction).await?)
+                        .await
+                }
+                Self::Memory(service) => {
+                    service
+                        .delete_many::<T>(query, memory_transaction(transaction).await?)
+                        .await
+                }
+            }
+        })
+        .await
+    }
+
+    async fn watch<T>(&self) -> DatabaseResult<ChangeStream<T>>
+    where
+        T: DecoratedDatabaseDocumentTrait + 'static,
+    {
+        self.observe("watch", T::collection_name(), async move {
+            match self {
+                Self::MongoDB(service) => service.watch::<T>().await,
+                Self::Memory(service) => service.watch::<T>().await,
+            }
+        })
+        .await
+    }
+
+    async fn aggreagte<T>(&self, pipeline: Vec<Document>) -> DatabaseResult<Vec<Document>>
+    where
+        T: DecoratedDatabaseDocumentTrait,
+    {
+        self.observe("aggreagte", T::collection_name(), async move {
+            match self {
+                Self::MongoDB(service) => service.aggreagte::<T>(pipeline).await,
+                Self::Memory(service) => service.aggreagte::<T>(pipeline).await,
+            }
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use bson::doc;
+    use tokio::sync::RwLock;
+
+    use crate::{
+        model::User,
+        service::database::{
+            AppDatabaseService, DatabaseServiceTrait, transaction::DatabaseTransactionTrait,
+        },
+    };
+
+    #[tokio::test]
+    async fn test_memory_dispatch_with_transaction() {
+        let database_service = AppDatabaseService::default();
+        let transaction = Arc::new(RwLock::new(
+            database_service.new_transaction().await.unwrap(),
+        ));
+
+        let user_id = database_service
+            .insert_one::<User>(
+                doc! {
+                    "first_name": "Marcel",
+                    "last_name": "Proust",
+                    "username": "marcel",
+                    "password_hash": "hash",
+                    "admin": false,
+                    "publisher": true,
+                    "version": 0_i64,
+                },
+                Some(transaction.clone()),
+            )
+            .await
+            .unwrap();
+        transaction
+            .write()
+            .await
+            .commit_transaction()
+            .await
+            .unwrap();
+
+        let user = database_service
+            .find_one::<User>(doc! { "_id": user_id })
+            .await
+            .unwrap();
+        assert!(user.is_some());
+    }
+}
@@ -1,6 +1,11 @@
-use bson::oid::ObjectId;
+use std::fmt::Display;
+
+use bson::{Bson, Document, doc, oid::ObjectId};
 use serde::{Serialize, de::DeserializeOwned};
 
+/// Name of the field that marks a document as soft deleted
+pub const DELETED_AT_FIELD: &str = "deleted_at";
+
 /// Extension of DatabaseDocumentTrait useful to avoid adding all the additional
 /// traits all over the code base
 pub trait DecoratedDatabaseDocumentTrait:
@@ -12,6 +17,85 @@ pub trait DecoratedDatabaseDocumentTrait:
 pub trait DatabaseDocumentTrait: Sized {
     fn get_id(&self) -> &ObjectId;
     fn collection_name() -> &'static str;
+
+    /// Whether the documents of the collection are soft deleted
+    /// by setting the `deleted_at` field instead of being removed
+    fn is_soft_deletable() -> bool {
+        false
+    }
+}
+
+/// Trait implemented by the documents declared as `versioned` in the
+/// `database_document` macro.
+///
+/// The version is incremented at each update and it is used to detect
+/// concurrent modifications of the same document.
+pub trait VersionedDocumentTrait: DatabaseDocumentTrait {
+    fn get_version(&self) -> i64;
+}
+
+/// Versions of the document accepted by `DatabaseServiceTrait::update_one_versioned`,
+/// they come from the `If-Match` header of the request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExpectedVersion {
+    /// Any version is accepted, the document only has to exist
+    Any,
+    /// The current version must be one of the listed ones
+    OneOf(Vec<i64>),
+}
+
+impl ExpectedVersion {
+    /// Returns true if the current version of the document is accepted
+    pub fn matches(&self, version: i64) -> bool {
+        match self {
+            Self::Any => true,
+            Self::OneOf(versions) => versions.contains(&version),
+        }
+    }
+
+    /// Returns the condition on the `version` field of the query, if any
+    pub fn to_condition(&self) -> Option<Document> {
+        match self {
+            Self::Any => None,
+            Self::OneOf(versions) => Some(doc! { "$in": versions }),
+        }
+    }
+}
+
+impl From<i64> for ExpectedVersion {
+    fn from(version: i64) -> Self {
+        Self::OneOf(vec![version])
+    }
+}
+
+impl Display for ExpectedVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Any => write!(f, "*"),
+            Self::OneOf(versions) => write!(
+                f,
+                "{}",
+                versions
+                    .iter()
+                    .map(i64::to_string)
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ),
+        }
+    }
+}
+
+/// Adds to the query the condition that excludes soft deleted documents.
+///
+/// The query is not modified when the document does not support soft deletion
+/// or when it already contains a condition on the `deleted_at` field, which is
+/// the way to explicitly query the deleted documents.
+pub fn exclude_soft_deleted<T: DatabaseDocumentTrait>(mut query: Document) -> Document {
+    if T::is_soft_deletable() && !query.contains_key(DELETED_AT_FIELD) {
+        // null matches both missing and null fields
+        query.insert(DELETED_AT_FIELD, Bson::Null);
+    }
+    query
 }
 
 /// The macro generates struct that implements DatabaseDocumentTrait trait
@@ -22,11 +106,20 @@ pub trait DatabaseDocumentTrait: Sized {
 /// The macro creates a builder that on the build method will insert the
 /// document on the database
 ///
+/// When the keyword `versioned` follows the collection name, the document
+/// has an additional `version` field that starts from zero and it is incremented
+/// by `DatabaseServiceTrait::update_one_versioned`.
+///
+/// When the keyword `soft_delete` follows the collection name (after `versioned`
+/// if both are present), the document has an additional `deleted_at` field and the
+/// find and count operations of `DatabaseServiceTrait` exclude the documents
+/// where it is set.
+///
 /// We specify the full path of entities because macro expansions happen
 /// in the caller's module scope and therefore they need to be imported there
 #[macro_export]
 macro_rules! database_document {
-    ( $(#[doc = $doc:expr])* $struct_name:ident, $collection_name:expr, $(
+    ( @document [$($version_field:ident)?] [$($deleted_field:ident)?] [$soft_deletable:literal] $(#[doc = $doc:expr])* $struct_name:ident, $collection_name:expr, $(
         $(#[$field_attr:meta])*
         $field_name:ident : $field_type:ty
     ),* $(,)? ) => {
@@ -36,6 +129,14 @@ macro_rules! database_document {
         pub struct $struct_name {
             #[serde(rename = "_id")]
             id: ::bson::oid::ObjectId,
+            $(
+                #[serde(default)]
+                $version_field: i64,
+            )?
+            $(
+                #[serde(default)]
+                $deleted_field: Option<::bson::DateTime>,
+            )?
             $(
                 $(#[$field_attr])*
                 $field_name: $field_type,
@@ -43,6 +144,20 @@ macro_rules! database_document {
         }
 
         impl $struct_name {
+            $(
+                #[allow(dead_code)]
+                pub fn $version_field(&self) -> i64 {
+                    self.$version_field
+                }
+            )?
+
+            $(
+                #[allow(dead_code)]
+                pub fn $deleted_field(&self) -> Option<::chrono::DateTime<::chrono::Utc>> {
+                    self.$deleted_field.map(|value| value.to_chrono())
+                }
+            )?
+
             // implementation of getter and setters
             ::paste::paste!{
                 $(
@@ -72,9 +187,21 @@ macro_rules! database_document {
             fn get_id(&self) -> &::bson::oid::ObjectId {
                 &self.id
             }
+
+            fn is_soft_deletable() -> bool {
+                $soft_deletable
+            }
         }
         impl $crate::service::database::document::DecoratedDatabaseDocumentTrait for $struct_name {}
 
+        $(
+            impl $crate::service::database::document::VersionedDocumentTrait for $struct_name {
+                fn get_version(&self) -> i64 {
+                    self.$version_field
+                }
+            }
+        )?
+
         // creation of builder
         ::paste::paste! {
             #[derive(Default)]
@@ -112,6 +239,9 @@ macro_rules! database_document {
                     transaction: Option<std::sync::Arc<tokio::sync::RwLock<T::Transaction>>>,
                 ) -> $crate::error::DatabaseResult<$struct_name> {
                     let document = mongodb::bson::doc! {
+                        $(
+                            stringify!($version_field): 0_i64,
+                        )?
                         $(
                             stringify!($field_name): self.$field_name.clone()
                                 .ok_or_else(|| $crate::error::DatabaseError::DocumentNotValid(
@@ -126,6 +256,12 @@ macro_rules! database_document {
                     ).await?;
                     Ok($struct_name {
                         id: doc_id,
+                        $(
+                            $version_field: 0,
+                        )?
+                        $(
+                            $deleted_field: None,
+                        )?
                         $(
                             $field_name: self.$field_name.unwrap(),
                         )*
@@ -135,6 +271,26 @@ macro_rules! database_document {
             }
         }
     };
+    ( $(#[doc = $doc:expr])* $struct_name:ident, $collection_name:expr, versioned, soft_delete, $($fields:tt)* ) => {
+        $crate::database_document!(
+            @document [version] [deleted_at] [true] $(#[doc = $doc])* $struct_name, $collection_name, $($fields)*
+        );
+    };
+    ( $(#[doc = $doc:expr])* $struct_name:ident, $collection_name:expr, versioned, $($fields:tt)* ) => {
+        $crate::database_document!(
+            @document [version] [] [false] $(#[doc = $doc])* $struct_name, $collection_name, $($fields)*
+        );
+    };
+    ( $(#[doc = $doc:expr])* $struct_name:ident, $collection_name:expr, soft_delete, $($fields:tt)* ) => {
+        $crate::database_document!(
+            @document [] [deleted_at] [true] $(#[doc = $doc])* $struct_name, $collection_name, $($fields)*
+        );
+    };
+    ( $(#[doc = $doc:expr])* $struct_name:ident, $collection_name:expr, $($fields:tt)* ) => {
+        $crate::database_document!(
+            @document [] [] [false] $(#[doc = $doc])* $struct_name, $collection_name, $($fields)*
+        );
+    };
 }
 
 /// The macro generates struct used as an object inside the database document
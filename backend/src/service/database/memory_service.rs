@@ -1,26 +1,108 @@
+use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::sync::Arc;
 
 use bson::oid::ObjectId;
 use bson::{Bson, Document, from_document};
-use tokio::sync::RwLock;
+use futures::StreamExt;
+use tokio::sync::{RwLock, broadcast};
 
 use crate::error::DatabaseError;
-use crate::service::database::DatabaseServiceTrait;
+use crate::service::database::{ChangeEvent, ChangeStream, DatabaseServiceTrait};
 
 use crate::service::database::transaction::MemoryDatabaseTransaction;
-use crate::{DatabaseResult, service::database::document::DecoratedDatabaseDocumentTrait};
+use crate::{
+    DatabaseResult,
+    service::database::document::{
+        DecoratedDatabaseDocumentTrait, ExpectedVersion, VersionedDocumentTrait,
+        exclude_soft_deleted,
+    },
+};
+
+/// Collections of the memory database, shared with its transactions
+pub type MemoryCollections = Arc<RwLock<HashMap<String, Vec<Document>>>>;
+
+/// Capacity of the channel used to notify the changes to the watchers
+const CHANGES_CHANNEL_CAPACITY: usize = 256;
 
 /// Memory database service stores data in memory in Vec objects
 ///
-/// It is used only for testing purposes without interacting with
-/// an actual database
-#[derive(Default, Debug)]
+/// It is used for testing purposes and demos without interacting with
+/// an actual database.
+///
+/// Queries support only the operators `$in`, `$nin`, `$exists`, `$ne`, `$lt`,
+/// `$lte`, `$gt` and `$gte`, the other operators and the aggregations return an error.
+///
+/// Changes are notified to the watchers with an in-process broadcast channel
+/// that carries the collection name and the changed document.
+///
+/// Transactions keep an undo log of the changed collections that is restored
+/// when they are aborted, but they are not isolated, see `MemoryDatabaseTransaction`.
+///
+/// Unique indexes are checked by the insertions, the updates ignore them.
+#[derive(Debug)]
 pub struct MemoryDatabaseService {
-    collections: RwLock<HashMap<String, Vec<Document>>>,
+    collections: MemoryCollections,
+    changes: broadcast::Sender<(String, ChangeEvent<Document>)>,
+    /// Fields of the unique indexes of each collection
+    unique_indexes: RwLock<HashMap<String, Vec<String>>>,
+}
+
+impl Default for MemoryDatabaseService {
+    fn default() -> Self {
+        let (changes, _) = broadcast::channel(CHANGES_CHANNEL_CAPACITY);
+        Self {
+            collections: MemoryCollections::default(),
+            changes,
+            unique_indexes: RwLock::default(),
+        }
+    }
 }
 
 impl MemoryDatabaseService {
+    /// Notify the change to the watchers, if any
+    fn notify(&self, collection: &str, change: ChangeEvent<Document>) {
+        // sending fails only when there are no watchers
+        let _ = self.changes.send((collection.to_string(), change));
+    }
+
+    /// Saves the collection in the undo log of the transaction, if any,
+    /// before it is changed for the first time inside the transaction
+    async fn save_undo(
+        &self,
+        collection: &str,
+        transaction: Option<&Arc<RwLock<MemoryDatabaseTransaction>>>,
+    ) {
+        if let Some(transaction) = transaction {
+            let mut transaction = transaction.write().await;
+            if !transaction.is_saved(collection) {
+                let documents = self
+                    .collections
+                    .read()
+                    .await
+                    .get(collection)
+                    .cloned()
+                    .unwrap_or_default();
+                transaction.save(collection, documents);
+            }
+        }
+    }
+
+    /// Returns the first unique field whose value in the document is already
+    /// used by one of the documents, a missing field is a null value
+    fn duplicated_field<'a>(
+        documents: &[Document],
+        document: &Document,
+        unique_fields: &'a [String],
+    ) -> Option<&'a String> {
+        unique_fields.iter().find(|field| {
+            let value = document.get(field.as_str()).unwrap_or(&Bson::Null);
+            documents
+                .iter()
+                .any(|other| other.get(field.as_str()).unwrap_or(&Bson::Null) == value)
+        })
+    }
+
     /// Utility function that matches a query with a document
     ///
     /// For each item in the query, we check if it is present
@@ -39,22 +121,11 @@ impl MemoryDatabaseService {
             match value {
                 Bson::Document(operator_document) => {
                     // we expect `operator_document` to be like { field_name: {"$in": [value1, value2, value3] }}
-                    if let Some(Bson::Array(query_array)) = operator_document.get("$in") {
-                        // if the key is not present then we return false
-                        // otherwise we check for the value according to the operator
-                        // in this case, given the array of values we check if the document
-                        // element is contained
-                        if let Some(field_content) = document.get(key) {
-                            match_result = !query_array
-                                .iter()
-                                .any(|candidate| candidate == field_content);
-                        } else {
-                            match_result = false;
-                        }
-                    } else {
-                        // add implementation for other operators
-                        todo!()
-                    }
+                    match_result = Self::match_operators(document, key, operator_document);
+                }
+                Bson::Null => {
+                    // null matches both null and missing fields
+                    match_result = matches!(document.get(key), None | Some(Bson::Null));
                 }
                 query_content => {
                     if let Some(field_document) = document.get(key) {
@@ -72,6 +143,93 @@ impl MemoryDatabaseService {
         match_result
     }
 
+    /// Verifies that the query uses only the operators supported by `match_operators`
+    fn check_operators(query: &Document) -> DatabaseResult<()> {
+        for value in query.values() {
+            if let Bson::Document(operator_document) = value {
+                for (operator, operand) in operator_document.iter() {
+                    let supported = match operator.as_str() {
+                        "$in" | "$nin" => matches!(operand, Bson::Array(_)),
+                        "$exists" => matches!(operand, Bson::Boolean(_)),
+                        "$ne" | "$lt" | "$lte" | "$gt" | "$gte" => true,
+                        _ => false,
+                    };
+                    if !supported {
+                        return Err(DatabaseError::DatabaseOperationError(format!(
+                            "Operator {operator} with operand {operand} is not supported by the memory database"
+                        )));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Utility function that matches the field `key` of the document with
+    /// all the operators of the operator document, like `{"$gte": 1, "$lt": 3}`
+    fn match_operators(document: &Document, key: &str, operator_document: &Document) -> bool {
+        let field_content = document.get(key);
+        operator_document
+            .iter()
+            .all(|(operator, operand)| match (operator.as_str(), operand) {
+                // given the array of values we check if the document element is contained,
+                // if the key is not present then we return false
+                ("$in", Bson::Array(query_array)) => {
+                    field_content.is_some_and(|field_content| query_array.contains(field_content))
+                }
+                // the opposite of `$in`, a missing key matches like in MongoDB
+                ("$nin", Bson::Array(query_array)) => {
+                    field_content.is_none_or(|field_content| !query_array.contains(field_content))
+                }
+                // checks only the presence of the key
+                ("$exists", Bson::Boolean(should_exist)) => {
+                    document.contains_key(key) == *should_exist
+                }
+                // a missing field is considered as null like MongoDB does
+                ("$ne", not_expected) => field_content.unwrap_or(&Bson::Null) != not_expected,
+                ("$lt", bound) => {
+                    Self::compare_field(field_content, bound, |ordering| ordering == Ordering::Less)
+                }
+                ("$lte", bound) => Self::compare_field(field_content, bound, |ordering| {
+                    ordering != Ordering::Greater
+                }),
+                ("$gt", bound) => Self::compare_field(field_content, bound, |ordering| {
+                    ordering == Ordering::Greater
+                }),
+                ("$gte", bound) => {
+                    Self::compare_field(field_content, bound, |ordering| ordering != Ordering::Less)
+                }
+                // the other operators are rejected by `check_operators`
+                _ => false,
+            })
+    }
+
+    /// Compares the field with the bound and applies the condition to the result,
+    /// missing fields or values that are not comparable do not match
+    fn compare_field(
+        field_content: Option<&Bson>,
+        bound: &Bson,
+        condition: impl Fn(Ordering) -> bool,
+    ) -> bool {
+        field_content
+            .and_then(|field_content| Self::compare(field_content, bound))
+            .is_some_and(condition)
+    }
+
+    /// Compares two values of the same type, it returns None when
+    /// the types are different or they are not comparable
+    fn compare(first: &Bson, second: &Bson) -> Option<Ordering> {
+        match (first, second) {
+            (Bson::DateTime(first), Bson::DateTime(second)) => first.partial_cmp(second),
+            (Bson::Int32(first), Bson::Int32(second)) => first.partial_cmp(second),
+            (Bson::Int64(first), Bson::Int64(second)) => first.partial_cmp(second),
+            (Bson::Double(first), Bson::Double(second)) => first.partial_cmp(second),
+            (Bson::String(first), Bson::String(second)) => first.partial_cmp(second),
+            (Bson::ObjectId(first), Bson::ObjectId(second)) => first.partial_cmp(second),
+            _ => None,
+        }
+    }
+
     fn apply_projection(document: &Document, projection: &Document) -> Document {
         if projection.is_empty() {
             document.clone()
@@ -96,7 +254,7 @@ impl DatabaseServiceTrait for MemoryDatabaseService {
         Ok(())
     }
 
-    async fn shutdown(&mut self) -> DatabaseResult<()> {
+    async fn shutdown(&self) -> DatabaseResult<()> {
         Ok(())
     }
 
@@ -104,14 +262,46 @@ impl DatabaseServiceTrait for MemoryDatabaseService {
         "database"
     }
 
+    async fn ping(&self) -> DatabaseResult<()> {
+        Ok(())
+    }
+
     async fn new_transaction(&self) -> DatabaseResult<Self::Transaction> {
-        Ok(MemoryDatabaseTransaction::new())
+        Ok(MemoryDatabaseTransaction::new(self.collections.clone()))
+    }
+
+    async fn create_unique_index<T>(&self, field: &str) -> DatabaseResult<()>
+    where
+        T: DecoratedDatabaseDocumentTrait,
+    {
+        let collection = T::collection_name();
+        let mut unique_indexes = self.unique_indexes.write().await;
+        let unique_fields = unique_indexes.entry(collection.into()).or_default();
+        if unique_fields
+            .iter()
+            .any(|unique_field| unique_field == field)
+        {
+            return Ok(());
+        }
+
+        let guard = self.collections.read().await;
+        let documents = guard.get(collection).map(Vec::as_slice).unwrap_or_default();
+        let field = vec![field.to_string()];
+        for (index, document) in documents.iter().enumerate() {
+            if let Some(field) = Self::duplicated_field(&documents[..index], document, &field) {
+                return Err(DatabaseError::DuplicateKey(format!(
+                    "{collection} has documents with the same {field}"
+                )));
+            }
+        }
+        unique_fields.extend(field);
+        Ok(())
     }
 
     async fn insert_one<T>(
         &self,
         document: bson::Document,
-        _transaction: Option<Arc<RwLock<Self::Transaction>>>,
+        transaction: Option<Arc<RwLock<Self::Transaction>>>,
     ) -> DatabaseResult<ObjectId>
     where
         T: DecoratedDatabaseDocumentTrait,
@@ -121,20 +311,31 @@ impl DatabaseServiceTrait for MemoryDatabaseService {
         document.insert("_id", object_id);
 
         let collection = T::collection_name();
+        self.save_undo(collection, transaction.as_ref()).await;
 
-        self.collections
-            .try_write()
-            .map_err(|err| DatabaseError::TransactionError(err.to_string()))?
-            .entry(collection.into())
-            .or_default()
-            .push(document);
+        let unique_indexes = self.unique_indexes.read().await;
+        let unique_fields = unique_indexes
+            .get(collection)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        // the check and the insertion are done under the same lock, hence
+        // concurrent insertions of the same value cannot both succeed
+        let mut guard = self.collections.write().await;
+        let documents = guard.entry(collection.into()).or_default();
+        if let Some(field) = Self::duplicated_field(documents, &document, unique_fields) {
+            return Err(DatabaseError::DuplicateKey(format!(
+                "{collection} has already a document with the same {field}"
+            )));
+        }
+        documents.push(document.clone());
+        self.notify(collection, ChangeEvent::Inserted(document));
         Ok(object_id)
     }
 
     async fn insert_many<T>(
         &self,
         documents: Vec<bson::Document>,
-        _transaction: Option<Arc<RwLock<Self::Transaction>>>,
+        transaction: Option<Arc<RwLock<Self::Transaction>>>,
     ) -> DatabaseResult<Vec<ObjectId>>
     where
         T: DecoratedDatabaseDocumentTrait,
@@ -142,7 +343,7 @@ impl DatabaseServiceTrait for MemoryDatabaseService {
         let mut inserted_ids = vec![];
 
         for document in documents {
-            inserted_ids.push(self.insert_one::<T>(document, None).await?);
+            inserted_ids.push(self.insert_one::<T>(document, transaction.clone()).await?);
         }
         Ok(inserted_ids)
     }
@@ -151,6 +352,8 @@ impl DatabaseServiceTrait for MemoryDatabaseService {
     where
         T: DecoratedDatabaseDocumentTrait,
     {
+        let query = exclude_soft_deleted::<T>(query);
+        Self::check_operators(&query)?;
         let collection = T::collection_name();
         if let Some(documents) = self.collections.read().await.get(collection) {
             for document in documents.iter() {
@@ -170,6 +373,8 @@ impl DatabaseServiceTrait for MemoryDatabaseService {
     where
         T: DecoratedDatabaseDocumentTrait,
     {
+        let query = exclude_soft_deleted::<T>(query);
+        Self::check_operators(&query)?;
         let mut documents_to_return = vec![];
         let collection = T::collection_name();
         if let Some(documents) = self.collections.read().await.get(collection) {
@@ -185,6 +390,66 @@ impl DatabaseServiceTrait for MemoryDatabaseService {
         Ok(documents_to_return)
     }
 
+    async fn find_page<T>(
+        &self,
+        query: bson::Document,
+        sort: bson::Document,
+        skip: u64,
+        limit: u64,
+    ) -> DatabaseResult<Vec<T>>
+    where
+        T: DecoratedDatabaseDocumentTrait,
+    {
+        let query = exclude_soft_deleted::<T>(query);
+        Self::check_operators(&query)?;
+        let collection = T::collection_name();
+        let mut matching_documents = self
+            .collections
+            .read()
+            .await
+            .get(collection)
+            .map(|documents| {
+                documents
+                    .iter()
+                    .filter(|document| Self::match_document(document, &query))
+                    .cloned()
+                    .collect::<Vec<Document>>()
+            })
+            .unwrap_or_default();
+
+        // sort keys are applied in order, missing fields come first
+        matching_documents.sort_by(|first, second| {
+            for (key, direction) in sort.iter() {
+                let ordering = match (first.get(key), second.get(key)) {
+                    (Some(first), Some(second)) => {
+                        Self::compare(first, second).unwrap_or(Ordering::Equal)
+                    }
+                    (None, Some(_)) => Ordering::Less,
+                    (Some(_), None) => Ordering::Greater,
+                    (None, None) => Ordering::Equal,
+                };
+                let ordering = if matches!(direction, Bson::Int32(-1) | Bson::Int64(-1)) {
+                    ordering.reverse()
+                } else {
+                    ordering
+                };
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            Ordering::Equal
+        });
+
+        matching_documents
+            .into_iter()
+            .skip(usize::try_from(skip).unwrap_or(usize::MAX))
+            .take(usize::try_from(limit).unwrap_or(usize::MAX))
+            .map(|document| {
+                from_document(document).map_err(|e| DatabaseError::DocumentNotValid(e.to_string()))
+            })
+            .collect()
+    }
+
     async fn find_one_projection<T, P>(
         &self,
         query: bson::Document,
@@ -194,6 +459,8 @@ impl DatabaseServiceTrait for MemoryDatabaseService {
         T: DecoratedDatabaseDocumentTrait,
         P: Send + Sync + serde::Serialize + serde::de::DeserializeOwned,
     {
+        let query = exclude_soft_deleted::<T>(query);
+        Self::check_operators(&query)?;
         let collection = T::collection_name();
         if let Some(documents) = self.collections.read().await.get(collection) {
             for document in documents.iter() {
@@ -217,6 +484,8 @@ impl DatabaseServiceTrait for MemoryDatabaseService {
         T: DecoratedDatabaseDocumentTrait,
         P: Send + Sync + serde::Serialize + serde::de::DeserializeOwned,
     {
+        let query = exclude_soft_deleted::<T>(query);
+        Self::check_operators(&query)?;
         let mut documents_to_return = vec![];
         let collection = T::collection_name();
         if let Some(documents) = self.collections.read().await.get(collection) {
@@ -236,6 +505,8 @@ impl DatabaseServiceTrait for MemoryDatabaseService {
     where
         T: DecoratedDatabaseDocumentTrait,
     {
+        let query = exclude_soft_deleted::<T>(query);
+        Self::check_operators(&query)?;
         let mut count = 0;
         let collection = T::collection_name();
         if let Some(documents) = self.collections.read().await.get(collection) {
@@ -252,17 +523,16 @@ impl DatabaseServiceTrait for MemoryDatabaseService {
         &self,
         query: bson::Document,
         update: bson::Document,
-        _transaction: Option<Arc<RwLock<Self::Transaction>>>,
+        transaction: Option<Arc<RwLock<Self::Transaction>>>,
     ) -> DatabaseResult<()>
     where
         T: DecoratedDatabaseDocumentTrait,
     {
         let collection = T::collection_name();
+        Self::check_operators(&query)?;
+        self.save_undo(collection, transaction.as_ref()).await;
 
-        let mut guard = self
-            .collections
-            .try_write()
-            .map_err(|err| DatabaseError::TransactionError(err.to_string()))?;
+        let mut guard = self.collections.write().await;
 
         if let Some(documents) = guard.get_mut(collection) {
             for document in documents.iter_mut() {
@@ -272,6 +542,7 @@ impl DatabaseServiceTrait for MemoryDatabaseService {
                             document.insert(key.clone(), value.clone());
                         }
                     }
+                    self.notify(collection, ChangeEvent::Updated(document.clone()));
                     break;
                 }
             }
@@ -281,21 +552,63 @@ impl DatabaseServiceTrait for MemoryDatabaseService {
         }
     }
 
+    async fn update_one_versioned<T>(
+        &self,
+        id: ObjectId,
+        expected_version: ExpectedVersion,
+        update: bson::Document,
+        transaction: Option<Arc<RwLock<Self::Transaction>>>,
+    ) -> DatabaseResult<i64>
+    where
+        T: DecoratedDatabaseDocumentTrait + VersionedDocumentTrait,
+    {
+        let collection = T::collection_name();
+        self.save_undo(collection, transaction.as_ref()).await;
+
+        let mut guard = self.collections.write().await;
+
+        let document = guard
+            .get_mut(collection)
+            .and_then(|documents| {
+                documents
+                    .iter_mut()
+                    .find(|document| document.get_object_id("_id").ok() == Some(id))
+            })
+            .ok_or(DatabaseError::DocumentDoesNotExist(id))?;
+
+        let current_version = document.get_i64("version").unwrap_or_default();
+        if !expected_version.matches(current_version) {
+            return Err(DatabaseError::VersionConflict {
+                id,
+                expected_version,
+            });
+        }
+
+        if let Some(Bson::Document(set_document)) = update.get("$set") {
+            for (key, value) in set_document.iter() {
+                document.insert(key.clone(), value.clone());
+            }
+        }
+        let new_version = current_version + 1;
+        document.insert("version", new_version);
+        self.notify(collection, ChangeEvent::Updated(document.clone()));
+        Ok(new_version)
+    }
+
     async fn update_many<T>(
         &self,
         query: bson::Document,
         update: bson::Document,
-        _transaction: Option<Arc<RwLock<Self::Transaction>>>,
+        transaction: Option<Arc<RwLock<Self::Transaction>>>,
     ) -> DatabaseResult<()>
     where
         T: DecoratedDatabaseDocumentTrait,
     {
         let collection = T::collection_name();
+        Self::check_operators(&query)?;
+        self.save_undo(collection, transaction.as_ref()).await;
 
-        let mut guard = self
-            .collections
-            .try_write()
-            .map_err(|err| DatabaseError::TransactionError(err.to_string()))?;
+        let mut guard = self.collections.write().await;
 
         if let Some(documents) = guard.get_mut(collection) {
             for document in documents.iter_mut() {
@@ -305,6 +618,7 @@ impl DatabaseServiceTrait for MemoryDatabaseService {
                     for (key, value) in set_document.iter() {
                         document.insert(key.clone(), value.clone());
                     }
+                    self.notify(collection, ChangeEvent::Updated(document.clone()));
                 }
             }
             Ok(())
@@ -316,18 +630,23 @@ impl DatabaseServiceTrait for MemoryDatabaseService {
     async fn delete_one<T>(
         &self,
         query: bson::Document,
-        _transaction: Option<Arc<RwLock<Self::Transaction>>>,
+        transaction: Option<Arc<RwLock<Self::Transaction>>>,
     ) -> DatabaseResult<()>
     where
         T: DecoratedDatabaseDocumentTrait,
     {
         let collection = T::collection_name();
+        Self::check_operators(&query)?;
+        self.save_undo(collection, transaction.as_ref()).await;
         if let Some(documents) = self.collections.write().await.get_mut(collection)
             && let Some(document_position) = documents
                 .iter()
                 .position(|doc| Self::match_document(doc, &query))
         {
-            documents.remove(document_position);
+            let document = documents.remove(document_position);
+            if let Ok(id) = document.get_object_id("_id") {
+                self.notify(collection, ChangeEvent::Deleted(id));
+            }
         }
         Ok(())
     }
@@ -335,18 +654,66 @@ impl DatabaseServiceTrait for MemoryDatabaseService {
     async fn delete_many<T>(
         &self,
         query: bson::Document,
-        _transaction: Option<Arc<RwLock<Self::Transaction>>>,
+        transaction: Option<Arc<RwLock<Self::Transaction>>>,
     ) -> DatabaseResult<()>
     where
         T: DecoratedDatabaseDocumentTrait,
     {
         let collection = T::collection_name();
+        Self::check_operators(&query)?;
+        self.save_undo(collection, transaction.as_ref()).await;
         if let Some(documents) = self.collections.write().await.get_mut(collection) {
-            documents.retain(|doc| !Self::match_document(doc, &query));
+            documents.retain(|doc| {
+                let matched = Self::match_document(doc, &query);
+                if matched && let Ok(id) = doc.get_object_id("_id") {
+                    self.notify(collection, ChangeEvent::Deleted(id));
+                }
+                !matched
+            });
         }
         Ok(())
     }
 
+    async fn watch<T>(&self) -> DatabaseResult<ChangeStream<T>>
+    where
+        T: DecoratedDatabaseDocumentTrait + 'static,
+    {
+        let receiver = self.changes.subscribe();
+        let collection = T::collection_name();
+
+        Ok(
+            futures::stream::unfold(receiver, move |mut receiver| async move {
+                loop {
+                    match receiver.recv().await {
+                        Ok((changed_collection, change)) if changed_collection == collection => {
+                            let change = match change {
+                                ChangeEvent::Inserted(document) => from_document(document)
+                                    .map(ChangeEvent::Inserted)
+                                    .map_err(|e| DatabaseError::DocumentNotValid(e.to_string())),
+                                ChangeEvent::Updated(document) => from_document(document)
+                                    .map(ChangeEvent::Updated)
+                                    .map_err(|e| DatabaseError::DocumentNotValid(e.to_string())),
+                                ChangeEvent::Deleted(id) => Ok(ChangeEvent::Deleted(id)),
+                            };
+                            return Some((change, receiver));
+                        }
+                        Ok(_) => continue,
+                        Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                            return Some((
+                                Err(DatabaseError::DatabaseOperationError(format!(
+                                    "watcher lagged behind, {skipped} changes were lost"
+                                ))),
+                                receiver,
+                            ));
+                        }
+                        Err(broadcast::error::RecvError::Closed) => return None,
+                    }
+                }
+            })
+            .boxed(),
+        )
+    }
+
     async fn aggreagte<T>(
         &self,
         _pipeline: Vec<bson::Document>,
@@ -354,6 +721,89 @@ impl DatabaseServiceTrait for MemoryDatabaseService {
     where
         T: DecoratedDatabaseDocumentTrait,
     {
-        todo!()
+        Err(DatabaseError::DatabaseOperationError(
+            "Aggregation pipelines are not supported by the memory database".into(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bson::doc;
+
+    use crate::{
+        error::DatabaseError,
+        model::User,
+        service::database::{DatabaseServiceTrait, memory_service::MemoryDatabaseService},
+    };
+
+    #[tokio::test]
+    async fn test_in_operators() {
+        let database_service = MemoryDatabaseService::default();
+        for username in ["marcel", "albert", "gustave"] {
+            database_service
+                .insert_one::<User>(
+                    doc! {
+                        "first_name": "first name",
+                        "last_name": "last name",
+                        "username": username,
+                        "password_hash": "hash",
+                        "admin": false,
+                        "publisher": true,
+                        "version": 0_i64
+                    },
+                    None,
+                )
+                .await
+                .unwrap();
+        }
+
+        // only the documents with one of the listed values match
+        let users = database_service
+            .find_many::<User>(doc! { "username": { "$in": ["marcel", "albert"] } })
+            .await
+            .unwrap();
+        let mut usernames = users
+            .iter()
+            .map(|user| user.username().as_str())
+            .collect::<Vec<&str>>();
+        usernames.sort();
+        assert_eq!(usernames, vec!["albert", "marcel"]);
+
+        // a missing field does not match
+        assert_eq!(
+            database_service
+                .count_documents::<User>(doc! { "missing": { "$in": ["marcel"] } })
+                .await
+                .unwrap(),
+            0
+        );
+
+        // `$nin` matches the documents without any of the values, or without the field
+        let users = database_service
+            .find_many::<User>(doc! { "username": { "$nin": ["marcel", "albert"] } })
+            .await
+            .unwrap();
+        assert_eq!(users.len(), 1);
+        assert_eq!(users[0].username(), "gustave");
+        assert_eq!(
+            database_service
+                .count_documents::<User>(doc! { "missing": { "$nin": ["marcel"] } })
+                .await
+                .unwrap(),
+            3
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_operator() {
+        let database_service = MemoryDatabaseService::default();
+        let result = database_service
+            .find_many::<User>(doc! { "username": { "$regex": "^mar" } })
+            .await;
+        assert!(matches!(
+            result,
+            Err(DatabaseError::DatabaseOperationError(_))
+        ));
     }
 }
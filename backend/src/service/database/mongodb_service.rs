@@ -1,10 +1,14 @@
 use std::sync::Arc;
 
-use bson::{Document, oid::ObjectId};
-use futures::TryStreamExt;
+use bson::{Document, doc, oid::ObjectId};
+use futures::{StreamExt, TryStreamExt};
 use mongodb::{
-    Client, Database,
-    options::{ClientOptions, FindOneOptions, FindOptions},
+    Client, Database, IndexModel,
+    change_stream::event::OperationType,
+    error::{ErrorKind, WriteFailure},
+    options::{
+        ClientOptions, FindOneOptions, FindOptions, FullDocumentType, IndexOptions, ReturnDocument,
+    },
 };
 use serde::Serialize;
 use tokio::sync::RwLock;
@@ -13,11 +17,18 @@ use crate::{
     DatabaseResult,
     error::DatabaseError,
     service::database::{
-        DatabaseServiceTrait, document::DecoratedDatabaseDocumentTrait,
+        ChangeEvent, ChangeStream, DatabaseServiceTrait,
+        document::{
+            DecoratedDatabaseDocumentTrait, ExpectedVersion, VersionedDocumentTrait,
+            exclude_soft_deleted,
+        },
         transaction::MongoDBDatabaseTransaction,
     },
 };
 
+/// Code of the error returned by MongoDB when a unique index is violated
+const DUPLICATE_KEY_ERROR_CODE: i32 = 11000;
+
 /// Database service struct using mongdb crate
 ///
 /// It connects to the database, creates session objects to perform transactions
@@ -49,6 +60,19 @@ impl MongoDBDatabaseService {
             Err(DatabaseError::ClientNotConnected)
         }
     }
+
+    /// Converts the error of an insertion, the violation of a unique index
+    /// becomes `DatabaseError::DuplicateKey`
+    fn insert_error(err: mongodb::error::Error) -> DatabaseError {
+        match err.kind.as_ref() {
+            ErrorKind::Write(WriteFailure::WriteError(write_error))
+                if write_error.code == DUPLICATE_KEY_ERROR_CODE =>
+            {
+                DatabaseError::DuplicateKey(write_error.message.clone())
+            }
+            _ => err.into(),
+        }
+    }
 }
 
 impl Default for MongoDBDatabaseService {
@@ -86,9 +110,11 @@ impl DatabaseServiceTrait for MongoDBDatabaseService {
         Ok(())
     }
 
-    async fn shutdown(&mut self) -> DatabaseResult<()> {
-        if let Some(client) = self.client.take() {
-            Client::shutdown(client).await;
+    async fn shutdown(&self) -> DatabaseResult<()> {
+        // the clones of the client share the connections, hence
+        // shutting down one of them closes all of them
+        if let Some(client) = &self.client {
+            client.clone().shutdown().await;
         }
         Ok(())
     }
@@ -97,6 +123,11 @@ impl DatabaseServiceTrait for MongoDBDatabaseService {
         &self.database_name
     }
 
+    async fn ping(&self) -> DatabaseResult<()> {
+        self.get_database()?.run_command(doc! {"ping": 1}).await?;
+        Ok(())
+    }
+
     async fn new_transaction(&self) -> DatabaseResult<MongoDBDatabaseTransaction> {
         if let Some(client) = &self.client {
             MongoDBDatabaseTransaction::new(client.start_session().await?).await
@@ -105,6 +136,22 @@ impl DatabaseServiceTrait for MongoDBDatabaseService {
         }
     }
 
+    async fn create_unique_index<T>(&self, field: &str) -> DatabaseResult<()>
+    where
+        T: DecoratedDatabaseDocumentTrait,
+    {
+        let index = IndexModel::builder()
+            .keys(doc! { field: 1 })
+            .options(IndexOptions::builder().unique(true).build())
+            .build();
+        self.get_database()?
+            .collection::<Document>(T::collection_name())
+            .create_index(index)
+            .await
+            .map_err(Self::insert_error)?;
+        Ok(())
+    }
+
     async fn insert_one<T>(
         &self,
         document: Document,
@@ -124,12 +171,12 @@ impl DatabaseServiceTrait for MongoDBDatabaseService {
         } else {
             operation.await
         };
-        query_result.map(|inner| {
+        query_result.map_err(Self::insert_error).and_then(|inner| {
             inner
                 .inserted_id
                 .as_object_id()
                 .ok_or(DatabaseError::InvalidObjectId)
-        })?
+        })
     }
 
     async fn insert_many<T>(
@@ -164,6 +211,7 @@ impl DatabaseServiceTrait for MongoDBDatabaseService {
     where
         T: DecoratedDatabaseDocumentTrait,
     {
+        let query = exclude_soft_deleted::<T>(query);
         let collection = self.get_database()?.collection::<T>(T::collection_name());
         Ok(collection.find_one(query).await?)
     }
@@ -172,10 +220,33 @@ impl DatabaseServiceTrait for MongoDBDatabaseService {
     where
         T: DecoratedDatabaseDocumentTrait,
     {
+        let query = exclude_soft_deleted::<T>(query);
         let collection = self.get_database()?.collection::<T>(T::collection_name());
         Ok(collection.find(query).await?.try_collect().await?)
     }
 
+    async fn find_page<T>(
+        &self,
+        query: Document,
+        sort: Document,
+        skip: u64,
+        limit: u64,
+    ) -> DatabaseResult<Vec<T>>
+    where
+        T: DecoratedDatabaseDocumentTrait,
+    {
+        let query = exclude_soft_deleted::<T>(query);
+        let collection = self.get_database()?.collection::<T>(T::collection_name());
+        Ok(collection
+            .find(query)
+            .sort(sort)
+            .skip(skip)
+            .limit(i64::try_from(limit).unwrap_or(i64::MAX))
+            .await?
+            .try_collect()
+            .await?)
+    }
+
     async fn find_one_projection<T, P>(
         &self,
         query: Document,
@@ -185,6 +256,7 @@ impl DatabaseServiceTrait for MongoDBDatabaseService {
         T: DecoratedDatabaseDocumentTrait,
         P: Send + Sync + Serialize + serde::de::DeserializeOwned,
     {
+        let query = exclude_soft_deleted::<T>(query);
         let collection = self.get_database()?.collection::<T>(T::collection_name());
         let query_options = FindOneOptions::builder().projection(projection).build();
         let result: Option<P> = collection
@@ -204,6 +276,7 @@ impl DatabaseServiceTrait for MongoDBDatabaseService {
         T: DecoratedDatabaseDocumentTrait,
         P: Send + Sync + Serialize + serde::de::DeserializeOwned,
     {
+        let query = exclude_soft_deleted::<T>(query);
         let collection = self.get_database()?.collection::<T>(T::collection_name());
         let query_options = FindOptions::builder().projection(projection).build();
         let result: Vec<P> = collection
@@ -220,6 +293,7 @@ impl DatabaseServiceTrait for MongoDBDatabaseService {
     where
         T: DecoratedDatabaseDocumentTrait,
     {
+        let query = exclude_soft_deleted::<T>(query);
         let collection = self.get_database()?.collection::<T>(T::collection_name());
         let result: u64 = collection.count_documents(query).await?;
         Ok(result)
@@ -248,6 +322,65 @@ impl DatabaseServiceTrait for MongoDBDatabaseService {
         Ok(())
     }
 
+    async fn update_one_versioned<T>(
+        &self,
+        id: ObjectId,
+        expected_version: ExpectedVersion,
+        update: Document,
+        transaction: Option<Arc<RwLock<Self::Transaction>>>,
+    ) -> DatabaseResult<i64>
+    where
+        T: DecoratedDatabaseDocumentTrait + VersionedDocumentTrait,
+    {
+        let collection = self.get_database()?.collection::<T>(T::collection_name());
+        let mut update = update;
+        update.insert("$inc", doc! { "version": 1_i64 });
+        let mut query = doc! { "_id": id };
+        if let Some(condition) = expected_version.to_condition() {
+            query.insert("version", condition);
+        }
+        let operation = collection
+            .find_one_and_update(query, update)
+            .return_document(ReturnDocument::After);
+
+        // the existence of the document is checked in the same session of the update
+        let (updated_document, matching_documents) = if let Some(transaction) = transaction {
+            let mut transaction_guard = transaction
+                .try_write()
+                .map_err(|err| DatabaseError::TransactionError(err.to_string()))?;
+            let session = transaction_guard.get_mut_session();
+            let updated_document = operation.session(&mut *session).await?;
+            let matching_documents = if updated_document.is_none() {
+                collection
+                    .count_documents(doc! { "_id": id })
+                    .session(session)
+                    .await?
+            } else {
+                1
+            };
+            (updated_document, matching_documents)
+        } else {
+            let updated_document = operation.await?;
+            let matching_documents = if updated_document.is_none() {
+                collection.count_documents(doc! { "_id": id }).await?
+            } else {
+                1
+            };
+            (updated_document, matching_documents)
+        };
+
+        match updated_document {
+            Some(document) => Ok(document.get_version()),
+            // nothing matched, we distinguish between a missing document
+            // and a document with a different version
+            None if matching_documents == 0 => Err(DatabaseError::DocumentDoesNotExist(id)),
+            None => Err(DatabaseError::VersionConflict {
+                id,
+                expected_version,
+            }),
+        }
+    }
+
     async fn update_many<T>(
         &self,
         query: Document,
@@ -315,6 +448,49 @@ impl DatabaseServiceTrait for MongoDBDatabaseService {
         Ok(())
     }
 
+    async fn watch<T>(&self) -> DatabaseResult<ChangeStream<T>>
+    where
+        T: DecoratedDatabaseDocumentTrait + 'static,
+    {
+        // change streams require a replica set, the same requirement of transactions
+        let collection = self
+            .get_database()?
+            .collection::<Document>(T::collection_name());
+        let change_stream = collection
+            .watch()
+            .full_document(FullDocumentType::UpdateLookup)
+            .await?;
+
+        Ok(change_stream
+            .map_err(DatabaseError::from)
+            .try_filter_map(|event| async move {
+                let change = match event.operation_type {
+                    OperationType::Insert | OperationType::Update | OperationType::Replace => {
+                        // the full document is missing when it has been deleted before the lookup
+                        match event.full_document {
+                            Some(document) => {
+                                let document = bson::from_document::<T>(document)
+                                    .map_err(|e| DatabaseError::DocumentNotValid(e.to_string()))?;
+                                if event.operation_type == OperationType::Insert {
+                                    Some(ChangeEvent::Inserted(document))
+                                } else {
+                                    Some(ChangeEvent::Updated(document))
+                                }
+                            }
+                            None => None,
+                        }
+                    }
+                    OperationType::Delete => event
+                        .document_key
+                        .and_then(|key| key.get_object_id("_id").ok())
+                        .map(ChangeEvent::Deleted),
+                    _ => None,
+                };
+                Ok(change)
+            })
+            .boxed())
+    }
+
     async fn aggreagte<T>(&self, pipeline: Vec<Document>) -> DatabaseResult<Vec<Document>>
     where
         T: DecoratedDatabaseDocumentTrait,
@@ -1,6 +1,12 @@
+use std::{collections::HashMap, sync::Arc};
+
+use bson::Document;
 use mongodb::ClientSession;
+use tokio::sync::RwLock;
 
-use crate::DatabaseResult;
+use crate::{
+    DatabaseResult, error::DatabaseError, service::database::memory_service::MemoryCollections,
+};
 
 pub trait DatabaseTransactionTrait {
     fn abort_transaction(&mut self)
@@ -42,26 +48,98 @@ impl DatabaseTransactionTrait for MongoDBDatabaseTransaction {
     }
 }
 
-pub struct MemoryDatabaseTransaction {}
+/// Transaction of the memory database service
+///
+/// Before its first change inside the transaction, every collection is copied in
+/// the undo log. Aborting the transaction restores the copies, committing it
+/// discards them. There is no isolation: the changes are visible outside of the
+/// transaction before the commit and an abort also reverts the changes done
+/// to the same collections by other operations in the meantime.
+pub struct MemoryDatabaseTransaction {
+    collections: MemoryCollections,
+    undo_log: HashMap<String, Vec<Document>>,
+}
 
 impl MemoryDatabaseTransaction {
-    pub fn new() -> Self {
-        Self {}
+    pub fn new(collections: MemoryCollections) -> Self {
+        Self {
+            collections,
+            undo_log: HashMap::new(),
+        }
+    }
+
+    /// Returns true if the collection has already been saved in the undo log
+    pub fn is_saved(&self, collection: &str) -> bool {
+        self.undo_log.contains_key(collection)
     }
-}
 
-impl Default for MemoryDatabaseTransaction {
-    fn default() -> Self {
-        Self::new()
+    /// Saves the documents of the collection before its first change,
+    /// later calls for the same collection are ignored
+    pub fn save(&mut self, collection: &str, documents: Vec<Document>) {
+        self.undo_log
+            .entry(collection.to_string())
+            .or_insert(documents);
     }
 }
 
 impl DatabaseTransactionTrait for MemoryDatabaseTransaction {
+    /// Restores the collections changed inside the transaction
     async fn abort_transaction(&mut self) -> DatabaseResult<()> {
+        let mut collections = self.collections.write().await;
+        for (collection, documents) in self.undo_log.drain() {
+            collections.insert(collection, documents);
+        }
         Ok(())
     }
 
+    /// Keeps the changes and empties the undo log
     async fn commit_transaction(&mut self) -> DatabaseResult<()> {
+        self.undo_log.clear();
         Ok(())
     }
 }
+
+/// Transaction of the `AppDatabaseService`, it wraps the transaction
+/// of the database service selected at runtime
+pub enum AppDatabaseTransaction {
+    MongoDB(Arc<RwLock<MongoDBDatabaseTransaction>>),
+    Memory(Arc<RwLock<MemoryDatabaseTransaction>>),
+}
+
+impl AppDatabaseTransaction {
+    /// Returns the wrapped MongoDB transaction
+    pub fn mongodb(&self) -> DatabaseResult<Arc<RwLock<MongoDBDatabaseTransaction>>> {
+        match self {
+            Self::MongoDB(transaction) => Ok(transaction.clone()),
+            Self::Memory(_) => Err(DatabaseError::TransactionError(
+                "Expected a MongoDB transaction, got a memory one".into(),
+            )),
+        }
+    }
+
+    /// Returns the wrapped memory transaction
+    pub fn memory(&self) -> DatabaseResult<Arc<RwLock<MemoryDatabaseTransaction>>> {
+        match self {
+            Self::Memory(transaction) => Ok(transaction.clone()),
+            Self::MongoDB(_) => Err(DatabaseError::TransactionError(
+                "Expected a memory transaction, got a MongoDB one".into(),
+            )),
+        }
+    }
+}
+
+impl DatabaseTransactionTrait for AppDatabaseTransaction {
+    async fn abort_transaction(&mut self) -> DatabaseResult<()> {
+        match self {
+            Self::MongoDB(transaction) => transaction.write().await.abort_transaction().await,
+            Self::Memory(transaction) => transaction.write().await.abort_transaction().await,
+        }
+    }
+
+    async fn commit_transaction(&mut self) -> DatabaseResult<()> {
+        match self {
+            Self::MongoDB(transaction) => transaction.write().await.commit_transaction().await,
+            Self::Memory(transaction) => transaction.write().await.commit_transaction().await,
+        }
+    }
+}
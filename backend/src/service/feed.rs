@@ -0,0 +1,245 @@
+use std::{fmt::Write, sync::Arc};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+
+use crate::{
+    ServiceResult,
+    model::{BlogPost, User},
+    service::{
+        blog::BlogService,
+        database::{DatabaseServiceTrait, document::DatabaseDocumentTrait},
+    },
+};
+
+/// Title of the blog, used by the feeds
+pub const BLOG_TITLE: &str = "Hello Blog";
+
+/// Maximum number of posts in a feed, the most recent ones
+const FEED_MAX_ENTRIES: u64 = 50;
+
+/// Feed of the most recent public blog posts, of the whole blog or of
+/// a single author, that can be rendered as RSS 2.0 or Atom
+pub struct Feed {
+    title: String,
+    description: String,
+    /// Posts sorted from the most recent one
+    posts: Vec<BlogPost>,
+    /// Most recent change of the posts, see `BlogService::get_last_modified`
+    last_modified: Option<DateTime<Utc>>,
+}
+
+impl Feed {
+    /// Time of the most recent publication, edit or deletion of the posts,
+    /// None when there has never been any post
+    pub fn last_modified(&self) -> Option<DateTime<Utc>> {
+        self.last_modified
+    }
+
+    /// Renders the feed as RSS 2.0, `public_url` is the base of the links
+    /// to the posts and `feed_url` is the URL the feed is served from
+    pub fn to_rss(&self, public_url: &str, feed_url: &str) -> String {
+        let mut xml = String::from(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+        xml.push_str(r#"<rss version="2.0" xmlns:atom="http://www.w3.org/2005/Atom"><channel>"#);
+        // writing to a String cannot fail
+        let _ = write!(
+            xml,
+            r#"<title>{}</title><link>{}/</link><description>{}</description><atom:link href="{}" rel="self" type="application/rss+xml"/>"#,
+            escape_xml(&self.title),
+            escape_xml(public_url),
+            escape_xml(&self.description),
+            escape_xml(feed_url),
+        );
+        if let Some(last_modified) = self.last_modified() {
+            let _ = write!(
+                xml,
+                "<lastBuildDate>{}</lastBuildDate>",
+                last_modified.to_rfc2822()
+            );
+        }
+        for post in &self.posts {
+            let link = escape_xml(&post_url(public_url, post));
+            let _ = write!(
+                xml,
+                r#"<item><title>{}</title><link>{link}</link><guid isPermaLink="true">{link}</guid><description>{}</description><pubDate>{}</pubDate></item>"#,
+                escape_xml(post.title()),
+                escape_xml(post.content()),
+                post.creation_date().to_rfc2822(),
+            );
+        }
+        xml.push_str("</channel></rss>");
+        xml
+    }
+
+    /// Renders the feed as Atom, `public_url` is the base of the links
+    /// to the posts and `feed_url` is the URL the feed is served from
+    pub fn to_atom(&self, public_url: &str, feed_url: &str) -> String {
+        let mut xml = String::from(r#"<?xml version="1.0" encoding="UTF-8"?>"#);
+        xml.push_str(r#"<feed xmlns="http://www.w3.org/2005/Atom">"#);
+        // the updated element is required, an empty feed has never been updated
+        let updated = self.last_modified().unwrap_or_default();
+        let _ = write!(
+            xml,
+            r#"<title>{}</title><subtitle>{}</subtitle><id>{feed_url}</id><link rel="self" href="{feed_url}"/><link href="{}/"/><updated>{}</updated>"#,
+            escape_xml(&self.title),
+            escape_xml(&self.description),
+            escape_xml(public_url),
+            atom_date(&updated),
+            feed_url = escape_xml(feed_url),
+        );
+        for post in &self.posts {
+            let link = escape_xml(&post_url(public_url, post));
+            let _ = write!(
+                xml,
+                r#"<entry><title>{}</title><id>{link}</id><link href="{link}"/><author><name>{}</name></author><published>{}</published><updated>{}</updated><content type="text">{}</content></entry>"#,
+                escape_xml(post.title()),
+                escape_xml(post.username()),
+                atom_date(post.creation_date()),
+                atom_date(post.updated_at()),
+                escape_xml(post.content()),
+            );
+        }
+        xml.push_str("</feed>");
+        xml
+    }
+}
+
+/// FeedService builds the feeds of the public blog posts
+pub struct FeedService<D: DatabaseServiceTrait> {
+    database_service: Arc<D>,
+}
+
+impl<D: DatabaseServiceTrait> FeedService<D> {
+    pub fn new(database_service: Arc<D>) -> Self {
+        Self { database_service }
+    }
+
+    /// Returns the feed of the most recent public posts, if the author
+    /// is specified then only the posts created by him are included
+    pub async fn get_feed(&self, author: Option<&User>) -> ServiceResult<Feed> {
+        let blog_service = BlogService::new(self.database_service.clone(), None);
+        let author_id = author.map(|author| *author.get_id());
+        let posts = blog_service
+            .get_recent_public_posts(author_id, FEED_MAX_ENTRIES)
+            .await?;
+
+        let (title, description) = match author {
+            Some(author) => (
+                format!("{BLOG_TITLE} - {}", author.username()),
+                format!("Posts written by {}", author.username()),
+            ),
+            None => (BLOG_TITLE.to_string(), "Posts of the blog".to_string()),
+        };
+        Ok(Feed {
+            title,
+            description,
+            posts,
+            last_modified: blog_service.get_last_modified(author_id).await?,
+        })
+    }
+}
+
+/// Link to the page of the post in the frontend
+fn post_url(public_url: &str, post: &BlogPost) -> String {
+    format!("{public_url}/post/{}", post.get_id())
+}
+
+fn atom_date(date: &DateTime<Utc>) -> String {
+    date.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// Escapes the text placed in XML elements and attributes, the characters
+/// that are not allowed by XML 1.0, like most of the control ones, are removed
+fn escape_xml(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for character in value.chars() {
+        match character {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(character),
+            '\u{0}'..='\u{1F}' | '\u{FFFE}' | '\u{FFFF}' => {}
+            _ => escaped.push(character),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use api_types::user::PostVisibility;
+    use bson::oid::ObjectId;
+    use chrono::{TimeZone, Utc};
+
+    use crate::{
+        model::BlogPostBuilder,
+        service::{
+            database::memory_service::MemoryDatabaseService,
+            feed::{FeedService, escape_xml},
+        },
+    };
+
+    #[test]
+    fn test_escape_xml() {
+        assert_eq!(
+            escape_xml("<b>Tom & \"Jerry\"</b>'\u{1}\n"),
+            "&lt;b&gt;Tom &amp; &quot;Jerry&quot;&lt;/b&gt;&apos;\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_feed() {
+        let database_service = Arc::new(MemoryDatabaseService::default());
+        let user_id = ObjectId::new();
+        // the old post has been edited after the publication of the other ones
+        for (day, updated_day, title, visibility) in [
+            (1, 4, "Old <post>", PostVisibility::Public),
+            (2, 2, "New post", PostVisibility::Public),
+            (3, 3, "Hidden post", PostVisibility::MembersOnly),
+        ] {
+            BlogPostBuilder::new(database_service.clone())
+                .title(title.into())
+                .content("Fish & chips".into())
+                .user_id(user_id)
+                .username("marcel".into())
+                .creation_date(Utc.with_ymd_and_hms(2025, 11, day, 10, 0, 0).unwrap())
+                .updated_at(
+                    Utc.with_ymd_and_hms(2025, 11, updated_day, 10, 0, 0)
+                        .unwrap(),
+                )
+                .visibility(visibility.to_string())
+                .build(None)
+                .await
+                .unwrap();
+        }
+
+        let feed = FeedService::new(database_service)
+            .get_feed(None)
+            .await
+            .unwrap();
+        assert_eq!(
+            feed.last_modified(),
+            Some(Utc.with_ymd_and_hms(2025, 11, 4, 10, 0, 0).unwrap())
+        );
+
+        let rss = feed.to_rss("https://blog.example", "https://blog.example/feed.xml");
+        assert!(!rss.contains("Hidden post"));
+        // the most recent post comes first
+        let new_post = rss.find("<title>New post</title>").unwrap();
+        let old_post = rss.find("<title>Old &lt;post&gt;</title>").unwrap();
+        assert!(new_post < old_post);
+        assert!(rss.contains("<description>Fish &amp; chips</description>"));
+        assert!(rss.contains("<pubDate>Sun, 2 Nov 2025 10:00:00 +0000</pubDate>"));
+
+        let atom = feed.to_atom("https://blog.example", "https://blog.example/atom.xml");
+        assert!(atom.contains("<id>https://blog.example/atom.xml</id>"));
+        assert!(atom.contains("<updated>2025-11-04T10:00:00Z</updated><entry>"));
+        assert!(atom.contains(
+            "<published>2025-11-01T10:00:00Z</published><updated>2025-11-04T10:00:00Z</updated>"
+        ));
+        assert!(atom.contains("<author><name>marcel</name></author>"));
+    }
+}
@@ -0,0 +1,174 @@
+use std::{
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    dtos::health_response::{DependencyHealth, HealthStatus, ReadinessReport},
+    enums::ObjectSourceType,
+    service::database::DatabaseServiceTrait,
+};
+
+/// Time given to each dependency to answer, an unreachable MongoDB cluster
+/// would otherwise block the probe until the server selection timeout
+const CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Service that checks the dependencies of the application
+/// to answer the readiness probe
+pub struct HealthService<D>
+where
+    D: DatabaseServiceTrait,
+{
+    database_service: Arc<D>,
+    storage_backend: ObjectSourceType,
+    storage_prefix_path: PathBuf,
+}
+
+impl<D> HealthService<D>
+where
+    D: DatabaseServiceTrait,
+{
+    pub fn new(
+        database_service: Arc<D>,
+        storage_backend: ObjectSourceType,
+        storage_prefix_path: PathBuf,
+    ) -> Self {
+        Self {
+            database_service,
+            storage_backend,
+            storage_prefix_path,
+        }
+    }
+
+    /// Checks all the dependencies concurrently, the status of
+    /// the report is down when any of them is down
+    pub async fn check(&self) -> ReadinessReport {
+        let (database, object_storage) =
+            tokio::join!(self.check_database(), self.check_object_storage());
+        let status = if [&database, &object_storage]
+            .iter()
+            .any(|dependency| dependency.status == HealthStatus::Down)
+        {
+            HealthStatus::Down
+        } else {
+            HealthStatus::Up
+        };
+        ReadinessReport {
+            status,
+            database,
+            object_storage,
+        }
+    }
+
+    async fn check_database(&self) -> DependencyHealth {
+        timed(async {
+            self.database_service
+                .ping()
+                .await
+                .map_err(|err| err.to_string())
+        })
+        .await
+    }
+
+    /// The local file system is reachable when the prefix path is a writable directory,
+    /// the remote object storages are not checked because they are not used by the backend
+    async fn check_object_storage(&self) -> DependencyHealth {
+        if self.storage_backend != ObjectSourceType::LocalFileSystem {
+            return DependencyHealth {
+                status: HealthStatus::Skipped,
+                latency_ms: 0.0,
+                error: None,
+            };
+        }
+        timed(async {
+            let metadata = tokio::fs::metadata(&self.storage_prefix_path)
+                .await
+                .map_err(|err| format!("{}: {err}", self.storage_prefix_path.display()))?;
+            if !metadata.is_dir() {
+                Err(format!(
+                    "{} is not a directory",
+                    self.storage_prefix_path.display()
+                ))
+            } else if metadata.permissions().readonly() {
+                Err(format!(
+                    "{} is read only",
+                    self.storage_prefix_path.display()
+                ))
+            } else {
+                Ok(())
+            }
+        })
+        .await
+    }
+}
+
+/// Runs the check within `CHECK_TIMEOUT` measuring its latency
+async fn timed(check: impl Future<Output = Result<(), String>>) -> DependencyHealth {
+    let start = Instant::now();
+    let result = tokio::time::timeout(CHECK_TIMEOUT, check)
+        .await
+        .unwrap_or_else(|_| Err(format!("no answer within {CHECK_TIMEOUT:?}")));
+    DependencyHealth {
+        status: if result.is_ok() {
+            HealthStatus::Up
+        } else {
+            HealthStatus::Down
+        },
+        latency_ms: start.elapsed().as_secs_f64() * 1000.0,
+        error: result.err(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use crate::{
+        dtos::health_response::HealthStatus,
+        enums::ObjectSourceType,
+        service::database::{AppDatabaseService, MongoDBDatabaseService},
+    };
+
+    use super::HealthService;
+
+    #[tokio::test]
+    async fn test_check_dependencies() {
+        let health_service = HealthService::new(
+            Arc::new(AppDatabaseService::default()),
+            ObjectSourceType::LocalFileSystem,
+            std::env::temp_dir(),
+        );
+        let report = health_service.check().await;
+        assert_eq!(report.status, HealthStatus::Up);
+        assert_eq!(report.database.status, HealthStatus::Up);
+        assert_eq!(report.object_storage.status, HealthStatus::Up);
+    }
+
+    #[tokio::test]
+    async fn test_check_unreachable_dependencies() {
+        // the MongoDB service is never connected
+        let health_service = HealthService::new(
+            Arc::new(AppDatabaseService::MongoDB(MongoDBDatabaseService::new(
+                "database".into(),
+                "mongodb://localhost:27017".into(),
+            ))),
+            ObjectSourceType::LocalFileSystem,
+            std::env::temp_dir().join("missing-object-storage"),
+        );
+        let report = health_service.check().await;
+        assert_eq!(report.status, HealthStatus::Down);
+        assert_eq!(report.database.status, HealthStatus::Down);
+        assert!(report.database.error.is_some());
+        assert_eq!(report.object_storage.status, HealthStatus::Down);
+
+        let health_service = HealthService::new(
+            Arc::new(AppDatabaseService::default()),
+            ObjectSourceType::AwsS3,
+            "bucket/prefix".into(),
+        );
+        let report = health_service.check().await;
+        assert_eq!(report.status, HealthStatus::Up);
+        assert_eq!(report.object_storage.status, HealthStatus::Skipped);
+    }
+}
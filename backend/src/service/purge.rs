@@ -0,0 +1,280 @@
+use std::{sync::Arc, time::Duration};
+
+use bson::{doc, oid::ObjectId};
+use chrono::{DateTime, Utc};
+use tokio::{sync::RwLock, task::JoinHandle};
+use tracing::{error, info};
+
+use crate::{
+    ServiceResult,
+    model::{BlogPost, RateLimitBucket, User},
+    service::database::{
+        DatabaseServiceTrait,
+        document::{DELETED_AT_FIELD, DatabaseDocumentTrait, DecoratedDatabaseDocumentTrait},
+        transaction::DatabaseTransactionTrait,
+    },
+};
+
+/// Time after which an unused rate limit bucket is deleted, it is
+/// longer than the period of any sensible limit
+const RATE_LIMIT_BUCKET_RETENTION: chrono::Duration = chrono::Duration::days(1);
+
+/// PurgeService is a secondary service that hard deletes the
+/// soft deleted documents once their retention period is expired,
+/// together with the unused rate limit buckets
+pub struct PurgeService<D: DatabaseServiceTrait> {
+    database_service: Arc<D>,
+    retention: chrono::Duration,
+}
+
+impl<D: DatabaseServiceTrait> PurgeService<D> {
+    pub fn new(database_service: Arc<D>, retention: chrono::Duration) -> Self {
+        Self {
+            database_service,
+            retention,
+        }
+    }
+
+    /// Deletes the users and blog posts that were soft deleted
+    /// before `now` minus the retention period, and the rate limit
+    /// buckets that have not been used for a day.
+    ///
+    /// The posts of the purged users are deleted as well, even if
+    /// they are not soft deleted.
+    pub async fn purge(&self, now: DateTime<Utc>) -> ServiceResult<()> {
+        let threshold = now - self.retention;
+        self.purge_users(threshold).await?;
+        self.purge_collection::<BlogPost>(threshold).await?;
+        self.database_service
+            .delete_many::<RateLimitBucket>(
+                doc! { "updated_at": { "$lt": bson::DateTime::from_chrono(now - RATE_LIMIT_BUCKET_RETENTION) } },
+                None,
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Deletes the expired users together with all their posts in the same transaction
+    async fn purge_users(&self, threshold: DateTime<Utc>) -> ServiceResult<()> {
+        let user_ids = self
+            .database_service
+            .find_many::<User>(
+                doc! { DELETED_AT_FIELD: { "$lt": bson::DateTime::from_chrono(threshold) } },
+            )
+            .await?
+            .iter()
+            .map(|user| *user.get_id())
+            .collect::<Vec<ObjectId>>();
+        if user_ids.is_empty() {
+            return Ok(());
+        }
+
+        let transaction = Arc::new(RwLock::new(self.database_service.new_transaction().await?));
+        let result = self.delete_users(user_ids, transaction.clone()).await;
+        let mut guard = transaction.write().await;
+        match result {
+            Ok(()) => guard.commit_transaction().await?,
+            Err(err) => {
+                guard.abort_transaction().await?;
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+
+    async fn delete_users(
+        &self,
+        user_ids: Vec<ObjectId>,
+        transaction: Arc<RwLock<D::Transaction>>,
+    ) -> ServiceResult<()> {
+        self.database_service
+            .delete_many::<BlogPost>(
+                doc! { "user_id": { "$in": user_ids.clone() } },
+                Some(transaction.clone()),
+            )
+            .await?;
+        self.database_service
+            .delete_many::<User>(doc! { "_id": { "$in": user_ids } }, Some(transaction))
+            .await?;
+        Ok(())
+    }
+
+    async fn purge_collection<T: DecoratedDatabaseDocumentTrait>(
+        &self,
+        threshold: DateTime<Utc>,
+    ) -> ServiceResult<()> {
+        self.database_service
+            .delete_many::<T>(
+                doc! { DELETED_AT_FIELD: { "$lt": bson::DateTime::from_chrono(threshold) } },
+                None,
+            )
+            .await?;
+        Ok(())
+    }
+}
+
+/// Spawns a background task that purges the expired soft deleted
+/// documents every `interval`
+pub fn spawn_purge_job<D>(
+    database_service: Arc<D>,
+    retention: chrono::Duration,
+    interval: Duration,
+) -> JoinHandle<()>
+where
+    D: DatabaseServiceTrait + Send + Sync + 'static,
+{
+    tokio::spawn(async move {
+        let purge_service = PurgeService::new(database_service, retention);
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match purge_service.purge(Utc::now()).await {
+                Ok(()) => info!("Purged soft deleted documents older than {retention}"),
+                Err(err) => error!("Error in purging soft deleted documents: {err}"),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use api_types::user::PostVisibility;
+    use bson::{doc, oid::ObjectId};
+    use chrono::{Duration, Utc};
+
+    use crate::{
+        model::{BlogPost, BlogPostBuilder, User, UserBuilder},
+        service::{
+            database::{
+                DatabaseServiceTrait, document::DatabaseDocumentTrait,
+                memory_service::MemoryDatabaseService,
+            },
+            purge::PurgeService,
+        },
+    };
+
+    #[tokio::test]
+    async fn test_purge() {
+        let database_service = Arc::new(MemoryDatabaseService::default());
+
+        let mut post_ids = vec![];
+        for i in 0..3 {
+            let post = BlogPostBuilder::new(database_service.clone())
+                .title(format!("title {i}"))
+                .content(format!("content {i}"))
+                .user_id(ObjectId::new())
+                .username("username".into())
+                .creation_date(Utc::now())
+                .updated_at(Utc::now())
+                .visibility(PostVisibility::Public.to_string())
+                .build(None)
+                .await
+                .unwrap();
+            post_ids.push(*post.get_id());
+        }
+
+        // the first post is deleted long ago, the second one recently
+        // and the third one is not deleted
+        let now = Utc::now();
+        for (post_id, deleted_at) in [
+            (post_ids[0], now - Duration::days(40)),
+            (post_ids[1], now - Duration::days(1)),
+        ] {
+            database_service
+                .update_one::<BlogPost>(
+                    doc! { "_id": post_id },
+                    doc! { "$set": { "deleted_at": bson::DateTime::from_chrono(deleted_at) } },
+                    None,
+                )
+                .await
+                .unwrap();
+        }
+
+        PurgeService::new(database_service.clone(), Duration::days(30))
+            .purge(now)
+            .await
+            .unwrap();
+
+        let remaining = database_service
+            .find_many::<BlogPost>(doc! { "deleted_at": { "$ne": null } })
+            .await
+            .unwrap();
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].get_id(), &post_ids[1]);
+        assert_eq!(
+            database_service
+                .count_documents::<BlogPost>(doc! {})
+                .await
+                .unwrap(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_purge_users_with_their_posts() {
+        let database_service = Arc::new(MemoryDatabaseService::default());
+
+        let mut user_ids = vec![];
+        for username in ["marcel", "albert"] {
+            let user = UserBuilder::new(database_service.clone())
+                .first_name("first name".into())
+                .last_name("last name".into())
+                .username(username.into())
+                .password_hash("hash".into())
+                .admin(false)
+                .publisher(true)
+                .build(None)
+                .await
+                .unwrap();
+            BlogPostBuilder::new(database_service.clone())
+                .title("title".into())
+                .content("content".into())
+                .user_id(*user.get_id())
+                .username(username.into())
+                .creation_date(Utc::now())
+                .updated_at(Utc::now())
+                .visibility(PostVisibility::Public.to_string())
+                .build(None)
+                .await
+                .unwrap();
+            user_ids.push(*user.get_id());
+        }
+
+        // only the first user is deleted, its post is not
+        let now = Utc::now();
+        database_service
+            .update_one::<User>(
+                doc! { "_id": user_ids[0] },
+                doc! { "$set": { "deleted_at": bson::DateTime::from_chrono(now - Duration::days(40)) } },
+                None,
+            )
+            .await
+            .unwrap();
+
+        PurgeService::new(database_service.clone(), Duration::days(30))
+            .purge(now)
+            .await
+            .unwrap();
+
+        let deleted_users = database_service
+            .find_many::<User>(doc! { "deleted_at": { "$ne": null } })
+            .await
+            .unwrap();
+        assert!(deleted_users.is_empty());
+        assert_eq!(
+            database_service
+                .count_documents::<User>(doc! {})
+                .await
+                .unwrap(),
+            1
+        );
+        let posts = database_service
+            .find_many::<BlogPost>(doc! {})
+            .await
+            .unwrap();
+        assert_eq!(posts.len(), 1);
+        assert_eq!(posts[0].user_id(), &user_ids[1]);
+    }
+}
@@ -0,0 +1,360 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use bson::doc;
+use chrono::{DateTime, Utc};
+use tracing::{error, warn};
+
+use crate::{
+    DatabaseResult,
+    enums::RateLimitStoreBackend,
+    error::DatabaseError,
+    model::{RateLimitBucket, RateLimitBucketBuilder},
+    service::database::{
+        AppDatabaseService, DatabaseServiceTrait, document::DatabaseDocumentTrait,
+    },
+    types::RateLimit,
+};
+
+/// Maximum number of keys kept by the memory store
+const MEMORY_STORE_CAPACITY: usize = 10_000;
+
+/// Number of the least recently used keys evicted when the memory store is full,
+/// evicting many keys at once runs the eviction only once every many new keys
+const MEMORY_STORE_EVICTION_BATCH: usize = MEMORY_STORE_CAPACITY / 4;
+
+/// Attempts to update a bucket of the database store when it is concurrently
+/// modified by other requests
+const DATABASE_STORE_MAX_ATTEMPTS: usize = 3;
+
+/// Outcome of a rate limited request
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum RateLimitDecision {
+    /// The request can proceed, a token has been taken from the bucket
+    Allowed,
+    /// The bucket is empty, the client can retry once the time is elapsed
+    Limited { retry_after: Duration },
+}
+
+/// Token bucket that holds up to `RateLimit::requests` tokens, each request
+/// takes one token and the tokens are refilled continuously
+#[derive(Debug, PartialEq, Clone, Copy)]
+struct TokenBucket {
+    tokens: f64,
+    updated_at: DateTime<Utc>,
+}
+
+impl TokenBucket {
+    fn full(limit: &RateLimit, now: DateTime<Utc>) -> Self {
+        Self {
+            tokens: f64::from(limit.requests),
+            updated_at: now,
+        }
+    }
+
+    /// Refills the tokens elapsed since the last update and takes one token
+    fn take(&mut self, limit: &RateLimit, now: DateTime<Utc>) -> RateLimitDecision {
+        let elapsed = (now - self.updated_at).to_std().unwrap_or_default();
+        self.tokens = (self.tokens + elapsed.as_secs_f64() * limit.refill_rate())
+            .min(f64::from(limit.requests));
+        self.updated_at = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            RateLimitDecision::Allowed
+        } else {
+            RateLimitDecision::Limited {
+                retry_after: Duration::from_secs_f64((1.0 - self.tokens) / limit.refill_rate()),
+            }
+        }
+    }
+}
+
+/// Store of the token buckets, selected with the `rate_limit.store` setting
+///
+/// The memory store is local to the process and keeps up to `MEMORY_STORE_CAPACITY`
+/// buckets, while the database store shares the buckets among all the instances
+/// of the application using the optimistic concurrency of the versioned documents.
+enum RateLimitStore {
+    Memory(Mutex<HashMap<String, TokenBucket>>),
+    Database(Arc<AppDatabaseService>),
+}
+
+/// RateLimitService is a secondary service that applies the token bucket
+/// algorithm to the requests identified by a key, like the client IP or
+/// the user id, combined with the limited route
+pub struct RateLimitService {
+    store: RateLimitStore,
+}
+
+impl RateLimitService {
+    pub fn new(backend: RateLimitStoreBackend, database_service: Arc<AppDatabaseService>) -> Self {
+        let store = match backend {
+            RateLimitStoreBackend::Memory => RateLimitStore::Memory(Mutex::default()),
+            RateLimitStoreBackend::Database => RateLimitStore::Database(database_service),
+        };
+        Self { store }
+    }
+
+    /// Takes a token from the bucket of `key` at time `now`.
+    ///
+    /// When the store is not available the request is allowed, so that an
+    /// error of the database does not block the whole application.
+    pub async fn acquire(
+        &self,
+        key: &str,
+        limit: &RateLimit,
+        now: DateTime<Utc>,
+    ) -> RateLimitDecision {
+        match &self.store {
+            RateLimitStore::Memory(buckets) => {
+                let mut buckets = buckets.lock().unwrap_or_else(|err| err.into_inner());
+                if buckets.len() >= MEMORY_STORE_CAPACITY && !buckets.contains_key(key) {
+                    Self::evict_least_recently_used(&mut buckets);
+                }
+                buckets
+                    .entry(key.to_string())
+                    .or_insert_with(|| TokenBucket::full(limit, now))
+                    .take(limit, now)
+            }
+            RateLimitStore::Database(database_service) => {
+                match Self::acquire_from_database(database_service, key, limit, now).await {
+                    Ok(decision) => decision,
+                    Err(err) => {
+                        error!("Error in reading rate limit bucket {key}: {err}");
+                        RateLimitDecision::Allowed
+                    }
+                }
+            }
+        }
+    }
+
+    /// Removes the `MEMORY_STORE_EVICTION_BATCH` buckets that have not been
+    /// used for the longest time, the update time of a bucket is its last use
+    fn evict_least_recently_used(buckets: &mut HashMap<String, TokenBucket>) {
+        let mut last_uses: Vec<(DateTime<Utc>, String)> = buckets
+            .iter()
+            .map(|(key, bucket)| (bucket.updated_at, key.clone()))
+            .collect();
+        let batch = MEMORY_STORE_EVICTION_BATCH.min(last_uses.len());
+        if batch == 0 {
+            return;
+        }
+        last_uses.select_nth_unstable_by_key(batch - 1, |(updated_at, _)| *updated_at);
+        for (_, key) in &last_uses[..batch] {
+            buckets.remove(key);
+        }
+    }
+
+    async fn acquire_from_database(
+        database_service: &Arc<AppDatabaseService>,
+        key: &str,
+        limit: &RateLimit,
+        now: DateTime<Utc>,
+    ) -> DatabaseResult<RateLimitDecision> {
+        for _ in 0..DATABASE_STORE_MAX_ATTEMPTS {
+            let Some(document) = database_service
+                .find_one::<RateLimitBucket>(doc! { "key": key })
+                .await?
+            else {
+                let mut bucket = TokenBucket::full(limit, now);
+                let decision = bucket.take(limit, now);
+                // the unique index on the key rejects the bucket when a concurrent
+                // request has just created it, then that bucket is updated
+                match RateLimitBucketBuilder::new(database_service.clone())
+                    .key(key.to_string())
+                    .tokens(bucket.tokens)
+                    .updated_at(bucket.updated_at)
+                    .build(None)
+                    .await
+                {
+                    Ok(_) => return Ok(decision),
+                    Err(DatabaseError::DuplicateKey(_)) => continue,
+                    Err(err) => return Err(err),
+                }
+            };
+
+            let mut bucket = TokenBucket {
+                tokens: *document.tokens(),
+                updated_at: *document.updated_at(),
+            };
+            let decision = bucket.take(limit, now);
+            // a limited request does not change the bucket, the refill is
+            // computed again from the stored update time
+            if decision != RateLimitDecision::Allowed {
+                return Ok(decision);
+            }
+            match database_service
+                .update_one_versioned::<RateLimitBucket>(
+                    *document.get_id(),
+                    document.version().into(),
+                    doc! { "$set": {
+                        "tokens": bucket.tokens,
+                        "updated_at": bson::DateTime::from_chrono(bucket.updated_at),
+                    } },
+                    None,
+                )
+                .await
+            {
+                Ok(_) => return Ok(decision),
+                Err(DatabaseError::VersionConflict { .. }) => continue,
+                Err(err) => return Err(err),
+            }
+        }
+        // the bucket is contended by many requests of the same client
+        warn!("Rate limit bucket {key} is updated concurrently, limiting the request");
+        Ok(RateLimitDecision::Limited {
+            retry_after: Duration::from_secs(1),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use bson::doc;
+    use chrono::{SubsecRound, Utc};
+    use futures::future::join_all;
+
+    use crate::{
+        enums::RateLimitStoreBackend,
+        model::RateLimitBucket,
+        service::{
+            database::{AppDatabaseService, DatabaseServiceTrait},
+            rate_limit::{
+                MEMORY_STORE_CAPACITY, MEMORY_STORE_EVICTION_BATCH, RateLimitDecision,
+                RateLimitService, RateLimitStore,
+            },
+        },
+        types::RateLimit,
+    };
+
+    /// Takes three tokens from a bucket of two tokens refilled every 20 seconds,
+    /// then checks the refill once the retry time is elapsed
+    async fn assert_token_bucket(backend: RateLimitStoreBackend) {
+        let service = RateLimitService::new(backend, Arc::new(AppDatabaseService::default()));
+        let limit = RateLimit::new(2, Duration::from_secs(20));
+        // the database keeps milliseconds, hence the time is rounded to get exact refills
+        let now = Utc::now().trunc_subsecs(0);
+
+        assert_eq!(
+            service.acquire("login:127.0.0.1", &limit, now).await,
+            RateLimitDecision::Allowed
+        );
+        assert_eq!(
+            service.acquire("login:127.0.0.1", &limit, now).await,
+            RateLimitDecision::Allowed
+        );
+        assert_eq!(
+            service.acquire("login:127.0.0.1", &limit, now).await,
+            RateLimitDecision::Limited {
+                retry_after: Duration::from_secs(10)
+            }
+        );
+        // other keys have their own bucket
+        assert_eq!(
+            service.acquire("login:10.0.0.1", &limit, now).await,
+            RateLimitDecision::Allowed
+        );
+
+        let later = now + Duration::from_secs(10);
+        assert_eq!(
+            service.acquire("login:127.0.0.1", &limit, later).await,
+            RateLimitDecision::Allowed
+        );
+        assert!(matches!(
+            service.acquire("login:127.0.0.1", &limit, later).await,
+            RateLimitDecision::Limited { .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_memory_store() {
+        assert_token_bucket(RateLimitStoreBackend::Memory).await;
+    }
+
+    #[tokio::test]
+    async fn test_database_store() {
+        assert_token_bucket(RateLimitStoreBackend::Database).await;
+    }
+
+    #[tokio::test]
+    async fn test_memory_store_evicts_least_recently_used() {
+        let service = RateLimitService::new(
+            RateLimitStoreBackend::Memory,
+            Arc::new(AppDatabaseService::default()),
+        );
+        let limit = RateLimit::new(1, Duration::from_secs(60));
+        let now = Utc::now();
+
+        for index in 0..MEMORY_STORE_CAPACITY {
+            let at = now + Duration::from_millis(index as u64);
+            service.acquire(&format!("key:{index}"), &limit, at).await;
+        }
+        // the first key becomes the most recently used one
+        let later = now + Duration::from_secs(20);
+        assert!(matches!(
+            service.acquire("key:0", &limit, later).await,
+            RateLimitDecision::Limited { .. }
+        ));
+        assert_eq!(
+            service.acquire("key:new", &limit, later).await,
+            RateLimitDecision::Allowed
+        );
+
+        let RateLimitStore::Memory(buckets) = &service.store else {
+            panic!("expected the memory store");
+        };
+        let buckets = buckets.lock().unwrap();
+        assert_eq!(
+            buckets.len(),
+            MEMORY_STORE_CAPACITY - MEMORY_STORE_EVICTION_BATCH + 1
+        );
+        assert!(buckets.contains_key("key:0"));
+        assert!(!buckets.contains_key("key:1"));
+        assert!(!buckets.contains_key(&format!("key:{MEMORY_STORE_EVICTION_BATCH}")));
+        assert!(buckets.contains_key(&format!("key:{}", MEMORY_STORE_EVICTION_BATCH + 1)));
+    }
+
+    #[tokio::test]
+    async fn test_database_store_creates_one_bucket_per_key() {
+        let database_service = Arc::new(AppDatabaseService::default());
+        database_service
+            .create_unique_index::<RateLimitBucket>("key")
+            .await
+            .unwrap();
+        let service =
+            RateLimitService::new(RateLimitStoreBackend::Database, database_service.clone());
+        let limit = RateLimit::new(10, Duration::from_secs(60));
+        let now = Utc::now();
+
+        let decisions =
+            join_all((0..3).map(|_| service.acquire("login:127.0.0.1", &limit, now))).await;
+        assert!(
+            decisions
+                .iter()
+                .all(|decision| *decision == RateLimitDecision::Allowed)
+        );
+        assert_eq!(
+            database_service
+                .count_documents::<RateLimitBucket>(doc! { "key": "login:127.0.0.1" })
+                .await
+                .unwrap(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_parse_rate_limit() {
+        assert_eq!(
+            "10/60".parse::<RateLimit>(),
+            Ok(RateLimit::new(10, Duration::from_secs(60)))
+        );
+        assert!("10".parse::<RateLimit>().is_err());
+        assert!("0/60".parse::<RateLimit>().is_err());
+    }
+}
@@ -0,0 +1,171 @@
+//! Telemetry module exports the spans of the application to an OpenTelemetry
+//! collector with the OTLP/HTTP protocol.
+//!
+//! The tracing spans, like the request span, the facade methods and the
+//! database operations, are converted to OpenTelemetry spans by the layer
+//! returned by `telemetry_layer`. The request span continues the trace
+//! propagated by the caller with the W3C `traceparent` header.
+
+use axum::http::HeaderMap;
+use opentelemetry::{global, trace::TracerProvider};
+use opentelemetry_http::HeaderExtractor;
+use opentelemetry_otlp::{ExporterBuildError, SpanExporter, WithExportConfig};
+use opentelemetry_sdk::{
+    Resource,
+    propagation::TraceContextPropagator,
+    trace::{Sampler, SdkTracerProvider},
+};
+use tracing::{Span, Subscriber};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::{Layer, registry::LookupSpan};
+
+use crate::EnvironmentServiceTrait;
+
+/// Path of the traces endpoint of the OTLP/HTTP collector
+const OTLP_TRACES_PATH: &str = "/v1/traces";
+
+/// Builds the provider that exports the spans in batches to the collector,
+/// the traces are sampled with the given ratio unless the parent span is
+/// propagated by the caller, in that case its sampling decision is kept
+fn build_tracer_provider(
+    otlp_endpoint: &str,
+    service_name: &str,
+    sample_ratio: f64,
+) -> Result<SdkTracerProvider, ExporterBuildError> {
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(format!(
+            "{}{OTLP_TRACES_PATH}",
+            otlp_endpoint.trim_end_matches('/')
+        ))
+        .build()?;
+    Ok(SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_sampler(Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(
+            sample_ratio,
+        ))))
+        .with_resource(
+            Resource::builder()
+                .with_service_name(service_name.to_string())
+                .build(),
+        )
+        .build())
+}
+
+/// Builds the tracer provider defined by the telemetry settings and installs
+/// the W3C trace context propagator, it is `None` when the OTLP endpoint is
+/// not configured.
+///
+/// The provider must be shut down before the process exits to export
+/// the pending spans.
+pub fn init_tracer_provider(
+    environment_service: &dyn EnvironmentServiceTrait,
+) -> Result<Option<SdkTracerProvider>, ExporterBuildError> {
+    let Some(otlp_endpoint) = environment_service.get_telemetry_otlp_endpoint() else {
+        return Ok(None);
+    };
+    let provider = build_tracer_provider(
+        otlp_endpoint,
+        environment_service.get_telemetry_service_name(),
+        environment_service.get_telemetry_sample_ratio(),
+    )?;
+    global::set_text_map_propagator(TraceContextPropagator::new());
+    Ok(Some(provider))
+}
+
+/// Builds the layer that sends the tracing spans to the provider
+pub fn telemetry_layer<S>(provider: &SdkTracerProvider) -> Box<dyn Layer<S> + Send + Sync>
+where
+    S: Subscriber + for<'a> LookupSpan<'a> + Send + Sync,
+{
+    tracing_opentelemetry::layer()
+        .with_tracer(provider.tracer(env!("CARGO_PKG_NAME")))
+        .boxed()
+}
+
+/// Sets the parent of the request span to the trace context propagated
+/// in the request headers, it does nothing when the headers do not contain
+/// a valid `traceparent` or when the telemetry is not enabled
+pub fn set_remote_parent(span: &Span, headers: &HeaderMap) {
+    let context =
+        global::get_text_map_propagator(|propagator| propagator.extract(&HeaderExtractor(headers)));
+    // the span is not recorded by the telemetry layer when it is disabled
+    let _ = span.set_parent(context);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use axum::{
+        Router,
+        body::Bytes,
+        http::{HeaderMap, HeaderValue},
+        routing::post,
+    };
+    use opentelemetry::{global, trace::TraceContextExt};
+    use opentelemetry_sdk::propagation::TraceContextPropagator;
+    use tracing_opentelemetry::OpenTelemetrySpanExt;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::{build_tracer_provider, set_remote_parent, telemetry_layer};
+
+    /// Starts a stand-in of the OTLP collector that keeps the bodies
+    /// of the export requests, it returns the collector base URL
+    async fn start_collector() -> (String, Arc<Mutex<Vec<Bytes>>>) {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let router = Router::new().route(
+            "/v1/traces",
+            post({
+                let requests = requests.clone();
+                move |body: Bytes| async move {
+                    requests.lock().unwrap().push(body);
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router).await });
+        (format!("http://{address}/"), requests)
+    }
+
+    /// Returns whether the protobuf message contains the bytes
+    fn contains(message: &[u8], bytes: &[u8]) -> bool {
+        message.windows(bytes.len()).any(|window| window == bytes)
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_export_spans_with_remote_parent() {
+        let (endpoint, requests) = start_collector().await;
+        let provider = build_tracer_provider(&endpoint, "backend-test", 1.0).unwrap();
+        global::set_text_map_propagator(TraceContextPropagator::new());
+        let subscriber = tracing_subscriber::registry().with(telemetry_layer(&provider));
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "traceparent",
+            HeaderValue::from_static("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01"),
+        );
+        let trace_id = tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!("request");
+            set_remote_parent(&span, &headers);
+            let _entered = span.enter();
+            tracing::info_span!("database", otel.name = "find_one user").in_scope(|| {});
+            span.context().span().span_context().trace_id()
+        });
+        assert_eq!(trace_id.to_string(), "4bf92f3577b34da6a3ce929d0e0e4736");
+
+        // the exporter runs in its own thread with a blocking client
+        tokio::task::spawn_blocking(move || provider.shutdown())
+            .await
+            .unwrap()
+            .unwrap();
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 1);
+        let message = &requests[0];
+        assert!(contains(message, b"backend-test"));
+        assert!(contains(message, b"find_one user"));
+        assert!(contains(message, &trace_id.to_bytes()));
+    }
+}
@@ -0,0 +1,201 @@
+//! Test utilities used by the end to end tests of the routers.
+//!
+//! `TestApp` builds the whole application with `build_app`, the in memory
+//! database and the environment service built from `test_config`, then
+//! requests are sent with `tower::ServiceExt::oneshot` without binding any port.
+//!
+//! The environment service is the one of the server built with
+//! `EnvironmentService::from_config(test_config())`, it replaced the test
+//! implementation of `EnvironmentServiceTrait` so that the tests exercise the
+//! same validation and defaults. Tests that need other settings modify the
+//! configuration and pass it to `TestApp::with_config`.
+
+use std::sync::Arc;
+
+use axum::{
+    Router,
+    body::Body,
+    http::{HeaderMap, Method, Request, StatusCode, header},
+};
+use tower::ServiceExt;
+
+use crate::{
+    AppConfig, AppState, EnvironmentService,
+    model::UserBuilder,
+    router::build_app,
+    service::database::{AppDatabaseService, document::DatabaseDocumentTrait},
+    utils::hash_password,
+};
+
+/// Configuration of the application under test, it does not read any
+/// environment variable and uses the in memory database
+pub fn test_config() -> AppConfig {
+    AppConfig::from_toml_str(
+        r#"
+        [logging]
+        level = "DEBUG"
+
+        [authentication]
+        jwt_secret = "test-secret"
+        jwt_expiration = 3600
+
+        [database]
+        backend = "memory"
+
+        [storage]
+        backend = "LocalFileSystem"
+        prefix_path = "../app-objects"
+
+        [frontend]
+        mode = "external"
+        "#,
+    )
+    .expect("test configuration is valid TOML")
+}
+
+/// Response of a test request with the body parsed as JSON,
+/// the body is `Null` when it is empty or it is not JSON,
+/// in that case it can be read from `text`
+pub struct TestResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: serde_json::Value,
+    pub text: String,
+}
+
+/// User created by the test app with its session token
+pub struct TestUser {
+    pub user_id: String,
+    pub token: String,
+}
+
+/// Application under test backed by the in memory database
+pub struct TestApp {
+    pub state: Arc<AppState>,
+    router: Router,
+}
+
+impl TestApp {
+    pub fn new() -> Self {
+        Self::with_config(test_config())
+    }
+
+    /// Builds the application with a configuration derived from `test_config`
+    pub fn with_config(config: AppConfig) -> Self {
+        let state = Arc::new(AppState::new(
+            Box::new(EnvironmentService::from_config(config).expect("test configuration is valid")),
+            Arc::new(AppDatabaseService::default()),
+        ));
+        let router = build_app(state.clone());
+        Self { state, router }
+    }
+
+    /// Builds a request with the optional JSON body and bearer token
+    pub fn request(
+        method: Method,
+        uri: &str,
+        token: Option<&str>,
+        body: Option<serde_json::Value>,
+    ) -> axum::http::request::Builder {
+        let mut builder = Request::builder().method(method).uri(uri);
+        if let Some(token) = token {
+            builder = builder.header(header::AUTHORIZATION, format!("Bearer {token}"));
+        }
+        if body.is_some() {
+            builder = builder.header(header::CONTENT_TYPE, "application/json");
+        }
+        builder
+    }
+
+    /// Sends the request to the application and collects the response
+    pub async fn send(&self, request: Request<Body>) -> TestResponse {
+        let response = self
+            .router
+            .clone()
+            .oneshot(request)
+            .await
+            .expect("router is infallible");
+        let status = response.status();
+        let headers = response.headers().clone();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .expect("error in reading response body");
+        TestResponse {
+            status,
+            headers,
+            body: serde_json::from_slice(&bytes).unwrap_or(serde_json::Value::Null),
+            text: String::from_utf8_lossy(&bytes).into_owned(),
+        }
+    }
+
+    /// Sends a request with the optional JSON body and bearer token
+    pub async fn call(
+        &self,
+        method: Method,
+        uri: &str,
+        token: Option<&str>,
+        body: Option<serde_json::Value>,
+    ) -> TestResponse {
+        let request = Self::request(method, uri, token, body.clone())
+            .body(body.map_or(Body::empty(), |body| Body::from(body.to_string())))
+            .expect("error in building request");
+        self.send(request).await
+    }
+
+    /// Registers a new user with the guest API and returns its token
+    pub async fn register_user(&self, username: &str, password: &str) -> TestUser {
+        let response = self
+            .call(
+                Method::POST,
+                "/api/guest/register",
+                None,
+                Some(serde_json::json!({
+                    "firstName": "first name",
+                    "lastName": "last name",
+                    "username": username,
+                    "password": password,
+                })),
+            )
+            .await;
+        assert_eq!(response.status, StatusCode::OK, "{}", response.body);
+        Self::test_user(response)
+    }
+
+    /// Logs in the user with the guest API and returns its token
+    pub async fn login(&self, username: &str, password: &str) -> TestUser {
+        let response = self
+            .call(
+                Method::POST,
+                "/api/guest/login",
+                None,
+                Some(serde_json::json!({ "username": username, "password": password })),
+            )
+            .await;
+        assert_eq!(response.status, StatusCode::OK, "{}", response.body);
+        Self::test_user(response)
+    }
+
+    /// Creates an admin user directly in the database, since the API does not
+    /// allow it without another admin, and logs it in
+    pub async fn create_admin(&self, username: &str, password: &str) -> TestUser {
+        UserBuilder::new(self.state.database_service.clone())
+            .first_name("admin".into())
+            .last_name("admin".into())
+            .username(username.into())
+            .password_hash(hash_password(password).expect("error in hashing password"))
+            .admin(true)
+            .publisher(false)
+            .build(None)
+            .await
+            .map(|user| *user.get_id())
+            .expect("error in creating admin");
+        self.login(username, password).await
+    }
+
+    fn test_user(response: TestResponse) -> TestUser {
+        TestUser {
+            user_id: response.body["userId"].as_str().unwrap().to_string(),
+            token: response.body["token"].as_str().unwrap().to_string(),
+        }
+    }
+}
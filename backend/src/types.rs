@@ -1,12 +1,19 @@
-use std::sync::Arc;
+use std::{str::FromStr, sync::Arc, time::Duration};
 
 use axum::{
     extract::{FromRef, FromRequest, FromRequestParts},
-    http::request::Parts,
+    http::{header, request::Parts},
     response::{IntoResponse, Response},
 };
 
-use crate::{EnvironmentServiceTrait, error::AppError, service::database::MongoDBDatabaseService};
+use crate::{
+    EnvironmentServiceTrait,
+    error::AppError,
+    service::{
+        database::{AppDatabaseService, document::ExpectedVersion},
+        rate_limit::RateLimitService,
+    },
+};
 
 /// JSON extractor wrapping `axum::Json`.
 /// This makes it easy to override the rejection and provide our
@@ -26,6 +33,187 @@ where
     }
 }
 
+/// JSON response with the `ETag` header containing the version
+/// of the returned document.
+///
+/// The version is the one of documents declared as `versioned` and it
+/// can be used by the client in the `If-Match` header of the following update.
+pub struct VersionedJson<T> {
+    pub version: i64,
+    pub body: T,
+}
+
+impl<T> VersionedJson<T> {
+    pub fn new(version: i64, body: T) -> Self {
+        Self { version, body }
+    }
+}
+
+impl<T> IntoResponse for VersionedJson<T>
+where
+    axum::Json<T>: IntoResponse,
+{
+    fn into_response(self) -> Response {
+        (
+            [(header::ETAG, format!("\"{}\"", self.version))],
+            axum::Json(self.body),
+        )
+            .into_response()
+    }
+}
+
+/// JSON response with the `Cache-Control` header, used by the public
+/// routes whose content is the same for every client and can be stored
+/// by browsers and shared caches for `max_age` seconds.
+pub struct CachedJson<T> {
+    pub max_age: u64,
+    pub body: T,
+}
+
+impl<T> CachedJson<T> {
+    pub fn new(max_age: u64, body: T) -> Self {
+        Self { max_age, body }
+    }
+}
+
+impl<T> IntoResponse for CachedJson<T>
+where
+    axum::Json<T>: IntoResponse,
+{
+    fn into_response(self) -> Response {
+        (
+            [(
+                header::CACHE_CONTROL,
+                format!("public, max-age={}", self.max_age),
+            )],
+            axum::Json(self.body),
+        )
+            .into_response()
+    }
+}
+
+/// Extractor of the `If-Match` header containing the versions of the
+/// document that the client expects to update.
+///
+/// The header contains either the wildcard `*`, matching any version, or a
+/// list of entity tags like `"1", "2"`. `If-Match` uses the strong comparison,
+/// hence weak tags like `W/"1"` never match and a header with only weak tags
+/// is rejected. A missing header is rejected with `428 Precondition Required`
+/// since the update must be conditional.
+pub struct IfMatchVersion(pub ExpectedVersion);
+
+impl<S> FromRequestParts<S> for IfMatchVersion
+where
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let mut values = parts.headers.get_all(header::IF_MATCH).iter().peekable();
+        if values.peek().is_none() {
+            return Err(AppError::PreconditionRequired(
+                "Missing If-Match header with the current version".into(),
+            ));
+        }
+
+        let mut any = false;
+        let mut versions = vec![];
+        for value in values {
+            let value = value
+                .to_str()
+                .map_err(|_| AppError::InvalidRequest("Invalid If-Match header".into()))?;
+            if value.trim() == "*" {
+                any = true;
+                continue;
+            }
+            let tags = parse_entity_tags(value).ok_or(AppError::InvalidRequest(format!(
+                "Invalid If-Match header `{value}`"
+            )))?;
+            // weak tags never match with the strong comparison
+            for (_, tag) in tags.into_iter().filter(|(weak, _)| !weak) {
+                versions.push(tag.parse::<i64>().map_err(|_| {
+                    AppError::InvalidRequest(format!("Invalid version `{tag}` in If-Match header"))
+                })?);
+            }
+        }
+
+        match (any, versions.is_empty()) {
+            (true, true) => Ok(IfMatchVersion(ExpectedVersion::Any)),
+            (true, false) => Err(AppError::InvalidRequest(
+                "If-Match header cannot contain both `*` and entity tags".into(),
+            )),
+            (false, true) => Err(AppError::InvalidRequest(
+                "If-Match header must contain strong entity tags".into(),
+            )),
+            (false, false) => Ok(IfMatchVersion(ExpectedVersion::OneOf(versions))),
+        }
+    }
+}
+
+/// Splits a list of entity tags like `"1", W/"2"` returning for each tag
+/// whether it is weak and its value, or None if the list is malformed
+fn parse_entity_tags(value: &str) -> Option<Vec<(bool, &str)>> {
+    let mut tags = vec![];
+    let mut rest = value;
+    loop {
+        rest = rest.trim_start_matches([' ', '\t', ',']);
+        if rest.is_empty() {
+            return Some(tags);
+        }
+        let (weak, tag) = match rest.strip_prefix("W/") {
+            Some(tag) => (true, tag),
+            None => (false, rest),
+        };
+        let (tag, remaining) = tag.strip_prefix('"')?.split_once('"')?;
+        tags.push((weak, tag));
+        // the following tag must be separated by a comma
+        rest = remaining.trim_start();
+        if !rest.is_empty() && !rest.starts_with(',') {
+            return None;
+        }
+    }
+}
+
+/// Number of requests allowed in a period of time, written as
+/// `<requests>/<seconds>` in the configuration, like `10/60`.
+///
+/// It is enforced with a token bucket of `requests` tokens refilled
+/// at a constant rate, hence bursts up to `requests` are allowed.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct RateLimit {
+    pub requests: u32,
+    pub period: Duration,
+}
+
+impl RateLimit {
+    pub fn new(requests: u32, period: Duration) -> Self {
+        Self { requests, period }
+    }
+
+    /// Tokens added to the bucket each second
+    pub fn refill_rate(&self) -> f64 {
+        f64::from(self.requests) / self.period.as_secs_f64()
+    }
+}
+
+impl FromStr for RateLimit {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (requests, seconds) = s
+            .split_once('/')
+            .ok_or(format!("`{s}` is not in the format <requests>/<seconds>"))?;
+        let requests = u32::from_str(requests.trim()).map_err(|err| err.to_string())?;
+        let seconds = u64::from_str(seconds.trim()).map_err(|err| err.to_string())?;
+        if requests == 0 || seconds == 0 {
+            return Err(format!(
+                "requests and seconds of `{s}` must be greater than zero"
+            ));
+        }
+        Ok(Self::new(requests, Duration::from_secs(seconds)))
+    }
+}
+
 /// Application global variables that defines the common state
 /// each request can access without creating new objects
 ///
@@ -33,27 +221,32 @@ where
 /// it is recommended to use dyn trait to leverage on dependency injection
 ///
 /// However, DatabaseServiceTrait cannot be used with dyn because it contains
-/// generics. Therefore, we must use generic as well.
-/// Since introducing generic in the AppState creates a lot (really a lot)
-/// problems in the handlers, JWTAuth and so on because the trait is not dyn,
-/// send or other things, and since, we always use the current database service,
-/// instead of using a generic we use directly the struct.
+/// generics and introducing a generic in the AppState creates a lot of problems
+/// in the handlers, JWTAuth and so on.
+/// Therefore, the state contains the `AppDatabaseService` enum that dispatches
+/// the operations to the database service selected at runtime, either MongoDB
+/// or the in memory one.
 ///
-/// For testing purposes, we just inject another database service (the in memory)
-/// at Service level. The state is just used a Router level so there is not limitation.
+/// The rate limit service keeps the token buckets shared by all the requests.
 pub struct AppState {
     pub environment_service: Box<dyn EnvironmentServiceTrait>,
-    pub database_service: Arc<MongoDBDatabaseService>,
+    pub database_service: Arc<AppDatabaseService>,
+    pub rate_limit_service: RateLimitService,
 }
 
 impl AppState {
     pub fn new(
         environment_service: Box<dyn EnvironmentServiceTrait>,
-        database_service: Arc<MongoDBDatabaseService>,
+        database_service: Arc<AppDatabaseService>,
     ) -> AppState {
+        let rate_limit_service = RateLimitService::new(
+            *environment_service.get_rate_limit_store(),
+            database_service.clone(),
+        );
         AppState {
             environment_service,
             database_service,
+            rate_limit_service,
         }
     }
 }
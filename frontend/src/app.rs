@@ -1,12 +1,27 @@
+use serde::{Deserialize, Serialize};
+
 use crate::{
-    component::{footer::Footer, header::Header},
+    component::{footer::Footer, header::Header, route_guard::RouteGuard, session::Session},
     environment::EnvironmentService,
-    page::{admin::Admin, home::Home, login::Login, not_found::NotFound, register::Register},
+    model::LoggedUserInfo,
+    page::{
+        admin::Admin, home::Home, login::Login, not_found::NotFound, post::Post, register::Register,
+    },
     service::auth::AuthService,
-    types::AppContext,
+    types::{AppContext, PreloadedData},
+};
+#[cfg(feature = "ssr")]
+use yew::AttrValue;
+use yew::{
+    ContextProvider, Html, Properties, UseStateHandle, function_component, html, use_effect_with,
+    use_state,
 };
-use yew::{ContextProvider, Html, UseStateHandle, function_component, html, use_state};
 use yew_router::{BrowserRouter, Routable, Switch};
+#[cfg(feature = "ssr")]
+use yew_router::{
+    Router,
+    history::{AnyHistory, History, MemoryHistory},
+};
 
 #[derive(Routable, Debug, Clone, PartialEq, Eq)]
 pub enum AppRoute {
@@ -18,48 +33,138 @@ pub enum AppRoute {
     Home,
     #[at("/admin")]
     Admin,
+    #[at("/post/:id")]
+    Post { id: String },
     #[not_found]
     #[at("/not-found")]
     NotFound,
 }
 
+/// Users that can access a route, it is enforced by `RouteGuard`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RouteAccess {
+    /// Any user, logged or not
+    Public,
+    /// Only users that are not logged, e.g. the login page
+    Guest,
+    /// Only logged users
+    User,
+    /// Only logged users with the admin role
+    Admin,
+}
+
+impl RouteAccess {
+    /// Whether the user, None if not logged, can access the route
+    pub fn allows(&self, user_info: Option<&LoggedUserInfo>) -> bool {
+        match self {
+            RouteAccess::Public => true,
+            RouteAccess::Guest => user_info.is_none(),
+            RouteAccess::User => user_info.is_some(),
+            RouteAccess::Admin => user_info.is_some_and(|user_info| user_info.admin),
+        }
+    }
+}
+
+impl AppRoute {
+    pub fn access(&self) -> RouteAccess {
+        match self {
+            AppRoute::Register | AppRoute::Login => RouteAccess::Guest,
+            AppRoute::Admin => RouteAccess::Admin,
+            AppRoute::Home | AppRoute::Post { .. } | AppRoute::NotFound => RouteAccess::Public,
+        }
+    }
+}
+
+/// Query of the login page, `redirect` is the path of the page shown after the login
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct LoginQuery {
+    pub redirect: Option<String>,
+}
+
 fn switch(route: AppRoute) -> Html {
-    match route {
+    let page = match route.clone() {
         AppRoute::Home => html! { <Home /> },
         AppRoute::Login => html! { <Login /> },
         AppRoute::Register => html! { <Register /> },
         AppRoute::Admin => html! { <Admin /> },
+        AppRoute::Post { id } => html! { <Post {id} /> },
         AppRoute::NotFound => html! { <NotFound /> },
+    };
+    html! {
+        <RouteGuard {route}>{page}</RouteGuard>
     }
 }
 
+#[derive(Properties, PartialEq, Default)]
+pub struct AppProps {
+    /// Data embedded in the page by the server side rendering
+    #[prop_or_default]
+    pub preloaded: PreloadedData,
+}
+
+/// Application rendered in the browser
 #[function_component(App)]
-pub fn app() -> Html {
+pub fn app(AppProps { preloaded }: &AppProps) -> Html {
+    html! {
+        <BrowserRouter>
+            <AppContent preloaded={preloaded.clone()}/>
+        </BrowserRouter>
+    }
+}
+
+#[cfg(feature = "ssr")]
+#[derive(Properties, PartialEq)]
+pub struct ServerAppProps {
+    /// Path and query of the requested page
+    pub url: AttrValue,
+    pub preloaded: PreloadedData,
+}
+
+/// Application rendered by the server, the browser history is replaced by
+/// an in memory one starting from the requested page
+#[cfg(feature = "ssr")]
+#[function_component(ServerApp)]
+pub fn server_app(ServerAppProps { url, preloaded }: &ServerAppProps) -> Html {
+    let history = AnyHistory::from(MemoryHistory::new());
+    history.push(url.as_str());
+
+    html! {
+        <Router {history}>
+            <AppContent preloaded={preloaded.clone()}/>
+        </Router>
+    }
+}
+
+/// Content of the application shared by the client and the server rendering,
+/// the server renders it as a guest because the token is in the browser storage
+#[function_component(AppContent)]
+fn app_content(AppProps { preloaded }: &AppProps) -> Html {
     /* According to documentation https://yew.rs/docs/concepts/contexts
     we create a state handle to contain general context that will be used
     by the application */
-    let app_context = use_state(|| AppContext::new(None));
-
-    let environment_service = EnvironmentService::new();
+    let app_context = use_state(AppContext::loading);
 
-    // Use the AuthService to load logged user info from local storage
-    // it will update the provided context with user info if they are present and valid
-    AuthService::new(
-        environment_service.token_storage_location_name,
-        app_context.clone(),
-    )
-    .set_logged_user_info_from_storage();
+    {
+        // Use the AuthService to load logged user info from local storage once
+        // it will update the provided context with user info if they are present and valid
+        let app_context = app_context.clone();
+        use_effect_with((), move |_| {
+            let environment_service = EnvironmentService::new();
+            AuthService::new(environment_service.token_storage_location_name, app_context)
+                .set_logged_user_info_from_storage();
+        });
+    }
 
     html! {
-        <BrowserRouter>
-            <ContextProvider<UseStateHandle<AppContext>> context={app_context}>
+        <ContextProvider<UseStateHandle<AppContext>> context={app_context}>
+            <ContextProvider<PreloadedData> context={preloaded.clone()}>
+                <Session/>
                 <Header/>
                 <main>
                     <Switch<AppRoute> render={switch}/>
                 </main>
                 <Footer/>
-            </ContextProvider<UseStateHandle<AppContext>>>
-        </BrowserRouter>
-
+            </ContextProvider<PreloadedData>>
+        </ContextProvider<UseStateHandle<AppContext>>>
     }
 }
@@ -1,6 +1,9 @@
+pub mod audit_list;
 pub mod footer;
 pub mod header;
 pub mod post_details;
 pub mod post_form;
 pub mod post_list;
+pub mod route_guard;
+pub mod session;
 pub mod user_list;
@@ -0,0 +1,52 @@
+use yew::{Html, Properties, function_component, html};
+
+use crate::model::AuditEvent;
+
+#[derive(Properties, PartialEq)]
+pub struct AuditListProp {
+    pub events: Vec<AuditEvent>,
+}
+
+fn format_state(state: &Option<serde_json::Value>) -> String {
+    state
+        .as_ref()
+        .map(|value| value.to_string())
+        .unwrap_or_else(|| "-".into())
+}
+
+#[function_component(AuditList)]
+pub fn audit_list(AuditListProp { events }: &AuditListProp) -> Html {
+    let html_events = events
+        .iter()
+        .map(|event| {
+            html! {
+                <tr key={event.id.clone()} class="audit-list-entry">
+                    <td>{event.creation_date.format("%Y-%m-%d %H:%M:%S").to_string()}</td>
+                    <td>{&event.actor_id}</td>
+                    <td>{&event.action}</td>
+                    <td>{format!("{}/{}", event.target_collection, event.target_id)}</td>
+                    <td>{format_state(&event.before)}</td>
+                    <td>{format_state(&event.after)}</td>
+                </tr>
+            }
+        })
+        .collect::<Html>();
+
+    html! {
+        <table class="audit-list">
+            <thead>
+                <tr>
+                    <th>{"Date"}</th>
+                    <th>{"Actor"}</th>
+                    <th>{"Action"}</th>
+                    <th>{"Target"}</th>
+                    <th>{"Before"}</th>
+                    <th>{"After"}</th>
+                </tr>
+            </thead>
+            <tbody>
+                {html_events}
+            </tbody>
+        </table>
+    }
+}
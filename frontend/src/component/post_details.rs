@@ -1,6 +1,6 @@
 use yew::{Html, Properties, function_component, html};
 
-use crate::model::BlogPost;
+use crate::model::{BlogPost, PostVisibility};
 
 #[derive(Properties, PartialEq)]
 pub struct PostDetailProps {
@@ -12,7 +12,10 @@ pub fn post_details(PostDetailProps { post }: &PostDetailProps) -> Html {
     html! {
         <div class="blog-post-details">
             <h4>{post.title.clone()}</h4>
-            <i>{format!("written by {} on {}", post.creator_username, post.creation_date)}</i>
+            <i>{format!("written by {} on {}", post.creator_username, post.creation_date.format("%Y/%m/%d"))}</i>
+            if post.visibility == PostVisibility::MembersOnly {
+                <i>{" - members only"}</i>
+            }
             <p class="blog-post-content">{post.content.clone()}</p>
         </div>
     }
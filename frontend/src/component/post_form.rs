@@ -6,8 +6,9 @@ use yew::{
 };
 
 use crate::{
-    enums::HttpStatus, environment::EnvironmentService, model::LoggedUserInfo,
-    service::api::ApiService, types::ApiResponse,
+    environment::EnvironmentService,
+    model::{LoggedUserInfo, PostVisibility},
+    service::api::{ApiService, ApiServiceTrait},
 };
 
 #[derive(Properties, PartialEq)]
@@ -30,11 +31,13 @@ pub fn post_form(
 
     let title_node_ref = use_node_ref();
     let content_node_ref = use_node_ref();
+    let members_only_node_ref = use_node_ref();
     let form_error: UseStateHandle<Option<String>> = use_state(|| None);
 
     let onsubmit = {
         let title_node_ref = title_node_ref.clone();
         let content_node_ref = content_node_ref.clone();
+        let members_only_node_ref = members_only_node_ref.clone();
         let form_error = form_error.clone();
         let user_info = user_info.clone();
         let on_close = on_close.clone();
@@ -51,6 +54,14 @@ pub fn post_form(
             let content: Option<String> = content_node_ref
                 .cast::<HtmlInputElement>()
                 .map(|content| content.value());
+            let members_only = members_only_node_ref
+                .cast::<HtmlInputElement>()
+                .is_some_and(|members_only| members_only.checked());
+            let visibility = if members_only {
+                PostVisibility::MembersOnly
+            } else {
+                PostVisibility::Public
+            };
 
             if let (Some(title), Some(content)) = (title, content) {
                 info!(
@@ -60,30 +71,16 @@ pub fn post_form(
                 );
                 wasm_bindgen_futures::spawn_local(async move {
                     let environment_service = EnvironmentService::new();
-                    let api_service = ApiService::new(
-                        environment_service.api_url,
-                        environment_service.mock,
-                        Some(user_info.token),
-                    );
-                    let api_response: Result<ApiResponse<()>, crate::error::ApiError> =
-                        api_service.publish_post(title, content).await;
-
-                    if let Ok(ApiResponse { body: _, status }) = api_response {
-                        match status {
-                            HttpStatus::Success(_) => {
-                                on_close.emit(());
-                                form_error.set(None);
-                            }
-                            _ => {
-                                form_error.set(Some(format!("Got error from backend: {status}")));
-                            }
+                    let api_service = ApiService::new(&environment_service, Some(user_info.token));
+                    match api_service.publish_post(title, content, visibility).await {
+                        Ok(_) => {
+                            on_close.emit(());
+                            form_error.set(None);
+                        }
+                        Err(err) => {
+                            error!("Encountered an error in publish post request. Error {err}");
+                            form_error.set(Some(err.to_string()));
                         }
-                    } else {
-                        error!(
-                            "Encountered an error in publish post request. Error {err}",
-                            err = api_response.err().unwrap()
-                        );
-                        form_error.set(Some("Got error from backend".to_string()));
                     }
                 });
             }
@@ -97,6 +94,10 @@ pub fn post_form(
                 <div class="blog-post-form-input-container">
                     <input placeholder="title" ref={title_node_ref}/>
                     <textarea type="text" placeholder="content" ref={content_node_ref}/>
+                    <label>
+                        <input type="checkbox" ref={members_only_node_ref}/>
+                        {"Only for the members"}
+                    </label>
                     if let Some(error_msg) = (*form_error).clone() {
                         <p style="color:red">{error_msg}</p>
                     }
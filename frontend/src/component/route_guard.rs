@@ -0,0 +1,94 @@
+use log::error;
+use yew::{
+    Html, Properties, UseStateHandle, function_component, html, use_context, use_effect_with,
+};
+use yew_router::{
+    Routable,
+    prelude::{use_location, use_navigator},
+};
+
+use crate::{
+    app::{AppRoute, LoginQuery, RouteAccess},
+    types::AppContext,
+};
+
+#[derive(Properties, PartialEq)]
+pub struct RouteGuardProps {
+    pub route: AppRoute,
+    pub children: Html,
+}
+
+/// Where the user is sent when the page cannot be shown
+#[derive(Clone, PartialEq)]
+enum GuardRedirect {
+    /// The login page, it returns to `redirect` after the login
+    Login {
+        redirect: String,
+    },
+    Route(AppRoute),
+}
+
+/// Renders the page of the route only if the logged user can access it,
+/// according to `AppRoute::access`, otherwise it redirects:
+///
+/// - guests are sent to the login page, that returns to the current page after the login
+/// - logged users are sent to the page they were redirected from by the login or to the home
+///   when they open a guest page
+/// - users without the admin role are sent to the not found page when they open an admin page
+///
+/// A loading message is shown while the stored token is validated, so that the pages
+/// are not redirected before knowing the logged user, except for the public pages
+#[function_component(RouteGuard)]
+pub fn route_guard_component(RouteGuardProps { route, children }: &RouteGuardProps) -> Html {
+    let app_context = use_context::<UseStateHandle<AppContext>>().expect("No app_context found");
+    let navigator = use_navigator().expect("RouteGuard must be inside a router");
+    let location = use_location().expect("RouteGuard must be inside a router");
+
+    let user_info = app_context.user_info.as_ref();
+    // the public pages do not depend on the user, hence they are rendered by the server too
+    let loading = app_context.loading && route.access() != RouteAccess::Public;
+    let redirect = match route.access() {
+        _ if loading => None,
+        access if access.allows(user_info) => None,
+        RouteAccess::Guest => {
+            let route = location
+                .query::<LoginQuery>()
+                .ok()
+                .and_then(|query| query.redirect)
+                .and_then(|path| AppRoute::recognize(&path))
+                .filter(|route| route.access().allows(user_info))
+                .unwrap_or(AppRoute::Home);
+            Some(GuardRedirect::Route(route))
+        }
+        _ if user_info.is_none() => Some(GuardRedirect::Login {
+            redirect: location.path().to_string(),
+        }),
+        _ => Some(GuardRedirect::Route(AppRoute::NotFound)),
+    };
+
+    {
+        // the current page is replaced to avoid going back to it
+        use_effect_with(redirect.clone(), move |redirect| match redirect {
+            Some(GuardRedirect::Login { redirect }) => {
+                let query = LoginQuery {
+                    redirect: Some(redirect.clone()),
+                };
+                if let Err(err) = navigator.replace_with_query(&AppRoute::Login, &query) {
+                    error!("Failed to redirect to the login page. Error {err}");
+                }
+            }
+            Some(GuardRedirect::Route(route)) => navigator.replace(route),
+            None => {}
+        });
+    }
+
+    if loading {
+        html! {
+            <div class="page-centered">{"Loading..."}</div>
+        }
+    } else if redirect.is_some() {
+        html! {}
+    } else {
+        children.clone()
+    }
+}
@@ -0,0 +1,67 @@
+use gloo_timers::callback::Timeout;
+use web_sys::AbortController;
+use yew::{Callback, Html, UseStateHandle, function_component, html, use_context, use_effect_with};
+
+use crate::{
+    environment::EnvironmentService,
+    service::{api::set_unauthorized_handler, auth::AuthService},
+    types::AppContext,
+};
+
+/// Keeps the session of the logged user, it renders nothing.
+///
+/// It refreshes the token before it expires and, when the backend rejects the
+/// token, it logs the user out. Then the route guard of the current page
+/// redirects to the login page, that returns to it after the login.
+///
+/// It must be placed inside the application context provider
+#[function_component(Session)]
+pub fn session_component() -> Html {
+    let app_context = use_context::<UseStateHandle<AppContext>>().expect("No app_context found");
+
+    {
+        let app_context = app_context.clone();
+        use_effect_with((), move |_| {
+            set_unauthorized_handler(Some(Callback::from(move |_| {
+                let environment_service = EnvironmentService::new();
+                AuthService::new(
+                    environment_service.token_storage_location_name,
+                    app_context.clone(),
+                )
+                .remove_logged_user();
+            })));
+            || set_unauthorized_handler(None)
+        });
+    }
+
+    {
+        // a new refresh is scheduled each time the token changes
+        let token = app_context
+            .user_info
+            .as_ref()
+            .map(|user_info| user_info.token.clone());
+        use_effect_with(token, move |token| {
+            let abort_controller =
+                AbortController::new().expect("Failed to create abort controller");
+            let refresh = token
+                .as_deref()
+                .and_then(AuthService::token_refresh_delay)
+                .map(|delay| {
+                    let environment_service = EnvironmentService::new();
+                    let auth_service = AuthService::new(
+                        environment_service.token_storage_location_name,
+                        app_context,
+                    );
+                    let abort_signal = abort_controller.signal();
+                    Timeout::new(delay, move || auth_service.refresh_token(abort_signal))
+                });
+            // the pending refresh is dropped when the user logs out
+            move || {
+                drop(refresh);
+                abort_controller.abort();
+            }
+        });
+    }
+
+    html! {}
+}
@@ -1,10 +1,10 @@
 use yew::{Html, Properties, function_component, html};
 
-use crate::model::UserInfo;
+use crate::model::User;
 
 #[derive(Properties, PartialEq)]
 pub struct UsersListProp {
-    pub users: Vec<UserInfo>,
+    pub users: Vec<User>,
 }
 
 #[function_component(UsersList)]
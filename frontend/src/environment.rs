@@ -8,6 +8,10 @@ use dotenv_codegen::dotenv;
 pub struct EnvironmentService {
     pub api_url: String,
     pub mock: bool,
+    /// Latency added to each mocked response
+    pub mock_latency_ms: u32,
+    /// Probability, between 0 and 1, that a mocked request fails with an internal server error
+    pub mock_failure_rate: f64,
     pub token_storage_location_name: String,
 }
 
@@ -16,6 +20,12 @@ impl EnvironmentService {
         Self {
             api_url: dotenv!("FE_API_URL").to_string(),
             mock: dotenv!("FE_MOCK").to_lowercase() == "true",
+            mock_latency_ms: dotenv!("FE_MOCK_LATENCY_MS")
+                .parse()
+                .expect("FE_MOCK_LATENCY_MS must be a number of milliseconds"),
+            mock_failure_rate: dotenv!("FE_MOCK_FAILURE_RATE")
+                .parse()
+                .expect("FE_MOCK_FAILURE_RATE must be a number between 0 and 1"),
             token_storage_location_name: dotenv!("FE_TOKEN_STORAGE_LOCATION_NAME").to_string(),
         }
     }
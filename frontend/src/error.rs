@@ -30,15 +30,20 @@ pub enum ApiError {
     /// Generic request error for all other cases
     #[error("Generic request error: {0}")]
     GenericRequestError(String),
+    /// The request has been aborted, e.g. the component that sent it was destroyed
+    #[error("The request has been cancelled")]
+    Cancelled,
     /// Request error from the gloo library
     #[error("Request error: {0}")]
     RequestError(#[from] gloo_net::Error),
 }
 
 impl ApiError {
+    /// Maps the status of a response to the error, `msg` is the message of
+    /// the error body. It is None for the successful statuses
     pub fn from_status(status: u16, msg: String) -> Option<Self> {
         match status {
-            400 => Some(ApiError::AccessControlError(msg)),
+            400 => Some(ApiError::InvalidRequest(msg)),
             401 => Some(ApiError::AuthorizationError(msg)),
             403 => Some(ApiError::AccessControlError(msg)),
             404 => Some(ApiError::DoesNotExist(msg)),
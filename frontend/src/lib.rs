@@ -6,4 +6,6 @@ pub mod error;
 pub mod model;
 pub mod page;
 pub mod service;
+#[cfg(feature = "ssr")]
+pub mod ssr;
 pub mod types;
@@ -1,6 +1,17 @@
-use frontend::app::App;
+use frontend::{
+    app::{App, AppProps},
+    types::PreloadedData,
+};
 
 fn main() {
     wasm_logger::init(wasm_logger::Config::default());
-    yew::Renderer::<App>::new().render();
+    // the pages rendered by the server embed their data and they are hydrated
+    match PreloadedData::from_document() {
+        Some(preloaded) => {
+            yew::Renderer::<App>::with_props(AppProps { preloaded }).hydrate();
+        }
+        None => {
+            yew::Renderer::<App>::new().render();
+        }
+    }
 }
@@ -1,5 +1,12 @@
 use serde::{Deserialize, Serialize};
 
+// request and response bodies are shared with the backend
+pub use api_types::{
+    admin::{AuditEvent, AuditEventPage, User},
+    guest::{JWTAuthPayload, LoggedUserInfoResponse, RegisterInfo},
+    user::{BlogPost, BlogPostChange, PostVisibility, PublishPost},
+};
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct JWTAuthClaim {
     pub exp: u32,
@@ -7,32 +14,6 @@ pub struct JWTAuthClaim {
     pub username: String,
 }
 
-#[derive(Clone, PartialEq, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct BlogPost {
-    pub id: String,
-    pub title: String,
-    pub content: String,
-    pub creation_date: String,
-    pub creator_id: String,
-    pub creator_username: String,
-}
-
-#[derive(Clone, PartialEq, Serialize)]
-#[serde(rename_all = "camelCase")]
-pub struct RegisterInfo {
-    pub first_name: String,
-    pub last_name: String,
-    pub username: String,
-    pub password: String,
-}
-
-#[derive(Clone, PartialEq, Serialize)]
-pub struct LoginInfo {
-    pub username: String,
-    pub password: String,
-}
-
 #[derive(Clone, PartialEq, Deserialize, Debug)]
 pub struct LoggedUserInfo {
     pub token: String,
@@ -40,28 +21,3 @@ pub struct LoggedUserInfo {
     pub username: String,
     pub admin: bool,
 }
-
-#[derive(Clone, PartialEq, Deserialize, Debug)]
-#[serde(rename_all = "camelCase")]
-pub struct LoggedUserInfoResponse {
-    #[serde(default)]
-    pub token: Option<String>,
-    pub user_id: String,
-    pub username: String,
-    pub admin: bool,
-}
-
-#[derive(Clone, PartialEq, Deserialize, Debug)]
-#[serde(rename_all = "camelCase")]
-pub struct UserInfo {
-    pub user_id: String,
-    pub username: String,
-    pub admin: bool,
-}
-
-#[derive(Serialize, Clone)]
-#[serde(rename_all = "camelCase")]
-pub struct PublishPostRequest {
-    pub title: String,
-    pub content: String,
-}
@@ -2,4 +2,5 @@ pub mod admin;
 pub mod home;
 pub mod login;
 pub mod not_found;
+pub mod post;
 pub mod register;
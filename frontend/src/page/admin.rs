@@ -1,65 +1,119 @@
 use log::error;
+use web_sys::AbortController;
 use yew::{
-    Html, UseStateHandle, function_component, html, use_context, use_effect_with, use_state,
+    Callback, Html, UseStateHandle, function_component, html, use_context, use_effect_with,
+    use_state,
 };
-use yew_router::prelude::Redirect;
 
 use crate::{
-    app::AppRoute,
-    component::user_list::UsersList,
-    enums::HttpStatus,
+    component::{audit_list::AuditList, user_list::UsersList},
     environment::EnvironmentService,
-    model::{LoggedUserInfo, UserInfo},
-    service::api::ApiService,
+    error::ApiError,
+    model::{AuditEventPage, LoggedUserInfo, User},
+    service::api::{ApiService, ApiServiceTrait},
     types::{ApiResponse, AppContext},
 };
 
+/// Number of audit events shown in each page of the admin panel
+const AUDIT_PAGE_SIZE: u64 = 20;
+
 #[function_component(Admin)]
 pub fn admin_component() -> Html {
     let app_context = use_context::<UseStateHandle<AppContext>>().expect("No app_context found");
     let user_info: UseStateHandle<Option<LoggedUserInfo>> = use_state(|| None);
-    let users: UseStateHandle<Vec<UserInfo>> = use_state(Vec::new);
+    let users: UseStateHandle<Vec<User>> = use_state(Vec::new);
     let get_users_error = use_state(|| None);
+    let audit_page: UseStateHandle<AuditEventPage> = use_state(AuditEventPage::default);
+    let audit_page_number = use_state(|| 1_u64);
+    let get_audit_error = use_state(|| None);
 
     {
         let users = users.clone();
         use_effect_with(user_info.clone(), move |user_info| {
             let users = users.clone();
             let user_info = user_info.clone();
+            // the pending request is aborted when the user changes or the page is left
+            let abort_controller =
+                AbortController::new().expect("Failed to create abort controller");
             if let Some(user_info) = (*user_info).clone() {
+                let environment_service = EnvironmentService::new();
+                let api_service = ApiService::new(&environment_service, Some(user_info.token))
+                    .with_abort_signal(abort_controller.signal());
                 wasm_bindgen_futures::spawn_local(async move {
-                    let environment_service = EnvironmentService::new();
-                    let api_service = ApiService::new(
-                        environment_service.api_url,
-                        environment_service.mock,
-                        Some(user_info.token),
-                    );
-                    let response = api_service.get_admin_users_list().await;
-                    if let Ok(ApiResponse { body, status }) = response {
-                        match status {
-                            HttpStatus::Success(_) => {
-                                users.set(body);
-                                get_users_error.set(None);
-                            }
-                            _ => {
-                                get_users_error.set(Some("Ops, something went wrong".into()));
-                            }
+                    match api_service.get_admin_users_list().await {
+                        Ok(ApiResponse { body, .. }) => {
+                            users.set(body);
+                            get_users_error.set(None);
+                        }
+                        Err(ApiError::Cancelled) => {}
+                        Err(err) => {
+                            error!("Encountered an error in get users list. Error {err}");
+                            get_users_error.set(Some(err.to_string()));
                         }
-                    } else {
-                        error!(
-                            "Encountered an error in get users list. Error {e}",
-                            e = response.err().unwrap()
-                        );
-                        get_users_error.set(Some("Got error from backend".to_string()));
                     }
                 });
             }
+            move || abort_controller.abort()
         });
     }
 
-    if let Some(context_user_info) = app_context.user_info.clone()
-        && context_user_info.admin
     {
+        let audit_page = audit_page.clone();
+        let get_audit_error = get_audit_error.clone();
+        use_effect_with(
+            (user_info.clone(), audit_page_number.clone()),
+            move |(user_info, audit_page_number)| {
+                let audit_page = audit_page.clone();
+                let page_number = **audit_page_number;
+                // changing page aborts the request of the previous one
+                let abort_controller =
+                    AbortController::new().expect("Failed to create abort controller");
+                if let Some(user_info) = (**user_info).clone() {
+                    let environment_service = EnvironmentService::new();
+                    let api_service = ApiService::new(&environment_service, Some(user_info.token))
+                        .with_abort_signal(abort_controller.signal());
+                    wasm_bindgen_futures::spawn_local(async move {
+                        let response = api_service
+                            .get_audit_events(page_number, AUDIT_PAGE_SIZE, None)
+                            .await;
+                        match response {
+                            Ok(ApiResponse { body, .. }) => {
+                                audit_page.set(body);
+                                get_audit_error.set(None);
+                            }
+                            Err(ApiError::Cancelled) => {}
+                            Err(err) => {
+                                error!("Encountered an error in get audit events. Error {err}");
+                                get_audit_error.set(Some(err.to_string()));
+                            }
+                        }
+                    });
+                }
+                move || abort_controller.abort()
+            },
+        );
+    }
+
+    let onclick_previous_page = {
+        let audit_page_number = audit_page_number.clone();
+        Callback::from(move |_| {
+            if *audit_page_number > 1 {
+                audit_page_number.set(*audit_page_number - 1);
+            }
+        })
+    };
+
+    let onclick_next_page = {
+        let audit_page_number = audit_page_number.clone();
+        let audit_page = audit_page.clone();
+        Callback::from(move |_| {
+            if *audit_page_number * AUDIT_PAGE_SIZE < audit_page.total {
+                audit_page_number.set(*audit_page_number + 1);
+            }
+        })
+    };
+
+    if let Some(context_user_info) = app_context.user_info.clone() {
         if user_info.is_none() {
             user_info.set(Some(context_user_info.clone()));
         }
@@ -71,11 +125,22 @@ pub fn admin_component() -> Html {
                     <h3>{"Users list:"}</h3>
                     <UsersList users={(*users).clone()}/>
                 </div>
+                <div class="admin-audit-list-container">
+                    <h3>{"Audit log:"}</h3>
+                    if let Some(message) = (*get_audit_error).clone() {
+                        <p style="color:red">{message}</p>
+                    }
+                    <AuditList events={audit_page.events.clone()}/>
+                    <div class="audit-list-pagination">
+                        <button class="form-button-secondary" onclick={onclick_previous_page}>{"Previous"}</button>
+                        <span>{format!("Page {} of {}", *audit_page_number, audit_page.total.div_ceil(AUDIT_PAGE_SIZE).max(1))}</span>
+                        <button class="form-button-secondary" onclick={onclick_next_page}>{"Next"}</button>
+                    </div>
+                </div>
             </div>
         }
     } else {
-        html! {
-            <Redirect<AppRoute> to={AppRoute::NotFound}/>
-        }
+        // the route guard shows the page only to the admin users
+        html! {}
     }
 }
@@ -1,63 +1,136 @@
 use log::error;
+use std::rc::Rc;
+
+use web_sys::AbortController;
 use yew::{
-    Callback, Html, UseStateHandle, function_component, html, use_context, use_effect_with,
-    use_state,
+    Callback, Html, Reducible, UseStateHandle, function_component, html, use_context,
+    use_effect_with, use_mut_ref, use_reducer, use_state,
 };
-use yew_router::prelude::Redirect;
 
 use crate::{
-    app::AppRoute,
     component::{post_details::PostDetails, post_form::PostForm, post_list::PostsList},
-    enums::HttpStatus,
     environment::EnvironmentService,
-    model::{BlogPost, LoggedUserInfo},
-    service::api::ApiService,
-    types::{ApiResponse, AppContext},
+    error::ApiError,
+    model::{BlogPost, BlogPostChange},
+    service::api::{ApiService, ApiServiceTrait},
+    types::{ApiResponse, AppContext, PreloadedData},
 };
 
+/// Actions that modify the list of blog posts displayed in the home page
+enum BlogPostsAction {
+    /// Replace the list with the posts fetched from the backend
+    Set(Vec<BlogPost>),
+    /// Apply a change received from the blog post stream
+    Apply(BlogPostChange),
+}
+
+#[derive(Default, PartialEq)]
+struct BlogPosts {
+    posts: Vec<BlogPost>,
+}
+
+impl Reducible for BlogPosts {
+    type Action = BlogPostsAction;
+
+    fn reduce(self: Rc<Self>, action: Self::Action) -> Rc<Self> {
+        let mut posts = self.posts.clone();
+        match action {
+            BlogPostsAction::Set(new_posts) => posts = new_posts,
+            BlogPostsAction::Apply(BlogPostChange::Created { post }) => posts.push(post),
+            BlogPostsAction::Apply(BlogPostChange::Updated { post }) => {
+                // a restored post is notified as updated
+                if let Some(existing) = posts.iter_mut().find(|elem| elem.id == post.id) {
+                    *existing = post;
+                } else {
+                    posts.push(post);
+                }
+            }
+            BlogPostsAction::Apply(BlogPostChange::Deleted { id }) => {
+                posts.retain(|post| post.id != id)
+            }
+        }
+        Rc::new(Self { posts })
+    }
+}
+
+/// Home page with the list of the posts, it is public: the guests see the
+/// public posts, rendered by the server, while the logged users see all the
+/// posts kept updated by the blog post stream and they can write new ones
 #[function_component(Home)]
 pub fn home_component() -> Html {
     let app_context = use_context::<UseStateHandle<AppContext>>().expect("No app_context found");
-    let user_info: UseStateHandle<Option<LoggedUserInfo>> = use_state(|| None);
-    let blog_posts: UseStateHandle<Vec<BlogPost>> = use_state(Vec::new);
+    let preloaded = use_context::<PreloadedData>().unwrap_or_default();
+    // the posts rendered by the server are shown without requesting them again
+    let skip_guest_fetch = use_mut_ref(|| preloaded.posts.is_some());
+    let blog_posts = use_reducer(|| BlogPosts {
+        posts: preloaded.posts.unwrap_or_default(),
+    });
     let blog_post_error = use_state(|| None);
     let selected_post = use_state(|| None);
     let write_new_post = use_state(|| false);
+    let token = app_context
+        .user_info
+        .as_ref()
+        .map(|user_info| user_info.token.clone());
 
     {
         let blog_posts = blog_posts.clone();
-        use_effect_with(user_info.clone(), move |user_info| {
-            let blog_posts = blog_posts.clone();
-            let user_info = user_info.clone();
-            if let Some(user_info) = (*user_info).clone() {
-                wasm_bindgen_futures::spawn_local(async move {
+        use_effect_with(
+            (token.clone(), app_context.loading),
+            move |(token, loading)| {
+                // the pending request is aborted when the user changes or the page is left
+                let abort_controller =
+                    AbortController::new().expect("Failed to create abort controller");
+                // the posts are requested once the logged user is known
+                let skip = *loading || (token.is_none() && skip_guest_fetch.replace(false));
+                if !skip {
                     let environment_service = EnvironmentService::new();
-                    let api_service = ApiService::new(
-                        environment_service.api_url,
-                        environment_service.mock,
-                        Some(user_info.token),
-                    );
-
-                    let blog_post_response = api_service.get_posts().await;
-                    if let Ok(ApiResponse { body, status }) = blog_post_response {
-                        match status {
-                            HttpStatus::Success(_) => {
-                                blog_posts.set(body);
+                    let api_service = ApiService::new(&environment_service, token.clone())
+                        .with_abort_signal(abort_controller.signal());
+                    let logged = token.is_some();
+                    wasm_bindgen_futures::spawn_local(async move {
+                        // the guests can read only the public posts
+                        let response = if logged {
+                            api_service.get_posts().await
+                        } else {
+                            api_service.get_public_posts().await
+                        };
+                        match response {
+                            Ok(ApiResponse { body, .. }) => {
+                                blog_posts.dispatch(BlogPostsAction::Set(body));
                                 blog_post_error.set(None);
                             }
-                            _ => {
-                                blog_post_error.set(Some("Ops, something went wrong".into()));
+                            Err(ApiError::Cancelled) => {}
+                            Err(err) => {
+                                error!("Encountered an error in get posts request. Error {err}");
+                                blog_post_error.set(Some(err.to_string()));
                             }
                         }
-                    } else {
-                        error!(
-                            "Encountered an error in get posts request. Error {err}",
-                            err = blog_post_response.err().unwrap()
-                        );
-                        blog_post_error.set(Some("Got error from backend".to_string()));
-                    }
+                    });
+                }
+                move || abort_controller.abort()
+            },
+        );
+    }
+
+    {
+        // keep the list of the logged users updated with the changes pushed by the backend
+        let blog_posts = blog_posts.clone();
+        use_effect_with(token, move |token| {
+            let mut subscription = None;
+            if let Some(token) = token.clone() {
+                let environment_service = EnvironmentService::new();
+                let api_service = ApiService::new(&environment_service, Some(token));
+                let on_change = Callback::from(move |change: BlogPostChange| {
+                    blog_posts.dispatch(BlogPostsAction::Apply(change))
                 });
+                match api_service.stream_posts(on_change) {
+                    Ok(stream) => subscription = Some(stream),
+                    Err(err) => error!("Encountered an error in opening post stream. Error {err}"),
+                }
             }
+            // dropping the subscription closes the stream
+            move || drop(subscription)
         });
     }
 
@@ -77,46 +150,36 @@ pub fn home_component() -> Html {
         })
     };
 
-    if let Some(context_user_info) = app_context.user_info.clone() {
-        if user_info.is_none() {
-            user_info.set(Some(context_user_info.clone()));
-        }
-
-        let blog_posts = blog_posts.clone();
-
-        let on_post_select = {
-            let selected_post = selected_post.clone();
-            Callback::from(move |post: BlogPost| selected_post.set(Some(post)))
-        };
-
-        let details = selected_post.as_ref().map(|post| {
-            html! {
-                <PostDetails post={post.clone()}/>
-            }
-        });
+    let on_post_select = {
+        let selected_post = selected_post.clone();
+        Callback::from(move |post: BlogPost| selected_post.set(Some(post)))
+    };
 
+    let details = selected_post.as_ref().map(|post| {
         html! {
-            <>
-                <div>
-                <h1>{ "Hello Blog!" }</h1>
-                <h2>{ "Your personal blog written totally in Rust ;)" }</h2>
-                </div>
+            <PostDetails post={post.clone()}/>
+        }
+    });
+
+    html! {
+        <>
+            <div>
+            <h1>{ "Hello Blog!" }</h1>
+            <h2>{ "Your personal blog written totally in Rust ;)" }</h2>
+            </div>
+            if let Some(user_info) = app_context.user_info.clone() {
                 <div class="home-write-post-container">
                     <button class="form-button-primary" onclick={onclick_write_post}>{"Write a new post"}</button>
                     if *write_new_post {
-                        <PostForm on_close={on_close_post_form} user_info={context_user_info}/>
+                        <PostForm on_close={on_close_post_form} user_info={user_info}/>
                     }
                 </div>
-                <div class="blog-posts-list-container">
-                    <h3>{"Here the list of published posts:"}</h3>
-                    <PostsList posts={(*blog_posts).clone()} on_click={on_post_select}/>
-                </div>
-                {for details}
-            </>
-        }
-    } else {
-        html! {
-            <Redirect<AppRoute> to={AppRoute::Login}/>
-        }
+            }
+            <div class="blog-posts-list-container">
+                <h3>{"Here the list of published posts:"}</h3>
+                <PostsList posts={blog_posts.posts.clone()} on_click={on_post_select}/>
+            </div>
+            {for details}
+        </>
     }
 }
@@ -4,14 +4,14 @@ use yew::{
     Callback, Html, SubmitEvent, UseStateHandle, function_component, html, use_context,
     use_node_ref, use_state,
 };
-use yew_router::prelude::Redirect;
 
 use crate::{
-    app::AppRoute,
-    enums::HttpStatus,
     environment::EnvironmentService,
     model::LoggedUserInfo,
-    service::{api::ApiService, auth::AuthService},
+    service::{
+        api::{ApiService, ApiServiceTrait},
+        auth::AuthService,
+    },
     types::{ApiResponse, AppContext},
 };
 
@@ -57,75 +57,51 @@ pub fn login_component() -> Html {
                     && !password.trim().is_empty()
                 {
                     let environment_service = EnvironmentService::new();
-                    let api_service = ApiService::new(
-                        environment_service.api_url,
-                        environment_service.mock,
-                        None,
-                    );
+                    let api_service = ApiService::new(&environment_service, None);
                     // make the request, if the type is Ok then set the token and update the app context
                     // if it is error then update the error boolean variable
                     let logged_user_info = api_service.login(username, password).await;
 
-                    if let Ok(ApiResponse { body, status }) = logged_user_info {
-                        match status {
-                            HttpStatus::Success(_) => {
-                                if let Some(body) = body {
-                                    let logged_user_info = LoggedUserInfo {
-                                        token: body
-                                            .token
-                                            .expect("Token must be present from register"),
-                                        user_id: body.user_id,
-                                        username: body.username,
-                                        admin: body.admin,
-                                    };
-                                    AuthService::new(
-                                        environment_service.token_storage_location_name,
-                                        app_context,
-                                    )
-                                    .set_logged_user_info(logged_user_info)
-                                    .expect("Failed to store token");
+                    match logged_user_info {
+                        Ok(ApiResponse { body, .. }) => {
+                            let logged_user_info = LoggedUserInfo {
+                                token: body.token.expect("Token must be present from login"),
+                                user_id: body.user_id,
+                                username: body.username,
+                                admin: body.admin,
+                            };
+                            AuthService::new(
+                                environment_service.token_storage_location_name,
+                                app_context,
+                            )
+                            .set_logged_user_info(logged_user_info)
+                            .expect("Failed to store token");
 
-                                    login_error.set(None);
-                                } else {
-                                    // if the body is None then it is an internal error
-                                    login_error
-                                        .set(Some(String::from("Ops, something went wrong.")));
-                                }
-                            }
-                            _ => {
-                                login_error.set(Some(format!("Got error from backend: {status}")));
-                            }
+                            login_error.set(None);
+                        }
+                        Err(err) => {
+                            error!("Encountered an error in login request. Error {err}");
+                            login_error.set(Some(err.to_string()));
                         }
-                    } else {
-                        error!(
-                            "Encountered an error in login request. Error {err}",
-                            err = logged_user_info.err().unwrap()
-                        );
-                        login_error.set(Some("Got error from backend".to_string()));
                     }
                 }
             });
         })
     };
 
-    if app_context.user_info.is_some() {
-        html! {
-            <Redirect<AppRoute> to={AppRoute::Home}/>
-        }
-    } else {
-        html! {
-            <div class="page-centered">
-                <h1>{"Welcome to Hello Blog!"}</h1>
-                <div>{"Please insert your credentials and log in."}</div>
-                <form class="form-container" {onsubmit}>
-                        <input type="text" placeholder="username" ref={username_node_ref}/>
-                        <input type="password" placeholder="password" ref={password_node_ref}/>
-                    <button type="submit" class="form-button-primary">{"Login"}</button>
-                    if let Some(error_msg) = (*login_error).clone() {
-                        <p style="color:red">{error_msg}</p>
-                    }
-                </form>
-            </div>
-        }
+    // the route guard redirects to the previous page after the login
+    html! {
+        <div class="page-centered">
+            <h1>{"Welcome to Hello Blog!"}</h1>
+            <div>{"Please insert your credentials and log in."}</div>
+            <form class="form-container" {onsubmit}>
+                    <input type="text" placeholder="username" ref={username_node_ref}/>
+                    <input type="password" placeholder="password" ref={password_node_ref}/>
+                <button type="submit" class="form-button-primary">{"Login"}</button>
+                if let Some(error_msg) = (*login_error).clone() {
+                    <p style="color:red">{error_msg}</p>
+                }
+            </form>
+        </div>
     }
 }
@@ -0,0 +1,86 @@
+use log::error;
+use web_sys::AbortController;
+use yew::{
+    Html, Properties, UseStateHandle, function_component, html, use_context, use_effect_with,
+    use_state,
+};
+
+use crate::{
+    component::post_details::PostDetails,
+    environment::EnvironmentService,
+    error::ApiError,
+    model::BlogPost,
+    service::api::{ApiService, ApiServiceTrait},
+    types::{ApiResponse, AppContext, PreloadedData},
+};
+
+#[derive(Properties, PartialEq)]
+pub struct PostProps {
+    pub id: String,
+}
+
+/// Page of a single post, it is public so that it is rendered by the server
+/// with the post preloaded and it can be indexed by the search engines
+#[function_component(Post)]
+pub fn post_component(PostProps { id }: &PostProps) -> Html {
+    let app_context = use_context::<UseStateHandle<AppContext>>().expect("No app_context found");
+    let preloaded = use_context::<PreloadedData>().unwrap_or_default();
+    // the post rendered by the server is shown without requesting it again
+    let post: UseStateHandle<Option<BlogPost>> = use_state(|| preloaded.post);
+    let post_error = use_state(|| None);
+
+    {
+        let post = post.clone();
+        let post_error = post_error.clone();
+        let token = app_context
+            .user_info
+            .as_ref()
+            .map(|user_info| user_info.token.clone());
+        use_effect_with(
+            (id.clone(), token, app_context.loading),
+            move |(id, token, loading)| {
+                let abort_controller =
+                    AbortController::new().expect("Failed to create abort controller");
+                // the post is requested when it is not preloaded, once the logged user is known
+                if !*loading && post.as_ref().is_none_or(|post| post.id != *id) {
+                    let environment_service = EnvironmentService::new();
+                    let api_service = ApiService::new(&environment_service, token.clone())
+                        .with_abort_signal(abort_controller.signal());
+                    let id = id.clone();
+                    let logged = token.is_some();
+                    wasm_bindgen_futures::spawn_local(async move {
+                        // the guests can read only the public posts
+                        let response = if logged {
+                            api_service.get_post(id).await
+                        } else {
+                            api_service.get_public_post(id).await
+                        };
+                        match response {
+                            Ok(ApiResponse { body, .. }) => {
+                                post.set(Some(body));
+                                post_error.set(None);
+                            }
+                            Err(ApiError::Cancelled) => {}
+                            Err(err) => {
+                                error!("Encountered an error in get post request. Error {err}");
+                                post_error.set(Some(err.to_string()));
+                            }
+                        }
+                    });
+                }
+                move || abort_controller.abort()
+            },
+        );
+    }
+
+    html! {
+        <div class="page">
+            if let Some(message) = (*post_error).clone() {
+                <p style="color:red">{message}</p>
+            }
+            if let Some(post) = post.as_ref().filter(|post| post.id == *id) {
+                <PostDetails post={post.clone()}/>
+            }
+        </div>
+    }
+}
@@ -4,14 +4,14 @@ use yew::{
     Callback, Html, SubmitEvent, UseStateHandle, function_component, html, use_context,
     use_node_ref, use_state,
 };
-use yew_router::prelude::Redirect;
 
 use crate::{
-    app::AppRoute,
-    enums::HttpStatus,
     environment::EnvironmentService,
     model::LoggedUserInfo,
-    service::{api::ApiService, auth::AuthService},
+    service::{
+        api::{ApiService, ApiServiceTrait},
+        auth::AuthService,
+    },
     types::{ApiResponse, AppContext},
 };
 
@@ -85,79 +85,53 @@ pub fn register_component() -> Html {
                         return;
                     }
                     let environment_service = EnvironmentService::new();
-                    let api_service = ApiService::new(
-                        environment_service.api_url,
-                        environment_service.mock,
-                        None,
-                    );
+                    let api_service = ApiService::new(&environment_service, None);
 
                     let register_response = api_service
                         .register(first_name, last_name, username, password)
                         .await;
-                    if let Ok(ApiResponse { body, status }) = register_response {
-                        match status {
-                            HttpStatus::Success(_) => {
-                                if let Some(body) = body {
-                                    let logged_user_info = LoggedUserInfo {
-                                        token: body
-                                            .token
-                                            .expect("Token must be present from register"),
-                                        user_id: body.user_id,
-                                        username: body.username,
-                                        admin: body.admin,
-                                    };
-                                    AuthService::new(
-                                        environment_service.token_storage_location_name,
-                                        app_context,
-                                    )
-                                    .set_logged_user_info(logged_user_info)
-                                    .expect("Failed to store token");
+                    match register_response {
+                        Ok(ApiResponse { body, .. }) => {
+                            let logged_user_info = LoggedUserInfo {
+                                token: body.token.expect("Token must be present from register"),
+                                user_id: body.user_id,
+                                username: body.username,
+                                admin: body.admin,
+                            };
+                            AuthService::new(
+                                environment_service.token_storage_location_name,
+                                app_context,
+                            )
+                            .set_logged_user_info(logged_user_info)
+                            .expect("Failed to store token");
 
-                                    request_error.set(None);
-                                } else {
-                                    // if the body is None then it is an internal error
-                                    request_error
-                                        .set(Some(String::from("Ops, something went wrong.")));
-                                }
-                            }
-                            _ => {
-                                request_error
-                                    .set(Some(format!("Got error from backend: {status}")));
-                            }
+                            request_error.set(None);
+                        }
+                        Err(err) => {
+                            error!("Encountered an error in register request. Error {err}");
+                            request_error.set(Some(err.to_string()));
                         }
-                    } else {
-                        error!(
-                            "Encountered an error in login request. Error {err}",
-                            err = register_response.err().unwrap()
-                        );
-                        request_error.set(Some("Got error from backend".to_string()));
                     }
                 }
             });
         })
     };
 
-    if app_context.user_info.is_some() {
-        html! {
-            <Redirect<AppRoute> to={AppRoute::Home}/>
-        }
-    } else {
-        html! {
-            <div class="page-centered">
-                <h1>{"Welcome to Hello Blog!"}</h1>
-                <div>{"Please insert your credentials and log in."}</div>
-                <form class="form-container" {onsubmit}>
-                        <input type="text" placeholder="first name" ref={first_name_nr}/>
-                        <input type="text" placeholder="last name" ref={last_name_nr}/>
-                        <input type="text" placeholder="username" ref={username_nr}/>
-                        <input type="password" placeholder="password" ref={password_nr}/>
-                        <input type="password" placeholder="confirm password" ref={confirm_password_nr}/>
-                    <button type="submit" class="form-button-primary">{"Register"}</button>
-                    if let Some(error_msg) = (*request_error).clone() {
-                        <p style="color:red">{error_msg}</p>
-                    }
-                </form>
-            </div>
-        }
+    html! {
+        <div class="page-centered">
+            <h1>{"Welcome to Hello Blog!"}</h1>
+            <div>{"Please insert your credentials and log in."}</div>
+            <form class="form-container" {onsubmit}>
+                    <input type="text" placeholder="first name" ref={first_name_nr}/>
+                    <input type="text" placeholder="last name" ref={last_name_nr}/>
+                    <input type="text" placeholder="username" ref={username_nr}/>
+                    <input type="password" placeholder="password" ref={password_nr}/>
+                    <input type="password" placeholder="confirm password" ref={confirm_password_nr}/>
+                <button type="submit" class="form-button-primary">{"Register"}</button>
+                if let Some(error_msg) = (*request_error).clone() {
+                    <p style="color:red">{error_msg}</p>
+                }
+            </form>
+        </div>
     }
 }
@@ -1,323 +1,284 @@
+//! Api module defines the client of the backend REST API.
+//!
+//! `ApiServiceTrait` describes the API calls, it is implemented by
+//! `HttpApiService` that sends the requests to the backend and by
+//! `MockApiService` that simulates the backend in the browser.
+//!
+//! `ApiService` is the client used by the components, the actual
+//! implementation is selected with the `FE_MOCK` environment variable.
+//!
+//! The calls rejected because the token is invalid or expired are reported
+//! to the handler registered with `set_unauthorized_handler`.
+
+pub mod http_service;
+pub mod mock_service;
+
+use std::cell::RefCell;
+
+use web_sys::AbortSignal;
+use yew::Callback;
+
 use crate::{
+    environment::EnvironmentService,
     error::ApiError,
     model::{
-        BlogPost, JWTAuthClaim, LoggedUserInfoResponse, LoginInfo, PublishPostRequest,
-        RegisterInfo, UserInfo,
+        AuditEventPage, BlogPost, BlogPostChange, LoggedUserInfoResponse, PostVisibility, User,
     },
-    types::{ApiResponse, ApiResult},
+    service::api::{http_service::HttpApiService, mock_service::MockApiService},
+    types::ApiResult,
 };
-use gloo_net::http::Request;
-use jsonwebtoken::{EncodingKey, Header, encode};
-use log::info;
 
-#[derive(Clone, Debug, PartialEq)]
-pub struct ApiService {
-    api_url: String,
-    token: Option<String>,
-    mock: bool,
+thread_local! {
+    static UNAUTHORIZED_HANDLER: RefCell<Option<Callback<()>>> = const { RefCell::new(None) };
 }
 
-impl ApiService {
-    pub fn new(api_url: String, mock: bool, token: Option<String>) -> ApiService {
+/// Sets the callback called when a call with the token of the logged user
+/// fails with 401, i.e. the token is not valid anymore.
+///
+/// There is a single handler for the whole application, `None` removes it
+pub fn set_unauthorized_handler(handler: Option<Callback<()>>) {
+    UNAUTHORIZED_HANDLER.set(handler);
+}
+
+/// Notifies the unauthorized handler, if any, that the token has been rejected
+fn notify_unauthorized() {
+    if let Some(handler) = UNAUTHORIZED_HANDLER.with_borrow(Clone::clone) {
+        handler.emit(());
+    }
+}
+
+/// Subscription to the blog post changes returned by `stream_posts`,
+/// the stream is closed when the subscription is dropped
+pub struct PostSubscription {
+    close: Option<Box<dyn FnOnce()>>,
+}
+
+impl PostSubscription {
+    pub fn new(close: impl FnOnce() + 'static) -> Self {
         Self {
-            api_url,
-            token,
-            mock,
+            close: Some(Box::new(close)),
+        }
+    }
+}
+
+impl Drop for PostSubscription {
+    fn drop(&mut self) {
+        if let Some(close) = self.close.take() {
+            close();
         }
     }
+}
+
+/// Calls of the backend REST API
+///
+/// The user and admin calls require the token of the logged user, they
+/// fail with `ApiError::AuthorizationError` when it is missing. The public
+/// calls return only the public posts and they do not require it
+pub trait ApiServiceTrait {
+    fn login(
+        &self,
+        username: String,
+        password: String,
+    ) -> impl Future<Output = ApiResult<LoggedUserInfoResponse>>;
 
-    pub async fn login(
+    fn register(
         &self,
+        first_name: String,
+        last_name: String,
         username: String,
         password: String,
-    ) -> ApiResult<Option<LoggedUserInfoResponse>> {
-        let (body, status) = if self.mock {
-            let now = chrono::offset::Local::now().timestamp() as u32;
-            let claims = JWTAuthClaim {
-                user_id: "user-id".into(),
-                username: "username".into(),
-                exp: now + 10000,
-            };
-            let token = encode(
-                &Header::default(),
-                &claims,
-                &EncodingKey::from_secret("secret".as_ref()),
-            )
-            .expect("failing to mock jwt");
-            (
-                Some(LoggedUserInfoResponse {
-                    token: Some(token),
-                    user_id: "user-id".into(),
-                    username: "username".into(),
-                    admin: true,
-                }),
-                200,
-            )
+    ) -> impl Future<Output = ApiResult<LoggedUserInfoResponse>>;
+
+    /// Returns the information of the user owning the token
+    fn get_user_info(&self) -> impl Future<Output = ApiResult<LoggedUserInfoResponse>>;
+
+    /// Returns the information of the user owning the token with a new token
+    /// that replaces the current one
+    fn refresh_token(&self) -> impl Future<Output = ApiResult<LoggedUserInfoResponse>>;
+
+    fn get_posts(&self) -> impl Future<Output = ApiResult<Vec<BlogPost>>>;
+
+    fn get_post(&self, id: String) -> impl Future<Output = ApiResult<BlogPost>>;
+
+    fn get_public_posts(&self) -> impl Future<Output = ApiResult<Vec<BlogPost>>>;
+
+    /// Returns the post if it is public, members only posts do not exist for this call
+    fn get_public_post(&self, id: String) -> impl Future<Output = ApiResult<BlogPost>>;
+
+    /// Opens the stream of the blog post changes and calls `on_change` for each of them
+    /// until the returned subscription is dropped
+    fn stream_posts(
+        &self,
+        on_change: Callback<BlogPostChange>,
+    ) -> Result<PostSubscription, ApiError>;
+
+    /// Publishes a new blog post, it returns the id of the post
+    fn publish_post(
+        &self,
+        title: String,
+        content: String,
+        visibility: PostVisibility,
+    ) -> impl Future<Output = ApiResult<String>>;
+
+    fn get_admin_users_list(&self) -> impl Future<Output = ApiResult<Vec<User>>>;
+
+    /// Returns a page of the audit log, pages start from 1
+    fn get_audit_events(
+        &self,
+        page: u64,
+        page_size: u64,
+        action: Option<String>,
+    ) -> impl Future<Output = ApiResult<AuditEventPage>>;
+}
+
+/// Api service used by the components, it is the mock one when `FE_MOCK` is true.
+///
+/// `ApiServiceTrait` cannot be used as trait object because its methods return
+/// `impl Future`, hence this enum dispatches each call to the wrapped service.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ApiService {
+    Http(HttpApiService),
+    Mock(MockApiService),
+}
+
+impl ApiService {
+    pub fn new(environment_service: &EnvironmentService, token: Option<String>) -> Self {
+        if environment_service.mock {
+            Self::Mock(MockApiService::new(
+                token,
+                environment_service.mock_latency_ms,
+                environment_service.mock_failure_rate,
+            ))
         } else {
-            let login_info = LoginInfo { username, password };
-
-            let mut url = String::from(&self.api_url);
-            url.push_str("/guest/login");
-
-            let response = Request::post(&url)
-                .header("Content-Type", "application/json")
-                .json(&login_info)?
-                .send()
-                .await?;
-
-            let body = if response.status() == 200 {
-                Some(response.json::<LoggedUserInfoResponse>().await?)
-            } else {
-                None
-            };
-            (body, response.status())
-        };
-        info!("logged user info response is {body:?}", body = body);
-        Ok(ApiResponse {
-            body,
-            status: status.into(),
-        })
+            Self::Http(HttpApiService::new(
+                environment_service.api_url.clone(),
+                token,
+            ))
+        }
+    }
+
+    /// Aborts the requests of the service when the signal is triggered,
+    /// they fail with `ApiError::Cancelled`.
+    ///
+    /// It is used by the components to cancel the pending requests when
+    /// they are destroyed
+    pub fn with_abort_signal(self, signal: AbortSignal) -> Self {
+        match self {
+            Self::Http(service) => Self::Http(service.with_abort_signal(signal)),
+            Self::Mock(service) => Self::Mock(service.with_abort_signal(signal)),
+        }
+    }
+}
+
+impl ApiServiceTrait for ApiService {
+    async fn login(&self, username: String, password: String) -> ApiResult<LoggedUserInfoResponse> {
+        match self {
+            Self::Http(service) => service.login(username, password).await,
+            Self::Mock(service) => service.login(username, password).await,
+        }
     }
 
-    pub async fn register(
+    async fn register(
         &self,
         first_name: String,
         last_name: String,
         username: String,
         password: String,
-    ) -> ApiResult<Option<LoggedUserInfoResponse>> {
-        let (body, status) = if self.mock {
-            let now = chrono::offset::Local::now().timestamp() as u32;
-            let claims = JWTAuthClaim {
-                user_id: "user-id".into(),
-                username: "username".into(),
-                exp: now + 10000,
-            };
-            let token = encode(
-                &Header::default(),
-                &claims,
-                &EncodingKey::from_secret("secret".as_ref()),
-            )
-            .expect("failing to mock jwt");
-            (
-                Some(LoggedUserInfoResponse {
-                    token: Some(token),
-                    user_id: "user-id".into(),
-                    username: "username".into(),
-                    admin: true,
-                }),
-                200,
-            )
-        } else {
-            let register_info = RegisterInfo {
-                first_name,
-                last_name,
-                username,
-                password,
-            };
-
-            let mut url = String::from(&self.api_url);
-            url.push_str("/guest/register");
-
-            let response = Request::post(&url)
-                .header("Content-Type", "application/json")
-                .json(&register_info)?
-                .send()
-                .await?;
-
-            let body = if response.status() == 200 {
-                Some(response.json::<LoggedUserInfoResponse>().await?)
-            } else {
-                None
-            };
-            (body, response.status())
-        };
-
-        Ok(ApiResponse {
-            body,
-            status: status.into(),
-        })
+    ) -> ApiResult<LoggedUserInfoResponse> {
+        match self {
+            Self::Http(service) => {
+                service
+                    .register(first_name, last_name, username, password)
+                    .await
+            }
+            Self::Mock(service) => {
+                service
+                    .register(first_name, last_name, username, password)
+                    .await
+            }
+        }
     }
 
-    pub async fn get_user_info(&self) -> ApiResult<Option<LoggedUserInfoResponse>> {
-        if let Some(token) = &self.token {
-            let (body, status) = if self.mock {
-                (
-                    Some(LoggedUserInfoResponse {
-                        token: None,
-                        user_id: "user-id".into(),
-                        username: "username".into(),
-                        admin: true,
-                    }),
-                    200,
-                )
-            } else {
-                let mut url = String::from(&self.api_url);
-                url.push_str("/user/info");
-
-                let response = Request::get(&url)
-                    .header("Content-Type", "application/json")
-                    .header("Authorization", &format!("Bearer {token}"))
-                    .send()
-                    .await?;
-
-                let body = if response.status() == 200 {
-                    response.json::<Option<LoggedUserInfoResponse>>().await?
-                } else {
-                    None
-                };
-                (body, response.status())
-            };
-
-            Ok(ApiResponse {
-                body,
-                status: status.into(),
-            })
-        } else {
-            Err(ApiError::AuthorizationError(
-                "Missing authorization token".to_string(),
-            ))
+    async fn get_user_info(&self) -> ApiResult<LoggedUserInfoResponse> {
+        match self {
+            Self::Http(service) => service.get_user_info().await,
+            Self::Mock(service) => service.get_user_info().await,
         }
     }
 
-    pub async fn get_posts(&self) -> ApiResult<Vec<BlogPost>> {
-        if let Some(token) = &self.token {
-            let (body, status) = if self.mock {
-                (vec![
-                BlogPost {
-                    id: "1".into(),
-                    title: "First blog".into(),
-                    content: "this is the content of the blog. I think that I could write something but for now I can simply write a long text just to see how it will be displayed on the screen. Who know how it will be printed on the screen.".into(),
-                    creation_date: "2025/11/14".into(),
-                    creator_id: "creator-id".into(),
-                    creator_username: "alex_sinks".into()
-                },
-                BlogPost {
-                    id: "2".into(),
-                    title: "Second blog".into(),
-                    content: "this is the content of the blog. I think that I could write something but for now I can simply write a long text just to see how it will be displayed on the screen. Who know how it will be printed on the screen.".into(),
-                    creation_date: "2025/11/14".into(),
-                    creator_id: "creator-id".into(),
-                    creator_username: "alex_sinks".into()
-                },
-                BlogPost {
-                    id: "3".into(),
-                    title: "Third blog".into(),
-                    content: "this is the content of the blog. I think that I could write something but for now I can simply write a long text just to see how it will be displayed on the screen. Who know how it will be printed on the screen.".into(),
-                    creation_date: "2025/11/14".into(),
-                    creator_id: "creator-id".into(),
-                    creator_username: "alex_sinks".into()
-                }
-            ], 200)
-            } else {
-                let mut url = String::from(&self.api_url);
-                url.push_str("/user/blog/post");
-
-                let response = Request::get(&url)
-                    .header("Content-Type", "application/json")
-                    .header("Authorization", &format!("Bearer {token}"))
-                    .send()
-                    .await?;
-
-                let body = if response.status() == 200 {
-                    response.json::<Vec<BlogPost>>().await?
-                } else {
-                    Vec::new()
-                };
-                (body, response.status())
-            };
-
-            Ok(ApiResponse {
-                body,
-                status: status.into(),
-            })
-        } else {
-            Err(ApiError::AuthorizationError(
-                "Missing authorization token".to_string(),
-            ))
+    async fn refresh_token(&self) -> ApiResult<LoggedUserInfoResponse> {
+        match self {
+            Self::Http(service) => service.refresh_token().await,
+            Self::Mock(service) => service.refresh_token().await,
         }
     }
 
-    pub async fn publish_post(&self, title: String, content: String) -> ApiResult<()> {
-        if let Some(token) = &self.token {
-            let (body, status) = if self.mock {
-                ((), 200)
-            } else {
-                let mut url = String::from(&self.api_url);
-                url.push_str("/user/blog/post");
-
-                let request_payload = PublishPostRequest { title, content };
-
-                let response = Request::post(&url)
-                    .header("Content-Type", "application/json")
-                    .header("Authorization", &format!("Bearer {token}"))
-                    .json(&request_payload)?
-                    .send()
-                    .await?;
-
-                ((), response.status())
-            };
-
-            Ok(ApiResponse {
-                body,
-                status: status.into(),
-            })
-        } else {
-            Err(ApiError::AuthorizationError(
-                "Missing authorization token".to_string(),
-            ))
+    async fn get_posts(&self) -> ApiResult<Vec<BlogPost>> {
+        match self {
+            Self::Http(service) => service.get_posts().await,
+            Self::Mock(service) => service.get_posts().await,
         }
     }
 
-    pub async fn get_admin_users_list(&self) -> ApiResult<Vec<UserInfo>> {
-        if let Some(token) = &self.token {
-            let (body, status) = if self.mock {
-                (
-                    vec![
-                        UserInfo {
-                            user_id: "user-0".into(),
-                            username: "username-0".into(),
-                            admin: false,
-                        },
-                        UserInfo {
-                            user_id: "user-1".into(),
-                            username: "username-1".into(),
-                            admin: true,
-                        },
-                        UserInfo {
-                            user_id: "user-2".into(),
-                            username: "username-2".into(),
-                            admin: false,
-                        },
-                    ],
-                    200,
-                )
-            } else {
-                let mut url = String::from(&self.api_url);
-                url.push_str("/admin/user");
-
-                let response = Request::get(&url)
-                    .header("Content-Type", "application/json")
-                    .header("Authorization", &format!("Bearer {token}"))
-                    .send()
-                    .await?;
-
-                let body = if response.status() == 200 {
-                    response.json::<Vec<UserInfo>>().await?
-                } else {
-                    Vec::new()
-                };
-                (body, response.status())
-            };
-
-            Ok(ApiResponse {
-                body,
-                status: status.into(),
-            })
-        } else {
-            Err(ApiError::AuthorizationError(
-                "Missing authorization token".to_string(),
-            ))
+    async fn get_post(&self, id: String) -> ApiResult<BlogPost> {
+        match self {
+            Self::Http(service) => service.get_post(id).await,
+            Self::Mock(service) => service.get_post(id).await,
+        }
+    }
+
+    async fn get_public_posts(&self) -> ApiResult<Vec<BlogPost>> {
+        match self {
+            Self::Http(service) => service.get_public_posts().await,
+            Self::Mock(service) => service.get_public_posts().await,
+        }
+    }
+
+    async fn get_public_post(&self, id: String) -> ApiResult<BlogPost> {
+        match self {
+            Self::Http(service) => service.get_public_post(id).await,
+            Self::Mock(service) => service.get_public_post(id).await,
+        }
+    }
+
+    fn stream_posts(
+        &self,
+        on_change: Callback<BlogPostChange>,
+    ) -> Result<PostSubscription, ApiError> {
+        match self {
+            Self::Http(service) => service.stream_posts(on_change),
+            Self::Mock(service) => service.stream_posts(on_change),
+        }
+    }
+
+    async fn publish_post(
+        &self,
+        title: String,
+        content: String,
+        visibility: PostVisibility,
+    ) -> ApiResult<String> {
+        match self {
+            Self::Http(service) => service.publish_post(title, content, visibility).await,
+            Self::Mock(service) => service.publish_post(title, content, visibility).await,
+        }
+    }
+
+    async fn get_admin_users_list(&self) -> ApiResult<Vec<User>> {
+        match self {
+            Self::Http(service) => service.get_admin_users_list().await,
+            Self::Mock(service) => service.get_admin_users_list().await,
+        }
+    }
+
+    async fn get_audit_events(
+        &self,
+        page: u64,
+        page_size: u64,
+        action: Option<String>,
+    ) -> ApiResult<AuditEventPage> {
+        match self {
+            Self::Http(service) => service.get_audit_events(page, page_size, action).await,
+            Self::Mock(service) => service.get_audit_events(page, page_size, action).await,
         }
     }
 }
@@ -0,0 +1,287 @@
+use api_types::error::ErrorResponse;
+use futures::StreamExt;
+use gloo_net::{
+    eventsource::futures::EventSource,
+    http::{Method, Request},
+};
+use gloo_timers::future::TimeoutFuture;
+use log::{error, info, warn};
+use serde::{Serialize, de::DeserializeOwned};
+use web_sys::AbortSignal;
+use yew::Callback;
+
+use crate::{
+    error::ApiError,
+    model::{
+        AuditEventPage, BlogPost, BlogPostChange, JWTAuthPayload, LoggedUserInfoResponse,
+        PostVisibility, PublishPost, RegisterInfo, User,
+    },
+    service::api::{ApiServiceTrait, PostSubscription, notify_unauthorized},
+    types::{ApiResponse, ApiResult},
+};
+
+/// Maximum number of attempts of an idempotent request failing
+/// because of a network error
+const MAX_ATTEMPTS: u32 = 3;
+
+/// Delay before the first retry, it doubles at each following attempt
+const RETRY_DELAY_MS: u32 = 250;
+
+/// Name of the error raised by `fetch` when the request is aborted
+const ABORT_ERROR_NAME: &str = "AbortError";
+
+/// Whether the endpoint can be called without the authorization token
+#[derive(Clone, Copy, PartialEq)]
+enum Access {
+    Guest,
+    Authorized,
+}
+
+/// Api service that sends the requests to the backend
+#[derive(Clone, Debug, PartialEq)]
+pub struct HttpApiService {
+    api_url: String,
+    token: Option<String>,
+    abort_signal: Option<AbortSignal>,
+}
+
+impl HttpApiService {
+    pub fn new(api_url: String, token: Option<String>) -> Self {
+        Self {
+            api_url,
+            token,
+            abort_signal: None,
+        }
+    }
+
+    pub fn with_abort_signal(mut self, signal: AbortSignal) -> Self {
+        self.abort_signal = Some(signal);
+        self
+    }
+
+    /// Sends a request to the backend and deserializes the JSON body of the response.
+    ///
+    /// The token is sent as bearer token when it is present and it is required by the
+    /// `Authorized` endpoints. Responses with a status different from 2xx are mapped to
+    /// the `ApiError` matching the status, with the message of the backend error body.
+    ///
+    /// A 401 response of an `Authorized` endpoint means that the token is not valid
+    /// anymore, it is reported to the unauthorized handler.
+    ///
+    /// Requests failing because of a network error are retried up to `MAX_ATTEMPTS` times,
+    /// except for POST and PATCH that are not idempotent
+    async fn request<T: DeserializeOwned>(
+        &self,
+        method: Method,
+        path: &str,
+        access: Access,
+        body: Option<String>,
+    ) -> ApiResult<T> {
+        if access == Access::Authorized && self.token.is_none() {
+            return Err(ApiError::AuthorizationError(
+                "Missing authorization token".to_string(),
+            ));
+        }
+
+        let url = format!("{}{path}", self.api_url);
+        let idempotent = !matches!(method, Method::POST | Method::PATCH);
+        let mut attempt = 1;
+        let response = loop {
+            let mut request = Request::new(&url)
+                .method(method)
+                .header("Content-Type", "application/json")
+                .abort_signal(self.abort_signal.as_ref());
+            if let Some(token) = &self.token {
+                request = request.header("Authorization", &format!("Bearer {token}"));
+            }
+            if let Some(body) = &body {
+                request = request.body(body.as_str());
+            }
+
+            match request.send().await {
+                Ok(response) => break response,
+                Err(gloo_net::Error::JsError(err)) if err.name == ABORT_ERROR_NAME => {
+                    return Err(ApiError::Cancelled);
+                }
+                Err(gloo_net::Error::JsError(err)) if idempotent && attempt < MAX_ATTEMPTS => {
+                    warn!("Request {method} {path} failed, retrying. Error {err}");
+                    TimeoutFuture::new(RETRY_DELAY_MS << (attempt - 1)).await;
+                    attempt += 1;
+                }
+                Err(err) => return Err(err.into()),
+            }
+        };
+
+        let status = response.status();
+        let text = response.text().await.map_err(|err| match err {
+            gloo_net::Error::JsError(err) if err.name == ABORT_ERROR_NAME => ApiError::Cancelled,
+            err => err.into(),
+        })?;
+
+        if !response.ok() {
+            if status == 401 && access == Access::Authorized {
+                notify_unauthorized();
+            }
+            let message = serde_json::from_str::<ErrorResponse>(&text)
+                .map(|body| body.message)
+                .unwrap_or_else(|_| response.status_text());
+            return Err(ApiError::from_status(status, message.clone())
+                .unwrap_or(ApiError::GenericRequestError(message)));
+        }
+
+        // endpoints without a response body are deserialized as unit
+        let text = if text.is_empty() { "null" } else { &text };
+        let body = serde_json::from_str(text).map_err(gloo_net::Error::from)?;
+        Ok(ApiResponse {
+            body,
+            status: status.into(),
+        })
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str, access: Access) -> ApiResult<T> {
+        self.request(Method::GET, path, access, None).await
+    }
+
+    async fn post<B: Serialize, T: DeserializeOwned>(
+        &self,
+        path: &str,
+        access: Access,
+        body: &B,
+    ) -> ApiResult<T> {
+        let body = serde_json::to_string(body).map_err(gloo_net::Error::from)?;
+        self.request(Method::POST, path, access, Some(body)).await
+    }
+}
+
+impl ApiServiceTrait for HttpApiService {
+    async fn login(&self, username: String, password: String) -> ApiResult<LoggedUserInfoResponse> {
+        let login_info = JWTAuthPayload { username, password };
+        let response = self.post("/guest/login", Access::Guest, &login_info).await;
+        if let Ok(response) = &response {
+            info!("logged user info response is {:?}", response.body);
+        }
+        response
+    }
+
+    async fn register(
+        &self,
+        first_name: String,
+        last_name: String,
+        username: String,
+        password: String,
+    ) -> ApiResult<LoggedUserInfoResponse> {
+        let register_info = RegisterInfo {
+            first_name,
+            last_name,
+            username,
+            password,
+        };
+        self.post("/guest/register", Access::Guest, &register_info)
+            .await
+    }
+
+    async fn get_user_info(&self) -> ApiResult<LoggedUserInfoResponse> {
+        self.get("/user/info", Access::Authorized).await
+    }
+
+    async fn refresh_token(&self) -> ApiResult<LoggedUserInfoResponse> {
+        self.post("/user/refresh", Access::Authorized, &()).await
+    }
+
+    async fn get_posts(&self) -> ApiResult<Vec<BlogPost>> {
+        self.get("/user/blog/post", Access::Authorized).await
+    }
+
+    async fn get_post(&self, id: String) -> ApiResult<BlogPost> {
+        self.get(&format!("/user/blog/post/{id}"), Access::Authorized)
+            .await
+    }
+
+    async fn get_public_posts(&self) -> ApiResult<Vec<BlogPost>> {
+        self.get("/public/blog/post", Access::Guest).await
+    }
+
+    async fn get_public_post(&self, id: String) -> ApiResult<BlogPost> {
+        self.get(&format!("/public/blog/post/{id}"), Access::Guest)
+            .await
+    }
+
+    fn stream_posts(
+        &self,
+        on_change: Callback<BlogPostChange>,
+    ) -> Result<PostSubscription, ApiError> {
+        let Some(token) = &self.token else {
+            return Err(ApiError::AuthorizationError(
+                "Missing authorization token".to_string(),
+            ));
+        };
+
+        let mut url = String::from(&self.api_url);
+        url.push_str(&format!("/user/blog/stream?access_token={token}"));
+
+        let mut event_source =
+            EventSource::new(&url).map_err(|err| ApiError::GenericRequestError(err.to_string()))?;
+        let mut subscriptions = vec![];
+        for event_type in ["created", "updated", "deleted"] {
+            subscriptions.push(
+                event_source
+                    .subscribe(event_type)
+                    .map_err(|err| ApiError::GenericRequestError(err.to_string()))?,
+            );
+        }
+
+        wasm_bindgen_futures::spawn_local(async move {
+            let mut events = futures::stream::select_all(subscriptions);
+            while let Some(event) = events.next().await {
+                match event {
+                    Ok((_, message)) => {
+                        let change = message
+                            .data()
+                            .as_string()
+                            .and_then(|data| serde_json::from_str::<BlogPostChange>(&data).ok());
+                        if let Some(change) = change {
+                            on_change.emit(change);
+                        } else {
+                            error!("Received an invalid blog post change");
+                        }
+                    }
+                    Err(err) => error!("Error in blog post stream: {err:?}"),
+                }
+            }
+        });
+
+        Ok(PostSubscription::new(move || event_source.close()))
+    }
+
+    async fn publish_post(
+        &self,
+        title: String,
+        content: String,
+        visibility: PostVisibility,
+    ) -> ApiResult<String> {
+        let request_payload = PublishPost {
+            title,
+            content,
+            visibility,
+        };
+        self.post("/user/blog/post", Access::Authorized, &request_payload)
+            .await
+    }
+
+    async fn get_admin_users_list(&self) -> ApiResult<Vec<User>> {
+        self.get("/admin/user", Access::Authorized).await
+    }
+
+    async fn get_audit_events(
+        &self,
+        page: u64,
+        page_size: u64,
+        action: Option<String>,
+    ) -> ApiResult<AuditEventPage> {
+        let mut path = format!("/admin/audit?page={page}&pageSize={page_size}");
+        if let Some(action) = action {
+            path.push_str(&format!("&action={action}"));
+        }
+        self.get(&path, Access::Authorized).await
+    }
+}
@@ -0,0 +1,461 @@
+//! Mock of the backend running in the browser, it allows to develop the
+//! frontend without running the backend.
+//!
+//! The state is kept in memory for the lifetime of the page, hence the
+//! registered users and the published posts are lost when the page is
+//! reloaded. It is seeded with the following users:
+//!
+//! - `admin` with password `admin`, administrator
+//! - `alex_sinks` with password `password`, author of the seeded posts,
+//!   the last one is readable only by the members
+//!
+//! The tokens issued by the mock expire after one hour, calls with an expired
+//! token are rejected like the backend does.
+//!
+//! Each response is delayed by the configured latency and each call fails
+//! with the configured probability, to test how the pages handle them.
+
+use std::cell::RefCell;
+
+use chrono::Utc;
+use gloo_timers::future::TimeoutFuture;
+use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation, decode, encode};
+use web_sys::AbortSignal;
+use yew::Callback;
+
+use crate::{
+    enums::HttpStatus,
+    error::ApiError,
+    model::{
+        AuditEvent, AuditEventPage, BlogPost, BlogPostChange, JWTAuthClaim, LoggedUserInfoResponse,
+        PostVisibility, User,
+    },
+    service::api::{ApiServiceTrait, PostSubscription, notify_unauthorized},
+    types::{ApiResponse, ApiResult},
+};
+
+/// Secret of the tokens issued by the mock, they are only valid for the mock
+const MOCK_JWT_SECRET: &[u8] = b"mock-secret";
+
+/// Validity of the tokens issued by the mock
+const MOCK_TOKEN_DURATION_SECONDS: u32 = 3600;
+
+const SEEDED_POST_CONTENT: &str = "this is the content of the blog. I think that I could write something but for now I can simply write a long text just to see how it will be displayed on the screen. Who know how it will be printed on the screen.";
+
+thread_local! {
+    static MOCK_STATE: RefCell<MockState> = RefCell::new(MockState::seeded());
+}
+
+struct MockUser {
+    user: User,
+    password: String,
+}
+
+/// Data of the mocked backend shared by all the `MockApiService` instances
+#[derive(Default)]
+struct MockState {
+    users: Vec<MockUser>,
+    posts: Vec<BlogPost>,
+    audit_events: Vec<AuditEvent>,
+    subscribers: Vec<(u64, Callback<BlogPostChange>)>,
+    last_id: u64,
+}
+
+impl MockState {
+    fn seeded() -> Self {
+        let mut state = MockState::default();
+        let admin = state.add_user("Admin", "Admin", "admin", "admin", true);
+        let author = state.add_user("Alex", "Sinks", "alex_sinks", "password", false);
+        for (title, visibility) in [
+            ("First blog", PostVisibility::Public),
+            ("Second blog", PostVisibility::Public),
+            ("Third blog", PostVisibility::MembersOnly),
+        ] {
+            state.add_post(&author, title, SEEDED_POST_CONTENT, visibility);
+        }
+        state.add_audit_event(&admin.user_id, "update", "user", &author.user_id);
+        state
+    }
+
+    fn next_id(&mut self, prefix: &str) -> String {
+        self.last_id += 1;
+        format!("{prefix}-{}", self.last_id)
+    }
+
+    fn add_user(
+        &mut self,
+        first_name: &str,
+        last_name: &str,
+        username: &str,
+        password: &str,
+        admin: bool,
+    ) -> User {
+        let user = User {
+            user_id: self.next_id("user"),
+            first_name: first_name.into(),
+            last_name: last_name.into(),
+            username: username.into(),
+            admin,
+            publisher: true,
+            deleted_at: None,
+        };
+        self.users.push(MockUser {
+            user: user.clone(),
+            password: password.into(),
+        });
+        self.add_audit_event(&user.user_id, "create", "user", &user.user_id);
+        user
+    }
+
+    fn add_post(
+        &mut self,
+        author: &User,
+        title: &str,
+        content: &str,
+        visibility: PostVisibility,
+    ) -> BlogPost {
+        let post = BlogPost {
+            id: self.next_id("post"),
+            title: title.into(),
+            content: content.into(),
+            creator_id: author.user_id.clone(),
+            creator_username: author.username.clone(),
+            creation_date: Utc::now(),
+            visibility,
+        };
+        self.posts.push(post.clone());
+        self.add_audit_event(&author.user_id, "create", "blog_post", &post.id);
+        post
+    }
+
+    fn add_audit_event(
+        &mut self,
+        actor_id: &str,
+        action: &str,
+        target_collection: &str,
+        target_id: &str,
+    ) {
+        let event = AuditEvent {
+            id: self.next_id("audit"),
+            actor_id: actor_id.into(),
+            action: action.into(),
+            target_collection: target_collection.into(),
+            target_id: target_id.into(),
+            before: None,
+            after: None,
+            creation_date: Utc::now(),
+        };
+        self.audit_events.push(event);
+    }
+
+    /// Returns the user owning the token, like the authorization extractor of the backend
+    fn authorize(&self, token: Option<&str>) -> Result<&User, ApiError> {
+        let token = token.ok_or(ApiError::AuthorizationError(
+            "Missing authorization token".into(),
+        ))?;
+        let claims = decode::<JWTAuthClaim>(
+            token,
+            &DecodingKey::from_secret(MOCK_JWT_SECRET),
+            &Validation::default(),
+        )
+        .map_err(|_| ApiError::AuthorizationError("Invalid token".into()))?
+        .claims;
+        self.users
+            .iter()
+            .map(|mock_user| &mock_user.user)
+            .find(|user| user.user_id == claims.user_id)
+            .ok_or(ApiError::AuthorizationError("Invalid token".into()))
+    }
+
+    fn authorize_admin(&self, token: Option<&str>) -> Result<&User, ApiError> {
+        let user = self.authorize(token)?;
+        if user.admin {
+            Ok(user)
+        } else {
+            Err(ApiError::AccessControlError("Admin role required".into()))
+        }
+    }
+}
+
+/// Builds the response of the login, register and refresh calls with a new token
+fn logged_user_info(user: &User) -> LoggedUserInfoResponse {
+    let claims = JWTAuthClaim {
+        user_id: user.user_id.clone(),
+        username: user.username.clone(),
+        exp: Utc::now().timestamp() as u32 + MOCK_TOKEN_DURATION_SECONDS,
+    };
+    let token = encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(MOCK_JWT_SECRET),
+    )
+    .expect("failing to mock jwt");
+    LoggedUserInfoResponse {
+        token: Some(token),
+        user_id: user.user_id.clone(),
+        username: user.username.clone(),
+        admin: user.admin,
+    }
+}
+
+/// Api service that simulates the backend in the browser
+#[derive(Clone, Debug, PartialEq)]
+pub struct MockApiService {
+    token: Option<String>,
+    latency_ms: u32,
+    failure_rate: f64,
+    abort_signal: Option<AbortSignal>,
+}
+
+impl MockApiService {
+    pub fn new(token: Option<String>, latency_ms: u32, failure_rate: f64) -> Self {
+        Self {
+            token,
+            latency_ms,
+            failure_rate,
+            abort_signal: None,
+        }
+    }
+
+    pub fn with_abort_signal(mut self, signal: AbortSignal) -> Self {
+        self.abort_signal = Some(signal);
+        self
+    }
+
+    /// Simulates a call to the backend, after the latency it fails with the
+    /// configured probability or it runs the handler on the mocked state
+    async fn respond<T>(
+        &self,
+        handler: impl FnOnce(&mut MockState, Option<&str>) -> Result<T, ApiError>,
+    ) -> ApiResult<T> {
+        if self.latency_ms > 0 {
+            TimeoutFuture::new(self.latency_ms).await;
+        }
+        if self
+            .abort_signal
+            .as_ref()
+            .is_some_and(|signal| signal.aborted())
+        {
+            return Err(ApiError::Cancelled);
+        }
+        if js_sys::Math::random() < self.failure_rate {
+            return Err(ApiError::InternalServerError(
+                "Injected mock failure".into(),
+            ));
+        }
+        let body = MOCK_STATE.with_borrow_mut(|state| handler(state, self.token.as_deref()));
+        // login fails with the same error, but it is called without token
+        if let Err(ApiError::AuthorizationError(_)) = &body
+            && self.token.is_some()
+        {
+            notify_unauthorized();
+        }
+        let body = body?;
+        Ok(ApiResponse {
+            body,
+            status: HttpStatus::Success(200),
+        })
+    }
+}
+
+impl ApiServiceTrait for MockApiService {
+    async fn login(&self, username: String, password: String) -> ApiResult<LoggedUserInfoResponse> {
+        self.respond(|state, _| {
+            state
+                .users
+                .iter()
+                .find(|mock_user| {
+                    mock_user.user.username == username && mock_user.password == password
+                })
+                .map(|mock_user| logged_user_info(&mock_user.user))
+                .ok_or(ApiError::AuthorizationError("Wrong credentials".into()))
+        })
+        .await
+    }
+
+    async fn register(
+        &self,
+        first_name: String,
+        last_name: String,
+        username: String,
+        password: String,
+    ) -> ApiResult<LoggedUserInfoResponse> {
+        self.respond(|state, _| {
+            if state
+                .users
+                .iter()
+                .any(|mock_user| mock_user.user.username == username)
+            {
+                return Err(ApiError::InternalServerError(
+                    "Username already exists".into(),
+                ));
+            }
+            let user = state.add_user(&first_name, &last_name, &username, &password, false);
+            Ok(logged_user_info(&user))
+        })
+        .await
+    }
+
+    async fn get_user_info(&self) -> ApiResult<LoggedUserInfoResponse> {
+        self.respond(|state, token| {
+            let user = state.authorize(token)?;
+            Ok(LoggedUserInfoResponse {
+                token: None,
+                user_id: user.user_id.clone(),
+                username: user.username.clone(),
+                admin: user.admin,
+            })
+        })
+        .await
+    }
+
+    async fn refresh_token(&self) -> ApiResult<LoggedUserInfoResponse> {
+        self.respond(|state, token| Ok(logged_user_info(state.authorize(token)?)))
+            .await
+    }
+
+    async fn get_posts(&self) -> ApiResult<Vec<BlogPost>> {
+        self.respond(|state, token| {
+            state.authorize(token)?;
+            Ok(state.posts.clone())
+        })
+        .await
+    }
+
+    async fn get_post(&self, id: String) -> ApiResult<BlogPost> {
+        self.respond(|state, token| {
+            state.authorize(token)?;
+            state
+                .posts
+                .iter()
+                .find(|post| post.id == id)
+                .cloned()
+                .ok_or(ApiError::DoesNotExist(format!(
+                    "Blog post {id} does not exist"
+                )))
+        })
+        .await
+    }
+
+    async fn get_public_posts(&self) -> ApiResult<Vec<BlogPost>> {
+        self.respond(|state, _| {
+            Ok(state
+                .posts
+                .iter()
+                .filter(|post| post.visibility == PostVisibility::Public)
+                .cloned()
+                .collect())
+        })
+        .await
+    }
+
+    async fn get_public_post(&self, id: String) -> ApiResult<BlogPost> {
+        self.respond(|state, _| {
+            state
+                .posts
+                .iter()
+                .find(|post| post.id == id && post.visibility == PostVisibility::Public)
+                .cloned()
+                .ok_or(ApiError::DoesNotExist(format!(
+                    "Blog post {id} does not exist"
+                )))
+        })
+        .await
+    }
+
+    fn stream_posts(
+        &self,
+        on_change: Callback<BlogPostChange>,
+    ) -> Result<PostSubscription, ApiError> {
+        let id = MOCK_STATE.with_borrow_mut(|state| {
+            state.authorize(self.token.as_deref())?;
+            let id = state.last_id + 1;
+            state.last_id = id;
+            state.subscribers.push((id, on_change));
+            Ok::<_, ApiError>(id)
+        })?;
+        Ok(PostSubscription::new(move || {
+            MOCK_STATE.with_borrow_mut(|state| {
+                state
+                    .subscribers
+                    .retain(|(subscriber, _)| *subscriber != id)
+            })
+        }))
+    }
+
+    async fn publish_post(
+        &self,
+        title: String,
+        content: String,
+        visibility: PostVisibility,
+    ) -> ApiResult<String> {
+        let (post, subscribers) = self
+            .respond(|state, token| {
+                let author = state.authorize(token)?.clone();
+                let post = state.add_post(&author, &title, &content, visibility);
+                let subscribers = state
+                    .subscribers
+                    .iter()
+                    .map(|(_, subscriber)| subscriber.clone())
+                    .collect::<Vec<_>>();
+                Ok((post, subscribers))
+            })
+            .await?
+            .body;
+
+        // the subscribers are notified outside of the state borrow because
+        // their callbacks may call the mock again
+        for subscriber in subscribers {
+            subscriber.emit(BlogPostChange::Created { post: post.clone() });
+        }
+        Ok(ApiResponse {
+            body: post.id,
+            status: HttpStatus::Success(200),
+        })
+    }
+
+    async fn get_admin_users_list(&self) -> ApiResult<Vec<User>> {
+        self.respond(|state, token| {
+            state.authorize_admin(token)?;
+            Ok(state
+                .users
+                .iter()
+                .map(|mock_user| mock_user.user.clone())
+                .collect())
+        })
+        .await
+    }
+
+    async fn get_audit_events(
+        &self,
+        page: u64,
+        page_size: u64,
+        action: Option<String>,
+    ) -> ApiResult<AuditEventPage> {
+        self.respond(|state, token| {
+            state.authorize_admin(token)?;
+            let mut events = state
+                .audit_events
+                .iter()
+                .filter(|event| action.as_ref().is_none_or(|action| &event.action == action))
+                .cloned()
+                .collect::<Vec<_>>();
+            // the events are added in chronological order, the backend
+            // returns the most recent ones first
+            events.reverse();
+            let total = events.len() as u64;
+            let events = events
+                .into_iter()
+                .skip((page.saturating_sub(1) * page_size) as usize)
+                .take(page_size as usize)
+                .collect();
+            Ok(AuditEventPage {
+                events,
+                page,
+                page_size,
+                total,
+            })
+        })
+        .await
+    }
+}
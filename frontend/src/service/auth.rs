@@ -1,16 +1,20 @@
 use gloo_storage::{LocalStorage, Storage, errors::StorageError};
 use jsonwebtoken::dangerous::insecure_decode;
 use log::error;
+use web_sys::AbortSignal;
 use yew::UseStateHandle;
 
 use crate::{
-    enums::HttpStatus,
     environment::EnvironmentService,
+    error::ApiError,
     model::{JWTAuthClaim, LoggedUserInfo},
-    service::api::ApiService,
+    service::api::{ApiService, ApiServiceTrait},
     types::{ApiResponse, AppContext, AppResult},
 };
 
+/// Seconds before the expiration of the token when it is refreshed
+const TOKEN_REFRESH_MARGIN_SECONDS: i64 = 60;
+
 /// Service responsible to manage the logged user session
 /// and its authentication information
 ///
@@ -44,83 +48,145 @@ impl AuthService {
     /// application context
     pub fn remove_logged_user(&self) {
         LocalStorage::delete(&self.token_storage_location_name);
-        self.app_context.set(AppContext { user_info: None });
+        self.app_context.set(AppContext::new(None));
     }
 
     /// Given the logged user info, it stores the token on the local storage
     /// and update the application context with them
     pub fn set_logged_user_info(&self, info: LoggedUserInfo) -> AppResult<()> {
         LocalStorage::set(&self.token_storage_location_name, info.token.clone())?;
-        self.app_context.set(AppContext {
-            user_info: Some(info),
-        });
+        self.app_context.set(AppContext::new(Some(info)));
         Ok(())
     }
 
     /// Tries to retrieve the token from the local storage and update the
-    /// application context.
+    /// application context, that stops loading when the token is validated.
     ///
-    /// If the token is not present or it is expired then the user info are
-    /// removed from the context
+    /// If the token is not present, it cannot be decoded, it is expired or
+    /// it is rejected by the backend then the user info are removed from the
+    /// context and the storage
     pub fn set_logged_user_info_from_storage(&self) {
-        // TODO: add a condition that if the app context already has the user info then don't do anything
-        if let Some(token) = self.load_token().unwrap_or(None) {
-            // verify the token in not expired
-            let insecure_decoded_claims = insecure_decode::<JWTAuthClaim>(&token).unwrap().claims;
+        let token = match self.load_token() {
+            Ok(Some(token)) => token,
+            Ok(None) => {
+                self.app_context.set(AppContext::new(None));
+                return;
+            }
+            Err(err) => {
+                error!("Failed to read the stored token, removing it. Error {err}");
+                self.remove_logged_user();
+                return;
+            }
+        };
+        // the signature cannot be verified by the frontend, it is done by the
+        // backend with the get user info request
+        let insecure_decoded_claims = match insecure_decode::<JWTAuthClaim>(&token) {
+            Ok(token_data) => token_data.claims,
+            Err(err) => {
+                error!("The stored token is corrupted, removing it. Error {err}");
+                self.remove_logged_user();
+                return;
+            }
+        };
 
-            let now = chrono::offset::Local::now().timestamp() as u32;
+        let now = chrono::offset::Local::now().timestamp() as u32;
 
-            if insecure_decoded_claims.exp >= now {
-                if self.app_context.user_info.is_some() {
-                    // if the user is already in the context then we just return avoiding making an additional request
-                    return;
-                }
-                // We make an api request to get the actual user information to validate the
-                // ones read from the local storage. Moreover, this api request returns the
-                // information if the user is admin
-                let environment_service = EnvironmentService::new();
-                let api_service = ApiService::new(
-                    environment_service.api_url,
-                    environment_service.mock,
-                    Some(token.clone()),
-                );
-
-                let app_context = self.app_context.clone();
-                let self_clone = self.clone();
-
-                wasm_bindgen_futures::spawn_local(async move {
-                    let response = api_service.get_user_info().await;
-                    if let Ok(ApiResponse { body, status }) = response {
-                        match status {
-                            HttpStatus::Success(_) => {
-                                let body = body.expect("Body must be present when it is success");
-                                let logged_user_info = LoggedUserInfo {
-                                    token,
-                                    user_id: body.user_id,
-                                    username: body.username,
-                                    admin: body.admin,
-                                };
-                                app_context.set(AppContext::new(Some(logged_user_info)));
-                            }
-                            _ => {
-                                self_clone.remove_logged_user();
-                                error!("Response returned status {status}", status = status);
-                            }
-                        }
-                    } else {
+        if insecure_decoded_claims.exp >= now {
+            // We make an api request to get the actual user information to validate the
+            // ones read from the local storage. Moreover, this api request returns the
+            // information if the user is admin
+            let environment_service = EnvironmentService::new();
+            let api_service = ApiService::new(&environment_service, Some(token.clone()));
+
+            let app_context = self.app_context.clone();
+            let self_clone = self.clone();
+
+            wasm_bindgen_futures::spawn_local(async move {
+                let response = api_service.get_user_info().await;
+                match response {
+                    Ok(ApiResponse { body, .. }) => {
+                        let logged_user_info = LoggedUserInfo {
+                            token,
+                            user_id: body.user_id,
+                            username: body.username,
+                            admin: body.admin,
+                        };
+                        app_context.set(AppContext::new(Some(logged_user_info)));
+                    }
+                    Err(
+                        err @ (ApiError::AuthorizationError(_) | ApiError::AccessControlError(_)),
+                    ) => {
+                        error!("The stored token has been rejected, removing it. Error {err}");
                         self_clone.remove_logged_user();
-                        error!(
-                            "Encountered an error in get user info request. Error {err}",
-                            err = response.err().unwrap()
-                        );
                     }
-                });
-            } else {
-                self.remove_logged_user();
-            }
+                    Err(err) => {
+                        // a transient error does not invalidate the token, the user stays
+                        // logged with the decoded claims, the admin pages are available
+                        // once the user info are read again
+                        error!("Encountered an error in get user info request. Error {err}");
+                        app_context.set(AppContext::new(Some(LoggedUserInfo {
+                            token,
+                            user_id: insecure_decoded_claims.user_id,
+                            username: insecure_decoded_claims.username,
+                            admin: false,
+                        })));
+                    }
+                }
+            });
+        } else {
+            self.remove_logged_user();
         }
     }
 
+    /// Returns the milliseconds to wait before refreshing the token, that is
+    /// `TOKEN_REFRESH_MARGIN_SECONDS` before its expiration.
+    ///
+    /// It is zero if the token is about to expire and None if it cannot be decoded
+    pub fn token_refresh_delay(token: &str) -> Option<u32> {
+        let claims = insecure_decode::<JWTAuthClaim>(token).ok()?.claims;
+        let now = chrono::offset::Local::now().timestamp();
+        let seconds = (i64::from(claims.exp) - TOKEN_REFRESH_MARGIN_SECONDS - now).max(0);
+        // browsers fire immediately the timeouts longer than i32::MAX milliseconds
+        Some((seconds * 1000).min(i64::from(i32::MAX)) as u32)
+    }
+
+    /// Replaces the token of the logged user with a new one issued by the backend,
+    /// the request is aborted with the given signal.
+    ///
+    /// When the backend rejects the current token the user is logged out by the
+    /// unauthorized handler of the api service
+    pub fn refresh_token(&self, abort_signal: AbortSignal) {
+        let Some(user_info) = self.app_context.user_info.clone() else {
+            return;
+        };
+        let environment_service = EnvironmentService::new();
+        let api_service = ApiService::new(&environment_service, Some(user_info.token))
+            .with_abort_signal(abort_signal);
+        let self_clone = self.clone();
+
+        wasm_bindgen_futures::spawn_local(async move {
+            match api_service.refresh_token().await {
+                Ok(ApiResponse { body, .. }) => {
+                    let Some(token) = body.token else {
+                        error!("The refresh token response does not contain the token");
+                        return;
+                    };
+                    let logged_user_info = LoggedUserInfo {
+                        token,
+                        user_id: body.user_id,
+                        username: body.username,
+                        admin: body.admin,
+                    };
+                    if let Err(err) = self_clone.set_logged_user_info(logged_user_info) {
+                        error!("Failed to store the refreshed token. Error {err}");
+                    }
+                }
+                Err(ApiError::Cancelled) => {}
+                Err(err) => error!("Encountered an error in refresh token request. Error {err}"),
+            }
+        });
+    }
+
     /// Returns the auth token from the application context
     ///
     /// Note that it does not load from the local storage any information
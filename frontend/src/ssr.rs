@@ -0,0 +1,43 @@
+//! Server side rendering of the application, it is used by the backend to
+//! serve the pages already rendered with the data they show.
+//!
+//! The template is the `index.html` built by trunk: the rendered application
+//! is placed in the body and the preloaded data in the head, then the client
+//! hydrates the body taking over the rendered elements.
+
+use yew::ServerRenderer;
+use yew_router::Routable;
+
+use crate::{
+    app::{AppRoute, ServerApp, ServerAppProps},
+    types::{PRELOADED_DATA_ELEMENT_ID, PreloadedData},
+};
+
+/// Returns the route of the path, the server uses it to know the data to preload
+pub fn recognize_route(path: &str) -> Option<AppRoute> {
+    AppRoute::recognize(path)
+}
+
+/// Renders the page of `url`, path and query, in the `index.html` template
+pub async fn render_page(template: &str, url: String, preloaded: PreloadedData) -> String {
+    // the closing tags are escaped to keep the data inside the script element
+    let data = serde_json::to_string(&preloaded)
+        .expect("Failed to serialize preloaded data")
+        .replace("</", "<\\/");
+    let body = ServerRenderer::<ServerApp>::with_props(move || ServerAppProps {
+        url: url.into(),
+        preloaded,
+    })
+    .render()
+    .await;
+
+    template
+        .replacen(
+            "</head>",
+            &format!(
+                r#"<script id="{PRELOADED_DATA_ELEMENT_ID}" type="application/json">{data}</script></head>"#
+            ),
+            1,
+        )
+        .replacen("<body>", &format!("<body>{body}"), 1)
+}
@@ -1,9 +1,15 @@
+use log::error;
+use serde::{Deserialize, Serialize};
+
 use crate::{
     enums::HttpStatus,
     error::{ApiError, AppError},
-    model::LoggedUserInfo,
+    model::{BlogPost, LoggedUserInfo},
 };
 
+/// Id of the script element of the page containing the `PreloadedData`
+pub const PRELOADED_DATA_ELEMENT_ID: &str = "preloaded-data";
+
 /// Struct containing general context for the application
 ///
 /// it is shared among all the components and contains the
@@ -11,11 +17,51 @@ use crate::{
 #[derive(Clone, Debug, PartialEq)]
 pub struct AppContext {
     pub user_info: Option<LoggedUserInfo>,
+    /// True while the token read from the local storage is validated,
+    /// the route guards wait for it before deciding if the user can
+    /// access the page
+    pub loading: bool,
 }
 
 impl AppContext {
     pub fn new(user_info: Option<LoggedUserInfo>) -> AppContext {
-        AppContext { user_info }
+        AppContext {
+            user_info,
+            loading: false,
+        }
+    }
+
+    /// Context of the application at startup, before the stored token is validated
+    pub fn loading() -> AppContext {
+        AppContext {
+            user_info: None,
+            loading: true,
+        }
+    }
+}
+
+/// Data loaded by the server when it renders the page, it is embedded in the
+/// page so that the client renders the same content without requesting it again
+///
+/// It is provided to the components as context
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct PreloadedData {
+    /// Post shown by the post page
+    pub post: Option<BlogPost>,
+    /// Public posts shown by the home page
+    pub posts: Option<Vec<BlogPost>>,
+}
+
+impl PreloadedData {
+    /// Reads the data embedded in the page, it is None when the page
+    /// has not been rendered by the server
+    pub fn from_document() -> Option<Self> {
+        let element = web_sys::window()?
+            .document()?
+            .get_element_by_id(PRELOADED_DATA_ELEMENT_ID)?;
+        serde_json::from_str(&element.text_content()?)
+            .inspect_err(|err| error!("Failed to read the preloaded data. Error {err}"))
+            .ok()
     }
 }
 